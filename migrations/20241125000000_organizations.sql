@@ -0,0 +1,101 @@
+-- Organizations (tenants) and per-organization roles
+CREATE TABLE IF NOT EXISTS organizations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL,
+    slug VARCHAR(63) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS organization_memberships (
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(32) NOT NULL DEFAULT 'member' CHECK (role IN ('owner', 'admin', 'member')),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (organization_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_organization_memberships_user ON organization_memberships (user_id);
+
+ALTER TABLE api_keys
+    ADD COLUMN IF NOT EXISTS organization_id UUID REFERENCES organizations(id) ON DELETE CASCADE;
+
+-- AI calls, attributed to the tenant and the user or API key that made them
+CREATE TABLE IF NOT EXISTS ai_usage (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE SET NULL,
+    operation VARCHAR(32) NOT NULL,
+    model VARCHAR(128) NOT NULL,
+    prompt_tokens INTEGER NOT NULL DEFAULT 0,
+    completion_tokens INTEGER NOT NULL DEFAULT 0,
+    total_tokens INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_usage_organization ON ai_usage (organization_id, created_at);
+
+-- Move existing data into a default organization. Global admins become owners.
+INSERT INTO organizations (name, slug)
+SELECT 'Default', 'default'
+WHERE EXISTS (SELECT 1 FROM users)
+ON CONFLICT (slug) DO NOTHING;
+
+INSERT INTO organization_memberships (organization_id, user_id, role)
+SELECT o.id, u.id, CASE WHEN u.role = 'admin' THEN 'owner' ELSE 'member' END
+FROM users u
+CROSS JOIN organizations o
+WHERE o.slug = 'default'
+ON CONFLICT DO NOTHING;
+
+UPDATE api_keys
+SET organization_id = (SELECT id FROM organizations WHERE slug = 'default')
+WHERE organization_id IS NULL;
+
+-- Row-level security. Tenant-scoped queries run inside a transaction that
+-- switches to `app_tenant` and sets `app.tenant_id`; the policies below only
+-- apply to that role. Global lookups (login, sessions) keep using the owner role.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'app_tenant') THEN
+        CREATE ROLE app_tenant NOLOGIN;
+    END IF;
+END
+$$;
+
+GRANT app_tenant TO CURRENT_USER;
+GRANT SELECT, INSERT, UPDATE, DELETE ON users, organizations, organization_memberships, ai_usage TO app_tenant;
+
+CREATE OR REPLACE FUNCTION current_tenant_id() RETURNS UUID
+LANGUAGE sql STABLE
+AS $$ SELECT NULLIF(current_setting('app.tenant_id', true), '')::UUID $$;
+
+ALTER TABLE organizations ENABLE ROW LEVEL SECURITY;
+ALTER TABLE organization_memberships ENABLE ROW LEVEL SECURITY;
+ALTER TABLE users ENABLE ROW LEVEL SECURITY;
+ALTER TABLE ai_usage ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS tenant_isolation ON organizations;
+CREATE POLICY tenant_isolation ON organizations TO app_tenant
+    USING (id = current_tenant_id());
+
+DROP POLICY IF EXISTS tenant_isolation ON organization_memberships;
+CREATE POLICY tenant_isolation ON organization_memberships TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
+
+-- Users are global accounts, a tenant sees those that are its members. New
+-- accounts can be inserted, they become visible once the membership exists.
+DROP POLICY IF EXISTS tenant_members ON users;
+CREATE POLICY tenant_members ON users TO app_tenant
+    USING (id IN (SELECT user_id FROM organization_memberships WHERE organization_id = current_tenant_id()));
+
+DROP POLICY IF EXISTS tenant_insert ON users;
+CREATE POLICY tenant_insert ON users FOR INSERT TO app_tenant
+    WITH CHECK (true);
+
+DROP POLICY IF EXISTS tenant_isolation ON ai_usage;
+CREATE POLICY tenant_isolation ON ai_usage TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
@@ -0,0 +1,18 @@
+-- Cached AI responses, keyed by a hash of tenant, model, normalized input and generation config
+CREATE TABLE IF NOT EXISTS ai_response_cache (
+    key_hash VARCHAR(64) PRIMARY KEY,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    model VARCHAR(128) NOT NULL,
+    text TEXT NOT NULL,
+    prompt_tokens INTEGER NOT NULL DEFAULT 0,
+    completion_tokens INTEGER NOT NULL DEFAULT 0,
+    total_tokens INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_hit_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_response_cache_last_hit ON ai_response_cache (last_hit_at);
+
+-- Cache hits are recorded as usage too, with the tokens they saved
+ALTER TABLE ai_usage ADD COLUMN IF NOT EXISTS cached BOOLEAN NOT NULL DEFAULT FALSE;
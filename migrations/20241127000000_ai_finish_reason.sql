@@ -0,0 +1,2 @@
+-- Cached responses keep the finish reason they were generated with
+ALTER TABLE ai_response_cache ADD COLUMN IF NOT EXISTS finish_reason VARCHAR(32) NOT NULL DEFAULT 'STOP';
@@ -0,0 +1,25 @@
+-- Named, versioned prompt templates per organization. Creating a template with
+-- an existing name adds a new version, older versions stay renderable.
+CREATE TABLE IF NOT EXISTS prompt_templates (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    version INTEGER NOT NULL,
+    description TEXT,
+    system_instruction TEXT,
+    body TEXT NOT NULL,
+    -- [{"name": "topic", "type": "string", "required": true, "default": null, "description": null}]
+    variables JSONB NOT NULL DEFAULT '[]',
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (organization_id, name, version)
+);
+
+GRANT SELECT, INSERT ON prompt_templates TO app_tenant;
+
+ALTER TABLE prompt_templates ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS tenant_isolation ON prompt_templates;
+CREATE POLICY tenant_isolation ON prompt_templates TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
@@ -0,0 +1,21 @@
+-- Images and documents uploaded for chat messages, referenced by id
+CREATE TABLE IF NOT EXISTS ai_files (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    filename VARCHAR(255) NOT NULL,
+    mime_type VARCHAR(128) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    data BYTEA NOT NULL,
+    uploaded_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+GRANT SELECT, INSERT, DELETE ON ai_files TO app_tenant;
+
+ALTER TABLE ai_files ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS tenant_isolation ON ai_files;
+CREATE POLICY tenant_isolation ON ai_files TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
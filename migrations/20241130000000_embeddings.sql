@@ -0,0 +1,32 @@
+-- Documents embedded for semantic search, grouped into named collections per
+-- organization. Needs the pgvector extension (the pgvector/pgvector images).
+CREATE EXTENSION IF NOT EXISTS vector;
+
+CREATE TABLE IF NOT EXISTS embeddings (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    collection VARCHAR(100) NOT NULL,
+    document_id VARCHAR(255) NOT NULL,
+    content TEXT NOT NULL,
+    metadata JSONB NOT NULL DEFAULT '{}',
+    -- Vectors of different models can't be compared, searches only match their own model
+    model VARCHAR(100) NOT NULL,
+    embedding vector(768) NOT NULL,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (organization_id, collection, document_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_embeddings_embedding ON embeddings
+    USING hnsw (embedding vector_cosine_ops);
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON embeddings TO app_tenant;
+
+ALTER TABLE embeddings ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS tenant_isolation ON embeddings;
+CREATE POLICY tenant_isolation ON embeddings TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
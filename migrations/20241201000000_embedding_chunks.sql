@@ -0,0 +1,8 @@
+-- Chunks of ingested documents are stored as embeddings with document ids
+-- `<source_id>#<chunk_index>`, so citations can point back to the document
+ALTER TABLE embeddings
+    ADD COLUMN IF NOT EXISTS source_id VARCHAR(255),
+    ADD COLUMN IF NOT EXISTS chunk_index INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_embeddings_source ON embeddings (organization_id, collection, source_id)
+    WHERE source_id IS NOT NULL;
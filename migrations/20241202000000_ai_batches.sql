@@ -0,0 +1,51 @@
+-- Generate requests submitted together and answered in the background
+CREATE TABLE IF NOT EXISTS ai_batches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    total_jobs INTEGER NOT NULL,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE SET NULL,
+    cancelled_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- One request of a batch. Workers claim pending jobs with FOR UPDATE SKIP
+-- LOCKED; a running job whose lease ran out is claimed again.
+CREATE TABLE IF NOT EXISTS ai_batch_jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    batch_id UUID NOT NULL REFERENCES ai_batches(id) ON DELETE CASCADE,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    request JSONB NOT NULL,
+    -- pending, running, succeeded, failed or cancelled
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    run_after TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMPTZ,
+    response JSONB,
+    error TEXT,
+    finished_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (batch_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_batch_jobs_pending ON ai_batch_jobs (run_after) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_ai_batch_jobs_running ON ai_batch_jobs (locked_until) WHERE status = 'running';
+
+-- Requests read and cancel batches of their tenant. Workers claim jobs of all
+-- tenants and connect without the app_tenant role.
+GRANT SELECT, INSERT, UPDATE ON ai_batches TO app_tenant;
+GRANT SELECT, INSERT, UPDATE ON ai_batch_jobs TO app_tenant;
+
+ALTER TABLE ai_batches ENABLE ROW LEVEL SECURITY;
+ALTER TABLE ai_batch_jobs ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS tenant_isolation ON ai_batches;
+CREATE POLICY tenant_isolation ON ai_batches TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
+
+DROP POLICY IF EXISTS tenant_isolation ON ai_batch_jobs;
+CREATE POLICY tenant_isolation ON ai_batch_jobs TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
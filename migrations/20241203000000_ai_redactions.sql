@@ -0,0 +1,23 @@
+-- Personal data redacted from prompts, one row per request and detector or
+-- pattern. Only counts are kept, never the values.
+CREATE TABLE IF NOT EXISTS ai_redactions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE SET NULL,
+    operation VARCHAR(32) NOT NULL,
+    kind VARCHAR(64) NOT NULL,
+    matches INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_redactions_organization ON ai_redactions (organization_id, created_at);
+
+GRANT SELECT, INSERT ON ai_redactions TO app_tenant;
+
+ALTER TABLE ai_redactions ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS tenant_isolation ON ai_redactions;
+CREATE POLICY tenant_isolation ON ai_redactions TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
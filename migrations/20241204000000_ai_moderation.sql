@@ -0,0 +1,33 @@
+-- Prompts and answers our moderation flagged or blocked, kept for review.
+-- The content is stored after personal data was redacted.
+CREATE TABLE IF NOT EXISTS ai_moderation_flags (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE SET NULL,
+    operation VARCHAR(32) NOT NULL,
+    -- input or output
+    stage VARCHAR(16) NOT NULL,
+    -- flag or block
+    action VARCHAR(16) NOT NULL,
+    categories TEXT[] NOT NULL,
+    content TEXT NOT NULL,
+    model VARCHAR(255) NOT NULL,
+    reviewed_at TIMESTAMPTZ,
+    reviewed_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    review_note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_moderation_flags_organization ON ai_moderation_flags (organization_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_ai_moderation_flags_open ON ai_moderation_flags (organization_id, created_at)
+    WHERE reviewed_at IS NULL;
+
+GRANT SELECT, INSERT, UPDATE ON ai_moderation_flags TO app_tenant;
+
+ALTER TABLE ai_moderation_flags ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS tenant_isolation ON ai_moderation_flags;
+CREATE POLICY tenant_isolation ON ai_moderation_flags TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
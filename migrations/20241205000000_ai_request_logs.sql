@@ -0,0 +1,37 @@
+-- Model calls of chat and generate requests, logged when AI_REQUEST_LOG_ENABLED
+-- is set. Requests are stored after redaction, without attachment content.
+-- Rows older than AI_REQUEST_LOG_RETENTION_DAYS are deleted in the background.
+CREATE TABLE IF NOT EXISTS ai_request_logs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    api_key_id UUID REFERENCES api_keys(id) ON DELETE SET NULL,
+    operation VARCHAR(32) NOT NULL,
+    provider VARCHAR(32),
+    requested_model VARCHAR(255) NOT NULL,
+    model VARCHAR(255),
+    request JSONB NOT NULL,
+    response TEXT,
+    tool_calls JSONB,
+    finish_reason VARCHAR(32),
+    error TEXT,
+    prompt_tokens INTEGER NOT NULL DEFAULT 0,
+    completion_tokens INTEGER NOT NULL DEFAULT 0,
+    total_tokens INTEGER NOT NULL DEFAULT 0,
+    latency_ms BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_request_logs_organization ON ai_request_logs (organization_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_ai_request_logs_created ON ai_request_logs (created_at);
+
+-- Requests write and search logs of their tenant. The retention task deletes
+-- old logs of all tenants and connects without the app_tenant role.
+GRANT SELECT, INSERT ON ai_request_logs TO app_tenant;
+
+ALTER TABLE ai_request_logs ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS tenant_isolation ON ai_request_logs;
+CREATE POLICY tenant_isolation ON ai_request_logs TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
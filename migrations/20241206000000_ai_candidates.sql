@@ -0,0 +1,3 @@
+-- Cached responses keep all candidates with their safety ratings and citations.
+-- Entries from before have none stored and return their text as the only candidate.
+ALTER TABLE ai_response_cache ADD COLUMN IF NOT EXISTS candidates JSONB;
@@ -0,0 +1,21 @@
+-- Members are added by invitation. The invited account joins once it accepts,
+-- so an organization can't take in an account without its consent.
+CREATE TABLE IF NOT EXISTS organization_invitations (
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(32) NOT NULL DEFAULT 'member' CHECK (role IN ('owner', 'admin', 'member')),
+    invited_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (organization_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_organization_invitations_user ON organization_invitations (user_id);
+
+GRANT SELECT, INSERT, UPDATE, DELETE ON organization_invitations TO app_tenant;
+
+ALTER TABLE organization_invitations ENABLE ROW LEVEL SECURITY;
+
+DROP POLICY IF EXISTS tenant_isolation ON organization_invitations;
+CREATE POLICY tenant_isolation ON organization_invitations TO app_tenant
+    USING (organization_id = current_tenant_id())
+    WITH CHECK (organization_id = current_tenant_id());
@@ -0,0 +1,3 @@
+-- Time step of the last accepted TOTP code, so a code can't be replayed
+-- within its validity window
+ALTER TABLE users ADD COLUMN IF NOT EXISTS totp_last_step BIGINT;
@@ -0,0 +1,162 @@
+use chrono::Utc;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    entities::organization::Organization,
+    features::ai_integration::domain::{
+        AIService, ContextWindow, EvalRunner, EvalSuite, ModerationPipeline, Redactor, ToolRegistry,
+    },
+    features::ai_integration::infrastructure::{create_model_router, MemoryAiUsageRepository, OfflineStorage},
+    features::auth::model::{ApiKeyPrincipal, ApiScope, Principal},
+    features::organizations::model::TenantContext,
+    shared::config::Config,
+};
+
+const USAGE: &str = "Usage: hello_cargo eval <suite.yaml|suite.jsonl> [--model <model or route>] \
+[--format json|junit] [--output <file>] [--min-pass-rate <0..1>]";
+
+/// Options of the `eval` command
+struct EvalArgs {
+    suite: PathBuf,
+    model: Option<String>,
+    junit: bool,
+    output: Option<PathBuf>,
+    min_pass_rate: f64,
+}
+
+impl EvalArgs {
+    fn parse(args: &[String]) -> anyhow::Result<Self> {
+        let mut suite = None;
+        let mut model = None;
+        let mut junit = false;
+        let mut output = None;
+        let mut min_pass_rate = 1.0;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("{} needs a value\n{}", name, USAGE))
+            };
+            match arg.as_str() {
+                "--model" => model = Some(value("--model")?),
+                "--format" => {
+                    junit = match value("--format")?.as_str() {
+                        "json" => false,
+                        "junit" => true,
+                        other => anyhow::bail!("Formats are json or junit, got {}", other),
+                    }
+                }
+                "--output" => output = Some(PathBuf::from(value("--output")?)),
+                "--min-pass-rate" => {
+                    min_pass_rate = value("--min-pass-rate")?
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|rate| (0.0..=1.0).contains(rate))
+                        .ok_or_else(|| anyhow::anyhow!("--min-pass-rate must be a number from 0 to 1"))?;
+                }
+                "-h" | "--help" => anyhow::bail!("{}", USAGE),
+                flag if flag.starts_with('-') => anyhow::bail!("Unknown option {}\n{}", flag, USAGE),
+                path if suite.is_none() => suite = Some(PathBuf::from(path)),
+                _ => anyhow::bail!("Only one suite can be given\n{}", USAGE),
+            }
+        }
+
+        Ok(Self {
+            suite: suite.ok_or_else(|| anyhow::anyhow!("{}", USAGE))?,
+            model,
+            junit,
+            output,
+            min_pass_rate,
+        })
+    }
+}
+
+/// Runs an evaluation suite against the configured providers and writes the
+/// report to stdout or `--output`. Fails when fewer cases than
+/// `--min-pass-rate` pass, all of them by default.
+///
+/// No database is needed: usage is counted in memory, and tools, uploaded
+/// files and retrieval aren't available. Neither is the response cache, so
+/// every case reaches the model. Redaction, moderation and context window
+/// settings apply as in the server.
+pub async fn run(config: &Config, args: &[String]) -> anyhow::Result<ExitCode> {
+    let args = EvalArgs::parse(args)?;
+    let suite = EvalSuite::load(&args.suite)?;
+
+    let models = create_model_router(&config.ai_routing, &config.gemini_api_key)?;
+    if let Some(model) = &args.model {
+        models
+            .resolve(Some(model))
+            .map_err(|e| anyhow::anyhow!("Invalid --model: {}", e))?;
+    }
+    let moderation = ModerationPipeline::new(&config.ai_moderation, &models)?;
+    let storage = Arc::new(OfflineStorage::new());
+    let ai_service = AIService::new(
+        models,
+        Arc::new(MemoryAiUsageRepository::new()),
+        None,
+        storage.clone(),
+        storage.clone(),
+        config.ai_attachments.clone(),
+        ContextWindow::new(&config.ai_context)?,
+        Redactor::new(&config.ai_redaction)?,
+        moderation,
+        storage,
+        None,
+        ToolRegistry::new(),
+        config.ai_tool_max_steps,
+    );
+
+    let report = EvalRunner::new(ai_service, eval_tenant())
+        .run(suite, args.model.as_deref())
+        .await;
+    tracing::info!(
+        suite = %report.suite,
+        passed = report.passed,
+        failed = report.failed,
+        errors = report.errors,
+        "Eval finished"
+    );
+
+    let output = match args.junit {
+        true => report.to_junit(),
+        false => serde_json::to_string_pretty(&report)? + "\n",
+    };
+    match &args.output {
+        Some(path) => std::fs::write(path, output)
+            .map_err(|e| anyhow::anyhow!("Can't write {}: {}", path.display(), e))?,
+        None => print!("{}", output),
+    }
+
+    Ok(match report.pass_rate >= args.min_pass_rate {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    })
+}
+
+/// Cases run as an API key of a throwaway organization
+fn eval_tenant() -> TenantContext {
+    let now = Utc::now();
+    let organization = Organization {
+        id: Uuid::new_v4(),
+        name: "Eval".to_string(),
+        slug: "eval".to_string(),
+        created_at: now,
+        updated_at: now,
+    };
+    TenantContext {
+        principal: Principal::ApiKey(ApiKeyPrincipal {
+            key_id: Uuid::new_v4(),
+            name: "eval".to_string(),
+            organization_id: Some(organization.id),
+            scopes: vec![ApiScope::AiInvoke],
+        }),
+        organization,
+        role: None,
+    }
+}
@@ -1,3 +1,5 @@
+pub mod eval;
+pub mod rate_limit;
 pub mod router;
 pub mod state;
 
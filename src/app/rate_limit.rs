@@ -0,0 +1,102 @@
+use axum::{
+    extract::{ConnectInfo, OptionalFromRequestParts, Request, State},
+    http::{request::Parts, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::net::SocketAddr;
+
+use crate::{
+    features::auth::model::Principal,
+    shared::error::AppError,
+    shared::rate_limit::RateLimitDecision,
+    app::state::AppState,
+};
+
+/// Route group a path is limited under, see `RateLimitConfig`
+fn route_group(path: &str) -> Option<&'static str> {
+    let group = match path.split('/').nth(1)? {
+        "auth" => "auth",
+        "users" | "organizations" => "users",
+        "ai" => "ai",
+        "graphql" => "graphql",
+        _ => return None,
+    };
+    Some(group)
+}
+
+/// Authenticated requests are limited per API key or user, anonymous ones and
+/// requests with an invalid token per client IP
+async fn client_key(parts: &mut Parts, state: &AppState) -> String {
+    match <Principal as OptionalFromRequestParts<AppState>>::from_request_parts(parts, state).await {
+        Ok(Some(principal)) => principal_key(&principal),
+        _ => format!("ip:{}", client_ip(parts, state)),
+    }
+}
+
+/// Bucket key of an authenticated client, also used for work that arrives
+/// outside HTTP requests such as WebSocket frames
+pub fn principal_key(principal: &Principal) -> String {
+    match principal {
+        Principal::ApiKey(key) => format!("key:{}", key.key_id),
+        Principal::User(auth) => format!("user:{}", auth.user.id),
+    }
+}
+
+fn client_ip(parts: &Parts, state: &AppState) -> String {
+    let forwarded = state
+        .rate_limiter
+        .trust_forwarded_for()
+        .then(|| parts.headers.get("x-forwarded-for"))
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|ip| ip.trim().to_string());
+
+    forwarded
+        .or_else(|| {
+            parts
+                .extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Token-bucket rate limiting per route group. Adds `RateLimit-Limit`,
+/// `RateLimit-Remaining` and `RateLimit-Reset` to limited routes and answers
+/// `429` with `Retry-After` once the bucket is empty.
+pub async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(group) = route_group(request.uri().path()) else {
+        return next.run(request).await;
+    };
+
+    // The principal is cached in the extensions, handlers don't authenticate twice
+    let (mut parts, body) = request.into_parts();
+    let client = client_key(&mut parts, &state).await;
+    let request = Request::from_parts(parts, body);
+
+    let Some(decision) = state.rate_limiter.check(group, &client).await else {
+        return next.run(request).await;
+    };
+
+    let mut response = if decision.allowed {
+        next.run(request).await
+    } else {
+        tracing::debug!(group, client = %client, "rate limit exceeded");
+        AppError::TooManyRequests {
+            retry_after: decision.retry_after,
+        }
+        .into_response()
+    };
+
+    add_headers(&mut response, &decision);
+    response
+}
+
+fn add_headers(response: &mut Response, decision: &RateLimitDecision) {
+    let headers = response.headers_mut();
+    headers.insert("ratelimit-limit", HeaderValue::from(decision.limit));
+    headers.insert("ratelimit-remaining", HeaderValue::from(decision.remaining));
+    headers.insert("ratelimit-reset", HeaderValue::from(decision.reset_after));
+}
@@ -1,13 +1,18 @@
 use axum::{
-    extract::Extension,
+    extract::{DefaultBodyLimit, Extension},
+    http::HeaderMap,
+    middleware,
     response::{Html, IntoResponse},
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 use async_graphql::{http::GraphiQLSource, EmptySubscription, Schema};
 use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
 
-use utoipa::OpenApi;
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
@@ -16,9 +21,51 @@ use crate::{
         MutationRoot, QueryRoot, AppSchema,
     },
     features::user_management::model::{CreateUserRequest, UpdateUserRequest, UserResponse},
-    features::ai_integration::api::{chat, chat_stream, generate},
-    features::ai_integration::model::{ChatRequest, ChatResponse, GenerateRequest, GenerateResponse},
-    entities::user::User,
+    features::ai_integration::api::{
+        batch_results, cancel_batch, chat, chat_stream, chat_ws, count_tokens, create_batch, create_template, delete_document, delete_file, embed, generate, get_batch, get_template,
+        get_request_log, ingest_document, list_files, list_models, list_request_logs, list_templates, list_tools, moderation_flags,
+        redactions, render_and_generate, replay_request_log, review_moderation_flag, search_documents,
+        store_documents, upload_file, usage, explain_user_activity, find_duplicate_users, suggest_user_bio,
+    },
+    features::ai_integration::model::{
+        AiRedactionSummary, AiUsageSummary, ChatClientFrame, ChatRequest, ChatResponse, ChatServerFrame, Citation, ContextUsage, CountTokensRequest, CountTokensResponse,
+        CreateBatchRequest, CreatePromptTemplateRequest, DocumentInput, EmbedRequest, EmbedResponse, GenerateRequest, GenerateResponse,
+        IngestDocumentRequest, IngestDocumentResponse, ModelInfo, ModelListResponse, RagOptions, ReplayRequest, ReplayResponse, DiffSegment, DiffOp,
+        ReviewModerationFlagRequest, RouteInfo, RenderTemplateRequest, SearchRequest, SearchResponse,
+        StoreDocumentsRequest, StoreDocumentsResponse, TemplateGenerateResponse, ToolInfo, ToolInvocation,
+        BioSuggestionRequest, BioSuggestionResponse, DuplicateUser, DuplicateUsersResponse, ExplainActivityRequest,
+        ActivityExplanationResponse,
+    },
+    features::organizations::api::{
+        accept_invitation, add_member, create_organization, decline_invitation, list_invitations, list_members,
+        list_organizations, update_member,
+    },
+    features::organizations::model::{
+        AddMemberRequest, CreateOrganizationRequest, InvitationResponse, MemberResponse, TenantSelector,
+        UpdateMemberRequest, UserOrganizationResponse,
+    },
+    features::auth::api::{
+        confirm_email_verification, confirm_password_reset, confirm_two_factor,
+        create_api_key, disable_two_factor, list_api_keys, revoke_api_key, enroll_two_factor, login, login_two_factor, logout, me,
+        update_me,
+        oidc_authorize, oidc_callback, oidc_providers,
+        regenerate_recovery_codes, request_email_verification, request_password_reset, step_up,
+    },
+    features::auth::model::{
+        ApiKeyResponse, ApiScope, ConfirmEmailVerificationRequest, ConfirmPasswordResetRequest,
+        CreateApiKeyRequest, CreatedApiKeyResponse, Principal,
+        EmailVerificationRequest, LoginRequest, LoginResponse, OidcProviderResponse,
+        PasswordResetRequest, RecoveryCodesResponse, TwoFactorCodeRequest, TwoFactorEnrollmentResponse,
+        TwoFactorLoginRequest, UpdateProfileRequest,
+    },
+    entities::ai::{
+        AiBatch, AiBatchResult, AiFile, AiRequestLog, BatchStatus, Candidate, CitationSource, ContextStrategy, DocumentFormat, EmbeddedDocument, EmbeddingMatch,
+        EmbeddingTask, JobStatus, ModerationAction, ModerationFlag, ModerationStage, PromptTemplate,
+    },
+    entities::organization::{OrgRole, Organization},
+    entities::user::{Role, User, UserActivity},
+    shared::error::SafetyRating,
+    app::rate_limit::rate_limit,
     app::state::AppState,
 };
 
@@ -33,19 +80,123 @@ use crate::{
         crate::features::ai_integration::api::rest::chat,
         crate::features::ai_integration::api::rest::generate,
         crate::features::ai_integration::api::rest::chat_stream,
+        crate::features::ai_integration::api::ws::chat_ws,
+        crate::features::ai_integration::api::rest::list_tools,
+        crate::features::ai_integration::api::rest::list_models,
+        crate::features::ai_integration::api::rest::count_tokens,
+        crate::features::ai_integration::api::rest::upload_file,
+        crate::features::ai_integration::api::rest::list_files,
+        crate::features::ai_integration::api::rest::delete_file,
+        crate::features::ai_integration::api::rest::usage,
+        crate::features::ai_integration::api::rest::redactions,
+        crate::features::ai_integration::api::rest::moderation_flags,
+        crate::features::ai_integration::api::rest::review_moderation_flag,
+        crate::features::ai_integration::api::rest::list_request_logs,
+        crate::features::ai_integration::api::rest::get_request_log,
+        crate::features::ai_integration::api::rest::replay_request_log,
+        crate::features::ai_integration::api::rest::suggest_user_bio,
+        crate::features::ai_integration::api::rest::find_duplicate_users,
+        crate::features::ai_integration::api::rest::explain_user_activity,
+        crate::features::ai_integration::api::rest::create_template,
+        crate::features::ai_integration::api::rest::list_templates,
+        crate::features::ai_integration::api::rest::get_template,
+        crate::features::ai_integration::api::rest::render_and_generate,
+        crate::features::ai_integration::api::rest::embed,
+        crate::features::ai_integration::api::rest::store_documents,
+        crate::features::ai_integration::api::rest::ingest_document,
+        crate::features::ai_integration::api::rest::delete_document,
+        crate::features::ai_integration::api::rest::search_documents,
+        crate::features::ai_integration::api::rest::create_batch,
+        crate::features::ai_integration::api::rest::get_batch,
+        crate::features::ai_integration::api::rest::batch_results,
+        crate::features::ai_integration::api::rest::cancel_batch,
+        crate::features::organizations::api::rest::create_organization,
+        crate::features::organizations::api::rest::list_organizations,
+        crate::features::organizations::api::rest::list_members,
+        crate::features::organizations::api::rest::add_member,
+        crate::features::organizations::api::rest::update_member,
+        crate::features::organizations::api::rest::list_invitations,
+        crate::features::organizations::api::rest::accept_invitation,
+        crate::features::organizations::api::rest::decline_invitation,
+        crate::features::auth::api::rest::request_email_verification,
+        crate::features::auth::api::rest::confirm_email_verification,
+        crate::features::auth::api::rest::request_password_reset,
+        crate::features::auth::api::rest::confirm_password_reset,
+        crate::features::auth::api::rest::login,
+        crate::features::auth::api::rest::login_two_factor,
+        crate::features::auth::api::rest::logout,
+        crate::features::auth::api::rest::me,
+        crate::features::auth::api::rest::update_me,
+        crate::features::auth::api::rest::enroll_two_factor,
+        crate::features::auth::api::rest::confirm_two_factor,
+        crate::features::auth::api::rest::disable_two_factor,
+        crate::features::auth::api::rest::regenerate_recovery_codes,
+        crate::features::auth::api::rest::step_up,
+        crate::features::auth::api::rest::oidc_providers,
+        crate::features::auth::api::rest::oidc_authorize,
+        crate::features::auth::api::rest::oidc_callback,
+        crate::features::auth::api::rest::create_api_key,
+        crate::features::auth::api::rest::list_api_keys,
+        crate::features::auth::api::rest::revoke_api_key,
     ),
     components(
-        schemas(User, CreateUserRequest, UpdateUserRequest, UserResponse, ChatRequest, ChatResponse, GenerateRequest, GenerateResponse)
+        schemas(
+            User, Role, CreateUserRequest, UpdateUserRequest, UserResponse, ChatRequest, ChatResponse, ChatClientFrame, ChatServerFrame, GenerateRequest, GenerateResponse,
+            EmailVerificationRequest, ConfirmEmailVerificationRequest, PasswordResetRequest, ConfirmPasswordResetRequest,
+            LoginRequest, LoginResponse, TwoFactorLoginRequest, TwoFactorCodeRequest, TwoFactorEnrollmentResponse, RecoveryCodesResponse, UpdateProfileRequest,
+            OidcProviderResponse, CreateApiKeyRequest, CreatedApiKeyResponse, ApiKeyResponse, ApiScope,
+            Organization, OrgRole, CreateOrganizationRequest, UserOrganizationResponse, AddMemberRequest,
+            UpdateMemberRequest, MemberResponse, InvitationResponse, AiUsageSummary, AiRedactionSummary,
+            PromptTemplate, CreatePromptTemplateRequest, RenderTemplateRequest, TemplateGenerateResponse,
+            AiFile, ToolInfo, ToolInvocation,
+            EmbedRequest, EmbedResponse, EmbeddingTask, StoreDocumentsRequest, DocumentInput, StoreDocumentsResponse,
+            EmbeddedDocument, SearchRequest, SearchResponse, EmbeddingMatch,
+            IngestDocumentRequest, IngestDocumentResponse, DocumentFormat, RagOptions, Citation,
+            Candidate, CitationSource, SafetyRating,
+            ContextStrategy, ContextUsage,
+            ModelInfo, RouteInfo, ModelListResponse, CountTokensRequest, CountTokensResponse,
+            CreateBatchRequest, AiBatch, AiBatchResult, BatchStatus, JobStatus,
+            ModerationFlag, ModerationStage, ModerationAction, ReviewModerationFlagRequest,
+            AiRequestLog, ReplayRequest, ReplayResponse, DiffSegment, DiffOp,
+            BioSuggestionRequest, BioSuggestionResponse, DuplicateUser, DuplicateUsersResponse,
+            ExplainActivityRequest, ActivityExplanationResponse, UserActivity
+        )
     ),
+    modifiers(&SecurityAddon),
     tags(
         (name = "users", description = "User management endpoints"),
+        (name = "organizations", description = "Organizations (tenants) and memberships"),
+        (name = "auth", description = "Login, SSO, two-factor authentication, API keys, email verification and password reset"),
         (name = "AI", description = "AI-powered endpoints using Gemini")
     )
 )]
 struct ApiDoc;
 
-async fn graphql_handler(schema: Extension<AppSchema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+async fn graphql_handler(
+    schema: Extension<AppSchema>,
+    principal: Option<Principal>,
+    headers: HeaderMap,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    // The tenant is resolved by the resolvers that need one
+    let mut req = req.into_inner().data(TenantSelector::from_headers(&headers));
+    if let Some(principal) = principal {
+        req = req.data(principal);
+    }
+    schema.execute(req).await.into()
 }
 
 async fn graphql_playground() -> impl IntoResponse {
@@ -56,16 +207,82 @@ pub fn create_router(state: AppState) -> Router {
     let schema = Schema::build(QueryRoot, MutationRoot, EmptySubscription)
         .data(state.user_service.clone())
         .data(state.ai_service.clone())
+        .data(state.user_insights_service.clone())
+        .data(state.auth_service.clone())
+        .data(state.oidc_service.clone())
+        .data(state.organization_service.clone())
         .finish();
+    let ai_body_limit = state.ai_service.max_body_bytes();
 
     Router::new()
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .route("/users", get(get_users).post(create_user))
         .route("/users/{id}", get(get_user).put(update_user).delete(delete_user))
-        .route("/ai/chat", post(chat))
+        .route("/ai/chat", post(chat).layer(DefaultBodyLimit::max(ai_body_limit)))
         .route("/ai/generate", post(generate))
-        .route("/ai/chat/stream", post(chat_stream))
-        .route("/graphql", get(graphql_playground).post(graphql_handler))
+        .route("/ai/chat/stream", post(chat_stream).layer(DefaultBodyLimit::max(ai_body_limit)))
+        .route("/ai/chat/ws", get(chat_ws))
+        .route(
+            "/ai/files",
+            get(list_files).post(upload_file).layer(DefaultBodyLimit::max(ai_body_limit)),
+        )
+        .route("/ai/files/{id}", delete(delete_file))
+        .route("/ai/tools", get(list_tools))
+        .route("/ai/models", get(list_models))
+        .route("/ai/count-tokens", post(count_tokens).layer(DefaultBodyLimit::max(ai_body_limit)))
+        .route("/ai/usage", get(usage))
+        .route("/ai/redactions", get(redactions))
+        .route("/ai/moderation/flags", get(moderation_flags))
+        .route("/ai/moderation/flags/{id}/review", post(review_moderation_flag))
+        .route("/ai/logs", get(list_request_logs))
+        .route("/ai/logs/{id}", get(get_request_log))
+        .route("/ai/logs/{id}/replay", post(replay_request_log))
+        .route("/ai/users/{id}/bio", post(suggest_user_bio))
+        .route("/ai/users/{id}/duplicates", get(find_duplicate_users))
+        .route("/ai/users/{id}/activity/explain", post(explain_user_activity))
+        .route("/ai/templates", get(list_templates).post(create_template))
+        .route("/ai/templates/{name}", get(get_template))
+        .route("/ai/templates/{name}/render-and-generate", post(render_and_generate))
+        .route("/ai/embeddings", post(embed))
+        .route("/ai/collections/{collection}/documents", put(store_documents))
+        .route("/ai/collections/{collection}/documents/{id}", delete(delete_document))
+        .route("/ai/collections/{collection}/ingest", post(ingest_document))
+        .route("/ai/collections/{collection}/search", post(search_documents))
+        .route("/ai/batches", post(create_batch).layer(DefaultBodyLimit::max(ai_body_limit)))
+        .route("/ai/batches/{id}", get(get_batch))
+        .route("/ai/batches/{id}/results", get(batch_results))
+        .route("/ai/batches/{id}/cancel", post(cancel_batch))
+        .route("/organizations", get(list_organizations).post(create_organization))
+        .route("/organizations/{id}/members", get(list_members).post(add_member))
+        .route("/organizations/{id}/members/{user_id}", put(update_member))
+        .route("/organizations/invitations", get(list_invitations))
+        .route("/organizations/invitations/{id}/accept", post(accept_invitation))
+        .route("/organizations/invitations/{id}/decline", post(decline_invitation))
+        .route("/auth/email-verification", post(request_email_verification))
+        .route("/auth/email-verification/confirm", post(confirm_email_verification))
+        .route("/auth/password-reset", post(request_password_reset))
+        .route("/auth/password-reset/confirm", post(confirm_password_reset))
+        .route("/auth/login", post(login))
+        .route("/auth/login/2fa", post(login_two_factor))
+        .route("/auth/logout", post(logout))
+        .route("/auth/me", get(me).put(update_me))
+        .route("/auth/2fa/enroll", post(enroll_two_factor))
+        .route("/auth/2fa/confirm", post(confirm_two_factor))
+        .route("/auth/2fa/disable", post(disable_two_factor))
+        .route("/auth/2fa/recovery-codes", post(regenerate_recovery_codes))
+        .route("/auth/2fa/step-up", post(step_up))
+        .route("/auth/oidc/providers", get(oidc_providers))
+        .route("/auth/oidc/{provider}/authorize", get(oidc_authorize))
+        .route("/auth/oidc/{provider}/callback", get(oidc_callback))
+        .route("/auth/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/auth/api-keys/{id}", delete(revoke_api_key))
+        .route(
+            "/graphql",
+            get(graphql_playground)
+                .post(graphql_handler)
+                .layer(DefaultBodyLimit::max(ai_body_limit)),
+        )
         .layer(Extension(schema))
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
         .with_state(state)
 }
@@ -1,17 +1,54 @@
 use crate::features::user_management::domain::UserService;
-use crate::features::ai_integration::domain::AIService;
+use crate::features::ai_integration::domain::{AIService, BatchService, EmbeddingService, PromptTemplateService, RequestLogService, UserInsightsService};
+use crate::features::auth::domain::{ApiKeyService, AuthService, OidcService};
+use crate::features::organizations::domain::OrganizationService;
+use crate::shared::rate_limit::RateLimiter;
 
 #[derive(Clone)]
 pub struct AppState {
     pub user_service: UserService,
     pub ai_service: AIService,
+    pub template_service: PromptTemplateService,
+    pub embedding_service: EmbeddingService,
+    pub batch_service: BatchService,
+    pub request_log_service: RequestLogService,
+    pub user_insights_service: UserInsightsService,
+    pub auth_service: AuthService,
+    pub oidc_service: OidcService,
+    pub api_key_service: ApiKeyService,
+    pub organization_service: OrganizationService,
+    pub rate_limiter: RateLimiter,
 }
 
 impl AppState {
-    pub fn new(user_service: UserService, ai_service: AIService) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        user_service: UserService,
+        ai_service: AIService,
+        template_service: PromptTemplateService,
+        embedding_service: EmbeddingService,
+        batch_service: BatchService,
+        request_log_service: RequestLogService,
+        user_insights_service: UserInsightsService,
+        auth_service: AuthService,
+        oidc_service: OidcService,
+        api_key_service: ApiKeyService,
+        organization_service: OrganizationService,
+        rate_limiter: RateLimiter,
+    ) -> Self {
         Self {
             user_service,
             ai_service,
+            template_service,
+            embedding_service,
+            batch_service,
+            request_log_service,
+            user_insights_service,
+            auth_service,
+            oidc_service,
+            api_key_service,
+            organization_service,
+            rate_limiter,
         }
     }
 }
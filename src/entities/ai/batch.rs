@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Progress of a batch as a whole
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum BatchStatus {
+    /// No job has started yet
+    Pending,
+    Running,
+    /// Every job succeeded or failed
+    Completed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Waiting for a worker, also between retries
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// Generate requests answered in the background, with job counts by status
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct AiBatch {
+    pub id: Uuid,
+    pub status: BatchStatus,
+    pub total: i64,
+    pub pending: i64,
+    pub running: i64,
+    pub succeeded: i64,
+    pub failed: i64,
+    pub cancelled: i64,
+    pub created_at: DateTime<Utc>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    /// When the last job finished, once all have
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of one request, a line of the results stream
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct AiBatchResult {
+    /// Position of the request in the batch
+    #[sqlx(rename = "position")]
+    pub index: i32,
+    pub status: JobStatus,
+    pub attempts: i32,
+    /// The `GenerateResponse` of a succeeded job
+    #[schema(value_type = Option<Object>)]
+    pub response: Option<serde_json::Value>,
+    pub error: Option<String>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,42 @@
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::shared::error::SafetyRating;
+
+/// One of the answers the model generated for a request
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject, ToSchema)]
+pub struct Candidate {
+    /// Position among the candidates, from 0
+    pub index: i32,
+    /// All text parts joined, empty when the candidate was blocked
+    pub text: String,
+    /// Gemini `finishReason`, e.g. STOP, MAX_TOKENS or SAFETY
+    pub finish_reason: String,
+    pub safety_ratings: Vec<SafetyRating>,
+    /// Sources the text quotes from
+    pub citation_sources: Vec<CitationSource>,
+}
+
+/// A quoted passage, Gemini `citationSources`
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject, ToSchema)]
+pub struct CitationSource {
+    /// Byte range of the quote in the candidate's text
+    pub start_index: Option<i32>,
+    pub end_index: Option<i32>,
+    pub uri: Option<String>,
+    pub license: Option<String>,
+}
+
+impl Candidate {
+    /// A finished answer without ratings or citations, e.g. from providers that report none
+    pub fn answer(text: &str, finish_reason: &str) -> Self {
+        Self {
+            index: 0,
+            text: text.to_string(),
+            finish_reason: finish_reason.to_string(),
+            safety_ratings: Vec::new(),
+            citation_sources: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use utoipa::ToSchema;
+
+/// What an embedding is used for, Gemini `taskType`. Documents and the
+/// queries searching them should use the matching retrieval tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum EmbeddingTask {
+    RetrievalDocument,
+    RetrievalQuery,
+    #[default]
+    SemanticSimilarity,
+    Classification,
+    Clustering,
+}
+
+/// How ingested content is turned into plain text before chunking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentFormat {
+    #[default]
+    Text,
+    /// Split preferably before headings
+    Markdown,
+    /// Tags, scripts and styles are removed
+    Html,
+}
+
+/// A document stored for semantic search, without its vector
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct EmbeddedDocument {
+    pub collection: String,
+    /// Caller chosen id, unique within the collection. Chunks of ingested documents are `<source_id>#<chunk_index>`.
+    #[sqlx(rename = "document_id")]
+    pub id: String,
+    /// Ingested document the chunk belongs to
+    pub source_id: Option<String>,
+    pub chunk_index: Option<i32>,
+    pub content: String,
+    #[schema(value_type = Object)]
+    pub metadata: serde_json::Value,
+    /// Model that embedded the content
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A search result, `score` is the cosine similarity to the query from -1 to 1
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct EmbeddingMatch {
+    #[sqlx(rename = "document_id")]
+    pub id: String,
+    pub source_id: Option<String>,
+    pub chunk_index: Option<i32>,
+    pub content: String,
+    #[schema(value_type = Object)]
+    pub metadata: serde_json::Value,
+    pub score: f64,
+}
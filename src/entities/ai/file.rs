@@ -0,0 +1,17 @@
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// A file uploaded for use in chat messages. The content is only read when a
+/// message references it.
+#[derive(Debug, Clone, Serialize, FromRow, SimpleObject, ToSchema)]
+pub struct AiFile {
+    pub id: Uuid,
+    pub filename: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
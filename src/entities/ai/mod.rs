@@ -1,3 +1,16 @@
+mod batch;
+mod candidate;
+mod embedding;
+mod file;
 mod model;
-
-pub use model::ChatMessage;
+mod moderation;
+mod request_log;
+mod template;
+pub use batch::{AiBatch, AiBatchResult, BatchStatus, JobStatus};
+pub use candidate::{Candidate, CitationSource};
+pub use embedding::{DocumentFormat, EmbeddedDocument, EmbeddingMatch, EmbeddingTask};
+pub use file::AiFile;
+pub use model::{ChatMessage, ChatRole, ContextStrategy, InlineData, MessagePart, SafetySetting};
+pub use moderation::{ModerationAction, ModerationFlag, ModerationStage};
+pub use request_log::AiRequestLog;
+pub use template::{PromptTemplate, TemplateVariable, VariableType};
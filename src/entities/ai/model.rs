@@ -1,26 +1,81 @@
 use serde::{Deserialize, Serialize};
-use async_graphql::InputObject;
+use async_graphql::{Enum, InputObject, OneofObject};
 use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Speaker of a chat turn. System prompts go in `system_instruction`, not in the history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    User,
+    /// Also accepted as "assistant"
+    #[serde(alias = "assistant")]
+    Model,
+}
+
+/// How a history too long for the model's context window is shortened.
+/// The system instruction and the current message are always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Leaves out the oldest turns
+    DropOldest,
+    /// Replaces the oldest turns with a summary written by the model
+    Summarize,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, InputObject, ToSchema)]
 pub struct ChatMessage {
-    pub role: String,
+    pub role: ChatRole,
+    /// Text of the message, may be empty when `parts` are given
+    #[serde(default)]
+    #[graphql(default)]
     pub content: String,
+    /// Further text, images and documents, sent after `content`
+    #[serde(default)]
+    #[graphql(default)]
+    pub parts: Vec<MessagePart>,
+}
+
+/// One piece of a message, e.g. `{"text": "..."}`, `{"inline_data": {...}}` or `{"file_id": "..."}`
+#[derive(Debug, Clone, Serialize, Deserialize, OneofObject, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MessagePart {
+    Text(String),
+    InlineData(InlineData),
+    /// A file uploaded to `/ai/files`
+    FileId(Uuid),
+}
+
+/// File content sent with the request
+#[derive(Debug, Clone, Serialize, Deserialize, InputObject, ToSchema)]
+pub struct InlineData {
+    /// e.g. "image/png" or "application/pdf"
+    pub mime_type: String,
+    /// Base64 encoded content
+    pub data: String,
 }
 
 #[allow(dead_code)]
 impl ChatMessage {
+    /// No text and no parts
+    pub fn is_empty(&self) -> bool {
+        self.content.trim().is_empty() && self.parts.is_empty()
+    }
+
     pub fn user(content: impl Into<String>) -> Self {
         Self {
-            role: "user".to_string(),
+            role: ChatRole::User,
             content: content.into(),
+            parts: Vec::new(),
         }
     }
 
     pub fn assistant(content: impl Into<String>) -> Self {
         Self {
-            role: "model".to_string(),
+            role: ChatRole::Model,
             content: content.into(),
+            parts: Vec::new(),
         }
     }
 }
@@ -49,3 +104,35 @@ impl Default for ChatHistory {
         Self::new()
     }
 }
+
+/// Gemini harm categories that safety settings apply to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Enum, ToSchema)]
+pub enum HarmCategory {
+    #[serde(rename = "HARM_CATEGORY_HARASSMENT")]
+    Harassment,
+    #[serde(rename = "HARM_CATEGORY_HATE_SPEECH")]
+    HateSpeech,
+    #[serde(rename = "HARM_CATEGORY_SEXUALLY_EXPLICIT")]
+    SexuallyExplicit,
+    #[serde(rename = "HARM_CATEGORY_DANGEROUS_CONTENT")]
+    DangerousContent,
+    #[serde(rename = "HARM_CATEGORY_CIVIC_INTEGRITY")]
+    CivicIntegrity,
+}
+
+/// Probability from which content of a category is blocked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum HarmBlockThreshold {
+    BlockLowAndAbove,
+    BlockMediumAndAbove,
+    BlockOnlyHigh,
+    BlockNone,
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, InputObject, ToSchema)]
+pub struct SafetySetting {
+    pub category: HarmCategory,
+    pub threshold: HarmBlockThreshold,
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Which side of a model call was checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationStage {
+    /// The prompt, before it is sent
+    Input,
+    /// The answer, before it is returned
+    Output,
+}
+
+/// What happens to a request once a moderator reports categories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationAction {
+    /// Only written to the log
+    Log,
+    /// Stored for review, the request goes on
+    Flag,
+    /// Stored for review, the request fails
+    Block,
+}
+
+/// A prompt or answer a moderator reported, waiting for or after review
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct ModerationFlag {
+    pub id: Uuid,
+    /// Route the request came through, e.g. chat
+    pub operation: String,
+    pub stage: ModerationStage,
+    /// Flag or block
+    pub action: ModerationAction,
+    pub categories: Vec<String>,
+    /// The checked text, with personal data already redacted
+    pub content: String,
+    pub model: String,
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub reviewed_by: Option<Uuid>,
+    pub review_note: Option<String>,
+}
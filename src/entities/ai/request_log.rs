@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// One call to a model as it was sent and answered
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct AiRequestLog {
+    pub id: Uuid,
+    /// e.g. chat, generate, summarize or replay
+    pub operation: String,
+    /// e.g. gemini, unset for models that are no longer allowed
+    pub provider: Option<String>,
+    /// The model or route the call went to
+    pub requested_model: String,
+    /// The model that answered, differs from the requested one after a fallback
+    pub model: Option<String>,
+    /// Message, history, tool steps and options after redaction, attachments
+    /// replaced by a note of their type and size
+    #[schema(value_type = Object)]
+    pub request: serde_json::Value,
+    pub response: Option<String>,
+    /// Tools the model asked for instead of answering
+    #[schema(value_type = Option<Object>)]
+    pub tool_calls: Option<serde_json::Value>,
+    pub finish_reason: Option<String>,
+    pub error: Option<String>,
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub total_tokens: i32,
+    pub latency_ms: i64,
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
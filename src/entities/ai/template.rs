@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use utoipa::ToSchema;
+
+/// Type a template variable value must have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: VariableType,
+    #[serde(default = "default_required")]
+    pub required: bool,
+    /// Used when an optional variable isn't given
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub default: Option<serde_json::Value>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+/// One version of a prompt template. `body` and `system_instruction` contain
+/// `{{variable}}` placeholders.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PromptTemplate {
+    pub id: Uuid,
+    pub name: String,
+    pub version: i32,
+    pub description: Option<String>,
+    pub system_instruction: Option<String>,
+    pub body: String,
+    pub variables: Vec<TemplateVariable>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
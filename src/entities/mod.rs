@@ -1,2 +1,3 @@
 pub mod user;
 pub mod ai;
+pub mod organization;
@@ -0,0 +1,34 @@
+use async_graphql::{Enum, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use utoipa::ToSchema;
+
+/// Role of a user within one organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, Enum, ToSchema)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Owner,
+    Admin,
+    Member,
+}
+
+impl OrgRole {
+    /// Owners and admins manage members, API keys and users of the organization
+    pub fn can_manage(&self) -> bool {
+        matches!(self, OrgRole::Owner | OrgRole::Admin)
+    }
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, SimpleObject, ToSchema)]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+    /// Used in subdomains and the `X-Organization` header
+    pub slug: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
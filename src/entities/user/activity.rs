@@ -0,0 +1,16 @@
+use async_graphql::SimpleObject;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// Something a user did or that happened to their account, assembled from the
+/// account, session, API key, AI usage and moderation records
+#[derive(Debug, Clone, FromRow, Serialize, SimpleObject, ToSchema)]
+pub struct UserActivity {
+    pub occurred_at: DateTime<Utc>,
+    /// e.g. login, api_key_created or ai_usage
+    pub kind: String,
+    /// Short description, never containing secrets
+    pub detail: String,
+}
@@ -1,3 +1,5 @@
+mod activity;
 mod model;
 
-pub use model::User;
+pub use activity::UserActivity;
+pub use model::{Role, User};
@@ -1,4 +1,4 @@
-use async_graphql::SimpleObject;
+use async_graphql::{Enum, SimpleObject};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -6,11 +6,38 @@ use chrono::{DateTime, Utc};
 
 use utoipa::ToSchema;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, Enum, ToSchema)]
+#[sqlx(type_name = "varchar", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Admin,
+}
+
 #[derive(Debug, FromRow, Deserialize, Serialize, Clone, SimpleObject, ToSchema)]
 pub struct User {
     pub id: Uuid,
     pub name: String,
     pub email: String,
+    pub role: Role,
+    #[serde(skip)]
+    #[graphql(skip)]
+    pub password_hash: Option<String>,
+    /// Base32 TOTP secret, set during enrollment
+    #[serde(skip)]
+    #[graphql(skip)]
+    pub totp_secret: Option<String>,
+    /// False until the user confirms ownership of `email`
+    pub email_verified: bool,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// True once TOTP enrollment has been confirmed with a first code
+    pub two_factor_enabled: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
+
+impl User {
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+}
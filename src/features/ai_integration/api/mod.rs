@@ -1,3 +1,5 @@
 pub mod rest;
+pub mod ws;
 
 pub use rest::*;
+pub use ws::*;
@@ -1,15 +1,30 @@
 use axum::{
-    extract::State,
-    response::{sse::Event, Sse},
+    body::Body,
+    extract::{Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    response::{sse::Event, IntoResponse, Sse},
     Json,
 };
 use futures::stream::{self, Stream};
-use futures::StreamExt;
+use futures::{StreamExt, TryStreamExt};
 use std::convert::Infallible;
 use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use uuid::Uuid;
 
 use crate::{
-    features::ai_integration::model::{ChatRequest, ChatResponse, GenerateRequest, GenerateResponse},
+    entities::ai::{AiBatch, AiBatchResult, AiFile, AiRequestLog, ModerationFlag, PromptTemplate},
+    features::ai_integration::model::{
+        ActivityExplanationResponse, BioSuggestionRequest, BioSuggestionResponse, DuplicateUsersQuery, DuplicateUsersResponse,
+        ExplainActivityRequest, AiRedactionSummary, AiUsageSummary, ChatRequest, ChatResponse, CountTokensRequest, CountTokensResponse, CreateBatchRequest, CreatePromptTemplateRequest, EmbedRequest, EmbedResponse,
+        GenerateRequest, GenerateResponse, IngestDocumentRequest, IngestDocumentResponse, ModelListResponse, ModerationFlagQuery, RenderTemplateRequest, ReplayRequest,
+        ReplayResponse, RequestLogQuery, ReviewModerationFlagRequest, SearchRequest, SearchResponse,
+        StoreDocumentsRequest, StoreDocumentsResponse, TemplateGenerateResponse, TemplateVersionQuery, ToolInfo,
+    },
+    features::auth::model::ApiScope,
+    features::organizations::model::TenantContext,
     shared::error::AppError,
     app::state::AppState,
 };
@@ -21,16 +36,22 @@ use crate::{
     request_body = ChatRequest,
     responses(
         (status = 200, description = "Successful chat response", body = ChatResponse),
-        (status = 400, description = "Bad request"),
+        (status = 400, description = "Bad request, invalid attachment or a model without image and document support"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
         (status = 502, description = "External service error")
     ),
+    security(("bearer" = [])),
     tag = "AI"
 )]
 pub async fn chat(
     State(state): State<AppState>,
+    tenant: TenantContext,
     Json(input): Json<ChatRequest>,
 ) -> Result<Json<ChatResponse>, AppError> {
-    let response = state.ai_service.chat(input).await?;
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let response = state.ai_service.chat(&tenant, input).await?;
     Ok(Json(response))
 }
 
@@ -42,15 +63,21 @@ pub async fn chat(
     responses(
         (status = 200, description = "Successful text generation", body = GenerateResponse),
         (status = 400, description = "Bad request"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
         (status = 502, description = "External service error")
     ),
+    security(("bearer" = [])),
     tag = "AI"
 )]
 pub async fn generate(
     State(state): State<AppState>,
+    tenant: TenantContext,
     Json(input): Json<GenerateRequest>,
 ) -> Result<Json<GenerateResponse>, AppError> {
-    let response = state.ai_service.generate(input).await?;
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let response = state.ai_service.generate(&tenant, input).await?;
     Ok(Json(response))
 }
 
@@ -60,34 +87,903 @@ pub async fn generate(
     path = "/ai/chat/stream",
     request_body = ChatRequest,
     responses(
-        (status = 200, description = "Streaming chat response"),
+        (status = 200, description = "Streaming chat response. Tool calls arrive as `tool_call` events with a ToolInvocation, then a `context` event with ContextUsage when the history was shortened, `citations` for a rag chat and the answer word by word; a failure after the first tool call ends the stream with an `error` event."),
         (status = 400, description = "Bad request"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
         (status = 502, description = "External service error")
     ),
+    security(("bearer" = [])),
     tag = "AI"
 )]
 pub async fn chat_stream(
     State(state): State<AppState>,
+    tenant: TenantContext,
     Json(input): Json<ChatRequest>,
 ) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    // Tool calls are streamed while the chat runs in the background. The task
+    // set lives as long as the response, so a client disconnecting drops it and
+    // aborts the chat and its request to the model.
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let service = state.ai_service.clone();
+    let mut chat = JoinSet::new();
+    chat.spawn(async move { service.chat_with_events(&tenant, input, Some(sender)).await });
+
+    // Until the first tool call, failures are returned as a regular error response
+    let (first_call, finished) = tokio::select! {
+        result = join_chat(&mut chat) => (None, Some(result)),
+        call = receiver.recv() => match call {
+            Some(call) => (Some(call), None),
+            None => (None, Some(join_chat(&mut chat).await)),
+        },
+    };
+    let finished = finished.transpose()?;
+
+    let tool_calls = stream::iter(first_call)
+        .chain(UnboundedReceiverStream::new(receiver))
+        .map(|call| {
+            Event::default()
+                .event("tool_call")
+                .data(serde_json::to_string(&call).unwrap_or_default())
+        });
+
     // For now, we'll simulate streaming by getting the full response
     // and sending it in chunks. In a real implementation, you'd use
     // the Gemini streaming API.
-    let response = state.ai_service.chat(input).await?;
-    
-    // Split response into words for streaming effect
-    let words: Vec<String> = response
-        .response
-        .split_whitespace()
-        .map(|s| s.to_string())
-        .collect();
-
-    let stream = stream::iter(words)
-        .map(|word| Ok(Event::default().data(word)))
-        .then(|event| async move {
-            tokio::time::sleep(Duration::from_millis(50)).await;
-            event
-        });
+    let answer = stream::once(async move {
+        match finished {
+            Some(response) => Ok(response),
+            None => join_chat(&mut chat).await,
+        }
+    })
+    .flat_map(|result| {
+        let events: Vec<Event> = match result {
+            // How the history was shortened and the sources of a rag chat first,
+            // then the response split into words for streaming effect
+            Ok(response) => response
+                .context
+                .strategy
+                .is_some()
+                .then(|| {
+                    Event::default()
+                        .event("context")
+                        .data(serde_json::to_string(&response.context).unwrap_or_default())
+                })
+                .into_iter()
+                .chain((!response.citations.is_empty()).then(|| {
+                    Event::default()
+                        .event("citations")
+                        .data(serde_json::to_string(&response.citations).unwrap_or_default())
+                }))
+                .chain(response.response.split_whitespace().map(|word| Event::default().data(word)))
+                .collect(),
+            Err(e) => {
+                tracing::error!("AI chat stream failed: {}", e);
+                vec![Event::default().event("error").data(e.public_message())]
+            }
+        };
+        stream::iter(events)
+    })
+    .then(|event| async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        event
+    });
+
+    Ok(Sse::new(tool_calls.chain(answer).map(Ok)))
+}
+
+async fn join_chat(chat: &mut JoinSet<Result<ChatResponse, AppError>>) -> Result<ChatResponse, AppError> {
+    match chat.join_next().await {
+        Some(result) => result.map_err(|e| AppError::Internal(e.into()))?,
+        None => Err(AppError::Internal(anyhow::anyhow!("Chat task was already joined"))),
+    }
+}
+
+/// List the tools chat requests can enable
+#[utoipa::path(
+    get,
+    path = "/ai/tools",
+    responses(
+        (status = 200, description = "Available tools with their argument schema", body = Vec<ToolInfo>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn list_tools(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+) -> Result<Json<Vec<ToolInfo>>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    Ok(Json(state.ai_service.tools()))
+}
+
+/// List the models and routes requests can choose from
+#[utoipa::path(
+    get,
+    path = "/ai/models",
+    responses(
+        (status = 200, description = "Allowed models with provider metadata, and the routes", body = ModelListResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn list_models(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+) -> Result<Json<ModelListResponse>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    Ok(Json(state.ai_service.list_models().await))
+}
+
+/// Count the tokens of a prompt before sending it
+#[utoipa::path(
+    post,
+    path = "/ai/count-tokens",
+    request_body = CountTokensRequest,
+    responses(
+        (status = 200, description = "Token count, exact when the provider counted it", body = CountTokensResponse),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn count_tokens(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Json(input): Json<CountTokensRequest>,
+) -> Result<Json<CountTokensResponse>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let response = state.ai_service.count_tokens(&tenant, input).await?;
+    Ok(Json(response))
+}
+
+/// Upload an image or document for use in chat messages
+#[utoipa::path(
+    post,
+    path = "/ai/files",
+    request_body(
+        content_type = "multipart/form-data",
+        description = "A `file` field with the content, its filename and content type"
+    ),
+    responses(
+        (status = 201, description = "File stored, reference it as `{\"file_id\": \"<id>\"}`", body = AiFile),
+        (status = 400, description = "Missing file, unsupported type or too large"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn upload_file(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<AiFile>), AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::Validation(e.body_text()))?
+    {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let filename = field.file_name().unwrap_or("upload").to_string();
+        let mime_type = field
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let data = field
+            .bytes()
+            .await
+            .map_err(|e| AppError::Validation(e.body_text()))?;
+
+        let file = state
+            .ai_service
+            .upload_file(&tenant, filename, mime_type, data.to_vec())
+            .await?;
+        return Ok((StatusCode::CREATED, Json(file)));
+    }
+
+    Err(AppError::Validation("Missing multipart field 'file'".to_string()))
+}
+
+/// List uploaded files
+#[utoipa::path(
+    get,
+    path = "/ai/files",
+    responses(
+        (status = 200, description = "Files of the organization, newest first", body = Vec<AiFile>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn list_files(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+) -> Result<Json<Vec<AiFile>>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let files = state.ai_service.list_files(&tenant).await?;
+    Ok(Json(files))
+}
+
+/// Delete an uploaded file
+#[utoipa::path(
+    delete,
+    path = "/ai/files/{id}",
+    params(("id" = Uuid, Path, description = "File id")),
+    responses(
+        (status = 200, description = "File deleted"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 404, description = "File not found")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn delete_file(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    state.ai_service.delete_file(&tenant, id).await?;
+    Ok(Json(serde_json::json!({ "message": "File deleted" })))
+}
+
+/// AI usage of the current organization
+#[utoipa::path(
+    get,
+    path = "/ai/usage",
+    responses(
+        (status = 200, description = "Requests and tokens per operation and model", body = Vec<AiUsageSummary>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role required")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn usage(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+) -> Result<Json<Vec<AiUsageSummary>>, AppError> {
+    tenant.require_user()?;
+    tenant.require_manager()?;
+
+    let usage = state.ai_service.usage(&tenant).await?;
+    Ok(Json(usage))
+}
+
+/// Personal data redacted from prompts of the current organization
+#[utoipa::path(
+    get,
+    path = "/ai/redactions",
+    responses(
+        (status = 200, description = "Redacted matches per operation and detector", body = Vec<AiRedactionSummary>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role required")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn redactions(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+) -> Result<Json<Vec<AiRedactionSummary>>, AppError> {
+    tenant.require_user()?;
+    tenant.require_manager()?;
+
+    let redactions = state.ai_service.redactions(&tenant).await?;
+    Ok(Json(redactions))
+}
+
+/// Prompts and answers moderation flagged or blocked in the current organization
+#[utoipa::path(
+    get,
+    path = "/ai/moderation/flags",
+    params(ModerationFlagQuery),
+    responses(
+        (status = 200, description = "Flags, newest first", body = Vec<ModerationFlag>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role required")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn moderation_flags(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Query(query): Query<ModerationFlagQuery>,
+) -> Result<Json<Vec<ModerationFlag>>, AppError> {
+    tenant.require_user()?;
+    tenant.require_manager()?;
+
+    let flags = state
+        .ai_service
+        .moderation_flags(&tenant, query.reviewed, query.limit.unwrap_or(100))
+        .await?;
+    Ok(Json(flags))
+}
+
+/// Mark a moderation flag as reviewed
+#[utoipa::path(
+    post,
+    path = "/ai/moderation/flags/{id}/review",
+    params(("id" = Uuid, Path, description = "Flag ID")),
+    request_body = ReviewModerationFlagRequest,
+    responses(
+        (status = 200, description = "Reviewed flag", body = ModerationFlag),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role required"),
+        (status = 404, description = "Flag not found"),
+        (status = 409, description = "Flag already reviewed")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn review_moderation_flag(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ReviewModerationFlagRequest>,
+) -> Result<Json<ModerationFlag>, AppError> {
+    tenant.require_user()?;
+    tenant.require_manager()?;
+
+    let flag = state.ai_service.review_moderation_flag(&tenant, id, payload).await?;
+    Ok(Json(flag))
+}
+
+/// Search logged model calls of the current organization
+#[utoipa::path(
+    get,
+    path = "/ai/logs",
+    params(RequestLogQuery),
+    responses(
+        (status = 200, description = "Logged calls, newest first", body = Vec<AiRequestLog>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role required")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn list_request_logs(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Query(query): Query<RequestLogQuery>,
+) -> Result<Json<Vec<AiRequestLog>>, AppError> {
+    tenant.require_user()?;
+    tenant.require_manager()?;
+
+    let logs = state.request_log_service.search(&tenant, query).await?;
+    Ok(Json(logs))
+}
+
+/// Get a logged model call
+#[utoipa::path(
+    get,
+    path = "/ai/logs/{id}",
+    params(("id" = Uuid, Path, description = "Log ID")),
+    responses(
+        (status = 200, description = "Logged call", body = AiRequestLog),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role required"),
+        (status = 404, description = "Log not found")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn get_request_log(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AiRequestLog>, AppError> {
+    tenant.require_user()?;
+    tenant.require_manager()?;
+
+    let log = state.request_log_service.get(&tenant, id).await?;
+    Ok(Json(log))
+}
+
+/// Send a logged model call again and compare the answers
+#[utoipa::path(
+    post,
+    path = "/ai/logs/{id}/replay",
+    params(("id" = Uuid, Path, description = "Log ID")),
+    request_body = ReplayRequest,
+    responses(
+        (status = 200, description = "Both answers and a word diff", body = ReplayResponse),
+        (status = 400, description = "Unknown model"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role required"),
+        (status = 404, description = "Log not found"),
+        (status = 502, description = "The AI provider failed")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn replay_request_log(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ReplayRequest>,
+) -> Result<Json<ReplayResponse>, AppError> {
+    tenant.require_user()?;
+    tenant.require_manager()?;
+
+    let replay = state.request_log_service.replay(&tenant, id, payload).await?;
+    Ok(Json(replay))
+}
+
+/// Suggest profile bios for a member of the current organization
+#[utoipa::path(
+    post,
+    path = "/ai/users/{id}/bio",
+    params(("id" = Uuid, Path, description = "User database id")),
+    request_body = BioSuggestionRequest,
+    responses(
+        (status = 200, description = "Suggested bios", body = BioSuggestionResponse),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Only owners and admins may ask for other members, API keys need ai:invoke and users:read"),
+        (status = 404, description = "User not found"),
+        (status = 502, description = "External service error")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn suggest_user_bio(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<BioSuggestionRequest>,
+) -> Result<Json<BioSuggestionResponse>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+    tenant.require_scope(ApiScope::UsersRead)?;
+    // Members may ask about themselves, everyone else needs owner or admin
+    if tenant.user().is_none_or(|auth| auth.user.id != id) {
+        tenant.require_manager()?;
+    }
+
+    let response = state.user_insights_service.suggest_bio(&tenant, id, payload).await?;
+    Ok(Json(response))
+}
+
+/// Find members that are likely the same person as a user
+#[utoipa::path(
+    get,
+    path = "/ai/users/{id}/duplicates",
+    params(("id" = Uuid, Path, description = "User database id"), DuplicateUsersQuery),
+    responses(
+        (status = 200, description = "Similar members, most similar first", body = DuplicateUsersResponse),
+        (status = 400, description = "Invalid min_score, or too many members to compare"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role, or an API key with ai:invoke and users:read, required"),
+        (status = 404, description = "User not found"),
+        (status = 502, description = "External service error")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn find_duplicate_users(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+    Query(query): Query<DuplicateUsersQuery>,
+) -> Result<Json<DuplicateUsersResponse>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+    tenant.require_scope(ApiScope::UsersRead)?;
+    tenant.require_manager()?;
+
+    let response = state
+        .user_insights_service
+        .find_duplicates(&tenant, id, query.min_score, query.limit)
+        .await?;
+    Ok(Json(response))
+}
+
+/// Explain a member's recent account activity in plain language
+#[utoipa::path(
+    post,
+    path = "/ai/users/{id}/activity/explain",
+    params(("id" = Uuid, Path, description = "User database id")),
+    request_body = ExplainActivityRequest,
+    responses(
+        (status = 200, description = "Explanation and the events it covers", body = ActivityExplanationResponse),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Only owners and admins may ask for other members, API keys need ai:invoke and users:read"),
+        (status = 404, description = "User not found"),
+        (status = 502, description = "External service error")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn explain_user_activity(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<ExplainActivityRequest>,
+) -> Result<Json<ActivityExplanationResponse>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+    tenant.require_scope(ApiScope::UsersRead)?;
+    // Members may ask about themselves, everyone else needs owner or admin
+    if tenant.user().is_none_or(|auth| auth.user.id != id) {
+        tenant.require_manager()?;
+    }
+
+    let response = state.user_insights_service.explain_activity(&tenant, id, payload).await?;
+    Ok(Json(response))
+}
+
+/// Create a prompt template, or a new version of an existing one
+#[utoipa::path(
+    post,
+    path = "/ai/templates",
+    request_body = CreatePromptTemplateRequest,
+    responses(
+        (status = 201, description = "Template version created", body = PromptTemplate),
+        (status = 400, description = "Invalid name, variables or placeholders"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role required")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn create_template(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Json(input): Json<CreatePromptTemplateRequest>,
+) -> Result<(StatusCode, Json<PromptTemplate>), AppError> {
+    tenant.require_user()?;
+    tenant.require_manager()?;
+
+    let template = state.template_service.create(&tenant, input).await?;
+    Ok((StatusCode::CREATED, Json(template)))
+}
+
+/// List prompt templates, latest version of each
+#[utoipa::path(
+    get,
+    path = "/ai/templates",
+    responses(
+        (status = 200, description = "Templates of the organization", body = Vec<PromptTemplate>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn list_templates(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+) -> Result<Json<Vec<PromptTemplate>>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let templates = state.template_service.list(&tenant).await?;
+    Ok(Json(templates))
+}
+
+/// Get a prompt template
+#[utoipa::path(
+    get,
+    path = "/ai/templates/{name}",
+    params(
+        ("name" = String, Path, description = "Template name"),
+        TemplateVersionQuery
+    ),
+    responses(
+        (status = 200, description = "Template version", body = PromptTemplate),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 404, description = "Template or version not found")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn get_template(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(name): Path<String>,
+    Query(query): Query<TemplateVersionQuery>,
+) -> Result<Json<PromptTemplate>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let template = state.template_service.get(&tenant, &name, query.version).await?;
+    Ok(Json(template))
+}
+
+/// Fill a prompt template and generate text from it
+#[utoipa::path(
+    post,
+    path = "/ai/templates/{name}/render-and-generate",
+    params(("name" = String, Path, description = "Template name")),
+    request_body = RenderTemplateRequest,
+    responses(
+        (status = 200, description = "Generated text with the template version used", body = TemplateGenerateResponse),
+        (status = 400, description = "Missing, unknown or mistyped variables"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 404, description = "Template or version not found"),
+        (status = 502, description = "External service error")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn render_and_generate(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(name): Path<String>,
+    Json(input): Json<RenderTemplateRequest>,
+) -> Result<Json<TemplateGenerateResponse>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let response = state
+        .template_service
+        .render_and_generate(&tenant, &name, input)
+        .await?;
+    Ok(Json(response))
+}
+
+/// Turn texts into embedding vectors
+#[utoipa::path(
+    post,
+    path = "/ai/embeddings",
+    request_body = EmbedRequest,
+    responses(
+        (status = 200, description = "One vector per text", body = EmbedResponse),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 502, description = "External service error")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn embed(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Json(input): Json<EmbedRequest>,
+) -> Result<Json<EmbedResponse>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let response = state.ai_service.embed(&tenant, input).await?;
+    Ok(Json(response))
+}
+
+/// Embed and store documents for semantic search, replacing those with the same id
+#[utoipa::path(
+    put,
+    path = "/ai/collections/{collection}/documents",
+    params(("collection" = String, Path, description = "Collection name, created on first use")),
+    request_body = StoreDocumentsRequest,
+    responses(
+        (status = 200, description = "Stored documents", body = StoreDocumentsResponse),
+        (status = 400, description = "Invalid collection name or documents"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 502, description = "External service error")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn store_documents(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(collection): Path<String>,
+    Json(input): Json<StoreDocumentsRequest>,
+) -> Result<Json<StoreDocumentsResponse>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let response = state.embedding_service.store(&tenant, &collection, input).await?;
+    Ok(Json(response))
+}
+
+/// Split a text, markdown or HTML document into chunks and store their embeddings for retrieval
+#[utoipa::path(
+    post,
+    path = "/ai/collections/{collection}/ingest",
+    params(("collection" = String, Path, description = "Collection name, created on first use")),
+    request_body = IngestDocumentRequest,
+    responses(
+        (status = 200, description = "Stored chunks, replacing those of an earlier version", body = IngestDocumentResponse),
+        (status = 400, description = "Invalid collection name, document or chunking settings"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 502, description = "External service error")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn ingest_document(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(collection): Path<String>,
+    Json(input): Json<IngestDocumentRequest>,
+) -> Result<Json<IngestDocumentResponse>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let response = state.embedding_service.ingest(&tenant, &collection, input).await?;
+    Ok(Json(response))
+}
+
+/// Delete a document, or all chunks of an ingested one, from a collection
+#[utoipa::path(
+    delete,
+    path = "/ai/collections/{collection}/documents/{id}",
+    params(
+        ("collection" = String, Path, description = "Collection name"),
+        ("id" = String, Path, description = "Document id, or the id of an ingested document")
+    ),
+    responses(
+        (status = 204, description = "Document deleted"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 404, description = "Document not found")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn delete_document(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path((collection, id)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    state.embedding_service.delete(&tenant, &collection, &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Find the documents of a collection closest in meaning to a query
+#[utoipa::path(
+    post,
+    path = "/ai/collections/{collection}/search",
+    params(("collection" = String, Path, description = "Collection name")),
+    request_body = SearchRequest,
+    responses(
+        (status = 200, description = "Top matches with their cosine similarity", body = SearchResponse),
+        (status = 400, description = "Bad request"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 502, description = "External service error")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn search_documents(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(collection): Path<String>,
+    Json(input): Json<SearchRequest>,
+) -> Result<Json<SearchResponse>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let response = state.ai_service.search(&tenant, &collection, input).await?;
+    Ok(Json(response))
+}
+
+/// Queue generate requests to be answered in the background
+#[utoipa::path(
+    post,
+    path = "/ai/batches",
+    request_body = CreateBatchRequest,
+    responses(
+        (status = 202, description = "Batch queued", body = AiBatch),
+        (status = 400, description = "Invalid item, unknown model or too many items"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 429, description = "The organization has too many batch jobs waiting or running")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn create_batch(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Json(input): Json<CreateBatchRequest>,
+) -> Result<(StatusCode, Json<AiBatch>), AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let batch = state.batch_service.create(&tenant, input).await?;
+    Ok((StatusCode::ACCEPTED, Json(batch)))
+}
+
+/// Get the status of a batch with its jobs counted by status
+#[utoipa::path(
+    get,
+    path = "/ai/batches/{id}",
+    params(("id" = Uuid, Path, description = "Batch id")),
+    responses(
+        (status = 200, description = "Batch status", body = AiBatch),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 404, description = "Batch not found")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn get_batch(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AiBatch>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let batch = state.batch_service.get(&tenant, id).await?;
+    Ok(Json(batch))
+}
+
+/// Stream the results of the finished jobs of a batch as NDJSON, one line per job in order
+#[utoipa::path(
+    get,
+    path = "/ai/batches/{id}/results",
+    params(("id" = Uuid, Path, description = "Batch id")),
+    responses(
+        (status = 200, description = "One `AiBatchResult` per line", body = AiBatchResult, content_type = "application/x-ndjson"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 404, description = "Batch not found")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn batch_results(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let results = state.batch_service.results(&tenant, id).await?;
+    let lines = results.and_then(|result| async move {
+        let mut line = serde_json::to_vec(&result).map_err(|e| AppError::Internal(e.into()))?;
+        line.push(b'\n');
+        Ok(line)
+    });
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], Body::from_stream(lines)))
+}
+
+/// Cancel the jobs of a batch that haven't finished
+#[utoipa::path(
+    post,
+    path = "/ai/batches/{id}/cancel",
+    params(("id" = Uuid, Path, description = "Batch id")),
+    responses(
+        (status = 200, description = "Batch cancelled", body = AiBatch),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope"),
+        (status = 404, description = "Batch not found"),
+        (status = 409, description = "Batch already completed")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn cancel_batch(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<AiBatch>, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
 
-    Ok(Sse::new(stream))
+    let batch = state.batch_service.cancel(&tenant, id).await?;
+    Ok(Json(batch))
 }
@@ -0,0 +1,338 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::Response,
+};
+use std::ops::ControlFlow;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::task::JoinSet;
+use tokio::time::{Instant, MissedTickBehavior};
+
+use crate::{
+    features::ai_integration::domain::AIService,
+    features::ai_integration::model::{ChatClientFrame, ChatResponse, ChatServerFrame, ToolInvocation},
+    features::auth::model::ApiScope,
+    features::organizations::model::TenantContext,
+    shared::error::AppError,
+    shared::rate_limit::RateLimiter,
+    app::rate_limit::principal_key,
+    app::state::AppState,
+};
+
+/// How often the server pings the client
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Connections that sent nothing for this long, not even a pong, are closed
+const IDLE_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// Pause between chunks of an answer
+const CHUNK_DELAY: Duration = Duration::from_millis(50);
+
+/// Chat over a WebSocket, with cancellation
+///
+/// After the upgrade the client sends `ChatClientFrame`s and receives
+/// `ChatServerFrame`s, both JSON text messages. Browsers may pass the token as
+/// `access_token` query parameter.
+#[utoipa::path(
+    get,
+    path = "/ai/chat/ws",
+    responses(
+        (status = 101, description = "Switched to the WebSocket protocol"),
+        (status = 400, description = "Not a WebSocket handshake"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the ai:invoke scope")
+    ),
+    security(("bearer" = [])),
+    tag = "AI"
+)]
+pub async fn chat_ws(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, AppError> {
+    tenant.require_scope(ApiScope::AiInvoke)?;
+
+    let ai_service = state.ai_service.clone();
+    let rate_limiter = state.rate_limiter.clone();
+    Ok(upgrade
+        .max_message_size(ai_service.max_body_bytes())
+        .on_upgrade(move |socket| ChatSocket::new(socket, ai_service, rate_limiter, tenant).run()))
+}
+
+/// The chat of a connection
+enum Chat {
+    Idle,
+    /// Waiting for the model, tool calls are sent as they are made.
+    /// Dropping the task set aborts the chat and its request to the model.
+    Running {
+        id: Option<String>,
+        task: JoinSet<Result<ChatResponse, AppError>>,
+        tool_calls: UnboundedReceiver<ToolInvocation>,
+    },
+    /// Sending the answer in chunks, `sent` bytes of it so far
+    Answering {
+        id: Option<String>,
+        response: ChatResponse,
+        sent: usize,
+        next_chunk_at: Instant,
+    },
+}
+
+enum Event {
+    Received(Option<Result<Message, axum::Error>>),
+    ToolCall(ToolInvocation),
+    Finished(Result<ChatResponse, AppError>),
+    ChunkDue,
+    Heartbeat,
+}
+
+impl Chat {
+    /// What the chat does next, never happens while idle
+    async fn next(&mut self) -> Event {
+        match self {
+            Chat::Idle => std::future::pending().await,
+            Chat::Running { task, tool_calls, .. } => tokio::select! {
+                biased;
+                Some(call) = tool_calls.recv() => Event::ToolCall(call),
+                Some(result) = task.join_next() => {
+                    Event::Finished(result.unwrap_or_else(|e| Err(AppError::Internal(e.into()))))
+                }
+            },
+            Chat::Answering { next_chunk_at, .. } => {
+                tokio::time::sleep_until(*next_chunk_at).await;
+                Event::ChunkDue
+            }
+        }
+    }
+}
+
+struct ChatSocket {
+    socket: WebSocket,
+    ai_service: AIService,
+    /// The upgrade only took one token, every chat started takes another
+    rate_limiter: RateLimiter,
+    tenant: TenantContext,
+    chat: Chat,
+    last_seen: Instant,
+}
+
+impl ChatSocket {
+    fn new(socket: WebSocket, ai_service: AIService, rate_limiter: RateLimiter, tenant: TenantContext) -> Self {
+        Self {
+            socket,
+            ai_service,
+            rate_limiter,
+            tenant,
+            chat: Chat::Idle,
+            last_seen: Instant::now(),
+        }
+    }
+
+    /// Serves the connection until it closes. A chat still running then is aborted.
+    async fn run(mut self) {
+        let mut heartbeat = tokio::time::interval_at(Instant::now() + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+        heartbeat.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            let event = tokio::select! {
+                message = self.socket.recv() => Event::Received(message),
+                event = self.chat.next() => event,
+                _ = heartbeat.tick() => Event::Heartbeat,
+            };
+            if self.handle(event).await.is_break() {
+                return;
+            }
+        }
+    }
+
+    async fn handle(&mut self, event: Event) -> ControlFlow<()> {
+        match event {
+            Event::Received(Some(Ok(message))) => {
+                self.last_seen = Instant::now();
+                match message {
+                    Message::Text(text) => self.receive(text.as_str()).await,
+                    Message::Binary(_) => {
+                        self.send_error(None, "Frames must be JSON text messages".to_string())
+                            .await
+                    }
+                    // Pings are answered by the socket itself
+                    Message::Ping(_) | Message::Pong(_) => ControlFlow::Continue(()),
+                    Message::Close(_) => ControlFlow::Break(()),
+                }
+            }
+            Event::Received(_) => ControlFlow::Break(()),
+            Event::ToolCall(call) => {
+                let id = self.chat_id();
+                self.send(&ChatServerFrame::ToolEvent { id, tool_call: call }).await
+            }
+            Event::Finished(result) => self.finish(result).await,
+            Event::ChunkDue => self.send_chunk().await,
+            Event::Heartbeat => {
+                if self.last_seen.elapsed() > IDLE_TIMEOUT {
+                    tracing::debug!("Closing AI chat socket of an unresponsive client");
+                    let _ = self.socket.send(Message::Close(None)).await;
+                    return ControlFlow::Break(());
+                }
+                self.send_message(Message::Ping(Default::default())).await
+            }
+        }
+    }
+
+    async fn receive(&mut self, text: &str) -> ControlFlow<()> {
+        let frame = match serde_json::from_str::<ChatClientFrame>(text) {
+            Ok(frame) => frame,
+            Err(e) => return self.send_error(None, format!("Invalid frame: {}", e)).await,
+        };
+
+        match frame {
+            ChatClientFrame::Start { id, request } => {
+                if !matches!(self.chat, Chat::Idle) {
+                    return self
+                        .send_error(id, "A chat is already running, cancel it first".to_string())
+                        .await;
+                }
+                let client = principal_key(&self.tenant.principal);
+                if let Some(decision) = self.rate_limiter.check("ai", &client).await
+                    && !decision.allowed
+                {
+                    let error = AppError::TooManyRequests {
+                        retry_after: decision.retry_after,
+                    };
+                    return self.send_error(id, error.public_message()).await;
+                }
+
+                let (sender, tool_calls) = mpsc::unbounded_channel();
+                let service = self.ai_service.clone();
+                let tenant = self.tenant.clone();
+                let mut task = JoinSet::new();
+                task.spawn(async move { service.chat_with_events(&tenant, *request, Some(sender)).await });
+                self.chat = Chat::Running { id, task, tool_calls };
+                ControlFlow::Continue(())
+            }
+            ChatClientFrame::Cancel => {
+                let (id, text) = match std::mem::replace(&mut self.chat, Chat::Idle) {
+                    Chat::Idle => return ControlFlow::Continue(()),
+                    Chat::Running { id, .. } => (id, String::new()),
+                    Chat::Answering { id, response, sent, .. } => {
+                        let mut text = response.response;
+                        text.truncate(sent);
+                        (id, text)
+                    }
+                };
+                tracing::info!(chat_id = ?id, sent_bytes = text.len(), "AI chat cancelled by the client");
+                self.send(&ChatServerFrame::Done {
+                    id,
+                    cancelled: true,
+                    text,
+                    response: None,
+                })
+                .await
+            }
+        }
+    }
+
+    async fn finish(&mut self, result: Result<ChatResponse, AppError>) -> ControlFlow<()> {
+        let Chat::Running { id, mut tool_calls, .. } = std::mem::replace(&mut self.chat, Chat::Idle) else {
+            return ControlFlow::Continue(());
+        };
+
+        // Tool calls made just before the answer may still be queued
+        while let Ok(call) = tool_calls.try_recv() {
+            self.send(&ChatServerFrame::ToolEvent {
+                id: id.clone(),
+                tool_call: call,
+            })
+            .await?;
+        }
+
+        match result {
+            Ok(response) => {
+                self.chat = Chat::Answering {
+                    id,
+                    response,
+                    sent: 0,
+                    next_chunk_at: Instant::now(),
+                };
+                ControlFlow::Continue(())
+            }
+            Err(e) => {
+                tracing::error!(chat_id = ?id, "AI chat failed: {}", e);
+                self.send_error(id, e.public_message()).await
+            }
+        }
+    }
+
+    /// Sends the next word with the whitespace after it, or `done` once all are sent
+    async fn send_chunk(&mut self) -> ControlFlow<()> {
+        let Chat::Answering {
+            id,
+            response,
+            sent,
+            next_chunk_at,
+        } = &mut self.chat
+        else {
+            return ControlFlow::Continue(());
+        };
+
+        let text = &response.response;
+        if *sent < text.len() {
+            let end = chunk_end(text, *sent);
+            let frame = ChatServerFrame::Chunk {
+                id: id.clone(),
+                text: text[*sent..end].to_string(),
+            };
+            *sent = end;
+            *next_chunk_at += CHUNK_DELAY;
+            return self.send(&frame).await;
+        }
+
+        let Chat::Answering { id, response, .. } = std::mem::replace(&mut self.chat, Chat::Idle) else {
+            unreachable!("the chat is answering");
+        };
+        self.send(&ChatServerFrame::Done {
+            id,
+            cancelled: false,
+            text: response.response.clone(),
+            response: Some(response),
+        })
+        .await
+    }
+
+    fn chat_id(&self) -> Option<String> {
+        match &self.chat {
+            Chat::Idle => None,
+            Chat::Running { id, .. } | Chat::Answering { id, .. } => id.clone(),
+        }
+    }
+
+    async fn send_error(&mut self, id: Option<String>, message: String) -> ControlFlow<()> {
+        self.send(&ChatServerFrame::Error { id, message }).await
+    }
+
+    async fn send(&mut self, frame: &ChatServerFrame) -> ControlFlow<()> {
+        let text = serde_json::to_string(frame).unwrap_or_default();
+        self.send_message(Message::Text(text.into())).await
+    }
+
+    /// Stops serving the connection once the client is gone
+    async fn send_message(&mut self, message: Message) -> ControlFlow<()> {
+        match self.socket.send(message).await {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        }
+    }
+}
+
+/// End of the chunk starting at `from`: the next word and the whitespace after it
+fn chunk_end(text: &str, from: usize) -> usize {
+    let rest = &text[from..];
+    let word_start = rest.len() - rest.trim_start().len();
+    let word_end = rest[word_start..]
+        .find(char::is_whitespace)
+        .map_or(rest.len(), |i| word_start + i);
+    let after = &rest[word_end..];
+    from + word_end + (after.len() - after.trim_start().len())
+}
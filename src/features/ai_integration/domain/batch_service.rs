@@ -0,0 +1,281 @@
+use futures::stream::{self, Stream, TryStreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::service::caller_ids;
+use super::AIService;
+use crate::{
+    entities::ai::{AiBatch, AiBatchResult, BatchStatus},
+    features::ai_integration::infrastructure::{BatchRepository, ClaimedJob, NewBatch},
+    features::ai_integration::model::{CreateBatchRequest, GenerateRequest},
+    features::auth::domain::{ApiKeyService, AuthService},
+    features::auth::model::{ApiScope, Principal},
+    features::organizations::domain::OrganizationService,
+    features::organizations::model::{TenantContext, TenantSelector},
+    shared::config::AiBatchConfig,
+    shared::error::AppError,
+};
+
+/// Results read from the database at a time while streaming them
+const RESULTS_PAGE_SIZE: i64 = 100;
+
+/// Suggested wait when the organization has too many jobs queued
+const PENDING_RETRY_AFTER_SECONDS: u64 = 60;
+
+/// Generate requests queued in Postgres and answered by background workers
+#[derive(Clone)]
+pub struct BatchService {
+    batches: Arc<dyn BatchRepository>,
+    ai_service: AIService,
+    auth_service: AuthService,
+    api_key_service: ApiKeyService,
+    organization_service: OrganizationService,
+    config: AiBatchConfig,
+}
+
+impl BatchService {
+    pub fn new(
+        batches: Arc<dyn BatchRepository>,
+        ai_service: AIService,
+        auth_service: AuthService,
+        api_key_service: ApiKeyService,
+        organization_service: OrganizationService,
+        config: AiBatchConfig,
+    ) -> Self {
+        Self {
+            batches,
+            ai_service,
+            auth_service,
+            api_key_service,
+            organization_service,
+            config,
+        }
+    }
+
+    /// Queues the requests as jobs. Each is checked now so that a batch
+    /// doesn't fail job by job on a typo. A batch is a single request to the
+    /// rate limiter, so the jobs an organization may have queued are capped
+    /// by `max_pending_jobs` instead.
+    pub async fn create(&self, tenant: &TenantContext, input: CreateBatchRequest) -> Result<AiBatch, AppError> {
+        input
+            .validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+        if input.items.len() > self.config.max_items {
+            return Err(AppError::Validation(format!(
+                "A batch has at most {} items, got {}",
+                self.config.max_items,
+                input.items.len()
+            )));
+        }
+
+        let mut requests = Vec::with_capacity(input.items.len());
+        for (index, item) in input.items.iter().enumerate() {
+            self.ai_service.check_model(item.model.as_deref()).map_err(|e| match e {
+                AppError::Validation(message) => AppError::Validation(format!("Item {}: {}", index, message)),
+                other => other,
+            })?;
+            requests.push(serde_json::to_value(item).map_err(|e| AppError::Internal(e.into()))?);
+        }
+
+        let (created_by, api_key_id) = caller_ids(tenant);
+        let batch = NewBatch {
+            requests,
+            created_by,
+            api_key_id,
+        };
+        self.batches
+            .create(tenant.organization_id(), batch, self.config.max_pending_jobs as i64)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or(AppError::TooManyRequests {
+                retry_after: PENDING_RETRY_AFTER_SECONDS,
+            })
+    }
+
+    pub async fn get(&self, tenant: &TenantContext, id: Uuid) -> Result<AiBatch, AppError> {
+        self.batches
+            .find(tenant.organization_id(), id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or(AppError::NotFound)
+    }
+
+    /// Finished jobs in order, read page by page. Jobs still running are
+    /// left out, poll the batch until it has completed.
+    pub async fn results(
+        &self,
+        tenant: &TenantContext,
+        id: Uuid,
+    ) -> Result<impl Stream<Item = Result<AiBatchResult, AppError>> + use<>, AppError> {
+        self.get(tenant, id).await?;
+
+        let batches = self.batches.clone();
+        let tenant_id = tenant.organization_id();
+        let pages = stream::try_unfold(Some(-1), move |after| {
+            let batches = batches.clone();
+            async move {
+                let Some(after) = after else {
+                    return Ok(None);
+                };
+                let page = batches
+                    .results(tenant_id, id, after, RESULTS_PAGE_SIZE)
+                    .await
+                    .map_err(|e| AppError::Database(e.to_string()));
+                page.map(|page| {
+                    // A short page is the last one
+                    let next = match page.last() {
+                        Some(last) if page.len() as i64 == RESULTS_PAGE_SIZE => Some(last.index),
+                        _ => None,
+                    };
+                    Some((stream::iter(page.into_iter().map(Ok)), next))
+                })
+            }
+        });
+        Ok(pages.try_flatten())
+    }
+
+    /// Cancels the jobs that haven't finished. Jobs already running are
+    /// answered, but their results are discarded.
+    pub async fn cancel(&self, tenant: &TenantContext, id: Uuid) -> Result<AiBatch, AppError> {
+        let batch = self.get(tenant, id).await?;
+        match batch.status {
+            BatchStatus::Completed => {
+                return Err(AppError::Conflict("The batch has already completed".to_string()));
+            }
+            BatchStatus::Cancelled => return Ok(batch),
+            BatchStatus::Pending | BatchStatus::Running => {}
+        }
+
+        self.batches
+            .cancel(tenant.organization_id(), id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or(AppError::NotFound)
+    }
+
+    /// Runs queued jobs in the background, `AI_BATCH_CONCURRENCY` at a time
+    pub fn start_workers(&self) {
+        let service = self.clone();
+        tokio::spawn(service.run_workers());
+        tracing::info!(concurrency = self.config.concurrency, "AI batch workers started");
+    }
+
+    async fn run_workers(self) {
+        let slots = Arc::new(Semaphore::new(self.config.concurrency.max(1)));
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+        let lease = Duration::from_secs(self.config.lease_seconds);
+
+        loop {
+            // Wait for a free slot, then claim at most as many jobs as there are free slots
+            let slot = slots
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("the worker semaphore is never closed");
+            let free = slots.available_permits() + 1;
+
+            let jobs = match self.batches.claim(free as i64, lease).await {
+                Ok(jobs) => jobs,
+                Err(e) => {
+                    tracing::error!("Failed to claim AI batch jobs: {}", e);
+                    Vec::new()
+                }
+            };
+            if jobs.is_empty() {
+                drop(slot);
+                tokio::time::sleep(poll_interval).await;
+                continue;
+            }
+
+            let mut slot = Some(slot);
+            for job in jobs {
+                // Only this loop takes slots, so the ones counted as free still are
+                let slot = match slot.take() {
+                    Some(slot) => slot,
+                    None => slots
+                        .clone()
+                        .try_acquire_owned()
+                        .expect("a slot is free for every claimed job"),
+                };
+                let service = self.clone();
+                tokio::spawn(async move {
+                    service.run_job(job).await;
+                    drop(slot);
+                });
+            }
+        }
+    }
+
+    /// Answers one job as the caller that submitted it. Provider errors and
+    /// timeouts are retried with exponential backoff, other errors fail the job.
+    async fn run_job(&self, job: ClaimedJob) {
+        let outcome = match job.attempts > self.config.max_attempts {
+            // Claimed again after its lease ran out, e.g. because an instance stopped
+            true => None,
+            false => Some(self.generate(&job).await),
+        };
+
+        let stored = match outcome {
+            None => {
+                let error = format!("Gave up after {} attempts that did not finish", job.attempts - 1);
+                self.batches.fail(&job, &error).await
+            }
+            Some(Ok(response)) => self.batches.succeed(&job, response).await,
+            Some(Err(e @ (AppError::ExternalService(_) | AppError::Database(_))))
+                if job.attempts < self.config.max_attempts =>
+            {
+                let delay = Duration::from_secs(
+                    self.config
+                        .retry_base_seconds
+                        .saturating_mul(1 << (job.attempts - 1).clamp(0, 16)),
+                );
+                tracing::warn!(
+                    job_id = %job.id,
+                    batch_id = %job.batch_id,
+                    attempt = job.attempts,
+                    "AI batch job failed, retrying in {} seconds: {}",
+                    delay.as_secs(),
+                    e
+                );
+                self.batches.retry(&job, &e.public_message(), delay).await
+            }
+            Some(Err(e)) => {
+                tracing::warn!(job_id = %job.id, batch_id = %job.batch_id, "AI batch job failed: {}", e);
+                self.batches.fail(&job, &e.public_message()).await
+            }
+        };
+        if let Err(e) = stored {
+            tracing::error!(job_id = %job.id, "Failed to store the outcome of an AI batch job: {}", e);
+        }
+    }
+
+    async fn generate(&self, job: &ClaimedJob) -> Result<serde_json::Value, AppError> {
+        let tenant = self.caller(job).await?;
+        let request: GenerateRequest = serde_json::from_value(job.request.clone())
+            .map_err(|e| AppError::Validation(format!("Invalid request: {}", e)))?;
+        let response = self.ai_service.generate(&tenant, request).await?;
+        serde_json::to_value(response).map_err(|e| AppError::Internal(e.into()))
+    }
+
+    /// The submitter as it is now. Jobs fail once its API key is revoked or
+    /// the user has left the organization.
+    async fn caller(&self, job: &ClaimedJob) -> Result<TenantContext, AppError> {
+        let principal = match (job.api_key_id, job.created_by) {
+            (Some(key_id), _) => Principal::ApiKey(self.api_key_service.principal(key_id).await?),
+            (None, Some(user_id)) => Principal::User(self.auth_service.principal(user_id).await?),
+            (None, None) => {
+                return Err(AppError::Unauthorized(
+                    "The user or API key that submitted the batch no longer exists".to_string(),
+                ));
+            }
+        };
+
+        let selector = TenantSelector::for_organization(job.organization_id.to_string());
+        let tenant = self.organization_service.resolve(principal, &selector).await?;
+        tenant.require_scope(ApiScope::AiInvoke)?;
+        Ok(tenant)
+    }
+}
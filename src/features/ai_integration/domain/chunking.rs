@@ -0,0 +1,245 @@
+use crate::entities::ai::DocumentFormat;
+
+/// Plain text of the content with normalized line breaks, paragraphs separated by a blank line
+pub fn to_plain_text(content: &str, format: DocumentFormat) -> String {
+    let text = match format {
+        DocumentFormat::Text | DocumentFormat::Markdown => content.replace("\r\n", "\n"),
+        DocumentFormat::Html => html_to_text(content),
+    };
+
+    let mut normalized = String::with_capacity(text.len());
+    let mut blank_lines = 0;
+    for line in text.lines().map(str::trim_end) {
+        if line.trim().is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        normalized.push_str(line);
+        blank_lines = 0;
+    }
+    normalized
+}
+
+/// Splits text into chunks of at most `size` characters, each repeating about
+/// the last `overlap` characters of the previous one. Chunks end at the best
+/// boundary in their second half: a markdown heading, paragraph, line,
+/// sentence or word, in that order.
+pub fn chunk_text(text: &str, size: usize, overlap: usize, format: DocumentFormat) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = (start + size).min(chars.len());
+        if end < chars.len() {
+            end = break_point(&chars, start + size / 2, end, format);
+        }
+
+        let chunk: String = chars[start..end].iter().collect();
+        let chunk = chunk.trim();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_string());
+        }
+        if end == chars.len() {
+            break;
+        }
+
+        // The overlap starts at a word, and every chunk moves forward
+        let mut next = end.saturating_sub(overlap).max(start + 1);
+        while next < end && !chars[next - 1].is_whitespace() {
+            next += 1;
+        }
+        start = next;
+    }
+    chunks
+}
+
+/// Position in `min..=max` to end a chunk at, `max` when there is no boundary
+fn break_point(chars: &[char], min: usize, max: usize, format: DocumentFormat) -> usize {
+    let heading = |i: usize| format == DocumentFormat::Markdown && chars[i] == '#' && chars[i - 1] == '\n';
+    let paragraph = |i: usize| i >= 2 && chars[i - 1] == '\n' && chars[i - 2] == '\n';
+    let line = |i: usize| chars[i - 1] == '\n';
+    let sentence = |i: usize| i >= 2 && chars[i - 1].is_whitespace() && matches!(chars[i - 2], '.' | '!' | '?');
+    let word = |i: usize| chars[i - 1].is_whitespace();
+
+    let rules: [&dyn Fn(usize) -> bool; 5] = [&heading, &paragraph, &line, &sentence, &word];
+    for rule in rules {
+        if let Some(position) = (min.max(1)..=max).rev().find(|&i| rule(i)) {
+            return position;
+        }
+    }
+    max
+}
+
+/// Tags whose start or end begins a new paragraph
+const BLOCK_TAGS: &[&str] = &[
+    "p", "div", "section", "article", "header", "footer", "main", "aside", "nav", "h1", "h2", "h3", "h4", "h5",
+    "h6", "ul", "ol", "table", "tr", "pre", "blockquote", "figure", "hr",
+];
+
+/// Drops tags, comments, scripts and styles and decodes entities
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(open) = rest.find('<') {
+        push_decoded(&mut text, &rest[..open].replace(['\n', '\r', '\t'], " "));
+        rest = &rest[open..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(close) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..close];
+        rest = &rest[close + 1..];
+
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        if (name == "script" || name == "style") && !tag.starts_with('/') {
+            let end_tag = format!("</{}", name);
+            rest = rest
+                .to_ascii_lowercase()
+                .find(&end_tag)
+                .map_or("", |end| &rest[end..]);
+            continue;
+        }
+
+        match name.as_str() {
+            "br" => text.push('\n'),
+            "li" if !tag.starts_with('/') => text.push_str("\n- "),
+            "td" | "th" => text.push(' '),
+            name if BLOCK_TAGS.contains(&name) => text.push_str("\n\n"),
+            _ => {}
+        }
+    }
+    push_decoded(&mut text, &rest.replace(['\n', '\r', '\t'], " "));
+
+    // Whitespace in HTML source is insignificant, only the breaks added above count
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn push_decoded(text: &mut String, raw: &str) {
+    let mut rest = raw;
+    while let Some(amp) = rest.find('&') {
+        text.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16).ok())
+                    .unwrap_or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                text.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                text.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_short_text_in_one_chunk() {
+        assert_eq!(chunk_text("one two", 100, 10, DocumentFormat::Text), vec!["one two"]);
+        assert!(chunk_text("", 100, 10, DocumentFormat::Text).is_empty());
+    }
+
+    #[test]
+    fn ends_chunks_at_paragraphs() {
+        let chunks = chunk_text("aaaa bbbb.\n\ncccc dddd", 16, 0, DocumentFormat::Text);
+
+        assert_eq!(chunks, vec!["aaaa bbbb.", "cccc dddd"]);
+    }
+
+    #[test]
+    fn prefers_markdown_headings() {
+        let text = "# One\nalpha\n# Two\nbeta\n\ngamma delta";
+
+        let markdown = chunk_text(text, 24, 0, DocumentFormat::Markdown);
+        assert_eq!(markdown[0], "# One\nalpha");
+
+        // Without markdown, the paragraph is the best boundary
+        let plain = chunk_text(text, 24, 0, DocumentFormat::Text);
+        assert_eq!(plain[0], "# One\nalpha\n# Two\nbeta");
+    }
+
+    #[test]
+    fn overlaps_at_word_boundaries() {
+        let chunks = chunk_text("one two three four five six seven eight nine ten", 20, 8, DocumentFormat::Text);
+
+        assert_eq!(chunks, vec!["one two three four", "four five six seven", "seven eight nine ten"]);
+        assert!(chunks.iter().all(|chunk| chunk.chars().count() <= 20));
+    }
+
+    #[test]
+    fn cuts_text_without_boundaries_at_the_size() {
+        assert_eq!(chunk_text("abcdefghij", 4, 0, DocumentFormat::Text), vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn moves_forward_when_the_overlap_exceeds_the_size() {
+        let chunks = chunk_text("a b c d e f", 2, 5, DocumentFormat::Text);
+
+        assert_eq!(chunks, vec!["a", "b", "c", "d", "e", "f"]);
+    }
+
+    #[test]
+    fn normalizes_line_breaks() {
+        let text = to_plain_text("a\r\nb  \n\n\n\nc\n", DocumentFormat::Text);
+
+        assert_eq!(text, "a\nb\n\nc");
+    }
+
+    #[test]
+    fn converts_html_to_paragraphs() {
+        let html = "<h1>Title</h1><p>One &amp; two</p><script>x()</script><!-- note --><ul><li>a</li><li>b</li></ul>";
+
+        assert_eq!(to_plain_text(html, DocumentFormat::Html), "Title\n\nOne & two\n\n- a\n- b");
+    }
+
+    #[test]
+    fn decodes_entities() {
+        let mut text = String::new();
+        push_decoded(&mut text, "&#65;&#x42;&bogus; &lt;&nbsp;&");
+
+        assert_eq!(text, "AB&bogus; < &");
+    }
+}
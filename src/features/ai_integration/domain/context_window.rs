@@ -0,0 +1,284 @@
+use crate::entities::ai::{ChatMessage, ChatRole, ContextStrategy, MessagePart};
+use crate::features::ai_integration::infrastructure::CompletionOptions;
+use crate::shared::config::AiContextConfig;
+
+/// Context windows of Gemini models in tokens, by model name prefix
+const MODEL_CONTEXT_LIMITS: &[(&str, usize)] = &[
+    ("gemini-1.0-pro", 30_720),
+    ("gemini-pro", 30_720),
+    ("gemini-1.5-flash", 1_048_576),
+    ("gemini-1.5-pro", 2_097_152),
+    ("gemini-2.0-flash", 1_048_576),
+    ("gemini-2.5-flash", 1_048_576),
+    ("gemini-2.5-pro", 1_048_576),
+];
+
+/// Gemini counts an image as 258 tokens, PDFs as that much per page
+const INLINE_DATA_TOKENS: usize = 258;
+
+/// Role and separators of a turn
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Token limits per model and the default strategy for histories that don't fit
+#[derive(Debug, Clone)]
+pub struct ContextWindow {
+    limits: Vec<(String, usize)>,
+    default_limit: usize,
+    reserved_output_tokens: usize,
+    pub default_strategy: ContextStrategy,
+}
+
+impl ContextWindow {
+    pub fn new(config: &AiContextConfig) -> anyhow::Result<Self> {
+        let default_strategy = match config.strategy.as_str() {
+            "drop_oldest" => ContextStrategy::DropOldest,
+            "summarize" => ContextStrategy::Summarize,
+            other => anyhow::bail!("Unknown AI context strategy: {}", other),
+        };
+
+        // Configured limits are looked up before the built-in ones
+        let limits = config
+            .model_limits
+            .iter()
+            .cloned()
+            .chain(MODEL_CONTEXT_LIMITS.iter().map(|(model, limit)| (model.to_string(), *limit)))
+            .collect();
+
+        Ok(Self {
+            limits,
+            default_limit: config.default_limit,
+            reserved_output_tokens: config.reserved_output_tokens,
+            default_strategy,
+        })
+    }
+
+    /// Context window of `model`, prompt and answer together
+    pub fn context_limit(&self, model: &str) -> usize {
+        self.limits
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default_limit, |(_, limit)| *limit)
+    }
+
+    /// Tokens a prompt to `model` may use, leaving room for the answer
+    pub fn prompt_limit(&self, model: &str) -> usize {
+        self.context_limit(model).saturating_sub(self.reserved_output_tokens)
+    }
+}
+
+/// Rough token count, about four characters per token for English text
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+pub fn message_tokens(message: &ChatMessage) -> usize {
+    let parts: usize = message
+        .parts
+        .iter()
+        .map(|part| match part {
+            MessagePart::Text(text) => estimate_tokens(text),
+            // Base64 is a third longer than the text it encodes
+            MessagePart::InlineData(inline) if inline.mime_type.starts_with("text/") => inline.data.len() * 3 / 16,
+            MessagePart::InlineData(_) | MessagePart::FileId(_) => INLINE_DATA_TOKENS,
+        })
+        .sum();
+    MESSAGE_OVERHEAD_TOKENS + estimate_tokens(&message.content) + parts
+}
+
+/// System instruction and tool declarations, sent with every turn
+pub fn instruction_tokens(options: &CompletionOptions) -> usize {
+    let tools = match options.tools.is_empty() {
+        true => 0,
+        false => estimate_tokens(&serde_json::to_string(&options.tools).unwrap_or_default()),
+    };
+    options.system_instruction.as_deref().map_or(0, estimate_tokens) + tools
+}
+
+pub fn conversation_tokens(messages: &[ChatMessage]) -> usize {
+    messages.iter().map(message_tokens).sum()
+}
+
+/// Removes the oldest messages until the rest fit `budget`, keeping the last
+/// one. Returns how many were removed.
+pub fn drop_oldest(messages: &mut Vec<ChatMessage>, budget: usize) -> usize {
+    let mut total = conversation_tokens(messages);
+    let mut dropped = 0;
+    while messages.len() > 1 && total > budget {
+        total -= message_tokens(&messages.remove(0));
+        dropped += 1;
+    }
+    // The shortened history still starts with a user turn
+    while dropped > 0 && messages.len() > 1 && messages[0].role != ChatRole::User {
+        messages.remove(0);
+        dropped += 1;
+    }
+    dropped
+}
+
+/// Index of the first message kept when summarizing: the newest messages
+/// that fit half of `budget`, starting with a user turn. The other half is
+/// left for the summary. 0 when there is nothing to summarize.
+pub fn summary_split(messages: &[ChatMessage], budget: usize) -> usize {
+    let mut start = messages.len().saturating_sub(1);
+    let mut total = messages.last().map_or(0, message_tokens);
+    while start > 0 && total + message_tokens(&messages[start - 1]) <= budget / 2 {
+        start -= 1;
+        total += message_tokens(&messages[start]);
+    }
+    while start < messages.len().saturating_sub(1) && messages[start].role != ChatRole::User {
+        start += 1;
+    }
+    start
+}
+
+/// The messages as "User: ..." and "Assistant: ..." lines, the newest that
+/// fit `max_tokens`. Attachments are mentioned, not included.
+pub fn transcript(messages: &[ChatMessage], max_tokens: usize) -> String {
+    let mut lines = Vec::new();
+    let mut total = 0;
+    for message in messages.iter().rev() {
+        let speaker = match message.role {
+            ChatRole::User => "User",
+            ChatRole::Model => "Assistant",
+        };
+        let mut text = message.content.trim().to_string();
+        for part in &message.parts {
+            match part {
+                MessagePart::Text(part) => {
+                    text.push('\n');
+                    text.push_str(part.trim());
+                }
+                MessagePart::InlineData(_) | MessagePart::FileId(_) => text.push_str("\n[attachment]"),
+            }
+        }
+
+        let line = format!("{}: {}", speaker, text.trim());
+        total += estimate_tokens(&line);
+        if total > max_tokens {
+            break;
+        }
+        lines.push(line);
+    }
+    lines.reverse();
+    lines.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::ai::InlineData;
+    use uuid::Uuid;
+
+    /// Six tokens each: the overhead and two for the content
+    fn conversation() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::user("first q."),
+            ChatMessage::assistant("first a."),
+            ChatMessage::user("second q"),
+            ChatMessage::assistant("second a"),
+            ChatMessage::user("third q."),
+        ]
+    }
+
+    fn context_window(strategy: &str) -> anyhow::Result<ContextWindow> {
+        ContextWindow::new(&AiContextConfig {
+            strategy: strategy.to_string(),
+            model_limits: vec![("gemini-1.5-pro-002".to_string(), 100)],
+            default_limit: 50,
+            reserved_output_tokens: 10,
+        })
+    }
+
+    #[test]
+    fn estimates_four_characters_per_token() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        // Characters, not bytes
+        assert_eq!(estimate_tokens("äöüß"), 1);
+    }
+
+    #[test]
+    fn counts_message_parts() {
+        let message = ChatMessage {
+            role: ChatRole::User,
+            content: String::new(),
+            parts: vec![
+                MessagePart::Text("abcd".to_string()),
+                MessagePart::InlineData(InlineData {
+                    mime_type: "image/png".to_string(),
+                    data: "aGVsbG8=".to_string(),
+                }),
+                MessagePart::InlineData(InlineData {
+                    mime_type: "text/plain".to_string(),
+                    data: "a".repeat(16),
+                }),
+                MessagePart::FileId(Uuid::nil()),
+            ],
+        };
+
+        assert_eq!(message_tokens(&message), MESSAGE_OVERHEAD_TOKENS + 1 + INLINE_DATA_TOKENS + 3 + INLINE_DATA_TOKENS);
+        assert_eq!(conversation_tokens(&conversation()), 30);
+    }
+
+    #[test]
+    fn looks_up_the_longest_model_prefix() {
+        let window = context_window("drop_oldest").unwrap();
+
+        assert_eq!(window.context_limit("gemini-1.5-pro-002"), 100);
+        assert_eq!(window.context_limit("gemini-1.5-pro-001"), 2_097_152);
+        assert_eq!(window.context_limit("mock"), 50);
+        assert_eq!(window.prompt_limit("mock"), 40);
+        assert!(context_window("truncate").is_err());
+    }
+
+    #[test]
+    fn drops_the_oldest_messages_beyond_the_budget() {
+        let mut messages = conversation();
+
+        assert_eq!(drop_oldest(&mut messages, 18), 2);
+        assert_eq!(messages[0].content, "second q");
+        assert_eq!(messages.len(), 3);
+    }
+
+    #[test]
+    fn starts_the_shortened_history_with_a_user_turn() {
+        let mut messages = conversation();
+
+        // Fitting 15 tokens would keep an assistant turn first
+        assert_eq!(drop_oldest(&mut messages, 15), 4);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "third q.");
+    }
+
+    #[test]
+    fn keeps_the_last_message_even_over_the_budget() {
+        let mut messages = vec![ChatMessage::user("a long question")];
+
+        assert_eq!(drop_oldest(&mut messages, 0), 0);
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[test]
+    fn keeps_the_newest_messages_in_half_the_budget_when_summarizing() {
+        let messages = conversation();
+
+        assert_eq!(summary_split(&messages, 36), 2);
+        // Two messages fit, but the kept part starts with a user turn
+        assert_eq!(summary_split(&messages, 24), 4);
+        assert_eq!(summary_split(&messages[..1], 100), 0);
+        assert_eq!(summary_split(&[], 100), 0);
+    }
+
+    #[test]
+    fn writes_a_transcript_of_the_newest_messages() {
+        let mut messages = vec![ChatMessage::user("hi"), ChatMessage::assistant("hello")];
+
+        assert_eq!(transcript(&messages, 100), "User: hi\n\nAssistant: hello");
+        assert_eq!(transcript(&messages, 5), "Assistant: hello");
+
+        messages[0].parts.push(MessagePart::FileId(Uuid::nil()));
+        assert!(transcript(&messages, 100).starts_with("User: hi\n[attachment]\n\n"));
+    }
+}
@@ -0,0 +1,98 @@
+use crate::features::ai_integration::model::{DiffOp, DiffSegment};
+
+/// Texts whose differing middle parts have more words than this, multiplied,
+/// are shown as replaced as a whole instead of compared word by word
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// How `new` differs from `old`, word by word. Each word keeps the whitespace
+/// after it, so the `equal` and `insert` segments add up to `new` and the
+/// `equal` and `delete` segments to `old`.
+pub fn word_diff(old: &str, new: &str) -> Vec<DiffSegment> {
+    let old = words(old);
+    let new = words(new);
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut ops: Vec<(DiffOp, &str)> = old[..prefix].iter().map(|word| (DiffOp::Equal, *word)).collect();
+    if (a.len() + 1).saturating_mul(b.len() + 1) > MAX_DIFF_CELLS {
+        ops.extend(a.iter().map(|word| (DiffOp::Delete, *word)));
+        ops.extend(b.iter().map(|word| (DiffOp::Insert, *word)));
+    } else {
+        ops.extend(common_subsequence(a, b));
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|word| (DiffOp::Equal, *word)));
+
+    // Neighbouring words with the same op become one segment
+    let mut segments: Vec<DiffSegment> = Vec::new();
+    for (op, word) in ops {
+        match segments.last_mut() {
+            Some(last) if last.op == op => last.text.push_str(word),
+            _ => segments.push(DiffSegment {
+                op,
+                text: word.to_string(),
+            }),
+        }
+    }
+    segments
+}
+
+/// Keeps the longest common subsequence of words, deletes and inserts the rest
+fn common_subsequence<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<(DiffOp, &'a str)> {
+    let width = b.len() + 1;
+    // lengths[i * width + j] is the longest common subsequence of a[i..] and b[j..]
+    let mut lengths = vec![0u32; (a.len() + 1) * width];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i * width + j] = match a[i] == b[j] {
+                true => lengths[(i + 1) * width + j + 1] + 1,
+                false => lengths[(i + 1) * width + j].max(lengths[i * width + j + 1]),
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut ops = Vec::with_capacity(a.len() + b.len());
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            ops.push((DiffOp::Equal, a[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            ops.push((DiffOp::Delete, a[i]));
+            i += 1;
+        } else {
+            ops.push((DiffOp::Insert, b[j]));
+            j += 1;
+        }
+    }
+    ops.extend(a[i..].iter().map(|word| (DiffOp::Delete, *word)));
+    ops.extend(b[j..].iter().map(|word| (DiffOp::Insert, *word)));
+    ops
+}
+
+/// Words with the whitespace after them, leading whitespace is a word of its own
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut after_space = false;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() {
+            after_space = true;
+        } else if after_space {
+            words.push(&text[start..i]);
+            start = i;
+            after_space = false;
+        }
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
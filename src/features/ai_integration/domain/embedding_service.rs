@@ -0,0 +1,174 @@
+use std::sync::Arc;
+use validator::Validate;
+
+use super::chunking::{chunk_text, to_plain_text};
+use super::service::caller_ids;
+use super::AIService;
+use crate::{
+    entities::ai::EmbeddingTask,
+    features::ai_integration::infrastructure::{EmbeddingRepository, NewEmbedding},
+    features::ai_integration::model::{
+        IngestDocumentRequest, IngestDocumentResponse, StoreDocumentsRequest, StoreDocumentsResponse,
+    },
+    features::organizations::model::TenantContext,
+    shared::config::AiChunkingConfig,
+    shared::error::AppError,
+};
+
+/// Longest documents `ingest` accepts
+const MAX_CHUNKS_PER_DOCUMENT: usize = 500;
+
+/// Documents stored in named collections for semantic search and retrieval
+#[derive(Clone)]
+pub struct EmbeddingService {
+    embeddings: Arc<dyn EmbeddingRepository>,
+    ai_service: AIService,
+    chunking: AiChunkingConfig,
+}
+
+impl EmbeddingService {
+    pub fn new(embeddings: Arc<dyn EmbeddingRepository>, ai_service: AIService, chunking: AiChunkingConfig) -> Self {
+        Self {
+            embeddings,
+            ai_service,
+            chunking,
+        }
+    }
+
+    /// Embeds and stores the documents, replacing those with the same id
+    pub async fn store(
+        &self,
+        tenant: &TenantContext,
+        collection: &str,
+        input: StoreDocumentsRequest,
+    ) -> Result<StoreDocumentsResponse, AppError> {
+        validate_collection(collection)?;
+        input
+            .validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+
+        let texts = input.documents.iter().map(|document| document.content.clone()).collect();
+        let embeddings = self
+            .ai_service
+            .embed_texts(tenant, texts, EmbeddingTask::RetrievalDocument)
+            .await?;
+
+        let (created_by, api_key_id) = caller_ids(tenant);
+        let documents = input
+            .documents
+            .into_iter()
+            .zip(embeddings.vectors)
+            .map(|(document, embedding)| NewEmbedding {
+                document_id: document.id,
+                source_id: None,
+                chunk_index: None,
+                content: document.content,
+                metadata: serde_json::Value::Object(document.metadata),
+                model: embeddings.model.clone(),
+                embedding,
+                created_by,
+                api_key_id,
+            })
+            .collect();
+
+        let documents = self
+            .embeddings
+            .upsert(tenant.organization_id(), collection, documents)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(StoreDocumentsResponse { documents })
+    }
+
+    /// Splits a text, markdown or HTML document into chunks and stores their
+    /// embeddings, replacing the chunks of an earlier version
+    pub async fn ingest(
+        &self,
+        tenant: &TenantContext,
+        collection: &str,
+        input: IngestDocumentRequest,
+    ) -> Result<IngestDocumentResponse, AppError> {
+        validate_collection(collection)?;
+        input
+            .validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+
+        let chunk_size = input.chunk_size.unwrap_or(self.chunking.chunk_size);
+        let chunk_overlap = input.chunk_overlap.unwrap_or(self.chunking.chunk_overlap);
+        if chunk_overlap > chunk_size / 2 {
+            return Err(AppError::Validation(
+                "chunk_overlap may be at most half of chunk_size".to_string(),
+            ));
+        }
+
+        let text = to_plain_text(&input.content, input.format);
+        let chunks = chunk_text(&text, chunk_size, chunk_overlap, input.format);
+        if chunks.is_empty() {
+            return Err(AppError::Validation("The document contains no text".to_string()));
+        }
+        if chunks.len() > MAX_CHUNKS_PER_DOCUMENT {
+            return Err(AppError::Validation(format!(
+                "The document has {} chunks, at most {} are allowed",
+                chunks.len(),
+                MAX_CHUNKS_PER_DOCUMENT
+            )));
+        }
+
+        let embeddings = self
+            .ai_service
+            .embed_texts(tenant, chunks.clone(), EmbeddingTask::RetrievalDocument)
+            .await?;
+
+        let (created_by, api_key_id) = caller_ids(tenant);
+        let metadata = serde_json::Value::Object(input.metadata);
+        let chunks = chunks
+            .into_iter()
+            .zip(embeddings.vectors)
+            .enumerate()
+            .map(|(index, (content, embedding))| NewEmbedding {
+                document_id: format!("{}#{}", input.id, index),
+                source_id: Some(input.id.clone()),
+                chunk_index: Some(index as i32),
+                content,
+                metadata: metadata.clone(),
+                model: embeddings.model.clone(),
+                embedding,
+                created_by,
+                api_key_id,
+            })
+            .collect();
+
+        let chunks = self
+            .embeddings
+            .replace_source(tenant.organization_id(), collection, &input.id, chunks)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(IngestDocumentResponse {
+            document_id: input.id,
+            chunks,
+        })
+    }
+
+    pub async fn delete(&self, tenant: &TenantContext, collection: &str, document_id: &str) -> Result<(), AppError> {
+        let deleted = self
+            .embeddings
+            .delete(tenant.organization_id(), collection, document_id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if deleted { Ok(()) } else { Err(AppError::NotFound) }
+    }
+}
+
+pub(super) fn validate_collection(name: &str) -> Result<(), AppError> {
+    let valid = (1..=100).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::Validation(
+            "Collection names are 1 to 100 lowercase letters, digits, '-' and '_'".to_string(),
+        ))
+    }
+}
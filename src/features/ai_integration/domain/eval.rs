@@ -0,0 +1,707 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::time::Instant;
+
+use super::response_schema::{check_schema, parse_structured};
+use super::AIService;
+use crate::{
+    entities::ai::EmbeddingTask,
+    features::ai_integration::model::{ChatRequest, GenerateRequest},
+    features::organizations::model::TenantContext,
+    shared::error::AppError,
+};
+
+/// Similarity a `similar` assertion needs when it doesn't set `min_score`
+const DEFAULT_MIN_SIMILARITY: f64 = 0.8;
+
+/// Cases to run against a model, read from YAML or JSON Lines
+#[derive(Debug, Deserialize)]
+pub struct EvalSuite {
+    /// The file name without extension when unset
+    #[serde(default)]
+    pub name: String,
+    /// Model or route of the cases that don't name one
+    #[serde(default)]
+    pub model: Option<String>,
+    pub cases: Vec<EvalCase>,
+}
+
+/// One request and what its answer must satisfy
+#[derive(Debug, Deserialize)]
+pub struct EvalCase {
+    pub name: String,
+    #[serde(flatten)]
+    pub input: EvalInput,
+    #[serde(default, rename = "assert")]
+    pub assertions: Vec<Assertion>,
+}
+
+/// The request of a case, as it would be posted to `/ai/chat` or `/ai/generate`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvalInput {
+    Chat(ChatRequest),
+    Generate(GenerateRequest),
+}
+
+/// A property of the answer. Text assertions apply to the response text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Assertion {
+    Equals(String),
+    Contains(String),
+    NotContains(String),
+    Regex(String),
+    /// The text parses as JSON matching the schema, in the subset `response_schema` supports
+    JsonSchema(serde_json::Value),
+    /// Cosine similarity of the embeddings of the text and the answer
+    Similar {
+        text: String,
+        #[serde(default)]
+        min_score: Option<f64>,
+    },
+    FinishReason(String),
+    /// A chat called this tool
+    ToolCalled(String),
+    MaxLatencyMs(u64),
+    /// Tokens of all model calls of the case
+    MaxTokens(i64),
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assertion::Equals(text) => write!(f, "equals {:?}", text),
+            Assertion::Contains(text) => write!(f, "contains {:?}", text),
+            Assertion::NotContains(text) => write!(f, "not_contains {:?}", text),
+            Assertion::Regex(pattern) => write!(f, "regex {:?}", pattern),
+            Assertion::JsonSchema(_) => write!(f, "json_schema"),
+            Assertion::Similar { text, min_score } => write!(
+                f,
+                "similar {:?} >= {}",
+                text,
+                min_score.unwrap_or(DEFAULT_MIN_SIMILARITY)
+            ),
+            Assertion::FinishReason(reason) => write!(f, "finish_reason {}", reason),
+            Assertion::ToolCalled(name) => write!(f, "tool_called {}", name),
+            Assertion::MaxLatencyMs(limit) => write!(f, "max_latency_ms {}", limit),
+            Assertion::MaxTokens(limit) => write!(f, "max_tokens {}", limit),
+        }
+    }
+}
+
+impl EvalSuite {
+    /// Files ending in `.jsonl` hold one case per line, others a YAML (or
+    /// JSON) suite with `cases`. Patterns and schemas are checked up front.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Can't read {}: {}", path.display(), e))?;
+
+        let mut suite = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("jsonl") => {
+                let cases = source
+                    .lines()
+                    .enumerate()
+                    .filter(|(_, line)| !line.trim().is_empty())
+                    .map(|(index, line)| {
+                        serde_json::from_str(line)
+                            .map_err(|e| anyhow::anyhow!("Invalid case on line {}: {}", index + 1, e))
+                    })
+                    .collect::<anyhow::Result<_>>()?;
+                EvalSuite {
+                    name: String::new(),
+                    model: None,
+                    cases,
+                }
+            }
+            // Assertions are maps with one key, like in JSON, rather than YAML tags
+            _ => serde_yaml::with::singleton_map_recursive::deserialize(serde_yaml::Deserializer::from_str(&source))
+                .map_err(|e| anyhow::anyhow!("Invalid suite: {}", e))?,
+        };
+
+        if suite.name.is_empty() {
+            suite.name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("eval")
+                .to_string();
+        }
+        if suite.cases.is_empty() {
+            anyhow::bail!("The suite has no cases");
+        }
+        for case in &suite.cases {
+            for assertion in &case.assertions {
+                match assertion {
+                    Assertion::Regex(pattern) => {
+                        Regex::new(pattern)
+                            .map_err(|e| anyhow::anyhow!("Invalid regex in case {}: {}", case.name, e))?;
+                    }
+                    Assertion::JsonSchema(schema) => {
+                        check_schema(schema)
+                            .map_err(|e| anyhow::anyhow!("Invalid json_schema in case {}: {}", case.name, e))?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(suite)
+    }
+}
+
+/// Tokens of the model calls of a case or suite
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct EvalTokens {
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseStatus {
+    Passed,
+    /// Answered, but an assertion failed
+    Failed,
+    /// The request failed, assertions weren't checked
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    /// Why it failed
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CaseResult {
+    pub name: String,
+    pub status: CaseStatus,
+    /// The model that answered
+    pub model: Option<String>,
+    pub latency_ms: u64,
+    pub tokens: EvalTokens,
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub assertions: Vec<AssertionResult>,
+}
+
+/// Over the answered cases
+#[derive(Debug, Default, Serialize)]
+pub struct LatencyStats {
+    pub min_ms: u64,
+    pub mean_ms: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EvalReport {
+    pub suite: String,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub cases: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub errors: usize,
+    /// Passed cases of all cases, from 0 to 1
+    pub pass_rate: f64,
+    pub latency: LatencyStats,
+    pub tokens: EvalTokens,
+    pub results: Vec<CaseResult>,
+}
+
+/// What a case got back
+struct Answer {
+    text: String,
+    model: String,
+    finish_reason: String,
+    tool_calls: Vec<String>,
+}
+
+/// Runs suites through `AIService` for one tenant, one case after another so
+/// the tokens recorded in between belong to the case
+pub struct EvalRunner {
+    ai_service: AIService,
+    tenant: TenantContext,
+}
+
+impl EvalRunner {
+    pub fn new(ai_service: AIService, tenant: TenantContext) -> Self {
+        Self { ai_service, tenant }
+    }
+
+    /// `model` replaces the models of the suite and its cases
+    pub async fn run(&self, suite: EvalSuite, model: Option<&str>) -> EvalReport {
+        let started_at = Utc::now();
+        let started = Instant::now();
+
+        let mut results = Vec::with_capacity(suite.cases.len());
+        for case in suite.cases {
+            let model = model.map(str::to_string).or_else(|| suite.model.clone());
+            let result = self.run_case(case, model).await;
+            tracing::info!(case = %result.name, status = ?result.status, latency_ms = result.latency_ms, "Eval case finished");
+            results.push(result);
+        }
+
+        let count = |status| results.iter().filter(|result| result.status == status).count();
+        let (passed, failed, errors) = (count(CaseStatus::Passed), count(CaseStatus::Failed), count(CaseStatus::Error));
+        let tokens = results.iter().fold(EvalTokens::default(), |sum, result| EvalTokens {
+            prompt_tokens: sum.prompt_tokens + result.tokens.prompt_tokens,
+            completion_tokens: sum.completion_tokens + result.tokens.completion_tokens,
+            total_tokens: sum.total_tokens + result.tokens.total_tokens,
+        });
+        let latencies: Vec<u64> = results
+            .iter()
+            .filter(|result| result.status != CaseStatus::Error)
+            .map(|result| result.latency_ms)
+            .collect();
+
+        EvalReport {
+            suite: suite.name,
+            started_at,
+            duration_ms: started.elapsed().as_millis() as u64,
+            cases: results.len(),
+            passed,
+            failed,
+            errors,
+            pass_rate: passed as f64 / results.len().max(1) as f64,
+            latency: latency_stats(latencies),
+            tokens,
+            results,
+        }
+    }
+
+    /// `model` is used when the case names none
+    async fn run_case(&self, case: EvalCase, model: Option<String>) -> CaseResult {
+        let before = self.tokens().await;
+        let started = Instant::now();
+        let answer = match case.input {
+            EvalInput::Chat(mut request) => {
+                request.model = request.model.or(model);
+                self.ai_service.chat(&self.tenant, request).await.map(|response| Answer {
+                    text: response.response,
+                    model: response.model,
+                    finish_reason: response.finish_reason,
+                    tool_calls: response.tool_calls.into_iter().map(|call| call.name).collect(),
+                })
+            }
+            EvalInput::Generate(mut request) => {
+                request.model = request.model.or(model);
+                self.ai_service.generate(&self.tenant, request).await.map(|response| Answer {
+                    text: response.text,
+                    model: response.model,
+                    finish_reason: response.finish_reason,
+                    tool_calls: Vec::new(),
+                })
+            }
+        };
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let after = self.tokens().await;
+        let tokens = EvalTokens {
+            prompt_tokens: after.prompt_tokens - before.prompt_tokens,
+            completion_tokens: after.completion_tokens - before.completion_tokens,
+            total_tokens: after.total_tokens - before.total_tokens,
+        };
+
+        let answer = match answer {
+            Ok(answer) => answer,
+            Err(e) => {
+                return CaseResult {
+                    name: case.name,
+                    status: CaseStatus::Error,
+                    model: None,
+                    latency_ms,
+                    tokens,
+                    response: None,
+                    error: Some(e.to_string()),
+                    assertions: Vec::new(),
+                };
+            }
+        };
+
+        let mut assertions = Vec::with_capacity(case.assertions.len());
+        for assertion in case.assertions {
+            let message = self.check(&assertion, &answer, latency_ms, tokens).await.err();
+            assertions.push(AssertionResult {
+                passed: message.is_none(),
+                assertion,
+                message,
+            });
+        }
+
+        CaseResult {
+            name: case.name,
+            status: match assertions.iter().all(|result| result.passed) {
+                true => CaseStatus::Passed,
+                false => CaseStatus::Failed,
+            },
+            model: Some(answer.model),
+            latency_ms,
+            tokens,
+            response: Some(answer.text),
+            error: None,
+            assertions,
+        }
+    }
+
+    /// `Err` says why the assertion failed
+    async fn check(&self, assertion: &Assertion, answer: &Answer, latency_ms: u64, tokens: EvalTokens) -> Result<(), String> {
+        match assertion {
+            Assertion::Similar { text: expected, min_score } => {
+                let min_score = min_score.unwrap_or(DEFAULT_MIN_SIMILARITY);
+                let score = self.similarity(&answer.text, expected).await.map_err(|e| e.to_string())?;
+                match score >= min_score {
+                    true => Ok(()),
+                    false => Err(format!("similarity {:.3} is below {}", score, min_score)),
+                }
+            }
+            assertion => check_answer(assertion, answer, latency_ms, tokens),
+        }
+    }
+
+    async fn similarity(&self, text: &str, expected: &str) -> Result<f64, AppError> {
+        let embeddings = self
+            .ai_service
+            .embed_texts(
+                &self.tenant,
+                vec![text.to_string(), expected.to_string()],
+                EmbeddingTask::SemanticSimilarity,
+            )
+            .await?;
+        let [a, b] = embeddings.vectors.as_slice() else {
+            return Err(AppError::ExternalService("The embedding model returned no vectors".to_string()));
+        };
+
+        let dot: f64 = a.iter().zip(b).map(|(x, y)| f64::from(*x) * f64::from(*y)).sum();
+        let norm = |v: &[f32]| v.iter().map(|x| f64::from(*x).powi(2)).sum::<f64>().sqrt();
+        let norms = norm(a) * norm(b);
+        Ok(if norms > 0.0 { dot / norms } else { 0.0 })
+    }
+
+    /// Tokens recorded for the tenant so far
+    async fn tokens(&self) -> EvalTokens {
+        let summary = self.ai_service.usage(&self.tenant).await.unwrap_or_default();
+        summary.iter().fold(EvalTokens::default(), |sum, row| EvalTokens {
+            prompt_tokens: sum.prompt_tokens + row.prompt_tokens,
+            completion_tokens: sum.completion_tokens + row.completion_tokens,
+            total_tokens: sum.total_tokens + row.total_tokens,
+        })
+    }
+}
+
+/// Checks the assertions that don't need a model, `similar` always fails here
+fn check_answer(assertion: &Assertion, answer: &Answer, latency_ms: u64, tokens: EvalTokens) -> Result<(), String> {
+    let text = answer.text.as_str();
+    match assertion {
+        Assertion::Equals(expected) if text.trim() == expected.trim() => Ok(()),
+        Assertion::Equals(_) => Err("the response differs".to_string()),
+        Assertion::Contains(expected) if text.contains(expected.as_str()) => Ok(()),
+        Assertion::Contains(_) => Err("not found in the response".to_string()),
+        Assertion::NotContains(unexpected) if !text.contains(unexpected.as_str()) => Ok(()),
+        Assertion::NotContains(_) => Err("found in the response".to_string()),
+        Assertion::Regex(pattern) => {
+            // Checked when the suite was loaded
+            let pattern = Regex::new(pattern).map_err(|e| e.to_string())?;
+            match pattern.is_match(text) {
+                true => Ok(()),
+                false => Err("no match in the response".to_string()),
+            }
+        }
+        Assertion::JsonSchema(schema) => parse_structured(text, schema).map(|_| ()),
+        Assertion::Similar { .. } => Err("similarity needs the embedding model".to_string()),
+        Assertion::FinishReason(expected) if answer.finish_reason.eq_ignore_ascii_case(expected) => Ok(()),
+        Assertion::FinishReason(_) => Err(format!("finished with {}", answer.finish_reason)),
+        Assertion::ToolCalled(name) if answer.tool_calls.contains(name) => Ok(()),
+        Assertion::ToolCalled(_) => Err(format!("called {:?}", answer.tool_calls)),
+        Assertion::MaxLatencyMs(limit) if latency_ms <= *limit => Ok(()),
+        Assertion::MaxLatencyMs(_) => Err(format!("took {} ms", latency_ms)),
+        Assertion::MaxTokens(limit) if tokens.total_tokens <= *limit => Ok(()),
+        Assertion::MaxTokens(_) => Err(format!("used {} tokens", tokens.total_tokens)),
+    }
+}
+
+/// Nearest-rank percentiles
+fn latency_stats(mut latencies: Vec<u64>) -> LatencyStats {
+    if latencies.is_empty() {
+        return LatencyStats::default();
+    }
+    latencies.sort_unstable();
+    let percentile = |p: usize| latencies[(latencies.len() * p).div_ceil(100).saturating_sub(1)];
+
+    LatencyStats {
+        min_ms: latencies[0],
+        mean_ms: latencies.iter().sum::<u64>() / latencies.len() as u64,
+        p50_ms: percentile(50),
+        p95_ms: percentile(95),
+        max_ms: latencies[latencies.len() - 1],
+    }
+}
+
+impl EvalReport {
+    /// The report as JUnit XML, one test case per eval case, for CI systems
+    /// that show test results
+    pub fn to_junit(&self) -> String {
+        let seconds = |ms: u64| format!("{:.3}", ms as f64 / 1000.0);
+        let suite = xml_escape(&self.suite);
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"eval\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\">\n",
+            self.cases,
+            self.failed,
+            self.errors,
+            seconds(self.duration_ms)
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{}\" timestamp=\"{}\">\n",
+            suite,
+            self.cases,
+            self.failed,
+            self.errors,
+            seconds(self.duration_ms),
+            self.started_at.format("%Y-%m-%dT%H:%M:%S")
+        ));
+        xml.push_str("    <properties>\n");
+        for (name, value) in [
+            ("pass_rate", format!("{:.4}", self.pass_rate)),
+            ("latency_p50_ms", self.latency.p50_ms.to_string()),
+            ("latency_p95_ms", self.latency.p95_ms.to_string()),
+            ("prompt_tokens", self.tokens.prompt_tokens.to_string()),
+            ("completion_tokens", self.tokens.completion_tokens.to_string()),
+            ("total_tokens", self.tokens.total_tokens.to_string()),
+        ] {
+            xml.push_str(&format!("      <property name=\"{}\" value=\"{}\"/>\n", name, value));
+        }
+        xml.push_str("    </properties>\n");
+
+        for result in &self.results {
+            xml.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\">\n",
+                xml_escape(&result.name),
+                suite,
+                seconds(result.latency_ms)
+            ));
+            match result.status {
+                CaseStatus::Passed => {}
+                CaseStatus::Failed => {
+                    let failures: Vec<String> = result
+                        .assertions
+                        .iter()
+                        .filter(|assertion| !assertion.passed)
+                        .map(|assertion| {
+                            format!("{}: {}", assertion.assertion, assertion.message.as_deref().unwrap_or_default())
+                        })
+                        .collect();
+                    xml.push_str(&format!(
+                        "      <failure message=\"{}\">{}</failure>\n",
+                        xml_escape(&failures[0]),
+                        xml_escape(&failures.join("\n"))
+                    ));
+                }
+                CaseStatus::Error => {
+                    let error = xml_escape(result.error.as_deref().unwrap_or_default());
+                    xml.push_str(&format!("      <error message=\"{}\">{}</error>\n", error, error));
+                }
+            }
+            if let Some(response) = &result.response {
+                xml.push_str(&format!("      <system-out>{}</system-out>\n", xml_escape(response)));
+            }
+            xml.push_str("    </testcase>\n");
+        }
+
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+/// Escapes text and attribute values, dropping characters XML 1.0 can't hold
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\t' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(text: &str) -> Answer {
+        Answer {
+            text: text.to_string(),
+            model: "mock".to_string(),
+            finish_reason: "STOP".to_string(),
+            tool_calls: vec!["get_user".to_string()],
+        }
+    }
+
+    fn tokens(total_tokens: i64) -> EvalTokens {
+        EvalTokens {
+            total_tokens,
+            ..Default::default()
+        }
+    }
+
+    fn check(assertion: Assertion, text: &str) -> Result<(), String> {
+        check_answer(&assertion, &answer(text), 120, tokens(50))
+    }
+
+    /// Writes `source` to a temporary file named `name`
+    fn suite_file(name: &str, source: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}", std::process::id(), name));
+        std::fs::write(&path, source).unwrap();
+        path
+    }
+
+    #[test]
+    fn checks_text_assertions() {
+        assert!(check(Assertion::Equals("Paris".into()), " Paris\n").is_ok());
+        assert_eq!(check(Assertion::Equals("Paris".into()), "Lyon").unwrap_err(), "the response differs");
+        assert!(check(Assertion::Contains("Par".into()), "Paris").is_ok());
+        assert!(check(Assertion::Contains("par".into()), "Paris").is_err());
+        assert!(check(Assertion::NotContains("error".into()), "Paris").is_ok());
+        assert!(check(Assertion::NotContains("Par".into()), "Paris").is_err());
+        assert!(check(Assertion::Regex("(?i)^paris$".into()), "PARIS").is_ok());
+        assert_eq!(check(Assertion::Regex("^a".into()), "b").unwrap_err(), "no match in the response");
+    }
+
+    #[test]
+    fn checks_json_schema_assertions() {
+        let schema = serde_json::json!({ "type": "object", "required": ["city"] });
+
+        assert!(check(Assertion::JsonSchema(schema.clone()), r#"{"city": "Paris"}"#).is_ok());
+        assert_eq!(check(Assertion::JsonSchema(schema), "{}").unwrap_err(), "$.city is required");
+    }
+
+    #[test]
+    fn checks_answer_metadata_and_limits() {
+        assert!(check(Assertion::FinishReason("stop".into()), "").is_ok());
+        assert_eq!(check(Assertion::FinishReason("MAX_TOKENS".into()), "").unwrap_err(), "finished with STOP");
+        assert!(check(Assertion::ToolCalled("get_user".into()), "").is_ok());
+        assert_eq!(check(Assertion::ToolCalled("search".into()), "").unwrap_err(), r#"called ["get_user"]"#);
+        assert!(check(Assertion::MaxLatencyMs(120), "").is_ok());
+        assert_eq!(check(Assertion::MaxLatencyMs(100), "").unwrap_err(), "took 120 ms");
+        assert!(check(Assertion::MaxTokens(50), "").is_ok());
+        assert_eq!(check(Assertion::MaxTokens(49), "").unwrap_err(), "used 50 tokens");
+    }
+
+    #[test]
+    fn computes_nearest_rank_percentiles() {
+        let stats = latency_stats(vec![100, 10, 90, 20, 80, 30, 70, 40, 60, 50]);
+
+        assert_eq!((stats.min_ms, stats.max_ms, stats.mean_ms), (10, 100, 55));
+        assert_eq!((stats.p50_ms, stats.p95_ms), (50, 100));
+
+        let single = latency_stats(vec![7]);
+        assert_eq!((single.p50_ms, single.p95_ms, single.mean_ms), (7, 7, 7));
+        assert_eq!(latency_stats(Vec::new()).max_ms, 0);
+    }
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(xml_escape("a<b>&\"'\n\t\u{1}c"), "a&lt;b&gt;&amp;&quot;&apos;&#10;\tc");
+    }
+
+    #[test]
+    fn writes_failures_and_errors_to_junit() {
+        let case = |name: &str, status, assertions, error: Option<&str>| CaseResult {
+            name: name.to_string(),
+            status,
+            model: None,
+            latency_ms: 1500,
+            tokens: EvalTokens::default(),
+            response: (status != CaseStatus::Error).then(|| "<b>answer</b>".to_string()),
+            error: error.map(str::to_string),
+            assertions,
+        };
+        let report = EvalReport {
+            suite: "smoke & more".to_string(),
+            started_at: Utc::now(),
+            duration_ms: 3000,
+            cases: 3,
+            passed: 1,
+            failed: 1,
+            errors: 1,
+            pass_rate: 1.0 / 3.0,
+            latency: LatencyStats::default(),
+            tokens: EvalTokens::default(),
+            results: vec![
+                case("passes", CaseStatus::Passed, Vec::new(), None),
+                case(
+                    "quotes \"x\"",
+                    CaseStatus::Failed,
+                    vec![AssertionResult {
+                        assertion: Assertion::Contains("<x>".to_string()),
+                        passed: false,
+                        message: Some("not found in the response".to_string()),
+                    }],
+                    None,
+                ),
+                case("fails", CaseStatus::Error, Vec::new(), Some("Model unavailable")),
+            ],
+        };
+
+        let xml = report.to_junit();
+
+        assert!(xml.contains(r#"<testsuites name="eval" tests="3" failures="1" errors="1" time="3.000">"#));
+        assert!(xml.contains(r#"<testcase name="quotes &quot;x&quot;" classname="smoke &amp; more" time="1.500">"#));
+        assert!(xml.contains(r#"<failure message="contains &quot;&lt;x&gt;&quot;: not found in the response">"#));
+        assert!(xml.contains(r#"<error message="Model unavailable">Model unavailable</error>"#));
+        assert!(xml.contains("<system-out>&lt;b&gt;answer&lt;/b&gt;</system-out>"));
+        assert_eq!(xml.matches("<testcase ").count(), 3);
+    }
+
+    #[test]
+    fn loads_yaml_suites() {
+        let suite = EvalSuite::load(Path::new("evals/smoke.yaml")).unwrap();
+
+        assert_eq!(suite.name, "smoke");
+        assert!(matches!(suite.cases[0].input, EvalInput::Chat(_)));
+        assert!(matches!(suite.cases[1].assertions[0], Assertion::Similar { min_score: Some(_), .. }));
+    }
+
+    #[test]
+    fn loads_json_lines_named_after_the_file() {
+        let path = suite_file(
+            "lines.jsonl",
+            "{\"name\": \"a\", \"generate\": {\"prompt\": \"Hi\"}, \"assert\": [{\"contains\": \"Hi\"}]}\n\n",
+        );
+
+        let suite = EvalSuite::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(suite.name, format!("{}-lines", std::process::id()));
+        assert_eq!(suite.cases.len(), 1);
+        assert!(matches!(suite.cases[0].input, EvalInput::Generate(_)));
+    }
+
+    #[test]
+    fn rejects_invalid_suites() {
+        let path = suite_file("regex.yaml", "cases:\n  - name: a\n    chat:\n      message: Hi\n    assert:\n      - regex: \"(\"\n");
+        let error = EvalSuite::load(&path).unwrap_err().to_string();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.starts_with("Invalid regex in case a"), "{}", error);
+
+        let path = suite_file("empty.yaml", "cases: []\n");
+        let error = EvalSuite::load(&path).unwrap_err().to_string();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error, "The suite has no cases");
+    }
+}
@@ -1,3 +1,28 @@
+mod batch_service;
+mod chunking;
+mod context_window;
+mod diff;
+mod embedding_service;
+mod eval;
+mod moderation;
+mod redaction;
+mod request_log_service;
+mod response_schema;
 mod service;
+mod template_service;
+mod tools;
+mod user_insights_service;
+mod user_tools;
 
+pub use batch_service::BatchService;
+pub use context_window::ContextWindow;
+pub use embedding_service::EmbeddingService;
+pub use eval::{EvalRunner, EvalSuite};
+pub use moderation::ModerationPipeline;
+pub use redaction::Redactor;
+pub use request_log_service::RequestLogService;
 pub use service::AIService;
+pub use template_service::PromptTemplateService;
+pub use tools::{Tool, ToolRegistry};
+pub use user_insights_service::UserInsightsService;
+pub use user_tools::{FindUserByEmailTool, GetUserTool, ListUsersTool};
@@ -0,0 +1,539 @@
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
+
+use crate::entities::ai::{ChatMessage, MessagePart};
+use crate::shared::config::AiRedactionConfig;
+
+const EMAIL: &str = r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b";
+
+/// International numbers start with `+`, others need separators like `030 1234567`
+const PHONE: &str = r"(?:\+|\()?\b\d[\d ()./-]{6,}\d\b";
+
+/// 13 to 19 digits, optionally in groups
+const CARD: &str = r"\b\d(?:[ -]?\d){12,18}\b";
+
+/// Country, check digits and up to 30 letters or digits, optionally in groups of four
+const IBAN: &str = r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b";
+
+/// Dates such as 2024-11-20 or 20.11.2024 look like phone numbers with separators
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:\d{4}[-./]\d{1,2}[-./]\d{1,2}|\d{1,2}[-./]\d{1,2}[-./]\d{4})").expect("the date pattern is valid")
+});
+
+/// A kind of personal data and how it is found
+struct Rule {
+    /// Detector or pattern name, used in counts
+    name: String,
+    /// Prefix of its placeholders, e.g. EMAIL
+    label: String,
+    pattern: Regex,
+    /// Rejects matches that only look like this kind, e.g. numbers failing the Luhn check
+    check: fn(&str) -> bool,
+}
+
+/// Finds personal data in prompts and replaces it with placeholders such as
+/// `[EMAIL_1]`. It doesn't call any provider, a `Redaction` works on plain text.
+pub struct Redactor {
+    /// Applied in order, a later rule doesn't match inside an earlier match
+    rules: Vec<Rule>,
+    rehydrate: bool,
+}
+
+impl Redactor {
+    pub fn new(config: &AiRedactionConfig) -> anyhow::Result<Self> {
+        if !config.enabled {
+            return Ok(Self {
+                rules: Vec::new(),
+                rehydrate: false,
+            });
+        }
+
+        let mut rules = Vec::new();
+        for (name, pattern) in &config.patterns {
+            if !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+                anyhow::bail!("Redaction pattern names use lowercase letters, digits and _, got {}", name);
+            }
+            let pattern = Regex::new(pattern)
+                .map_err(|e| anyhow::anyhow!("Invalid redaction pattern {}: {}", name, e))?;
+            rules.push(Rule {
+                name: name.clone(),
+                label: name.to_uppercase(),
+                pattern,
+                check: |_| true,
+            });
+        }
+
+        // Cards and IBANs first, their digits would otherwise pass as phone numbers
+        let mut detectors = config.detectors.clone();
+        detectors.sort_by_key(|detector| ["card", "iban", "email", "phone"].iter().position(|d| d == detector));
+        for detector in detectors {
+            let (pattern, check): (&str, fn(&str) -> bool) = match detector.as_str() {
+                "card" => (CARD, luhn_valid),
+                "iban" => (IBAN, iban_valid),
+                "email" => (EMAIL, |_| true),
+                "phone" => (PHONE, phone_like),
+                other => anyhow::bail!("Unknown redaction detector: {}", other),
+            };
+            rules.push(Rule {
+                label: detector.to_uppercase(),
+                name: detector,
+                pattern: Regex::new(pattern).expect("built-in redaction patterns are valid"),
+                check,
+            });
+        }
+
+        Ok(Self {
+            rules,
+            rehydrate: config.rehydrate,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    /// Placeholders for one request
+    pub fn session(&self) -> Redaction<'_> {
+        Redaction {
+            redactor: self,
+            placeholders: HashMap::new(),
+            originals: Vec::new(),
+            numbers: HashMap::new(),
+            counts: BTreeMap::new(),
+        }
+    }
+}
+
+/// The values one request replaced. The same value always gets the same
+/// placeholder, and values are numbered in the order they appear, so a
+/// conversation resent turn by turn keeps its placeholders.
+pub struct Redaction<'a> {
+    redactor: &'a Redactor,
+    /// Placeholder of each value
+    placeholders: HashMap<String, String>,
+    /// Placeholders with their values, in order
+    originals: Vec<(String, String)>,
+    /// Placeholders given per label
+    numbers: HashMap<String, usize>,
+    /// Matches per rule name
+    counts: BTreeMap<String, i32>,
+}
+
+impl Redaction<'_> {
+    pub fn redact(&mut self, text: &str) -> String {
+        if !self.redactor.is_enabled() {
+            return text.to_string();
+        }
+
+        // Matches of earlier rules win, later ones may not overlap them
+        let mut matches: Vec<(usize, usize, usize)> = Vec::new();
+        for (index, rule) in self.redactor.rules.iter().enumerate() {
+            for found in rule.pattern.find_iter(text) {
+                let overlaps = matches
+                    .iter()
+                    .any(|&(start, end, _)| found.start() < end && start < found.end());
+                if !found.is_empty() && !overlaps && (rule.check)(found.as_str()) {
+                    matches.push((found.start(), found.end(), index));
+                }
+            }
+        }
+        if matches.is_empty() {
+            return text.to_string();
+        }
+        matches.sort_unstable();
+
+        let mut redacted = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end, index) in matches {
+            redacted.push_str(&text[last..start]);
+            redacted.push_str(&self.placeholder(index, &text[start..end]));
+            last = end;
+        }
+        redacted.push_str(&text[last..]);
+        redacted
+    }
+
+    /// The text of the messages, attachments are sent as they are
+    pub fn redact_messages(&mut self, messages: &mut [ChatMessage]) {
+        for message in messages {
+            message.content = self.redact(&message.content);
+            for part in &mut message.parts {
+                if let MessagePart::Text(text) = part {
+                    *text = self.redact(text);
+                }
+            }
+        }
+    }
+
+    /// Every string in the value, e.g. a tool result
+    pub fn redact_json(&mut self, value: &mut serde_json::Value) {
+        if !self.redactor.is_enabled() {
+            return;
+        }
+        match value {
+            serde_json::Value::String(text) => *text = self.redact(text),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|item| self.redact_json(item)),
+            serde_json::Value::Object(fields) => fields.values_mut().for_each(|field| self.redact_json(field)),
+            _ => {}
+        }
+    }
+
+    /// Puts the original values back, e.g. into tool arguments that run locally
+    pub fn restore(&self, text: &str) -> String {
+        self.originals
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, value)| {
+                if text.contains(placeholder.as_str()) {
+                    text.replace(placeholder.as_str(), value)
+                } else {
+                    text
+                }
+            })
+    }
+
+    pub fn restore_json(&self, value: &mut serde_json::Value) {
+        if self.originals.is_empty() {
+            return;
+        }
+        match value {
+            serde_json::Value::String(text) => *text = self.restore(text),
+            serde_json::Value::Array(items) => items.iter_mut().for_each(|item| self.restore_json(item)),
+            serde_json::Value::Object(fields) => fields.values_mut().for_each(|field| self.restore_json(field)),
+            _ => {}
+        }
+    }
+
+    /// An answer with the original values, unless rehydration is off
+    pub fn rehydrate(&self, text: String) -> String {
+        match self.redactor.rehydrate {
+            true => self.restore(&text),
+            false => text,
+        }
+    }
+
+    pub fn rehydrate_json(&self, value: &mut serde_json::Value) {
+        if self.redactor.rehydrate {
+            self.restore_json(value);
+        }
+    }
+
+    /// Matches per detector or pattern name, values aren't kept
+    pub fn counts(&self) -> &BTreeMap<String, i32> {
+        &self.counts
+    }
+
+    fn placeholder(&mut self, rule: usize, value: &str) -> String {
+        let rule = &self.redactor.rules[rule];
+        *self.counts.entry(rule.name.clone()).or_default() += 1;
+
+        if let Some(placeholder) = self.placeholders.get(value) {
+            return placeholder.clone();
+        }
+        let number = self.numbers.entry(rule.label.clone()).or_default();
+        *number += 1;
+        let placeholder = format!("[{}_{}]", rule.label, number);
+        self.placeholders.insert(value.to_string(), placeholder.clone());
+        self.originals.push((placeholder.clone(), value.to_string()));
+        placeholder
+    }
+}
+
+fn digits(text: &str) -> Vec<u32> {
+    text.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+fn luhn_valid(text: &str) -> bool {
+    let sum: u32 = digits(text)
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match i % 2 {
+            1 if digit * 2 > 9 => digit * 2 - 9,
+            1 => digit * 2,
+            _ => digit,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// ISO 13616 check: the country and check digits moved to the end, letters
+/// as numbers from 10, leave 1 modulo 97
+fn iban_valid(text: &str) -> bool {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if !(15..=34).contains(&compact.len()) {
+        return false;
+    }
+    let (head, tail) = compact.split_at(4);
+    tail.chars()
+        .chain(head.chars())
+        .try_fold(0u32, |remainder, c| {
+            let value = c.to_digit(36)?;
+            Some(match value {
+                0..=9 => (remainder * 10 + value) % 97,
+                _ => (remainder * 100 + value) % 97,
+            })
+        })
+        == Some(1)
+}
+
+fn phone_like(text: &str) -> bool {
+    let count = digits(text).len();
+    if !(9..=15).contains(&count) {
+        return false;
+    }
+    text.starts_with('+') || (text.contains([' ', '(', ')', '.', '/', '-']) && !DATE.is_match(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::ai::ChatRole;
+
+    fn redactor(detectors: &[&str], patterns: &[(&str, &str)], rehydrate: bool) -> Redactor {
+        Redactor::new(&AiRedactionConfig {
+            enabled: true,
+            detectors: detectors.iter().map(|d| d.to_string()).collect(),
+            patterns: patterns
+                .iter()
+                .map(|(name, pattern)| (name.to_string(), pattern.to_string()))
+                .collect(),
+            rehydrate,
+        })
+        .unwrap()
+    }
+
+    fn all() -> Redactor {
+        redactor(&["email", "phone", "card", "iban"], &[], true)
+    }
+
+    #[test]
+    fn disabled_redactor_leaves_text_alone() {
+        let redactor = Redactor::new(&AiRedactionConfig {
+            enabled: false,
+            detectors: vec!["email".to_string()],
+            patterns: Vec::new(),
+            rehydrate: true,
+        })
+        .unwrap();
+        let mut session = redactor.session();
+
+        assert!(!redactor.is_enabled());
+        assert_eq!(session.redact("mail ann@example.com"), "mail ann@example.com");
+        assert!(session.counts().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_detectors_and_bad_patterns() {
+        let config = |detectors: Vec<String>, patterns: Vec<(String, String)>| AiRedactionConfig {
+            enabled: true,
+            detectors,
+            patterns,
+            rehydrate: true,
+        };
+
+        assert!(Redactor::new(&config(vec!["ssn".to_string()], Vec::new())).is_err());
+        assert!(Redactor::new(&config(Vec::new(), vec![("Employee".to_string(), r"E\d+".to_string())])).is_err());
+        assert!(Redactor::new(&config(Vec::new(), vec![("employee".to_string(), r"E(\d+".to_string())])).is_err());
+    }
+
+    #[test]
+    fn detects_emails() {
+        let redactor = all();
+        let mut session = redactor.session();
+
+        assert_eq!(
+            session.redact("Write to ann.lee+work@mail.example.co.uk today"),
+            "Write to [EMAIL_1] today"
+        );
+        assert_eq!(session.redact("Not an address: ann@localhost or @example.com"), "Not an address: ann@localhost or @example.com");
+    }
+
+    #[test]
+    fn detects_cards_passing_luhn() {
+        let redactor = all();
+        let mut session = redactor.session();
+
+        assert_eq!(session.redact("Card 4111 1111 1111 1111 expires"), "Card [CARD_1] expires");
+        // Values are compared as written, other separators make another placeholder
+        assert_eq!(session.redact("Card 4111-1111-1111-1111"), "Card [CARD_2]");
+        // Fails the Luhn check and has too many digits for a phone number
+        assert_eq!(session.redact("Order 4111 1111 1111 1112"), "Order 4111 1111 1111 1112");
+        assert_eq!(session.counts().get("card"), Some(&2));
+    }
+
+    #[test]
+    fn luhn_check() {
+        assert!(luhn_valid("79927398713"));
+        assert!(luhn_valid("5555 5555 5555 4444"));
+        assert!(!luhn_valid("79927398710"));
+        assert!(!luhn_valid("1234 5678 9012 3456"));
+    }
+
+    #[test]
+    fn detects_ibans_passing_mod_97() {
+        let redactor = all();
+        let mut session = redactor.session();
+
+        assert_eq!(session.redact("Pay to DE89 3704 0044 0532 0130 00."), "Pay to [IBAN_1].");
+        assert_eq!(session.redact("Pay to GB82WEST12345698765432"), "Pay to [IBAN_2]");
+        assert_eq!(session.redact("Pay to DE88 3704 0044 0532 0130 00"), "Pay to DE88 3704 0044 0532 0130 00");
+    }
+
+    #[test]
+    fn iban_check() {
+        assert!(iban_valid("DE89370400440532013000"));
+        assert!(iban_valid("FR14 2004 1010 0505 0001 3M02 606"));
+        assert!(!iban_valid("DE89370400440532013001"));
+        // Too short for any country
+        assert!(!iban_valid("DE8937040044"));
+    }
+
+    #[test]
+    fn detects_phone_numbers() {
+        let redactor = all();
+        let mut session = redactor.session();
+
+        assert_eq!(session.redact("Call +49 30 1234567"), "Call [PHONE_1]");
+        assert_eq!(session.redact("Call 030 12345678 now"), "Call [PHONE_2] now");
+        assert_eq!(session.redact("Call (030) 123-4567"), "Call [PHONE_3]");
+    }
+
+    #[test]
+    fn leaves_dates_and_plain_numbers_alone() {
+        let redactor = all();
+        let mut session = redactor.session();
+
+        for text in [
+            "Due 2024-11-20 12",
+            "Due 20.11.2024 10",
+            "Invoice 123456789012",
+            "Room 1234 567",
+            "Version 1.2.3",
+        ] {
+            assert_eq!(session.redact(text), text);
+        }
+        assert!(session.counts().is_empty());
+    }
+
+    #[test]
+    fn phone_heuristics() {
+        assert!(phone_like("+1 555 0100 22"));
+        assert!(phone_like("030/1234567"));
+        assert!(!phone_like("0301234567"));
+        assert!(!phone_like("+49 30"));
+        assert!(!phone_like("2024/11/20 123"));
+        assert!(!phone_like("+1234567890123456"));
+    }
+
+    #[test]
+    fn cards_win_over_phone_numbers() {
+        let redactor = redactor(&["phone", "card"], &[], true);
+        let mut session = redactor.session();
+
+        assert_eq!(session.redact("4111 1111 1111 1111"), "[CARD_1]");
+        assert_eq!(session.counts().get("phone"), None);
+    }
+
+    #[test]
+    fn custom_patterns_take_precedence() {
+        let redactor = redactor(
+            &["email"],
+            &[("employee_id", r"\bE-\d{6}\b"), ("internal", r"\b[a-z]+@corp\.example\b")],
+            true,
+        );
+        let mut session = redactor.session();
+
+        assert_eq!(
+            session.redact("E-123456 is ann@corp.example, bob@example.com"),
+            "[EMPLOYEE_ID_1] is [INTERNAL_1], [EMAIL_1]"
+        );
+        assert_eq!(session.counts().get("employee_id"), Some(&1));
+        assert_eq!(session.counts().get("internal"), Some(&1));
+        assert_eq!(session.counts().get("email"), Some(&1));
+    }
+
+    #[test]
+    fn repeated_values_reuse_their_placeholder() {
+        let redactor = all();
+        let mut session = redactor.session();
+
+        assert_eq!(
+            session.redact("ann@example.com, bob@example.com, ann@example.com"),
+            "[EMAIL_1], [EMAIL_2], [EMAIL_1]"
+        );
+        // Numbering continues across texts of the same request
+        assert_eq!(session.redact("cc bob@example.com and cy@example.com"), "cc [EMAIL_2] and [EMAIL_3]");
+        assert_eq!(session.counts().get("email"), Some(&5));
+
+        // A new request starts over in the same order
+        let mut next = redactor.session();
+        assert_eq!(next.redact("ann@example.com"), "[EMAIL_1]");
+    }
+
+    #[test]
+    fn redacts_message_text_and_parts() {
+        let redactor = all();
+        let mut session = redactor.session();
+        let mut messages = vec![
+            ChatMessage {
+                role: ChatRole::User,
+                content: "I'm ann@example.com".to_string(),
+                parts: vec![MessagePart::Text("Call +49 30 1234567".to_string())],
+            },
+            ChatMessage {
+                role: ChatRole::Model,
+                content: "Hello ann@example.com".to_string(),
+                parts: Vec::new(),
+            },
+        ];
+
+        session.redact_messages(&mut messages);
+
+        assert_eq!(messages[0].content, "I'm [EMAIL_1]");
+        assert!(matches!(&messages[0].parts[0], MessagePart::Text(text) if text == "Call [PHONE_1]"));
+        assert_eq!(messages[1].content, "Hello [EMAIL_1]");
+    }
+
+    #[test]
+    fn restores_original_values() {
+        let redactor = all();
+        let mut session = redactor.session();
+        let redacted = session.redact("ann@example.com pays with 4111 1111 1111 1111");
+
+        assert_eq!(redacted, "[EMAIL_1] pays with [CARD_1]");
+        assert_eq!(
+            session.rehydrate("Charged [CARD_1] for [EMAIL_1], [PHONE_9] is unknown".to_string()),
+            "Charged 4111 1111 1111 1111 for ann@example.com, [PHONE_9] is unknown"
+        );
+
+        let mut value = serde_json::json!({ "to": "[EMAIL_1]", "cards": ["[CARD_1]"], "n": 1 });
+        session.rehydrate_json(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!({ "to": "ann@example.com", "cards": ["4111 1111 1111 1111"], "n": 1 })
+        );
+    }
+
+    #[test]
+    fn keeps_placeholders_without_rehydration() {
+        let redactor = redactor(&["email"], &[], false);
+        let mut session = redactor.session();
+        session.redact("ann@example.com");
+
+        assert_eq!(session.rehydrate("Hi [EMAIL_1]".to_string()), "Hi [EMAIL_1]");
+        // Tools still get the original values
+        assert_eq!(session.restore("lookup [EMAIL_1]"), "lookup ann@example.com");
+    }
+
+    #[test]
+    fn redacts_strings_in_json() {
+        let redactor = all();
+        let mut session = redactor.session();
+        let mut value = serde_json::json!({ "user": { "email": "ann@example.com" }, "ids": [1, 2] });
+
+        session.redact_json(&mut value);
+
+        assert_eq!(value, serde_json::json!({ "user": { "email": "[EMAIL_1]" }, "ids": [1, 2] }));
+    }
+}
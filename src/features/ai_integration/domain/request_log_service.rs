@@ -0,0 +1,119 @@
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::diff::word_diff;
+use super::AIService;
+use crate::{
+    entities::ai::AiRequestLog,
+    features::ai_integration::infrastructure::{LoggedRequest, RequestLogFilter, RequestLogRepository},
+    features::ai_integration::model::{ReplayRequest, ReplayResponse, RequestLogQuery},
+    features::organizations::model::TenantContext,
+    shared::config::AiRequestLogConfig,
+    shared::error::AppError,
+};
+
+/// How often logs past their retention are deleted
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Search and replay of logged model calls, and their retention
+#[derive(Clone)]
+pub struct RequestLogService {
+    logs: Arc<dyn RequestLogRepository>,
+    ai_service: AIService,
+    config: AiRequestLogConfig,
+}
+
+impl RequestLogService {
+    pub fn new(logs: Arc<dyn RequestLogRepository>, ai_service: AIService, config: AiRequestLogConfig) -> Self {
+        Self {
+            logs,
+            ai_service,
+            config,
+        }
+    }
+
+    pub async fn search(&self, tenant: &TenantContext, query: RequestLogQuery) -> Result<Vec<AiRequestLog>, AppError> {
+        let filter = RequestLogFilter {
+            operation: query.operation,
+            model: query.model,
+            user_id: query.user_id,
+            api_key_id: query.api_key_id,
+            failed: query.failed,
+            text: query.q.filter(|q| !q.trim().is_empty()),
+            since: query.since,
+            until: query.until,
+            limit: query.limit.unwrap_or(50).clamp(1, 200),
+        };
+        self.logs
+            .search(tenant.organization_id(), &filter)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub async fn get(&self, tenant: &TenantContext, id: Uuid) -> Result<AiRequestLog, AppError> {
+        self.logs
+            .find(tenant.organization_id(), id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or(AppError::NotFound)
+    }
+
+    /// Sends a logged call again and compares the answers. Tools the model
+    /// asks for aren't called, the logged tool results are sent as they were.
+    pub async fn replay(
+        &self,
+        tenant: &TenantContext,
+        id: Uuid,
+        input: ReplayRequest,
+    ) -> Result<ReplayResponse, AppError> {
+        let log = self.get(tenant, id).await?;
+        let request: LoggedRequest = serde_json::from_value(log.request.clone())
+            .map_err(|e| AppError::Validation(format!("The logged request can't be replayed: {}", e)))?;
+        let model = input
+            .model
+            .or_else(|| log.model.clone())
+            .unwrap_or_else(|| log.requested_model.clone());
+
+        let (completion, latency) = self.ai_service.replay(tenant, request, &model).await?;
+        let original = log.response.as_deref().unwrap_or_default();
+        Ok(ReplayResponse {
+            log_id: log.id,
+            identical: log.response.as_deref() == Some(completion.text.as_str()),
+            diff: word_diff(original, &completion.text),
+            model: completion.model,
+            original_model: log.model,
+            response: completion.text,
+            original_response: log.response,
+            tool_calls: (!completion.tool_calls.is_empty())
+                .then(|| serde_json::to_value(&completion.tool_calls).unwrap_or_default()),
+            finish_reason: completion.finish_reason,
+            total_tokens: completion.usage.total_tokens,
+            latency_ms: latency.as_millis() as i64,
+            original_latency_ms: log.latency_ms,
+        })
+    }
+
+    /// Deletes logs older than `AI_REQUEST_LOG_RETENTION_DAYS` every hour,
+    /// also while logging is off. Nothing is deleted when the retention is 0.
+    pub fn start_retention(&self) {
+        if self.config.retention_days == 0 {
+            return;
+        }
+        let service = self.clone();
+        tokio::spawn(service.run_retention());
+    }
+
+    async fn run_retention(self) {
+        let age = Duration::from_secs(u64::from(self.config.retention_days) * 24 * 3600);
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match self.logs.purge(age).await {
+                Ok(0) => {}
+                Ok(deleted) => tracing::info!(deleted, "Deleted expired AI request logs"),
+                Err(e) => tracing::error!("Failed to delete expired AI request logs: {}", e),
+            }
+        }
+    }
+}
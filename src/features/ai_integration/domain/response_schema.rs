@@ -0,0 +1,227 @@
+use serde_json::Value;
+
+use crate::shared::error::AppError;
+
+/// Checks that a request's `response_schema` is usable: an object with a `type`
+pub fn check_schema(schema: &Value) -> Result<(), AppError> {
+    match schema.get("type").and_then(Value::as_str) {
+        Some(kind) if schema_type(kind).is_some() => Ok(()),
+        _ => Err(AppError::Validation(
+            "response_schema must be an object with a type of string, number, integer, boolean, array or object"
+                .to_string(),
+        )),
+    }
+}
+
+/// Parses model output as JSON and validates it against `schema`. Markdown
+/// code fences around the JSON are tolerated.
+pub fn parse_structured(text: &str, schema: &Value) -> Result<Value, String> {
+    let value: Value =
+        serde_json::from_str(strip_code_fence(text)).map_err(|e| format!("the output is not valid JSON: {}", e))?;
+    validate(schema, &value, "$")?;
+    Ok(value)
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    // Drop the language tag on the opening line, e.g. ```json
+    let body = rest.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// Type names as Gemini accepts them, in either case
+fn schema_type(kind: &str) -> Option<&'static str> {
+    match kind.to_ascii_lowercase().as_str() {
+        "string" => Some("string"),
+        "number" => Some("number"),
+        "integer" => Some("integer"),
+        "boolean" => Some("boolean"),
+        "array" => Some("array"),
+        "object" => Some("object"),
+        _ => None,
+    }
+}
+
+/// Validates the keywords of the OpenAPI schema subset Gemini supports:
+/// `type`, `nullable`, `enum`, `properties`, `required`, `items`, `minItems`,
+/// `maxItems`, `minimum` and `maximum`. Other keywords are ignored.
+fn validate(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    if value.is_null() {
+        return if schema.get("nullable").and_then(Value::as_bool) == Some(true) || schema.get("type").is_none() {
+            Ok(())
+        } else {
+            Err(format!("{} must not be null", path))
+        };
+    }
+
+    if let Some(kind) = schema.get("type").and_then(Value::as_str).and_then(schema_type) {
+        let matches = match kind {
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "boolean" => value.is_boolean(),
+            "array" => value.is_array(),
+            _ => value.is_object(),
+        };
+        if !matches {
+            return Err(format!("{} must be of type {}", path, kind));
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array)
+        && !allowed.contains(value)
+    {
+        return Err(format!("{} must be one of {}", path, Value::Array(allowed.clone())));
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64)
+            && number < minimum
+        {
+            return Err(format!("{} must be at least {}", path, minimum));
+        }
+        if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64)
+            && number > maximum
+        {
+            return Err(format!("{} must be at most {}", path, maximum));
+        }
+    }
+
+    if let Some(items) = value.as_array() {
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64)
+            && (items.len() as u64) < min
+        {
+            return Err(format!("{} must have at least {} items", path, min));
+        }
+        if let Some(max) = schema.get("maxItems").and_then(Value::as_u64)
+            && (items.len() as u64) > max
+        {
+            return Err(format!("{} must have at most {} items", path, max));
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                validate(item_schema, item, &format!("{}[{}]", path, index))?;
+            }
+        }
+    }
+
+    if let Some(object) = value.as_object() {
+        for name in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+            if let Some(name) = name.as_str()
+                && !object.contains_key(name)
+            {
+                return Err(format!("{}.{} is required", path, name));
+            }
+        }
+        if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+            for (name, property_schema) in properties {
+                if let Some(property) = object.get(name) {
+                    validate(property_schema, property, &format!("{}.{}", path, name))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn person() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer", "minimum": 0, "maximum": 150 },
+                "role": { "type": "string", "enum": ["admin", "member"] },
+                "tags": { "type": "array", "items": { "type": "string" }, "minItems": 1, "maxItems": 2 },
+                "manager": { "type": "string", "nullable": true }
+            },
+            "required": ["name", "age"]
+        })
+    }
+
+    #[test]
+    fn accepts_schemas_with_a_known_type() {
+        assert!(check_schema(&json!({ "type": "object" })).is_ok());
+        assert!(check_schema(&json!({ "type": "STRING" })).is_ok());
+    }
+
+    #[test]
+    fn rejects_schemas_without_a_known_type() {
+        assert!(check_schema(&json!({})).is_err());
+        assert!(check_schema(&json!({ "type": "date" })).is_err());
+        assert!(check_schema(&json!("object")).is_err());
+    }
+
+    #[test]
+    fn parses_valid_output() {
+        let value = parse_structured(r#"{"name": "Ann", "age": 41, "tags": ["a"]}"#, &person()).unwrap();
+
+        assert_eq!(value, json!({ "name": "Ann", "age": 41, "tags": ["a"] }));
+    }
+
+    #[test]
+    fn strips_code_fences() {
+        let text = "```json\n{\"name\": \"Ann\", \"age\": 41}\n```";
+
+        assert_eq!(parse_structured(text, &person()).unwrap()["name"], "Ann");
+        assert_eq!(strip_code_fence("```\n[1]\n```"), "[1]");
+        assert_eq!(strip_code_fence("  {}  "), "{}");
+    }
+
+    #[test]
+    fn reports_invalid_json() {
+        let error = parse_structured("Sure! Here it is", &person()).unwrap_err();
+
+        assert!(error.starts_with("the output is not valid JSON"), "{}", error);
+    }
+
+    #[test]
+    fn reports_missing_required_properties() {
+        let error = parse_structured(r#"{"name": "Ann"}"#, &person()).unwrap_err();
+
+        assert_eq!(error, "$.age is required");
+    }
+
+    #[test]
+    fn reports_wrong_types_with_their_path() {
+        let error = parse_structured(r#"{"name": "Ann", "age": 41.5}"#, &person()).unwrap_err();
+        assert_eq!(error, "$.age must be of type integer");
+
+        let error = parse_structured(r#"{"name": "Ann", "age": 1, "tags": ["a", 2]}"#, &person());
+        assert_eq!(error.unwrap_err(), "$.tags[1] must be of type string");
+    }
+
+    #[test]
+    fn checks_bounds_and_enums() {
+        let error = parse_structured(r#"{"name": "Ann", "age": 200}"#, &person()).unwrap_err();
+        assert_eq!(error, "$.age must be at most 150");
+
+        let error = parse_structured(r#"{"name": "Ann", "age": -1}"#, &person()).unwrap_err();
+        assert_eq!(error, "$.age must be at least 0");
+
+        let error = parse_structured(r#"{"name": "Ann", "age": 1, "role": "owner"}"#, &person()).unwrap_err();
+        assert_eq!(error, r#"$.role must be one of ["admin","member"]"#);
+
+        let error = parse_structured(r#"{"name": "Ann", "age": 1, "tags": []}"#, &person()).unwrap_err();
+        assert_eq!(error, "$.tags must have at least 1 items");
+
+        let error = parse_structured(r#"{"name": "Ann", "age": 1, "tags": ["a", "b", "c"]}"#, &person());
+        assert_eq!(error.unwrap_err(), "$.tags must have at most 2 items");
+    }
+
+    #[test]
+    fn allows_null_only_when_nullable() {
+        assert!(parse_structured(r#"{"name": "Ann", "age": 1, "manager": null}"#, &person()).is_ok());
+
+        let error = parse_structured(r#"{"name": null, "age": 1}"#, &person()).unwrap_err();
+        assert_eq!(error, "$.name must not be null");
+    }
+}
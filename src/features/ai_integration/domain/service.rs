@@ -1,52 +1,1448 @@
-use std::sync::Arc;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
 use validator::Validate;
 
+use super::context_window::{
+    conversation_tokens, drop_oldest, estimate_tokens, instruction_tokens, message_tokens, summary_split, transcript,
+    ContextWindow,
+};
+use super::embedding_service::validate_collection;
+use super::moderation::ModerationPipeline;
+use super::redaction::{Redaction, Redactor};
+use super::response_schema::{check_schema, parse_structured};
+use super::ToolRegistry;
 use crate::{
-    features::ai_integration::infrastructure::AIRepository,
-    features::ai_integration::model::{ChatRequest, ChatResponse, GenerateRequest, GenerateResponse},
+    entities::ai::{
+        AiFile, Candidate, ChatMessage, ChatRole, ContextStrategy, EmbeddingTask, InlineData, MessagePart, ModerationAction,
+        ModerationFlag, ModerationStage,
+    },
+    features::ai_integration::infrastructure::{
+        AIRepository, AiCompletion, AiEmbeddings, AiFileRepository, AiRedactionRecord, AiUsageRecord, AiUsageRepository,
+        CompletionOptions, EmbeddingQuery, EmbeddingRepository, GenerationConfig, LoggedRequest, ModelRouter,
+        ModerationRepository, NewAiFile, NewModerationFlag, NewRequestLog, RequestLogRepository, ResponseCache,
+        TokenUsage, ToolCall, ToolStep, EMBEDDING_DIMENSIONS,
+    },
+    features::ai_integration::model::{
+        AiRedactionSummary, AiUsageSummary, ChatRequest, ChatResponse, Citation, ContextUsage, CountTokensRequest, CountTokensResponse,
+        EmbedRequest, EmbedResponse, GenerateRequest, GenerateResponse, ModelInfo, ModelListResponse, RagOptions,
+        ReviewModerationFlagRequest, RouteInfo, SearchRequest, SearchResponse, ToolInfo, ToolInvocation,
+    },
+    features::auth::model::Principal,
+    features::organizations::model::TenantContext,
+    shared::config::AiAttachmentConfig,
     shared::error::AppError,
+    shared::security::hash_token,
 };
 
+/// Retries when structured output doesn't match its schema
+const MAX_JSON_REPAIRS: usize = 1;
+
+/// Longest summary of the turns left out of a chat
+const MAX_SUMMARY_TOKENS: usize = 1024;
+
+/// How long the model list with the providers' details is reused
+const MODEL_LIST_TTL: Duration = Duration::from_secs(3600);
+
 #[derive(Clone)]
 pub struct AIService {
-    repository: Arc<dyn AIRepository>,
+    models: Arc<ModelRouter>,
+    usage: Arc<dyn AiUsageRepository>,
+    cache: Option<Arc<dyn ResponseCache>>,
+    files: Arc<dyn AiFileRepository>,
+    embeddings: Arc<dyn EmbeddingRepository>,
+    attachments: Arc<AiAttachmentConfig>,
+    context: Arc<ContextWindow>,
+    redactor: Arc<Redactor>,
+    moderation: Arc<ModerationPipeline>,
+    moderation_flags: Arc<dyn ModerationRepository>,
+    /// Set when `AI_REQUEST_LOG_ENABLED` is on
+    request_log: Option<Arc<dyn RequestLogRepository>>,
+    /// `list_models` result and when it was built
+    model_list: Arc<Mutex<Option<(Instant, ModelListResponse)>>>,
+    tools: Arc<ToolRegistry>,
+    /// Model turns a chat may take calling tools before it must answer
+    max_tool_steps: usize,
 }
 
 impl AIService {
-    pub fn new(repository: Arc<dyn AIRepository>) -> Self {
-        Self { repository }
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        models: ModelRouter,
+        usage: Arc<dyn AiUsageRepository>,
+        cache: Option<Arc<dyn ResponseCache>>,
+        files: Arc<dyn AiFileRepository>,
+        embeddings: Arc<dyn EmbeddingRepository>,
+        attachments: AiAttachmentConfig,
+        context: ContextWindow,
+        redactor: Redactor,
+        moderation: ModerationPipeline,
+        moderation_flags: Arc<dyn ModerationRepository>,
+        request_log: Option<Arc<dyn RequestLogRepository>>,
+        tools: ToolRegistry,
+        max_tool_steps: usize,
+    ) -> Self {
+        Self {
+            models: Arc::new(models),
+            usage,
+            cache,
+            files,
+            embeddings,
+            attachments: Arc::new(attachments),
+            context: Arc::new(context),
+            redactor: Arc::new(redactor),
+            moderation: Arc::new(moderation),
+            moderation_flags,
+            request_log,
+            model_list: Arc::new(Mutex::new(None)),
+            tools: Arc::new(tools),
+            max_tool_steps,
+        }
+    }
+
+    /// Largest request body the AI routes accept, all attachments base64 encoded plus some room for the rest
+    pub fn max_body_bytes(&self) -> usize {
+        self.attachments.max_request_bytes / 3 * 4 + 1024 * 1024
     }
 
-    pub async fn chat(&self, input: ChatRequest) -> Result<ChatResponse, AppError> {
+    /// Rejects a `model` that is neither an allowed model nor a route
+    pub fn check_model(&self, model: Option<&str>) -> Result<(), AppError> {
+        self.models.resolve(model).map(|_| ())
+    }
+
+    pub async fn chat(&self, tenant: &TenantContext, input: ChatRequest) -> Result<ChatResponse, AppError> {
+        self.chat_with_events(tenant, input, None).await
+    }
+
+    /// Like `chat`, and sends each tool call to `events` as soon as it is done
+    pub async fn chat_with_events(
+        &self,
+        tenant: &TenantContext,
+        input: ChatRequest,
+        events: Option<UnboundedSender<ToolInvocation>>,
+    ) -> Result<ChatResponse, AppError> {
+        let mut redaction = self.redactor.session();
+        let result = self.run_chat(tenant, input, events, &mut redaction).await;
+        self.record_redactions(tenant, "chat", &redaction).await;
+        result
+    }
+
+    async fn run_chat(
+        &self,
+        tenant: &TenantContext,
+        input: ChatRequest,
+        events: Option<UnboundedSender<ToolInvocation>>,
+        redaction: &mut Redaction<'_>,
+    ) -> Result<ChatResponse, AppError> {
         // Validate input
         input
             .validate()
             .map_err(|e| AppError::Validation(e.to_string()))?;
+        let repository = self.models.resolve(input.model.as_deref())?;
+
+        let options = CompletionOptions {
+            system_instruction: input.system_instruction.map(|instruction| redaction.redact(&instruction)),
+            generation: GenerationConfig {
+                temperature: input.temperature,
+                max_output_tokens: None,
+                candidate_count: input.candidate_count,
+                ..Default::default()
+            },
+            safety_settings: input.safety_settings,
+            tools: self.tools.declarations(&input.tools)?,
+        };
+
+        // Before the history is summarized or moderated, which are provider calls too
+        let mut messages = input.history;
+        messages.push(ChatMessage {
+            role: ChatRole::User,
+            content: input.message.clone(),
+            parts: input.parts,
+        });
+        redaction.redact_messages(&mut messages);
+        let message = messages.last().expect("the current message was just pushed");
+        if message.is_empty() {
+            return Err(AppError::Validation("Message cannot be empty".to_string()));
+        }
+        let prompt = moderated_prompt(&messages, options.system_instruction.as_deref());
+        self.moderate(tenant, "chat", ModerationStage::Input, repository.model(), &prompt)
+            .await?;
+
+        // Retrieved chunks go into the message, so cached answers keep their sources
+        let citations = match &input.rag {
+            Some(rag) => {
+                // Searched with the redacted message, the query is embedded by the provider
+                let query = message.content.clone();
+                let citations = self.retrieve(tenant, rag, &query).await?;
+                let sources: Vec<Citation> = citations
+                    .iter()
+                    .map(|citation| Citation {
+                        content: redaction.redact(&citation.content),
+                        ..citation.clone()
+                    })
+                    .collect();
+                let message = messages.last_mut().expect("the current message was just pushed");
+                message.content = grounded_prompt(&message.content, &sources);
+                citations
+            }
+            None => Vec::new(),
+        };
+
+        self.resolve_parts(tenant, repository.as_ref(), &mut messages).await?;
+        let context = self
+            .fit_context(tenant, repository.as_ref(), &mut messages, &options, input.context_strategy)
+            .await?;
+
+        if !options.tools.is_empty() {
+            let message = messages.pop().expect("the current message was just pushed");
+            let (completion, tool_calls) = self
+                .run_tools(tenant, repository.as_ref(), message, messages, &options, events, redaction)
+                .await?;
+            self.moderate_output(tenant, "chat", &completion).await?;
+            return Ok(ChatResponse {
+                candidates: rehydrate_candidates(redaction, completion.candidates),
+                response: redaction.rehydrate(completion.text),
+                fallback: completion.model != repository.model(),
+                model: completion.model,
+                finish_reason: completion.finish_reason,
+                cached: false,
+                tool_calls,
+                citations,
+                context,
+            });
+        }
+
+        let key = self.cache_key(tenant, "chat", repository.model(), &messages, &options, input.cache);
 
         // Call repository
-        let response = self
-            .repository
-            .chat(input.message, input.history)
+        let message = messages.pop().expect("the current message was just pushed");
+        let (completion, cached) = self
+            .complete(
+                tenant,
+                "chat",
+                repository.model(),
+                key,
+                self.call_model(
+                    tenant,
+                    "chat",
+                    repository.as_ref(),
+                    LoggedRequest::Chat {
+                        message,
+                        history: messages,
+                        steps: Vec::new(),
+                        options: options.clone(),
+                    },
+                ),
+            )
             .await?;
+        self.moderate_output(tenant, "chat", &completion).await?;
 
         Ok(ChatResponse {
-            response,
-            model: "gemini-2.0-flash-exp".to_string(),
+            candidates: rehydrate_candidates(redaction, completion.candidates),
+            response: redaction.rehydrate(completion.text),
+            fallback: completion.model != repository.model(),
+            model: completion.model,
+            finish_reason: completion.finish_reason,
+            cached,
+            tool_calls: Vec::new(),
+            citations,
+            context,
         })
     }
 
-    pub async fn generate(&self, input: GenerateRequest) -> Result<GenerateResponse, AppError> {
+    /// Shortens the history when the conversation doesn't fit the model's
+    /// context window. The system instruction, tools and the current message
+    /// are always sent; when they alone don't fit, the chat is rejected.
+    async fn fit_context(
+        &self,
+        tenant: &TenantContext,
+        repository: &dyn AIRepository,
+        messages: &mut Vec<ChatMessage>,
+        options: &CompletionOptions,
+        strategy: Option<ContextStrategy>,
+    ) -> Result<ContextUsage, AppError> {
+        let token_limit = self.context.prompt_limit(repository.model());
+        let fixed_tokens = instruction_tokens(options);
+        let budget = token_limit.saturating_sub(fixed_tokens);
+
+        let mut usage = ContextUsage {
+            estimated_tokens: 0,
+            token_limit: token_limit as i32,
+            strategy: None,
+            dropped_messages: 0,
+            summarized_messages: 0,
+        };
+
+        if conversation_tokens(messages) > budget {
+            let current_tokens = messages.last().map_or(0, message_tokens);
+            if current_tokens > budget {
+                return Err(AppError::Validation(format!(
+                    "The message is too long for the model (about {} tokens, {} available)",
+                    current_tokens, budget
+                )));
+            }
+
+            let strategy = strategy.unwrap_or(self.context.default_strategy);
+            usage.strategy = Some(strategy);
+            if strategy == ContextStrategy::Summarize {
+                let start = summary_split(messages, budget);
+                if start > 0 {
+                    let older: Vec<_> = messages.drain(..start).collect();
+                    let room = (budget / 2).min(MAX_SUMMARY_TOKENS);
+                    let summary = self.summarize(tenant, repository, &older, budget, room).await?;
+                    let first = &mut messages[0];
+                    first.content = format!(
+                        "Summary of our earlier conversation:\n{}\n\n{}",
+                        summary, first.content
+                    );
+                    usage.summarized_messages = older.len() as i32;
+                }
+            }
+            // Drops what still doesn't fit, e.g. after an overlong summary
+            usage.dropped_messages = drop_oldest(messages, budget) as i32;
+        }
+
+        usage.estimated_tokens = (fixed_tokens + conversation_tokens(messages)) as i32;
+        if usage.strategy.is_some() {
+            tracing::debug!(
+                organization_id = %tenant.organization_id(),
+                strategy = ?usage.strategy,
+                dropped = usage.dropped_messages,
+                summarized = usage.summarized_messages,
+                "Shortened AI chat history"
+            );
+        }
+        Ok(usage)
+    }
+
+    /// Summary of earlier chat turns written by the model, at most `max_tokens`
+    /// long. Summaries are cached like other deterministic answers, so a
+    /// repeated chat gets the same prompt.
+    async fn summarize(
+        &self,
+        tenant: &TenantContext,
+        repository: &dyn AIRepository,
+        messages: &[ChatMessage],
+        budget: usize,
+        max_tokens: usize,
+    ) -> Result<String, AppError> {
+        let instruction = "Summarize the conversation below in a few sentences for the assistant to continue it. \
+                           Keep names, numbers, decisions and open questions.";
+        let prompt = format!(
+            "{}\n\n{}",
+            instruction,
+            transcript(messages, budget.saturating_sub(estimate_tokens(instruction)))
+        );
+        let options = CompletionOptions {
+            generation: GenerationConfig {
+                temperature: Some(0.0),
+                max_output_tokens: Some(max_tokens as i32),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let messages = [ChatMessage::user(prompt.clone())];
+        let key = self.cache_key(tenant, "summarize", repository.model(), &messages, &options, None);
+        let (completion, _) = self
+            .complete(
+                tenant,
+                "summarize",
+                repository.model(),
+                key,
+                self.call_model(tenant, "summarize", repository, LoggedRequest::Generate { prompt, options }),
+            )
+            .await?;
+
+        // The model may ignore the limit
+        let summary = completion.text.trim();
+        Ok(match summary.char_indices().nth(max_tokens * 4) {
+            Some((end, _)) => summary[..end].to_string(),
+            None => summary.to_string(),
+        })
+    }
+
+    /// Chunks of the collection closest to the message, numbered for citing
+    async fn retrieve(
+        &self,
+        tenant: &TenantContext,
+        rag: &RagOptions,
+        message: &str,
+    ) -> Result<Vec<Citation>, AppError> {
+        if message.trim().is_empty() {
+            return Err(AppError::Validation(
+                "A chat with rag needs a text message to search for".to_string(),
+            ));
+        }
+
+        let search = self
+            .search(
+                tenant,
+                &rag.collection,
+                SearchRequest {
+                    query: message.to_string(),
+                    top_k: rag.top_k,
+                    min_score: rag.min_score,
+                    filter: rag.filter.clone().map(|filter| filter.0),
+                },
+            )
+            .await?;
+
+        Ok(search
+            .matches
+            .into_iter()
+            .zip(1..)
+            .map(|(chunk, index)| Citation {
+                index,
+                document_id: chunk.source_id.unwrap_or_else(|| chunk.id.clone()),
+                chunk_id: chunk.id,
+                chunk_index: chunk.chunk_index,
+                score: chunk.score,
+                content: chunk.content,
+            })
+            .collect())
+    }
+
+    /// Documents of a collection closest in meaning to the query, best first
+    pub async fn search(
+        &self,
+        tenant: &TenantContext,
+        collection: &str,
+        input: SearchRequest,
+    ) -> Result<SearchResponse, AppError> {
+        validate_collection(collection)?;
+        input
+            .validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+
+        let embeddings = self
+            .embed_texts(tenant, vec![input.query], EmbeddingTask::RetrievalQuery)
+            .await?;
+        let embedding = embeddings
+            .vectors
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::ExternalService("No embedding returned for the query".to_string()))?;
+
+        let matches = self
+            .embeddings
+            .search(
+                tenant.organization_id(),
+                collection,
+                EmbeddingQuery {
+                    embedding,
+                    model: embeddings.model.clone(),
+                    limit: input.top_k,
+                    filter: input.filter.map(serde_json::Value::Object),
+                    min_score: input.min_score,
+                },
+            )
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(SearchResponse {
+            model: embeddings.model,
+            matches,
+        })
+    }
+
+    pub fn tools(&self) -> Vec<ToolInfo> {
+        self.tools.list()
+    }
+
+    /// Allowed models with the details their provider lists, and the routes.
+    /// Kept for `MODEL_LIST_TTL` unless a provider couldn't be reached.
+    pub async fn list_models(&self) -> ModelListResponse {
+        if let Some((built, list)) = &*self.model_list.lock().expect("model list lock poisoned")
+            && built.elapsed() < MODEL_LIST_TTL
+        {
+            return list.clone();
+        }
+
+        // One list per provider covers all of its models
+        let mut catalogs = HashMap::new();
+        let mut complete = true;
+        for allowed in self.models.models() {
+            if catalogs.contains_key(allowed.provider.as_str()) {
+                continue;
+            }
+            let catalog = allowed.repository.list_models().await.unwrap_or_else(|e| {
+                tracing::warn!(provider = %allowed.provider, "Failed to list AI models: {}", e);
+                complete = false;
+                Vec::new()
+            });
+            catalogs.insert(allowed.provider.as_str(), catalog);
+        }
+
+        let models = self
+            .models
+            .models()
+            .iter()
+            .map(|allowed| {
+                let name = allowed.repository.model();
+                let listed = catalogs[allowed.provider.as_str()]
+                    .iter()
+                    .find(|model| model.name == name);
+                let capabilities = allowed.repository.capabilities();
+                ModelInfo {
+                    name: name.to_string(),
+                    provider: allowed.provider.clone(),
+                    display_name: listed.and_then(|model| model.display_name.clone()),
+                    description: listed.and_then(|model| model.description.clone()),
+                    input_token_limit: listed
+                        .and_then(|model| model.input_token_limit)
+                        .unwrap_or(self.context.context_limit(name) as i32),
+                    output_token_limit: listed.and_then(|model| model.output_token_limit),
+                    multimodal: capabilities.multimodal,
+                    function_calling: capabilities.function_calling,
+                    supported_methods: listed.map(|model| model.supported_methods.clone()).unwrap_or_default(),
+                }
+            })
+            .collect();
+
+        let list = ModelListResponse {
+            models,
+            routes: self
+                .models
+                .routes()
+                .iter()
+                .map(|(name, models)| RouteInfo {
+                    name: name.clone(),
+                    models: models.clone(),
+                })
+                .collect(),
+            default_model: self.models.default_name().to_string(),
+        };
+        if complete {
+            *self.model_list.lock().expect("model list lock poisoned") = Some((Instant::now(), list.clone()));
+        }
+        list
+    }
+
+    /// Prompt tokens of a chat, counted by the provider when it can and
+    /// estimated otherwise, and whether they fit the model's context window
+    pub async fn count_tokens(
+        &self,
+        tenant: &TenantContext,
+        input: CountTokensRequest,
+    ) -> Result<CountTokensResponse, AppError> {
+        let mut redaction = self.redactor.session();
+        let result = self.run_count_tokens(tenant, input, &mut redaction).await;
+        self.record_redactions(tenant, "count_tokens", &redaction).await;
+        result
+    }
+
+    async fn run_count_tokens(
+        &self,
+        tenant: &TenantContext,
+        input: CountTokensRequest,
+        redaction: &mut Redaction<'_>,
+    ) -> Result<CountTokensResponse, AppError> {
+        let repository = self.models.resolve(input.model.as_deref())?;
+        let options = CompletionOptions {
+            system_instruction: input.system_instruction.map(|instruction| redaction.redact(&instruction)),
+            tools: self.tools.declarations(&input.tools)?,
+            ..Default::default()
+        };
+
+        let message = ChatMessage {
+            role: ChatRole::User,
+            content: input.message,
+            parts: input.parts,
+        };
+        if message.is_empty() {
+            return Err(AppError::Validation("Message cannot be empty".to_string()));
+        }
+        let mut messages = input.history;
+        messages.push(message);
+        redaction.redact_messages(&mut messages);
+        self.resolve_parts(tenant, repository.as_ref(), &mut messages).await?;
+        let estimate = (instruction_tokens(&options) + conversation_tokens(&messages)) as i32;
+
+        let message = messages.pop().expect("the current message was just pushed");
+        let (total_tokens, exact) = match repository.count_tokens(message, messages, &options).await {
+            Ok(Some(tokens)) => (tokens, true),
+            Ok(None) => (estimate, false),
+            Err(e) => {
+                tracing::warn!(model = repository.model(), "Counting tokens failed, using an estimate: {}", e);
+                (estimate, false)
+            }
+        };
+
+        let token_limit = self.context.prompt_limit(repository.model()) as i32;
+        Ok(CountTokensResponse {
+            total_tokens,
+            model: repository.model().to_string(),
+            exact,
+            token_limit,
+            fits: total_tokens <= token_limit,
+        })
+    }
+
+    /// Lets the model call tools until it answers, at most `max_tool_steps`
+    /// turns. Tool results depend on live data, so nothing is cached. Tools
+    /// get the original values of placeholders, their results are redacted
+    /// before they go back to the model.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_tools(
+        &self,
+        tenant: &TenantContext,
+        repository: &dyn AIRepository,
+        message: ChatMessage,
+        history: Vec<ChatMessage>,
+        options: &CompletionOptions,
+        events: Option<UnboundedSender<ToolInvocation>>,
+        redaction: &mut Redaction<'_>,
+    ) -> Result<(AiCompletion, Vec<ToolInvocation>), AppError> {
+        if !repository.capabilities().function_calling {
+            return Err(AppError::UnsupportedCapability {
+                model: repository.model().to_string(),
+                capability: "function calling",
+            });
+        }
+
+        let mut steps = Vec::new();
+        let mut invocations = Vec::new();
+        for step in 1..=self.max_tool_steps {
+            let request = LoggedRequest::Chat {
+                message: message.clone(),
+                history: history.clone(),
+                steps: steps.clone(),
+                options: options.clone(),
+            };
+            let completion = self.call_model(tenant, "chat", repository, request).await?;
+            self.record_usage(tenant, "chat", &completion.model, completion.usage, false).await;
+
+            if completion.tool_calls.is_empty() {
+                return Ok((completion, invocations));
+            }
+
+            let mut results = Vec::with_capacity(completion.tool_calls.len());
+            for call in &completion.tool_calls {
+                let mut call = call.clone();
+                redaction.restore_json(&mut call.args);
+                let invocation = self.invoke_tool(tenant, step, &call).await;
+                let mut result = match (&invocation.result, &invocation.error) {
+                    (Some(result), _) => serde_json::json!({ "result": result.0 }),
+                    (None, error) => serde_json::json!({ "error": error }),
+                };
+                redaction.redact_json(&mut result);
+                results.push(result);
+                if let Some(events) = &events {
+                    // The receiver going away only means nobody listens anymore
+                    let _ = events.send(invocation.clone());
+                }
+                invocations.push(invocation);
+            }
+            steps.push(ToolStep {
+                calls: completion.tool_calls,
+                results,
+            });
+        }
+
+        Err(AppError::ExternalService(format!(
+            "The model did not answer within {} tool steps",
+            self.max_tool_steps
+        )))
+    }
+
+    /// Runs one call. Failures go back to the model as an error rather than ending the chat.
+    async fn invoke_tool(&self, tenant: &TenantContext, step: usize, call: &ToolCall) -> ToolInvocation {
+        let started = Instant::now();
+        let result = match self.tools.get(&call.name) {
+            Some(tool) => tool.call(tenant, call.args.clone()).await,
+            None => Err(AppError::Validation(format!("Unknown tool '{}'", call.name))),
+        };
+        let duration_ms = started.elapsed().as_millis() as i64;
+
+        tracing::info!(
+            organization_id = %tenant.organization_id(),
+            tool = %call.name,
+            step,
+            duration_ms,
+            ok = result.is_ok(),
+            "AI tool call"
+        );
+
+        let (result, error) = match result {
+            Ok(value) => (Some(async_graphql::Json(value)), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        ToolInvocation {
+            step: step as i32,
+            name: call.name.clone(),
+            arguments: async_graphql::Json(call.args.clone()),
+            result,
+            error,
+            duration_ms,
+        }
+    }
+
+    pub async fn generate(
+        &self,
+        tenant: &TenantContext,
+        input: GenerateRequest,
+    ) -> Result<GenerateResponse, AppError> {
+        let mut redaction = self.redactor.session();
+        let result = self.run_generate(tenant, input, &mut redaction).await;
+        self.record_redactions(tenant, "generate", &redaction).await;
+        result
+    }
+
+    async fn run_generate(
+        &self,
+        tenant: &TenantContext,
+        input: GenerateRequest,
+        redaction: &mut Redaction<'_>,
+    ) -> Result<GenerateResponse, AppError> {
         // Validate input
         input
             .validate()
             .map_err(|e| AppError::Validation(e.to_string()))?;
+        let schema = input.response_schema.map(|schema| schema.0);
+        if let Some(schema) = &schema {
+            check_schema(schema)?;
+        }
+        let repository = self.models.resolve(input.model.as_deref())?;
 
-        // Call repository
-        let text = self.repository.generate(input.prompt).await?;
+        let prompt = redaction.redact(&input.prompt);
+        let options = CompletionOptions {
+            system_instruction: input.system_instruction.map(|instruction| redaction.redact(&instruction)),
+            generation: GenerationConfig {
+                temperature: input.temperature,
+                max_output_tokens: input.max_tokens,
+                candidate_count: input.candidate_count,
+                response_mime_type: schema.as_ref().map(|_| "application/json".to_string()),
+                response_schema: schema.clone(),
+            },
+            safety_settings: input.safety_settings,
+            tools: Vec::new(),
+        };
+        let messages = [ChatMessage::user(prompt.clone())];
+        let moderated = moderated_prompt(&messages, options.system_instruction.as_deref());
+        self.moderate(tenant, "generate", ModerationStage::Input, repository.model(), &moderated)
+            .await?;
+        let key = self.cache_key(tenant, "generate", repository.model(), &messages, &options, input.cache);
+
+        // Call repository, structured output is validated before it is cached
+        let call = async {
+            let request = LoggedRequest::Generate {
+                prompt: prompt.clone(),
+                options: options.clone(),
+            };
+            let completion = self.call_model(tenant, "generate", repository.as_ref(), request).await?;
+            match &schema {
+                Some(schema) => {
+                    self.repair_structured(tenant, repository.as_ref(), prompt, completion, schema, &options)
+                        .await
+                }
+                None => Ok(completion),
+            }
+        };
+        let (completion, cached) = self.complete(tenant, "generate", repository.model(), key, call).await?;
+        self.moderate_output(tenant, "generate", &completion).await?;
+
+        // Parsed before the original values are back, they could break the JSON
+        let mut json = schema
+            .map(|schema| parse_structured(&completion.text, &schema))
+            .transpose()
+            .map_err(|e| AppError::ExternalService(format!("The model output does not match the schema: {}", e)))?;
+        if let Some(json) = &mut json {
+            redaction.rehydrate_json(json);
+        }
 
         Ok(GenerateResponse {
-            text,
-            model: "gemini-2.0-flash-exp".to_string(),
+            candidates: rehydrate_candidates(redaction, completion.candidates),
+            text: redaction.rehydrate(completion.text),
+            json: json.map(async_graphql::Json),
+            fallback: completion.model != repository.model(),
+            model: completion.model,
+            finish_reason: completion.finish_reason,
+            cached,
+        })
+    }
+
+    /// Asks the model to fix output that doesn't match the schema, at most
+    /// `MAX_JSON_REPAIRS` times. The returned usage covers all attempts, and
+    /// is recorded here when the output can't be repaired. Only the answer
+    /// is repaired, other candidates are returned as generated.
+    async fn repair_structured(
+        &self,
+        tenant: &TenantContext,
+        repository: &dyn AIRepository,
+        prompt: String,
+        mut completion: AiCompletion,
+        schema: &serde_json::Value,
+        options: &CompletionOptions,
+    ) -> Result<AiCompletion, AppError> {
+        let mut attempt = 0;
+        loop {
+            let error = match parse_structured(&completion.text, schema) {
+                Ok(_) => return Ok(completion),
+                Err(error) => error,
+            };
+            if attempt == MAX_JSON_REPAIRS {
+                self.record_usage(tenant, "generate", &completion.model, completion.usage, false).await;
+                return Err(AppError::ExternalService(format!(
+                    "The model output does not match the schema: {}",
+                    error
+                )));
+            }
+            attempt += 1;
+            tracing::debug!(attempt, "Repairing structured AI output: {}", error);
+
+            let history = vec![
+                ChatMessage::user(prompt.clone()),
+                ChatMessage::assistant(completion.text.clone()),
+            ];
+            let message = ChatMessage::user(format!(
+                "That response is invalid: {}. Reply with only the corrected JSON.",
+                error
+            ));
+            let previous = completion;
+            let options = CompletionOptions {
+                generation: GenerationConfig {
+                    candidate_count: None,
+                    ..options.generation.clone()
+                },
+                ..options.clone()
+            };
+            let request = LoggedRequest::Chat {
+                message,
+                history,
+                steps: Vec::new(),
+                options,
+            };
+            completion = self.call_model(tenant, "generate", repository, request).await?;
+            completion.usage += previous.usage;
+            completion.candidates = previous
+                .candidates
+                .into_iter()
+                .map(|candidate| match candidate.text == previous.text {
+                    true => Candidate {
+                        text: completion.text.clone(),
+                        finish_reason: completion.finish_reason.clone(),
+                        ..candidate
+                    },
+                    false => candidate,
+                })
+                .collect();
+        }
+    }
+
+    /// Turns texts into vectors for semantic search or comparison
+    pub async fn embed(
+        &self,
+        tenant: &TenantContext,
+        input: EmbedRequest,
+    ) -> Result<EmbedResponse, AppError> {
+        input
+            .validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+        if input.texts.iter().any(|text| text.trim().is_empty()) {
+            return Err(AppError::Validation("Texts cannot be empty".to_string()));
+        }
+
+        let embeddings = self.embed_texts(tenant, input.texts, input.task_type).await?;
+        Ok(EmbedResponse {
+            model: embeddings.model,
+            dimensions: EMBEDDING_DIMENSIONS,
+            embeddings: embeddings.vectors,
         })
     }
+
+    /// Embeds without validating, for services that already checked their input
+    pub async fn embed_texts(
+        &self,
+        tenant: &TenantContext,
+        texts: Vec<String>,
+        task: EmbeddingTask,
+    ) -> Result<AiEmbeddings, AppError> {
+        let embeddings = self.models.embeddings().embed(texts, task).await?;
+        // Gemini doesn't report tokens for embeddings, only the request is counted
+        self.record_usage(tenant, "embed", &embeddings.model, TokenUsage::default(), false)
+            .await;
+        Ok(embeddings)
+    }
+
+    /// Stores a file that chat messages can reference by id
+    pub async fn upload_file(
+        &self,
+        tenant: &TenantContext,
+        filename: String,
+        mime_type: String,
+        data: Vec<u8>,
+    ) -> Result<AiFile, AppError> {
+        let mime_type = mime_type.to_lowercase();
+        self.check_attachment(&mime_type, data.len())?;
+        if filename.is_empty() || filename.len() > 255 {
+            return Err(AppError::Validation(
+                "Filename must be between 1 and 255 characters".to_string(),
+            ));
+        }
+
+        let (uploaded_by, api_key_id) = caller_ids(tenant);
+        self.files
+            .create(
+                tenant.organization_id(),
+                NewAiFile {
+                    filename,
+                    mime_type,
+                    data,
+                    uploaded_by,
+                    api_key_id,
+                },
+            )
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub async fn list_files(&self, tenant: &TenantContext) -> Result<Vec<AiFile>, AppError> {
+        self.files
+            .list(tenant.organization_id())
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub async fn delete_file(&self, tenant: &TenantContext, id: Uuid) -> Result<(), AppError> {
+        let deleted = self
+            .files
+            .delete(tenant.organization_id(), id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if deleted { Ok(()) } else { Err(AppError::NotFound) }
+    }
+
+    pub async fn usage(&self, tenant: &TenantContext) -> Result<Vec<AiUsageSummary>, AppError> {
+        self.usage
+            .summary(tenant.organization_id())
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub async fn redactions(&self, tenant: &TenantContext) -> Result<Vec<AiRedactionSummary>, AppError> {
+        self.usage
+            .redaction_summary(tenant.organization_id())
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Flagged and blocked prompts and answers, newest first
+    pub async fn moderation_flags(
+        &self,
+        tenant: &TenantContext,
+        reviewed: Option<bool>,
+        limit: i64,
+    ) -> Result<Vec<ModerationFlag>, AppError> {
+        self.moderation_flags
+            .list(tenant.organization_id(), reviewed, limit.clamp(1, 500))
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Marks a flag as reviewed by the user, once
+    pub async fn review_moderation_flag(
+        &self,
+        tenant: &TenantContext,
+        id: Uuid,
+        input: ReviewModerationFlagRequest,
+    ) -> Result<ModerationFlag, AppError> {
+        input
+            .validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+        let reviewer = tenant.require_user()?.user.id;
+        let reviewed = self
+            .moderation_flags
+            .review(tenant.organization_id(), id, reviewer, input.note)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if let Some(flag) = reviewed {
+            return Ok(flag);
+        }
+
+        match self
+            .moderation_flags
+            .find(tenant.organization_id(), id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+        {
+            Some(_) => Err(AppError::Conflict("The flag has already been reviewed".to_string())),
+            None => Err(AppError::NotFound),
+        }
+    }
+
+    /// Replaces uploaded file references with their content and checks all
+    /// attachments against the limits and the model's capabilities
+    async fn resolve_parts(
+        &self,
+        tenant: &TenantContext,
+        repository: &dyn AIRepository,
+        messages: &mut [ChatMessage],
+    ) -> Result<(), AppError> {
+        let mut total_bytes = 0;
+
+        for part in messages.iter_mut().flat_map(|message| message.parts.iter_mut()) {
+            let size = match part {
+                MessagePart::Text(_) => continue,
+                MessagePart::InlineData(inline) => {
+                    inline.mime_type = inline.mime_type.to_lowercase();
+                    let size = STANDARD
+                        .decode(&inline.data)
+                        .map_err(|_| AppError::Validation("Inline data must be base64 encoded".to_string()))?
+                        .len();
+                    self.check_attachment(&inline.mime_type, size)?;
+                    size
+                }
+                MessagePart::FileId(id) => {
+                    let file = self
+                        .files
+                        .content(tenant.organization_id(), *id)
+                        .await
+                        .map_err(|e| AppError::Database(e.to_string()))?
+                        .ok_or_else(|| AppError::Validation(format!("File {} not found", id)))?;
+                    let size = file.data.len();
+                    self.check_attachment(&file.mime_type, size)?;
+
+                    *part = MessagePart::InlineData(InlineData {
+                        mime_type: file.mime_type,
+                        data: STANDARD.encode(file.data),
+                    });
+                    size
+                }
+            };
+
+            if !repository.capabilities().multimodal {
+                return Err(AppError::UnsupportedCapability {
+                    model: repository.model().to_string(),
+                    capability: "image and document input",
+                });
+            }
+            total_bytes += size;
+        }
+
+        if total_bytes > self.attachments.max_request_bytes {
+            return Err(AppError::Validation(format!(
+                "Attachments may not exceed {} bytes in total",
+                self.attachments.max_request_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_attachment(&self, mime_type: &str, size: usize) -> Result<(), AppError> {
+        if !self.attachments.allowed_mime_types.iter().any(|allowed| allowed == mime_type) {
+            return Err(AppError::Validation(format!(
+                "Unsupported file type {}, allowed are {}",
+                mime_type,
+                self.attachments.allowed_mime_types.join(", ")
+            )));
+        }
+        if size == 0 {
+            return Err(AppError::Validation("File cannot be empty".to_string()));
+        }
+        if size > self.attachments.max_file_bytes {
+            return Err(AppError::Validation(format!(
+                "Files may not exceed {} bytes",
+                self.attachments.max_file_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    /// Hash of everything that determines the answer. `None` when the request
+    /// can't be cached: no cache configured, opted out, or a temperature above
+    /// 0 (unset counts as the model default) without `cache: true`.
+    fn cache_key(
+        &self,
+        tenant: &TenantContext,
+        operation: &str,
+        model: &str,
+        messages: &[ChatMessage],
+        options: &CompletionOptions,
+        allow: Option<bool>,
+    ) -> Option<String> {
+        self.cache.as_ref()?;
+        let deterministic = options.generation.temperature == Some(0.0);
+        if allow == Some(false) || (allow.is_none() && !deterministic) {
+            return None;
+        }
+
+        // Verbatim, whitespace can change the answer (code, tables, poems)
+        let messages: Vec<_> = messages
+            .iter()
+            .map(|message| serde_json::json!([message.role, message.content, message.parts]))
+            .collect();
+
+        let material = serde_json::json!({
+            "organization_id": tenant.organization_id(),
+            "operation": operation,
+            "model": model,
+            "messages": messages,
+            "options": options,
+        });
+        Some(hash_token(&material.to_string()))
+    }
+
+    /// Answers from the cache when possible, otherwise calls the model and
+    /// caches the result. Cache failures are logged and fall back to the model.
+    /// Answers of a fallback aren't cached, the key is for the `model` asked.
+    async fn complete(
+        &self,
+        tenant: &TenantContext,
+        operation: &'static str,
+        model: &str,
+        key: Option<String>,
+        call: impl Future<Output = Result<AiCompletion, AppError>>,
+    ) -> Result<(AiCompletion, bool), AppError> {
+        let cache = self.cache.as_ref().zip(key.as_deref());
+
+        if let Some((cache, key)) = cache {
+            match cache.get(key).await {
+                Ok(Some(completion)) => {
+                    tracing::debug!(operation, organization_id = %tenant.organization_id(), "AI cache hit");
+                    self.record_usage(tenant, operation, &completion.model, completion.usage, true).await;
+                    return Ok((completion, true));
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("AI cache lookup failed: {}", e),
+            }
+        }
+
+        let completion = call.await?;
+        self.record_usage(tenant, operation, &completion.model, completion.usage, false).await;
+
+        if let Some((cache, key)) = cache {
+            if completion.model != model {
+                tracing::debug!(operation, model, fallback = %completion.model, "Not caching a fallback answer");
+            } else if let Err(e) = cache.put(tenant.organization_id(), key, &completion).await {
+                tracing::warn!("Failed to cache AI response: {}", e);
+            }
+        }
+        Ok((completion, false))
+    }
+
+    /// Sends a request to the model, and logs it when request logging is on
+    async fn call_model(
+        &self,
+        tenant: &TenantContext,
+        operation: &'static str,
+        repository: &dyn AIRepository,
+        request: LoggedRequest,
+    ) -> Result<AiCompletion, AppError> {
+        let logged = self.request_log.as_ref().map(|_| loggable(&request));
+        let started = Instant::now();
+        let result = match request {
+            LoggedRequest::Chat {
+                message,
+                history,
+                steps,
+                options,
+            } => repository.chat(message, history, &steps, &options).await,
+            LoggedRequest::Generate { prompt, options } => repository.generate(prompt, &options).await,
+        };
+        if let Some(logged) = logged {
+            self.log_request(tenant, operation, repository.model(), logged, &result, started.elapsed())
+                .await;
+        }
+        result
+    }
+
+    /// Sends a logged request again, to `model` or the model or route it
+    /// names. Logged and counted as the `replay` operation.
+    pub async fn replay(
+        &self,
+        tenant: &TenantContext,
+        request: LoggedRequest,
+        model: &str,
+    ) -> Result<(AiCompletion, Duration), AppError> {
+        let repository = self.models.resolve(Some(model))?;
+        let started = Instant::now();
+        let completion = self.call_model(tenant, "replay", repository.as_ref(), request).await?;
+        let latency = started.elapsed();
+        self.record_usage(tenant, "replay", &completion.model, completion.usage, false).await;
+        Ok((completion, latency))
+    }
+
+    /// Like usage, a failure to log is logged rather than returned
+    async fn log_request(
+        &self,
+        tenant: &TenantContext,
+        operation: &'static str,
+        requested_model: &str,
+        request: serde_json::Value,
+        result: &Result<AiCompletion, AppError>,
+        latency: Duration,
+    ) {
+        let Some(request_log) = &self.request_log else {
+            return;
+        };
+        let (user_id, api_key_id) = caller_ids(tenant);
+
+        let log = match result {
+            Ok(completion) => NewRequestLog {
+                user_id,
+                api_key_id,
+                operation,
+                provider: self.models.provider(&completion.model).map(str::to_string),
+                requested_model: requested_model.to_string(),
+                model: Some(completion.model.clone()),
+                request,
+                response: Some(completion.text.clone()),
+                tool_calls: (!completion.tool_calls.is_empty())
+                    .then(|| serde_json::to_value(&completion.tool_calls).unwrap_or_default()),
+                finish_reason: Some(completion.finish_reason.clone()),
+                error: None,
+                usage: completion.usage,
+                latency_ms: latency.as_millis() as i64,
+            },
+            Err(e) => NewRequestLog {
+                user_id,
+                api_key_id,
+                operation,
+                provider: self.models.provider(requested_model).map(str::to_string),
+                requested_model: requested_model.to_string(),
+                model: None,
+                request,
+                response: None,
+                tool_calls: None,
+                finish_reason: None,
+                error: Some(e.to_string()),
+                usage: TokenUsage::default(),
+                latency_ms: latency.as_millis() as i64,
+            },
+        };
+        if let Err(e) = request_log.create(tenant.organization_id(), log).await {
+            tracing::error!(organization_id = %tenant.organization_id(), "Failed to log AI request: {}", e);
+        }
+    }
+
+    /// Attributes a call to the tenant and caller. The answer was already paid
+    /// for, so a failure to record is logged rather than returned.
+    async fn record_usage(
+        &self,
+        tenant: &TenantContext,
+        operation: &'static str,
+        model: &str,
+        usage: TokenUsage,
+        cached: bool,
+    ) {
+        let (user_id, api_key_id) = caller_ids(tenant);
+
+        let record = AiUsageRecord {
+            user_id,
+            api_key_id,
+            operation,
+            model: model.to_string(),
+            usage,
+            cached,
+        };
+        if let Err(e) = self.usage.record(tenant.organization_id(), record).await {
+            tracing::error!(organization_id = %tenant.organization_id(), "Failed to record AI usage: {}", e);
+        }
+    }
+
+    /// Checks the answer and every other distinct candidate
+    async fn moderate_output(
+        &self,
+        tenant: &TenantContext,
+        operation: &'static str,
+        completion: &AiCompletion,
+    ) -> Result<(), AppError> {
+        let mut checked = HashSet::new();
+        let texts = std::iter::once(&completion.text).chain(completion.candidates.iter().map(|candidate| &candidate.text));
+        for text in texts {
+            if checked.insert(text.as_str()) {
+                self.moderate(tenant, operation, ModerationStage::Output, &completion.model, text)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the moderators over a prompt or answer and applies the route's
+    /// policy to what they report. Flagged and blocked texts are stored for
+    /// review; a failure to store is logged, the policy still applies.
+    async fn moderate(
+        &self,
+        tenant: &TenantContext,
+        operation: &'static str,
+        stage: ModerationStage,
+        model: &str,
+        text: &str,
+    ) -> Result<(), AppError> {
+        if !self.moderation.is_enabled() || text.trim().is_empty() {
+            return Ok(());
+        }
+
+        let mut categories = Vec::new();
+        for verdict in self.moderation.check(text, stage).await {
+            if let Some((model, usage)) = verdict.usage {
+                self.record_usage(tenant, "moderate", &model, usage, false).await;
+            }
+            categories.extend(verdict.categories);
+        }
+        if categories.is_empty() {
+            return Ok(());
+        }
+
+        let action = self.moderation.action(operation);
+        tracing::warn!(
+            organization_id = %tenant.organization_id(),
+            operation,
+            ?stage,
+            ?action,
+            ?categories,
+            "AI moderation reported content"
+        );
+        if action == ModerationAction::Log {
+            return Ok(());
+        }
+
+        let (user_id, api_key_id) = caller_ids(tenant);
+        let flag = NewModerationFlag {
+            user_id,
+            api_key_id,
+            operation,
+            stage,
+            action,
+            categories: categories.clone(),
+            content: text.to_string(),
+            model: model.to_string(),
+        };
+        if let Err(e) = self.moderation_flags.create(tenant.organization_id(), flag).await {
+            tracing::error!(organization_id = %tenant.organization_id(), "Failed to store AI moderation flag: {}", e);
+        }
+
+        match action {
+            ModerationAction::Block => Err(AppError::ModerationBlocked {
+                stage: match stage {
+                    ModerationStage::Input => "prompt",
+                    ModerationStage::Output => "response",
+                },
+                categories,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts what a request had redacted, also when it failed afterwards.
+    /// Like usage, a failure to record is logged rather than returned.
+    async fn record_redactions(&self, tenant: &TenantContext, operation: &'static str, redaction: &Redaction<'_>) {
+        if redaction.counts().is_empty() {
+            return;
+        }
+        let (user_id, api_key_id) = caller_ids(tenant);
+
+        let record = AiRedactionRecord {
+            user_id,
+            api_key_id,
+            operation,
+            counts: redaction
+                .counts()
+                .iter()
+                .map(|(kind, matches)| (kind.clone(), *matches))
+                .collect(),
+        };
+        if let Err(e) = self.usage.record_redactions(tenant.organization_id(), record).await {
+            tracing::error!(organization_id = %tenant.organization_id(), "Failed to record AI redactions: {}", e);
+        }
+    }
+}
+
+/// The question with the retrieved chunks as numbered sources
+fn grounded_prompt(question: &str, citations: &[Citation]) -> String {
+    if citations.is_empty() {
+        return format!(
+            "No sources were found for the following question. Say that you don't know the answer.\n\nQuestion: {}",
+            question
+        );
+    }
+
+    let sources: Vec<String> = citations
+        .iter()
+        .map(|citation| format!("[{}] ({})\n{}", citation.index, citation.chunk_id, citation.content))
+        .collect();
+    format!(
+        "Answer the question using only the numbered sources below and cite them like [1]. \
+         If the sources don't contain the answer, say so.\n\nSources:\n{}\n\nQuestion: {}",
+        sources.join("\n\n"),
+        question
+    )
+}
+
+/// What input moderation checks: the system instruction and the text of every
+/// message. The history comes from the client like the new message, nothing
+/// says it was checked before.
+fn moderated_prompt(messages: &[ChatMessage], system_instruction: Option<&str>) -> String {
+    let texts = messages.iter().flat_map(|message| {
+        std::iter::once(message.content.as_str()).chain(message.parts.iter().filter_map(|part| match part {
+            MessagePart::Text(text) => Some(text.as_str()),
+            _ => None,
+        }))
+    });
+    system_instruction
+        .into_iter()
+        .chain(texts)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Candidates with the redacted values put back, like the answer
+fn rehydrate_candidates(redaction: &Redaction<'_>, candidates: Vec<Candidate>) -> Vec<Candidate> {
+    candidates
+        .into_iter()
+        .map(|candidate| Candidate {
+            text: redaction.rehydrate(candidate.text),
+            ..candidate
+        })
+        .collect()
+}
+
+/// The request as it is logged, attachments replaced by a note of their type and size
+fn loggable(request: &LoggedRequest) -> serde_json::Value {
+    let strip = |message: &ChatMessage| ChatMessage {
+        role: message.role,
+        content: message.content.clone(),
+        parts: message
+            .parts
+            .iter()
+            .map(|part| match part {
+                MessagePart::InlineData(data) => MessagePart::Text(format!(
+                    "[{} attachment, {} bytes]",
+                    data.mime_type,
+                    data.data.len() / 4 * 3
+                )),
+                other => other.clone(),
+            })
+            .collect(),
+    };
+    let request = match request {
+        LoggedRequest::Chat {
+            message,
+            history,
+            steps,
+            options,
+        } => LoggedRequest::Chat {
+            message: strip(message),
+            history: history.iter().map(strip).collect(),
+            steps: steps.clone(),
+            options: options.clone(),
+        },
+        LoggedRequest::Generate { .. } => request.clone(),
+    };
+    serde_json::to_value(request).unwrap_or_default()
+}
+
+/// User or API key making the call
+pub(super) fn caller_ids(tenant: &TenantContext) -> (Option<Uuid>, Option<Uuid>) {
+    match &tenant.principal {
+        Principal::User(auth) => (Some(auth.user.id), None),
+        Principal::ApiKey(key) => (None, Some(key.key_id)),
+    }
 }
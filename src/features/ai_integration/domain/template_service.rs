@@ -0,0 +1,253 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use validator::Validate;
+
+use super::AIService;
+use crate::{
+    entities::ai::{PromptTemplate, TemplateVariable, VariableType},
+    features::ai_integration::infrastructure::{NewPromptTemplate, PromptTemplateRepository},
+    features::ai_integration::model::{
+        CreatePromptTemplateRequest, GenerateRequest, RenderTemplateRequest, TemplateGenerateResponse,
+    },
+    features::organizations::model::TenantContext,
+    shared::error::AppError,
+};
+
+#[derive(Clone)]
+pub struct PromptTemplateService {
+    templates: Arc<dyn PromptTemplateRepository>,
+    ai_service: AIService,
+}
+
+impl PromptTemplateService {
+    pub fn new(templates: Arc<dyn PromptTemplateRepository>, ai_service: AIService) -> Self {
+        Self { templates, ai_service }
+    }
+
+    /// Stores a new template, or a new version when the name exists
+    pub async fn create(
+        &self,
+        tenant: &TenantContext,
+        input: CreatePromptTemplateRequest,
+    ) -> Result<PromptTemplate, AppError> {
+        input
+            .validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+
+        let valid_name = input
+            .name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(AppError::Validation(
+                "Name may only contain lowercase letters, digits, '-' and '_'".to_string(),
+            ));
+        }
+        validate_variables(&input.variables)?;
+
+        // Every placeholder must be declared, so rendering can't leave one behind
+        let declared: HashSet<&str> = input.variables.iter().map(|v| v.name.as_str()).collect();
+        for text in std::iter::once(&input.body).chain(input.system_instruction.as_ref()) {
+            for placeholder in placeholders(text)? {
+                if !declared.contains(placeholder) {
+                    return Err(AppError::Validation(format!(
+                        "Placeholder {{{{{}}}}} is not a declared variable",
+                        placeholder
+                    )));
+                }
+            }
+        }
+
+        self.templates
+            .create(
+                tenant.organization_id(),
+                NewPromptTemplate {
+                    name: input.name,
+                    description: input.description,
+                    system_instruction: input.system_instruction,
+                    body: input.body,
+                    variables: input.variables,
+                    created_by: tenant.user().map(|auth| auth.user.id),
+                },
+            )
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub async fn list(&self, tenant: &TenantContext) -> Result<Vec<PromptTemplate>, AppError> {
+        self.templates
+            .list(tenant.organization_id())
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub async fn get(
+        &self,
+        tenant: &TenantContext,
+        name: &str,
+        version: Option<i32>,
+    ) -> Result<PromptTemplate, AppError> {
+        self.templates
+            .find(tenant.organization_id(), name, version)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or(AppError::NotFound)
+    }
+
+    /// Fills the template with the given values and generates from the result
+    pub async fn render_and_generate(
+        &self,
+        tenant: &TenantContext,
+        name: &str,
+        input: RenderTemplateRequest,
+    ) -> Result<TemplateGenerateResponse, AppError> {
+        input
+            .validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+
+        let template = self.get(tenant, name, input.version).await?;
+        let values = resolve_values(&template.variables, &input.variables)?;
+
+        let prompt = render(&template.body, &values)?;
+        let system_instruction = template
+            .system_instruction
+            .as_deref()
+            .map(|text| render(text, &values))
+            .transpose()?;
+
+        let generation = self
+            .ai_service
+            .generate(
+                tenant,
+                GenerateRequest {
+                    prompt,
+                    system_instruction,
+                    max_tokens: input.max_tokens,
+                    temperature: input.temperature,
+                    candidate_count: input.candidate_count,
+                    cache: input.cache,
+                    safety_settings: input.safety_settings,
+                    response_schema: input.response_schema,
+                    model: input.model,
+                },
+            )
+            .await?;
+
+        Ok(TemplateGenerateResponse {
+            template: template.name,
+            template_version: template.version,
+            generation,
+        })
+    }
+}
+
+fn validate_variables(variables: &[TemplateVariable]) -> Result<(), AppError> {
+    let mut seen = HashSet::new();
+    for variable in variables {
+        let valid_name = variable
+            .name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && variable.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(AppError::Validation(format!(
+                "Invalid variable name '{}', use letters, digits and '_'",
+                variable.name
+            )));
+        }
+        if !seen.insert(variable.name.as_str()) {
+            return Err(AppError::Validation(format!(
+                "Variable '{}' is declared twice",
+                variable.name
+            )));
+        }
+        if let Some(default) = &variable.default {
+            format_value(variable, default)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks the given values against the declared variables and formats them for the prompt
+fn resolve_values(
+    variables: &[TemplateVariable],
+    given: &serde_json::Map<String, serde_json::Value>,
+) -> Result<HashMap<String, String>, AppError> {
+    if let Some(unknown) = given.keys().find(|key| !variables.iter().any(|v| &v.name == *key)) {
+        return Err(AppError::Validation(format!("Unknown variable '{}'", unknown)));
+    }
+
+    let mut values = HashMap::new();
+    for variable in variables {
+        let value = match given.get(&variable.name).or(variable.default.as_ref()) {
+            Some(value) => format_value(variable, value)?,
+            None if variable.required => {
+                return Err(AppError::Validation(format!(
+                    "Missing required variable '{}'",
+                    variable.name
+                )))
+            }
+            None => String::new(),
+        };
+        values.insert(variable.name.clone(), value);
+    }
+    Ok(values)
+}
+
+fn format_value(variable: &TemplateVariable, value: &serde_json::Value) -> Result<String, AppError> {
+    use serde_json::Value;
+
+    let formatted = match (variable.kind, value) {
+        (VariableType::String, Value::String(text)) => Some(text.clone()),
+        (VariableType::Number, Value::Number(number)) => Some(number.to_string()),
+        (VariableType::Integer, Value::Number(number)) if number.is_i64() || number.is_u64() => {
+            Some(number.to_string())
+        }
+        (VariableType::Boolean, Value::Bool(flag)) => Some(flag.to_string()),
+        _ => None,
+    };
+
+    formatted.ok_or_else(|| {
+        AppError::Validation(format!(
+            "Variable '{}' must be of type {:?}",
+            variable.name, variable.kind
+        ))
+    })
+}
+
+/// Names of the `{{name}}` placeholders in `text`
+fn placeholders(text: &str) -> Result<Vec<&str>, AppError> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| AppError::Validation("Unclosed '{{' in template".to_string()))?;
+        names.push(after[..end].trim());
+        rest = &after[end + 2..];
+    }
+    Ok(names)
+}
+
+/// Replaces placeholders in one pass, so values containing `{{...}}` stay as they are
+fn render(text: &str, values: &HashMap<String, String>) -> Result<String, AppError> {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| AppError::Validation("Unclosed '{{' in template".to_string()))?;
+        let name = after[..end].trim();
+        let value = values.get(name).ok_or_else(|| {
+            AppError::Validation(format!("Placeholder {{{{{}}}}} has no value", name))
+        })?;
+        rendered.push_str(value);
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
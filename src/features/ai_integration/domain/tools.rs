@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::{
+    features::ai_integration::infrastructure::ToolDeclaration,
+    features::ai_integration::model::ToolInfo,
+    features::organizations::model::TenantContext,
+    shared::error::AppError,
+};
+
+/// A backend function the model can call during a chat. Tools run with the
+/// caller's tenant and permissions.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    /// Letters, digits and `_`, as Gemini requires for function names
+    fn name(&self) -> &'static str;
+    /// Tells the model when to use the tool
+    fn description(&self) -> &'static str;
+    /// JSON schema of the arguments object, in the OpenAPI subset Gemini accepts
+    fn parameters(&self) -> serde_json::Value;
+    async fn call(&self, tenant: &TenantContext, args: serde_json::Value) -> Result<serde_json::Value, AppError>;
+}
+
+/// Tools available to chat requests, by name
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<&'static str, Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, tool: impl Tool + 'static) {
+        self.tools.insert(tool.name(), Arc::new(tool));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Tool>> {
+        self.tools.get(name)
+    }
+
+    pub fn list(&self) -> Vec<ToolInfo> {
+        self.tools
+            .values()
+            .map(|tool| ToolInfo {
+                name: tool.name().to_string(),
+                description: tool.description().to_string(),
+                parameters: tool.parameters(),
+            })
+            .collect()
+    }
+
+    /// Declarations of the requested tools for the model
+    pub fn declarations(&self, names: &[String]) -> Result<Vec<ToolDeclaration>, AppError> {
+        names
+            .iter()
+            .map(|name| {
+                let tool = self.get(name).ok_or_else(|| {
+                    AppError::Validation(format!(
+                        "Unknown tool '{}', available are {}",
+                        name,
+                        self.tools.keys().copied().collect::<Vec<_>>().join(", ")
+                    ))
+                })?;
+
+                Ok(ToolDeclaration {
+                    name: tool.name().to_string(),
+                    description: tool.description().to_string(),
+                    parameters: tool.parameters(),
+                })
+            })
+            .collect()
+    }
+}
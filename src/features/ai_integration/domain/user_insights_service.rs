@@ -0,0 +1,275 @@
+use chrono::{Duration, Utc};
+use std::fmt::Write;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::AIService;
+use crate::{
+    entities::ai::EmbeddingTask,
+    entities::user::User,
+    features::ai_integration::model::{
+        ActivityExplanationResponse, BioSuggestionRequest, BioSuggestionResponse, DuplicateUser,
+        DuplicateUsersResponse, ExplainActivityRequest, GenerateRequest,
+    },
+    features::organizations::model::TenantContext,
+    features::user_management::domain::UserService,
+    shared::error::AppError,
+};
+
+/// Characters of a single bio fact at most
+const MAX_FACT_CHARS: usize = 500;
+/// Members a duplicate search embeds at most, besides the user
+const MAX_COMPARED_USERS: usize = 1000;
+/// Activity events given to the model at most, the newest
+const MAX_ACTIVITY_EVENTS: i64 = 200;
+
+const BIO_INSTRUCTION: &str = "You write short profile bios in the third person. Use only the facts given, \
+                               never invent employers, titles or achievements. Reply with the bio only.";
+
+const ACTIVITY_INSTRUCTION: &str = "You explain a user's recent account activity to an administrator in plain \
+                                    language. Summarize what happened and when in a few sentences, group repeated \
+                                    events, and point out anything that matters for account security, such as \
+                                    revoked API keys, used recovery codes or moderated content. Only use the \
+                                    events given.";
+
+/// AI features over the members of an organization: bio suggestions,
+/// duplicate detection and plain-language activity summaries. Callers check
+/// scopes and roles, the user must be a member of the tenant.
+#[derive(Clone)]
+pub struct UserInsightsService {
+    users: UserService,
+    ai_service: AIService,
+}
+
+impl UserInsightsService {
+    pub fn new(users: UserService, ai_service: AIService) -> Self {
+        Self { users, ai_service }
+    }
+
+    /// Suggests bios from the user's name, the organization and the given facts
+    pub async fn suggest_bio(
+        &self,
+        tenant: &TenantContext,
+        id: Uuid,
+        input: BioSuggestionRequest,
+    ) -> Result<BioSuggestionResponse, AppError> {
+        input
+            .validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+        if input
+            .facts
+            .iter()
+            .any(|fact| fact.trim().is_empty() || fact.chars().count() > MAX_FACT_CHARS)
+        {
+            return Err(AppError::Validation(format!(
+                "Facts must be between 1 and {} characters",
+                MAX_FACT_CHARS
+            )));
+        }
+        let user = self.users.get_user(tenant.organization_id(), id).await?;
+
+        let mut prompt = format!(
+            "Write a {} profile bio of at most {} words for {}, a member of {}.",
+            input.tone.as_deref().unwrap_or("professional"),
+            input.max_words.unwrap_or(60),
+            user.name,
+            tenant.organization.name
+        );
+        if !input.facts.is_empty() {
+            prompt.push_str("\n\nFacts:");
+            for fact in &input.facts {
+                let _ = write!(prompt, "\n- {}", fact.trim());
+            }
+        }
+
+        let response = self
+            .ai_service
+            .generate(
+                tenant,
+                GenerateRequest {
+                    prompt,
+                    system_instruction: Some(BIO_INSTRUCTION.to_string()),
+                    max_tokens: None,
+                    temperature: None,
+                    candidate_count: input.suggestions,
+                    cache: None,
+                    safety_settings: Vec::new(),
+                    response_schema: None,
+                    model: input.model,
+                },
+            )
+            .await?;
+
+        Ok(BioSuggestionResponse {
+            user_id: user.id,
+            suggestions: response
+                .candidates
+                .into_iter()
+                .map(|candidate| candidate.text.trim().to_string())
+                .filter(|text| !text.is_empty())
+                .collect(),
+            model: response.model,
+            cached: response.cached,
+        })
+    }
+
+    /// Other members whose name and email embed close to the user's. Every
+    /// member is embedded on each call, nothing is stored.
+    pub async fn find_duplicates(
+        &self,
+        tenant: &TenantContext,
+        id: Uuid,
+        min_score: Option<f64>,
+        limit: Option<i64>,
+    ) -> Result<DuplicateUsersResponse, AppError> {
+        let min_score = min_score.unwrap_or(0.9);
+        if !(-1.0..=1.0).contains(&min_score) {
+            return Err(AppError::Validation("min_score must be between -1 and 1".to_string()));
+        }
+        let limit = limit.unwrap_or(10).clamp(1, 50) as usize;
+
+        let user = self.users.get_user(tenant.organization_id(), id).await?;
+        let others: Vec<User> = self
+            .users
+            .get_users(tenant.organization_id())
+            .await?
+            .into_iter()
+            .filter(|other| other.id != user.id)
+            .collect();
+        if others.len() > MAX_COMPARED_USERS {
+            return Err(AppError::Validation(format!(
+                "Organizations with more than {} members can't be searched for duplicates",
+                MAX_COMPARED_USERS
+            )));
+        }
+        if others.is_empty() {
+            return Ok(DuplicateUsersResponse {
+                user_id: user.id,
+                model: None,
+                compared: 0,
+                duplicates: Vec::new(),
+            });
+        }
+
+        let texts = std::iter::once(&user).chain(&others).map(identity_text).collect();
+        let embeddings = self
+            .ai_service
+            .embed_texts(tenant, texts, EmbeddingTask::SemanticSimilarity)
+            .await?;
+        let Some((target, vectors)) = embeddings.vectors.split_first() else {
+            return Err(AppError::ExternalService("The embedding model returned no vectors".to_string()));
+        };
+
+        let mut duplicates: Vec<DuplicateUser> = others
+            .iter()
+            .zip(vectors)
+            .map(|(other, vector)| DuplicateUser {
+                id: other.id,
+                name: other.name.clone(),
+                email: other.email.clone(),
+                score: cosine_similarity(target, vector),
+            })
+            .filter(|duplicate| duplicate.score >= min_score)
+            .collect();
+        duplicates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        duplicates.truncate(limit);
+
+        Ok(DuplicateUsersResponse {
+            user_id: user.id,
+            model: Some(embeddings.model),
+            compared: others.len() as i32,
+            duplicates,
+        })
+    }
+
+    /// Summarizes the user's recent activity in plain language. Managers see
+    /// what the member did in the organization; sign-ins, SSO, recovery codes
+    /// and account changes are global and only included for the user
+    /// themselves.
+    pub async fn explain_activity(
+        &self,
+        tenant: &TenantContext,
+        id: Uuid,
+        input: ExplainActivityRequest,
+    ) -> Result<ActivityExplanationResponse, AppError> {
+        input
+            .validate()
+            .map_err(|e| AppError::Validation(e.to_string()))?;
+        let since = Utc::now() - Duration::days(input.days.unwrap_or(30));
+        let own_account = tenant.user().is_some_and(|auth| auth.user.id == id);
+
+        let user = self.users.get_user(tenant.organization_id(), id).await?;
+        let events = self
+            .users
+            .get_activity(tenant.organization_id(), id, since, MAX_ACTIVITY_EVENTS, own_account)
+            .await?;
+        if events.is_empty() {
+            return Ok(ActivityExplanationResponse {
+                user_id: user.id,
+                explanation: format!("{} has no recorded activity since {}.", user.name, since.format("%Y-%m-%d")),
+                events,
+                since,
+                model: None,
+                cached: false,
+            });
+        }
+
+        let mut prompt = format!(
+            "Account activity of {} in {} since {}, newest first:",
+            user.name,
+            tenant.organization.name,
+            since.format("%Y-%m-%d")
+        );
+        for event in &events {
+            let _ = write!(
+                prompt,
+                "\n- {} {}: {}",
+                event.occurred_at.format("%Y-%m-%d %H:%M UTC"),
+                event.kind,
+                event.detail
+            );
+        }
+
+        // Deterministic, so asking again about the same events is answered from the cache
+        let response = self
+            .ai_service
+            .generate(
+                tenant,
+                GenerateRequest {
+                    prompt,
+                    system_instruction: Some(ACTIVITY_INSTRUCTION.to_string()),
+                    max_tokens: None,
+                    temperature: Some(0.0),
+                    candidate_count: None,
+                    cache: None,
+                    safety_settings: Vec::new(),
+                    response_schema: None,
+                    model: input.model,
+                },
+            )
+            .await?;
+
+        Ok(ActivityExplanationResponse {
+            user_id: user.id,
+            explanation: response.text,
+            events,
+            since,
+            model: Some(response.model),
+            cached: response.cached,
+        })
+    }
+}
+
+/// Name and address as compared for duplicates. The address is split into
+/// words, so "jane.doe@example.com" has something in common with "Jane Doe".
+fn identity_text(user: &User) -> String {
+    let address = user.email.to_lowercase().replace(['.', '_', '-', '+', '@'], " ");
+    format!("{} {}", user.name.to_lowercase(), address)
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    let dot: f64 = a.iter().zip(b).map(|(x, y)| f64::from(*x) * f64::from(*y)).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| f64::from(*x).powi(2)).sum::<f64>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms > 0.0 { dot / norms } else { 0.0 }
+}
@@ -0,0 +1,148 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use super::Tool;
+use crate::{
+    features::auth::model::ApiScope,
+    features::organizations::model::TenantContext,
+    features::user_management::domain::UserService,
+    features::user_management::model::UserResponse,
+    shared::error::AppError,
+};
+
+/// Users returned by `list_users` at most, to keep the model's context small
+const MAX_LISTED_USERS: usize = 50;
+
+/// Looks up a user by email. Like the other user tools it is read-only and needs `users:read`.
+pub struct FindUserByEmailTool {
+    users: UserService,
+}
+
+impl FindUserByEmailTool {
+    pub fn new(users: UserService) -> Self {
+        Self { users }
+    }
+}
+
+#[derive(Deserialize)]
+struct EmailArgs {
+    email: String,
+}
+
+#[async_trait]
+impl Tool for FindUserByEmailTool {
+    fn name(&self) -> &'static str {
+        "find_user_by_email"
+    }
+
+    fn description(&self) -> &'static str {
+        "Looks up a user of the organization by email address"
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "email": { "type": "string", "description": "Email address of the user" }
+            },
+            "required": ["email"]
+        })
+    }
+
+    async fn call(&self, tenant: &TenantContext, args: serde_json::Value) -> Result<serde_json::Value, AppError> {
+        tenant.require_scope(ApiScope::UsersRead)?;
+        let args: EmailArgs = parse_args(args)?;
+
+        let user = self.users.find_user_by_email(tenant.organization_id(), &args.email).await?;
+        Ok(json!(UserResponse::from(user)))
+    }
+}
+
+pub struct GetUserTool {
+    users: UserService,
+}
+
+impl GetUserTool {
+    pub fn new(users: UserService) -> Self {
+        Self { users }
+    }
+}
+
+#[derive(Deserialize)]
+struct IdArgs {
+    id: Uuid,
+}
+
+#[async_trait]
+impl Tool for GetUserTool {
+    fn name(&self) -> &'static str {
+        "get_user"
+    }
+
+    fn description(&self) -> &'static str {
+        "Gets a user of the organization by id"
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "description": "UUID of the user" }
+            },
+            "required": ["id"]
+        })
+    }
+
+    async fn call(&self, tenant: &TenantContext, args: serde_json::Value) -> Result<serde_json::Value, AppError> {
+        tenant.require_scope(ApiScope::UsersRead)?;
+        let args: IdArgs = parse_args(args)?;
+
+        let user = self.users.get_user(tenant.organization_id(), args.id).await?;
+        Ok(json!(UserResponse::from(user)))
+    }
+}
+
+pub struct ListUsersTool {
+    users: UserService,
+}
+
+impl ListUsersTool {
+    pub fn new(users: UserService) -> Self {
+        Self { users }
+    }
+}
+
+#[async_trait]
+impl Tool for ListUsersTool {
+    fn name(&self) -> &'static str {
+        "list_users"
+    }
+
+    fn description(&self) -> &'static str {
+        "Lists the users of the organization with their name, email and role"
+    }
+
+    fn parameters(&self) -> serde_json::Value {
+        json!({ "type": "object", "properties": {} })
+    }
+
+    async fn call(&self, tenant: &TenantContext, _args: serde_json::Value) -> Result<serde_json::Value, AppError> {
+        tenant.require_scope(ApiScope::UsersRead)?;
+
+        let users = self.users.get_users(tenant.organization_id()).await?;
+        let total = users.len();
+        let users: Vec<UserResponse> = users
+            .into_iter()
+            .take(MAX_LISTED_USERS)
+            .map(UserResponse::from)
+            .collect();
+
+        Ok(json!({ "users": users, "total": total }))
+    }
+}
+
+fn parse_args<T: serde::de::DeserializeOwned>(args: serde_json::Value) -> Result<T, AppError> {
+    serde_json::from_value(args).map_err(|e| AppError::Validation(format!("Invalid arguments: {}", e)))
+}
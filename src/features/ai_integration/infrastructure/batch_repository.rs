@@ -0,0 +1,286 @@
+use async_trait::async_trait;
+use sqlx::{FromRow, PgConnection, PgPool};
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::entities::ai::{AiBatch, AiBatchResult};
+use crate::shared::database::begin_tenant_transaction;
+
+/// Requests of a new batch, in order, and who submitted them
+#[derive(Debug, Clone)]
+pub struct NewBatch {
+    pub requests: Vec<serde_json::Value>,
+    pub created_by: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+}
+
+/// A job a worker has claimed, with the caller to run it as
+#[derive(Debug, Clone, FromRow)]
+pub struct ClaimedJob {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    pub organization_id: Uuid,
+    pub created_by: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub request: serde_json::Value,
+    /// Including the current one. Updates of an earlier attempt whose lease ran out are ignored.
+    pub attempts: i32,
+}
+
+/// Batches are read per tenant under row-level security. Workers claim and
+/// finish jobs of all tenants.
+#[async_trait]
+pub trait BatchRepository: Send + Sync {
+    /// `None` when the tenant would have more than `max_pending` jobs
+    /// waiting or running
+    async fn create(&self, tenant_id: Uuid, batch: NewBatch, max_pending: i64) -> Result<Option<AiBatch>, sqlx::Error>;
+    async fn find(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<AiBatch>, sqlx::Error>;
+    /// Finished jobs after position `after`, in order
+    async fn results(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+        after: i32,
+        limit: i64,
+    ) -> Result<Vec<AiBatchResult>, sqlx::Error>;
+    /// Cancels the jobs that haven't finished. `None` when no such batch exists.
+    async fn cancel(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<AiBatch>, sqlx::Error>;
+    /// Marks up to `limit` due jobs as running for `lease`
+    async fn claim(&self, limit: i64, lease: Duration) -> Result<Vec<ClaimedJob>, sqlx::Error>;
+    async fn succeed(&self, job: &ClaimedJob, response: serde_json::Value) -> Result<(), sqlx::Error>;
+    async fn fail(&self, job: &ClaimedJob, error: &str) -> Result<(), sqlx::Error>;
+    /// Puts the job back in the queue until `delay` has passed
+    async fn retry(&self, job: &ClaimedJob, error: &str, delay: Duration) -> Result<(), sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresBatchRepository {
+    pool: PgPool,
+}
+
+impl PostgresBatchRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl BatchRepository for PostgresBatchRepository {
+    async fn create(&self, tenant_id: Uuid, batch: NewBatch, max_pending: i64) -> Result<Option<AiBatch>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+
+        // Batches of one tenant are created one at a time, so two can't both
+        // pass the check
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1, 0))")
+            .bind(format!("ai_batches:{}", tenant_id))
+            .execute(&mut *tx)
+            .await?;
+        let pending = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM ai_batch_jobs WHERE status IN ('pending', 'running')",
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if pending + batch.requests.len() as i64 > max_pending {
+            return Ok(None);
+        }
+
+        let (id,): (Uuid,) = sqlx::query_as(
+            r#"
+            INSERT INTO ai_batches (organization_id, total_jobs, created_by, api_key_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(batch.requests.len() as i32)
+        .bind(batch.created_by)
+        .bind(batch.api_key_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO ai_batch_jobs (batch_id, organization_id, position, request)
+            SELECT $1, $2, requests.position - 1, requests.request
+            FROM UNNEST($3::JSONB[]) WITH ORDINALITY AS requests(request, position)
+            "#,
+        )
+        .bind(id)
+        .bind(tenant_id)
+        .bind(batch.requests)
+        .execute(&mut *tx)
+        .await?;
+
+        let created = fetch_batch(&mut tx, id).await?.ok_or(sqlx::Error::RowNotFound)?;
+        tx.commit().await?;
+        Ok(Some(created))
+    }
+
+    async fn find(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<AiBatch>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let batch = fetch_batch(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(batch)
+    }
+
+    async fn results(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+        after: i32,
+        limit: i64,
+    ) -> Result<Vec<AiBatchResult>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let results = sqlx::query_as::<_, AiBatchResult>(
+            r#"
+            SELECT position, status, attempts, response, error, finished_at
+            FROM ai_batch_jobs
+            WHERE batch_id = $1
+              AND position > $2
+              AND status IN ('succeeded', 'failed', 'cancelled')
+            ORDER BY position
+            LIMIT $3
+            "#,
+        )
+        .bind(id)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(results)
+    }
+
+    async fn cancel(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<AiBatch>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let cancelled = sqlx::query("UPDATE ai_batches SET cancelled_at = NOW() WHERE id = $1 AND cancelled_at IS NULL")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        if cancelled.rows_affected() > 0 {
+            sqlx::query(
+                r#"
+                UPDATE ai_batch_jobs
+                SET status = 'cancelled', locked_until = NULL, finished_at = NOW()
+                WHERE batch_id = $1 AND status IN ('pending', 'running')
+                "#,
+            )
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let batch = fetch_batch(&mut tx, id).await?;
+        tx.commit().await?;
+        Ok(batch)
+    }
+
+    async fn claim(&self, limit: i64, lease: Duration) -> Result<Vec<ClaimedJob>, sqlx::Error> {
+        sqlx::query_as::<_, ClaimedJob>(
+            r#"
+            WITH due AS (
+                SELECT id FROM ai_batch_jobs
+                WHERE (status = 'pending' AND run_after <= NOW())
+                   OR (status = 'running' AND locked_until < NOW())
+                ORDER BY run_after, position
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE ai_batch_jobs jobs
+            SET status = 'running',
+                attempts = jobs.attempts + 1,
+                locked_until = NOW() + make_interval(secs => $2)
+            FROM due, ai_batches batches
+            WHERE jobs.id = due.id AND batches.id = jobs.batch_id
+            RETURNING jobs.id, jobs.batch_id, jobs.organization_id, batches.created_by, batches.api_key_id,
+                      jobs.request, jobs.attempts
+            "#,
+        )
+        .bind(limit)
+        .bind(lease.as_secs_f64())
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn succeed(&self, job: &ClaimedJob, response: serde_json::Value) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE ai_batch_jobs
+            SET status = 'succeeded', response = $3, error = NULL, locked_until = NULL, finished_at = NOW()
+            WHERE id = $1 AND attempts = $2 AND status = 'running'
+            "#,
+        )
+        .bind(job.id)
+        .bind(job.attempts)
+        .bind(response)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn fail(&self, job: &ClaimedJob, error: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE ai_batch_jobs
+            SET status = 'failed', error = $3, locked_until = NULL, finished_at = NOW()
+            WHERE id = $1 AND attempts = $2 AND status = 'running'
+            "#,
+        )
+        .bind(job.id)
+        .bind(job.attempts)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn retry(&self, job: &ClaimedJob, error: &str, delay: Duration) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE ai_batch_jobs
+            SET status = 'pending', error = $3, locked_until = NULL, run_after = NOW() + make_interval(secs => $4)
+            WHERE id = $1 AND attempts = $2 AND status = 'running'
+            "#,
+        )
+        .bind(job.id)
+        .bind(job.attempts)
+        .bind(error)
+        .bind(delay.as_secs_f64())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// The batch with its jobs counted by status
+async fn fetch_batch(conn: &mut PgConnection, id: Uuid) -> Result<Option<AiBatch>, sqlx::Error> {
+    sqlx::query_as::<_, AiBatch>(
+        r#"
+        SELECT id, total, pending, running, succeeded, failed, cancelled, created_at, cancelled_at,
+               CASE
+                   WHEN cancelled_at IS NOT NULL THEN 'cancelled'
+                   WHEN pending + running = 0 THEN 'completed'
+                   WHEN running + succeeded + failed > 0 THEN 'running'
+                   ELSE 'pending'
+               END::VARCHAR AS status,
+               CASE WHEN pending + running = 0 THEN last_finished_at END AS completed_at
+        FROM (
+            SELECT batches.id, batches.created_at, batches.cancelled_at,
+                   batches.total_jobs::BIGINT AS total,
+                   COUNT(*) FILTER (WHERE jobs.status = 'pending') AS pending,
+                   COUNT(*) FILTER (WHERE jobs.status = 'running') AS running,
+                   COUNT(*) FILTER (WHERE jobs.status = 'succeeded') AS succeeded,
+                   COUNT(*) FILTER (WHERE jobs.status = 'failed') AS failed,
+                   COUNT(*) FILTER (WHERE jobs.status = 'cancelled') AS cancelled,
+                   MAX(jobs.finished_at) AS last_finished_at
+            FROM ai_batches batches
+            JOIN ai_batch_jobs jobs ON jobs.batch_id = batches.id
+            WHERE batches.id = $1
+            GROUP BY batches.id
+        ) counts
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(conn)
+    .await
+}
@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use lru::LruCache;
+use sqlx::{types::Json, FromRow, PgPool};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::{AiCompletion, TokenUsage};
+use crate::entities::ai::Candidate;
+use crate::shared::config::AiCacheConfig;
+use crate::shared::error::AppError;
+
+/// How often expired and surplus entries are deleted
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Stores completions by cache key. Entries expire after the TTL and the least
+/// recently used ones are evicted beyond the size bound.
+#[async_trait]
+pub trait ResponseCache: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<AiCompletion>, AppError>;
+    async fn put(&self, organization_id: Uuid, key: &str, completion: &AiCompletion) -> Result<(), AppError>;
+    /// Deletes expired entries and the least recently used ones beyond the
+    /// bound, returns how many were deleted
+    async fn evict(&self) -> Result<u64, AppError>;
+}
+
+/// Builds the cache selected by `AI_CACHE_BACKEND`, `None` disables caching
+pub fn create_response_cache(
+    config: &AiCacheConfig,
+    pool: PgPool,
+) -> anyhow::Result<Option<Arc<dyn ResponseCache>>> {
+    let ttl = Duration::from_secs(config.ttl_seconds);
+    let max_entries = NonZeroUsize::new(config.max_entries)
+        .ok_or_else(|| anyhow::anyhow!("AI_CACHE_MAX_ENTRIES must be greater than 0"))?;
+
+    let cache: Arc<dyn ResponseCache> = match config.backend.as_str() {
+        "none" => return Ok(None),
+        "memory" => Arc::new(MemoryResponseCache::new(ttl, max_entries)),
+        "postgres" => Arc::new(PostgresResponseCache::new(pool, ttl, max_entries)),
+        other => anyhow::bail!("Unknown AI_CACHE_BACKEND: {}", other),
+    };
+
+    Ok(Some(cache))
+}
+
+/// Evicts entries every minute, so writes don't pay for the sweep
+pub fn start_eviction(cache: Arc<dyn ResponseCache>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+        loop {
+            interval.tick().await;
+            match cache.evict().await {
+                Ok(0) => {}
+                Ok(deleted) => tracing::debug!(deleted, "Evicted AI response cache entries"),
+                Err(e) => tracing::error!("Failed to evict AI response cache entries: {}", e),
+            }
+        }
+    });
+}
+
+/// Per-process LRU cache
+pub struct MemoryResponseCache {
+    entries: Mutex<LruCache<String, (AiCompletion, Instant)>>,
+    ttl: Duration,
+}
+
+impl MemoryResponseCache {
+    pub fn new(ttl: Duration, max_entries: NonZeroUsize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(max_entries)),
+            ttl,
+        }
+    }
+}
+
+#[async_trait]
+impl ResponseCache for MemoryResponseCache {
+    async fn get(&self, key: &str) -> Result<Option<AiCompletion>, AppError> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        match entries.get(key) {
+            Some((completion, stored_at)) if stored_at.elapsed() < self.ttl => Ok(Some(completion.clone())),
+            Some(_) => {
+                entries.pop(key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn put(&self, _organization_id: Uuid, key: &str, completion: &AiCompletion) -> Result<(), AppError> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .put(key.to_string(), (completion.clone(), Instant::now()));
+        Ok(())
+    }
+
+    /// The LRU bounds itself and expired entries are dropped when read
+    async fn evict(&self) -> Result<u64, AppError> {
+        Ok(0)
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct CachedCompletion {
+    model: String,
+    text: String,
+    finish_reason: String,
+    prompt_tokens: i32,
+    completion_tokens: i32,
+    total_tokens: i32,
+    candidates: Option<Json<Vec<Candidate>>>,
+}
+
+/// Cache shared by all instances, in `ai_response_cache`
+pub struct PostgresResponseCache {
+    pool: PgPool,
+    ttl: Duration,
+    max_entries: NonZeroUsize,
+}
+
+impl PostgresResponseCache {
+    pub fn new(pool: PgPool, ttl: Duration, max_entries: NonZeroUsize) -> Self {
+        Self { pool, ttl, max_entries }
+    }
+}
+
+#[async_trait]
+impl ResponseCache for PostgresResponseCache {
+    async fn get(&self, key: &str) -> Result<Option<AiCompletion>, AppError> {
+        let cached = sqlx::query_as::<_, CachedCompletion>(
+            r#"
+            UPDATE ai_response_cache
+            SET last_hit_at = NOW()
+            WHERE key_hash = $1 AND expires_at > NOW()
+            RETURNING model, text, finish_reason, prompt_tokens, completion_tokens, total_tokens, candidates
+            "#,
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(cached.map(|cached| AiCompletion {
+            candidates: match cached.candidates {
+                Some(Json(candidates)) => candidates,
+                None => vec![Candidate::answer(&cached.text, &cached.finish_reason)],
+            },
+            text: cached.text,
+            model: cached.model,
+            finish_reason: cached.finish_reason,
+            tool_calls: Vec::new(),
+            usage: TokenUsage {
+                prompt_tokens: cached.prompt_tokens,
+                completion_tokens: cached.completion_tokens,
+                total_tokens: cached.total_tokens,
+            },
+        }))
+    }
+
+    async fn put(&self, organization_id: Uuid, key: &str, completion: &AiCompletion) -> Result<(), AppError> {
+        let expires_at = Utc::now()
+            + chrono::Duration::from_std(self.ttl).map_err(|e| AppError::Internal(e.into()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO ai_response_cache
+                (key_hash, organization_id, model, text, finish_reason, prompt_tokens, completion_tokens, total_tokens,
+                 candidates, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (key_hash) DO UPDATE
+            SET text = EXCLUDED.text,
+                finish_reason = EXCLUDED.finish_reason,
+                prompt_tokens = EXCLUDED.prompt_tokens,
+                completion_tokens = EXCLUDED.completion_tokens,
+                total_tokens = EXCLUDED.total_tokens,
+                candidates = EXCLUDED.candidates,
+                last_hit_at = NOW(),
+                expires_at = EXCLUDED.expires_at
+            "#,
+        )
+        .bind(key)
+        .bind(organization_id)
+        .bind(&completion.model)
+        .bind(&completion.text)
+        .bind(&completion.finish_reason)
+        .bind(completion.usage.prompt_tokens)
+        .bind(completion.usage.completion_tokens)
+        .bind(completion.usage.total_tokens)
+        .bind(Json(&completion.candidates))
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(())
+    }
+
+    async fn evict(&self) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM ai_response_cache
+            WHERE expires_at <= NOW()
+               OR key_hash IN (
+                   SELECT key_hash FROM ai_response_cache
+                   ORDER BY last_hit_at DESC
+                   OFFSET $1
+               )
+            "#,
+        )
+        .bind(self.max_entries.get() as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(result.rows_affected())
+    }
+}
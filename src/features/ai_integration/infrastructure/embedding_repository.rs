@@ -0,0 +1,199 @@
+use async_trait::async_trait;
+use pgvector::Vector;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::entities::ai::{EmbeddedDocument, EmbeddingMatch};
+use crate::shared::database::begin_tenant_transaction;
+
+/// A document and its vector, replacing any document with the same id in the collection
+#[derive(Debug, Clone)]
+pub struct NewEmbedding {
+    pub document_id: String,
+    /// Set for chunks of an ingested document
+    pub source_id: Option<String>,
+    pub chunk_index: Option<i32>,
+    pub content: String,
+    pub metadata: serde_json::Value,
+    pub model: String,
+    pub embedding: Vec<f32>,
+    pub created_by: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+}
+
+/// Nearest neighbours of `embedding` among the vectors of `model` in a collection
+#[derive(Debug, Clone)]
+pub struct EmbeddingQuery {
+    pub embedding: Vec<f32>,
+    pub model: String,
+    pub limit: i64,
+    /// Only documents whose metadata contains this object
+    pub filter: Option<serde_json::Value>,
+    pub min_score: Option<f64>,
+}
+
+/// Embeddings are stored per tenant under row-level security
+#[async_trait]
+pub trait EmbeddingRepository: Send + Sync {
+    async fn upsert(
+        &self,
+        tenant_id: Uuid,
+        collection: &str,
+        documents: Vec<NewEmbedding>,
+    ) -> Result<Vec<EmbeddedDocument>, sqlx::Error>;
+    /// Replaces all chunks of an ingested document with `chunks`
+    async fn replace_source(
+        &self,
+        tenant_id: Uuid,
+        collection: &str,
+        source_id: &str,
+        chunks: Vec<NewEmbedding>,
+    ) -> Result<Vec<EmbeddedDocument>, sqlx::Error>;
+    /// Best matches first
+    async fn search(
+        &self,
+        tenant_id: Uuid,
+        collection: &str,
+        query: EmbeddingQuery,
+    ) -> Result<Vec<EmbeddingMatch>, sqlx::Error>;
+    /// Deletes a document, or all chunks of an ingested one. `false` when nothing matched.
+    async fn delete(&self, tenant_id: Uuid, collection: &str, document_id: &str) -> Result<bool, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresEmbeddingRepository {
+    pool: PgPool,
+}
+
+impl PostgresEmbeddingRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EmbeddingRepository for PostgresEmbeddingRepository {
+    async fn upsert(
+        &self,
+        tenant_id: Uuid,
+        collection: &str,
+        documents: Vec<NewEmbedding>,
+    ) -> Result<Vec<EmbeddedDocument>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let stored = insert_embeddings(&mut tx, tenant_id, collection, documents).await?;
+        tx.commit().await?;
+        Ok(stored)
+    }
+
+    async fn replace_source(
+        &self,
+        tenant_id: Uuid,
+        collection: &str,
+        source_id: &str,
+        chunks: Vec<NewEmbedding>,
+    ) -> Result<Vec<EmbeddedDocument>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        sqlx::query("DELETE FROM embeddings WHERE collection = $1 AND source_id = $2")
+            .bind(collection)
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?;
+        let stored = insert_embeddings(&mut tx, tenant_id, collection, chunks).await?;
+        tx.commit().await?;
+        Ok(stored)
+    }
+
+    async fn search(
+        &self,
+        tenant_id: Uuid,
+        collection: &str,
+        query: EmbeddingQuery,
+    ) -> Result<Vec<EmbeddingMatch>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+
+        // The HNSW index spans all tenants and collections. Without iterative
+        // scans, rows removed by the filters could leave fewer than `limit` results.
+        sqlx::query("SET LOCAL hnsw.iterative_scan = strict_order")
+            .execute(&mut *tx)
+            .await?;
+
+        let matches = sqlx::query_as::<_, EmbeddingMatch>(
+            r#"
+            SELECT document_id, source_id, chunk_index, content, metadata, 1 - (embedding <=> $1) AS score
+            FROM embeddings
+            WHERE collection = $2
+              AND model = $3
+              AND ($4::JSONB IS NULL OR metadata @> $4)
+              AND ($5::FLOAT8 IS NULL OR 1 - (embedding <=> $1) >= $5)
+            ORDER BY embedding <=> $1
+            LIMIT $6
+            "#,
+        )
+        .bind(Vector::from(query.embedding))
+        .bind(collection)
+        .bind(query.model)
+        .bind(query.filter)
+        .bind(query.min_score)
+        .bind(query.limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(matches)
+    }
+
+    async fn delete(&self, tenant_id: Uuid, collection: &str, document_id: &str) -> Result<bool, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let result = sqlx::query(
+            "DELETE FROM embeddings WHERE collection = $1 AND (document_id = $2 OR source_id = $2)",
+        )
+        .bind(collection)
+        .bind(document_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+async fn insert_embeddings(
+    conn: &mut PgConnection,
+    tenant_id: Uuid,
+    collection: &str,
+    documents: Vec<NewEmbedding>,
+) -> Result<Vec<EmbeddedDocument>, sqlx::Error> {
+    let mut stored = Vec::with_capacity(documents.len());
+    for document in documents {
+        let row = sqlx::query_as::<_, EmbeddedDocument>(
+            r#"
+            INSERT INTO embeddings
+                (organization_id, collection, document_id, source_id, chunk_index, content, metadata, model,
+                 embedding, created_by, api_key_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            ON CONFLICT (organization_id, collection, document_id) DO UPDATE
+            SET source_id = EXCLUDED.source_id,
+                chunk_index = EXCLUDED.chunk_index,
+                content = EXCLUDED.content,
+                metadata = EXCLUDED.metadata,
+                model = EXCLUDED.model,
+                embedding = EXCLUDED.embedding,
+                updated_at = NOW()
+            RETURNING collection, document_id, source_id, chunk_index, content, metadata, model, created_at, updated_at
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(collection)
+        .bind(document.document_id)
+        .bind(document.source_id)
+        .bind(document.chunk_index)
+        .bind(document.content)
+        .bind(document.metadata)
+        .bind(document.model)
+        .bind(Vector::from(document.embedding))
+        .bind(document.created_by)
+        .bind(document.api_key_id)
+        .fetch_one(&mut *conn)
+        .await?;
+        stored.push(row);
+    }
+    Ok(stored)
+}
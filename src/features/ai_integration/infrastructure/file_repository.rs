@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::entities::ai::AiFile;
+use crate::shared::database::begin_tenant_transaction;
+
+/// An upload and who made it
+#[derive(Debug, Clone)]
+pub struct NewAiFile {
+    pub filename: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+    pub uploaded_by: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct AiFileContent {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Files are stored per tenant under row-level security
+#[async_trait]
+pub trait AiFileRepository: Send + Sync {
+    async fn create(&self, tenant_id: Uuid, file: NewAiFile) -> Result<AiFile, sqlx::Error>;
+    async fn list(&self, tenant_id: Uuid) -> Result<Vec<AiFile>, sqlx::Error>;
+    async fn content(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<AiFileContent>, sqlx::Error>;
+    /// `false` when no such file exists
+    async fn delete(&self, tenant_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresAiFileRepository {
+    pool: PgPool,
+}
+
+impl PostgresAiFileRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AiFileRepository for PostgresAiFileRepository {
+    async fn create(&self, tenant_id: Uuid, file: NewAiFile) -> Result<AiFile, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let created = sqlx::query_as::<_, AiFile>(
+            r#"
+            INSERT INTO ai_files (organization_id, filename, mime_type, size_bytes, data, uploaded_by, api_key_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, filename, mime_type, size_bytes, created_at
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(file.filename)
+        .bind(file.mime_type)
+        .bind(file.data.len() as i64)
+        .bind(file.data)
+        .bind(file.uploaded_by)
+        .bind(file.api_key_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(created)
+    }
+
+    async fn list(&self, tenant_id: Uuid) -> Result<Vec<AiFile>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let files = sqlx::query_as::<_, AiFile>(
+            "SELECT id, filename, mime_type, size_bytes, created_at FROM ai_files ORDER BY created_at DESC",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(files)
+    }
+
+    async fn content(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<AiFileContent>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let content = sqlx::query_as::<_, AiFileContent>("SELECT mime_type, data FROM ai_files WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(content)
+    }
+
+    async fn delete(&self, tenant_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let result = sqlx::query("DELETE FROM ai_files WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
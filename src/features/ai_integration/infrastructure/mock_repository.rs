@@ -0,0 +1,100 @@
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+
+use super::{AIRepository, AiCompletion, AiEmbeddings, CompletionOptions, TokenUsage, ToolStep, EMBEDDING_DIMENSIONS};
+use crate::entities::ai::{Candidate, ChatMessage, EmbeddingTask};
+use crate::shared::error::AppError;
+
+/// Offline provider for development and tests, selected with `AI_PROVIDER=mock`.
+/// Answers echo the prompt and embeddings hash the words of a text, so texts
+/// sharing words are similar. Deterministic and free, but not meaningful.
+pub struct MockAIRepository {
+    model: String,
+}
+
+impl MockAIRepository {
+    /// Any name works, e.g. "mock" or "mock-fast" to tell models of a route apart
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+        }
+    }
+
+    /// Every candidate is the same answer
+    fn answer(&self, prompt: &str, options: &CompletionOptions) -> AiCompletion {
+        let text = format!("Mock answer to: {}", prompt);
+        let count = options.generation.candidate_count.unwrap_or(1).max(1);
+        let prompt_tokens = prompt.split_whitespace().count() as i32;
+        let completion_tokens = text.split_whitespace().count() as i32 * count;
+        let candidates = (0..count)
+            .map(|index| Candidate {
+                index,
+                ..Candidate::answer(&text, "STOP")
+            })
+            .collect();
+
+        AiCompletion {
+            text,
+            model: self.model.clone(),
+            usage: TokenUsage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            },
+            finish_reason: "STOP".to_string(),
+            tool_calls: Vec::new(),
+            candidates,
+        }
+    }
+}
+
+#[async_trait]
+impl AIRepository for MockAIRepository {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn chat(
+        &self,
+        message: ChatMessage,
+        _history: Vec<ChatMessage>,
+        _steps: &[ToolStep],
+        options: &CompletionOptions,
+    ) -> Result<AiCompletion, AppError> {
+        Ok(self.answer(&message.content, options))
+    }
+
+    async fn generate(&self, prompt: String, options: &CompletionOptions) -> Result<AiCompletion, AppError> {
+        Ok(self.answer(&prompt, options))
+    }
+
+    fn embedding_model(&self) -> &str {
+        "mock-embedding"
+    }
+
+    async fn embed(&self, texts: Vec<String>, _task: EmbeddingTask) -> Result<AiEmbeddings, AppError> {
+        Ok(AiEmbeddings {
+            model: self.embedding_model().to_string(),
+            vectors: texts.iter().map(|text| hash_embedding(text)).collect(),
+        })
+    }
+}
+
+/// Adds ±1 per lowercased word at a position picked by its hash, normalized to length 1
+fn hash_embedding(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0f32; EMBEDDING_DIMENSIONS];
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let hash = Sha256::digest(word.to_lowercase().as_bytes());
+        let index = u32::from_le_bytes([hash[0], hash[1], hash[2], hash[3]]) as usize % EMBEDDING_DIMENSIONS;
+        vector[index] += if hash[4] & 1 == 0 { 1.0 } else { -1.0 };
+    }
+
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
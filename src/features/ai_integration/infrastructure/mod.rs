@@ -1,3 +1,32 @@
+mod batch_repository;
+mod cache_repository;
+mod embedding_repository;
+mod file_repository;
+mod mock_repository;
+mod model_router;
+mod moderation_repository;
+mod offline_repository;
 mod repository;
+mod request_log_repository;
+mod template_repository;
+mod usage_repository;
 
-pub use repository::{AIRepository, GeminiRepository};
+pub use batch_repository::{BatchRepository, ClaimedJob, NewBatch, PostgresBatchRepository};
+pub use cache_repository::{create_response_cache, start_eviction, ResponseCache};
+pub use embedding_repository::{EmbeddingQuery, EmbeddingRepository, NewEmbedding, PostgresEmbeddingRepository};
+pub use file_repository::{AiFileRepository, NewAiFile, PostgresAiFileRepository};
+pub use mock_repository::MockAIRepository;
+pub use model_router::{create_model_router, ModelRouter};
+pub use moderation_repository::{ModerationRepository, NewModerationFlag, PostgresModerationRepository};
+pub use offline_repository::{MemoryAiUsageRepository, OfflineStorage};
+pub use repository::{
+    create_ai_repository, AIRepository, AiCompletion, AiEmbeddings, CompletionOptions, GenerationConfig,
+    TokenUsage, ToolCall, ToolDeclaration, ToolStep, EMBEDDING_DIMENSIONS,
+};
+pub use request_log_repository::{
+    LoggedRequest, NewRequestLog, PostgresRequestLogRepository, RequestLogFilter, RequestLogRepository,
+};
+pub use template_repository::{
+    NewPromptTemplate, PostgresPromptTemplateRepository, PromptTemplateRepository,
+};
+pub use usage_repository::{AiRedactionRecord, AiUsageRecord, AiUsageRepository, PostgresAiUsageRepository};
@@ -0,0 +1,250 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::repository::{ModelCapabilities, ProviderModel};
+use super::{create_ai_repository, AIRepository, AiCompletion, AiEmbeddings, CompletionOptions, ToolStep};
+use crate::entities::ai::{ChatMessage, EmbeddingTask};
+use crate::shared::config::AiRoutingConfig;
+use crate::shared::error::AppError;
+
+type Attempt<'a> = Pin<Box<dyn Future<Output = Result<AiCompletion, AppError>> + Send + 'a>>;
+
+/// A model of `AI_MODELS` and the provider serving it
+pub struct AllowedModel {
+    pub provider: String,
+    pub repository: Arc<dyn AIRepository>,
+}
+
+/// Models and named routes that requests may choose from
+pub struct ModelRouter {
+    /// Answers requests that name no model
+    default: Arc<dyn AIRepository>,
+    default_name: String,
+    /// The default model first, then `AI_MODELS` in order
+    models: Vec<AllowedModel>,
+    /// Names of the models of each route, in fallback order
+    route_models: Vec<(String, Vec<String>)>,
+    /// Allowed models by model name and routes by route name
+    routes: HashMap<String, Arc<dyn AIRepository>>,
+    /// The default model, embeddings must keep coming from the same model to stay comparable
+    embeddings: Arc<dyn AIRepository>,
+}
+
+impl ModelRouter {
+    /// The route or model called `name`, the default when unset
+    pub fn resolve(&self, name: Option<&str>) -> Result<Arc<dyn AIRepository>, AppError> {
+        let Some(name) = name else {
+            return Ok(self.default.clone());
+        };
+        self.routes.get(name).cloned().ok_or_else(|| {
+            let mut allowed: Vec<_> = self.routes.keys().map(String::as_str).collect();
+            allowed.sort_unstable();
+            AppError::Validation(format!("Unknown model '{}', allowed are {}", name, allowed.join(", ")))
+        })
+    }
+
+    pub fn embeddings(&self) -> &dyn AIRepository {
+        self.embeddings.as_ref()
+    }
+
+    /// Route or model answering requests that name none
+    pub fn default_name(&self) -> &str {
+        &self.default_name
+    }
+
+    pub fn models(&self) -> &[AllowedModel] {
+        &self.models
+    }
+
+    pub fn routes(&self) -> &[(String, Vec<String>)] {
+        &self.route_models
+    }
+
+    /// Provider serving an allowed model, e.g. "gemini"
+    pub fn provider(&self, model: &str) -> Option<&str> {
+        self.models
+            .iter()
+            .find(|allowed| allowed.repository.model() == model)
+            .map(|allowed| allowed.provider.as_str())
+    }
+}
+
+/// Builds the allowed models and the routes of `AI_ROUTES`. Requests can
+/// name a model alone, e.g. "gemini-1.5-pro", or a route, e.g. "fast".
+pub fn create_model_router(config: &AiRoutingConfig, gemini_api_key: &str) -> anyhow::Result<ModelRouter> {
+    let timeout = Duration::from_secs(config.timeout_seconds);
+
+    let mut models: HashMap<String, Arc<dyn AIRepository>> = HashMap::new();
+    let mut allowed = Vec::new();
+    for target in std::iter::once(&config.default_model).chain(&config.models) {
+        let model = create_ai_repository(target, config, gemini_api_key)?;
+        if models.contains_key(model.model()) {
+            anyhow::bail!("AI model {} is listed twice", model.model());
+        }
+        let provider = target.split_once(':').map_or("", |(provider, _)| provider);
+        allowed.push((provider.to_string(), model.model().to_string()));
+        models.insert(model.model().to_string(), model);
+    }
+    let embeddings = models[default_model_name(config)].clone();
+
+    let mut routes: HashMap<String, Arc<dyn AIRepository>> = models
+        .iter()
+        .map(|(name, model)| {
+            let single: Arc<dyn AIRepository> = Arc::new(FallbackRepository::new(vec![model.clone()], timeout));
+            (name.clone(), single)
+        })
+        .collect();
+    let allowed = allowed
+        .into_iter()
+        .map(|(provider, name)| AllowedModel {
+            provider,
+            repository: routes[&name].clone(),
+        })
+        .collect();
+
+    let mut route_models = Vec::with_capacity(config.routes.len());
+    for route in &config.routes {
+        if routes.contains_key(&route.name) {
+            anyhow::bail!("AI route {} has the name of a model or another route", route.name);
+        }
+        let mut targets = Vec::with_capacity(route.models.len());
+        for target in &route.models {
+            let name = target.split_once(':').map_or(target.as_str(), |(_, model)| model);
+            let model = models
+                .get(name)
+                .ok_or_else(|| anyhow::anyhow!("AI route {} uses {}, which is not in AI_MODELS", route.name, target))?;
+            // Answers are told apart by model, see `AIService`
+            if targets.iter().any(|other: &Arc<dyn AIRepository>| other.model() == model.model()) {
+                anyhow::bail!("AI route {} lists {} twice", route.name, target);
+            }
+            targets.push(model.clone());
+        }
+        if targets.is_empty() {
+            anyhow::bail!("AI route {} has no models", route.name);
+        }
+        route_models.push((route.name.clone(), targets.iter().map(|model| model.model().to_string()).collect()));
+        routes.insert(route.name.clone(), Arc::new(FallbackRepository::new(targets, timeout)));
+    }
+
+    let default_name = config.default_route.as_deref().unwrap_or(default_model_name(config));
+    let default = routes
+        .get(default_name)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Unknown AI_DEFAULT_ROUTE: {}", default_name))?;
+
+    Ok(ModelRouter {
+        default,
+        default_name: default_name.to_string(),
+        models: allowed,
+        route_models,
+        routes,
+        embeddings,
+    })
+}
+
+fn default_model_name(config: &AiRoutingConfig) -> &str {
+    config
+        .default_model
+        .split_once(':')
+        .map_or(config.default_model.as_str(), |(_, model)| model)
+}
+
+/// Tries its models in order until one answers, each within `timeout`.
+/// Service failures and timeouts move on to the next model, other errors
+/// such as blocked content are returned right away. Settings and
+/// embeddings are those of the first model.
+struct FallbackRepository {
+    targets: Vec<Arc<dyn AIRepository>>,
+    timeout: Duration,
+}
+
+impl FallbackRepository {
+    fn new(targets: Vec<Arc<dyn AIRepository>>, timeout: Duration) -> Self {
+        Self { targets, timeout }
+    }
+
+    fn primary(&self) -> &dyn AIRepository {
+        self.targets[0].as_ref()
+    }
+
+    async fn first_answer<'a>(
+        &'a self,
+        call: impl Fn(&'a dyn AIRepository) -> Attempt<'a>,
+    ) -> Result<AiCompletion, AppError> {
+        let mut errors = Vec::new();
+        for (index, target) in self.targets.iter().enumerate() {
+            let error = match tokio::time::timeout(self.timeout, call(target.as_ref())).await {
+                Ok(Ok(completion)) => return Ok(completion),
+                Ok(Err(AppError::ExternalService(error))) => error,
+                Ok(Err(error)) => return Err(error),
+                Err(_) => format!("{} did not answer within {} seconds", target.model(), self.timeout.as_secs()),
+            };
+            if index + 1 < self.targets.len() {
+                tracing::warn!(model = target.model(), "AI model failed, trying the next one: {}", error);
+            }
+            errors.push(error);
+        }
+
+        Err(AppError::ExternalService(if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            format!("All models failed: {}", errors.join("; "))
+        }))
+    }
+}
+
+#[async_trait]
+impl AIRepository for FallbackRepository {
+    fn model(&self) -> &str {
+        self.primary().model()
+    }
+
+    fn capabilities(&self) -> ModelCapabilities {
+        self.primary().capabilities()
+    }
+
+    async fn chat(
+        &self,
+        message: ChatMessage,
+        history: Vec<ChatMessage>,
+        steps: &[ToolStep],
+        options: &CompletionOptions,
+    ) -> Result<AiCompletion, AppError> {
+        self.first_answer(|target| target.chat(message.clone(), history.clone(), steps, options))
+            .await
+    }
+
+    async fn generate(&self, prompt: String, options: &CompletionOptions) -> Result<AiCompletion, AppError> {
+        self.first_answer(|target| target.generate(prompt.clone(), options)).await
+    }
+
+    fn embedding_model(&self) -> &str {
+        self.primary().embedding_model()
+    }
+
+    async fn embed(&self, texts: Vec<String>, task: EmbeddingTask) -> Result<AiEmbeddings, AppError> {
+        self.primary().embed(texts, task).await
+    }
+
+    async fn list_models(&self) -> Result<Vec<ProviderModel>, AppError> {
+        tokio::time::timeout(self.timeout, self.primary().list_models())
+            .await
+            .map_err(|_| AppError::ExternalService(format!("{} did not list its models in time", self.model())))?
+    }
+
+    /// Counted for the first model, a fallback may count differently
+    async fn count_tokens(
+        &self,
+        message: ChatMessage,
+        history: Vec<ChatMessage>,
+        options: &CompletionOptions,
+    ) -> Result<Option<i32>, AppError> {
+        tokio::time::timeout(self.timeout, self.primary().count_tokens(message, history, options))
+            .await
+            .map_err(|_| AppError::ExternalService(format!("{} did not count tokens in time", self.model())))?
+    }
+}
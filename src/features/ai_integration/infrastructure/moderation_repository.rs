@@ -0,0 +1,136 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::entities::ai::{ModerationAction, ModerationFlag, ModerationStage};
+use crate::shared::database::begin_tenant_transaction;
+
+/// A prompt or answer to keep for review, and who sent it
+#[derive(Debug, Clone)]
+pub struct NewModerationFlag {
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub operation: &'static str,
+    pub stage: ModerationStage,
+    pub action: ModerationAction,
+    pub categories: Vec<String>,
+    pub content: String,
+    pub model: String,
+}
+
+#[async_trait]
+pub trait ModerationRepository: Send + Sync {
+    async fn create(&self, tenant_id: Uuid, flag: NewModerationFlag) -> Result<(), sqlx::Error>;
+    /// Newest first, only reviewed or open ones when `reviewed` is set
+    async fn list(&self, tenant_id: Uuid, reviewed: Option<bool>, limit: i64) -> Result<Vec<ModerationFlag>, sqlx::Error>;
+    async fn find(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<ModerationFlag>, sqlx::Error>;
+    /// `None` when no such flag exists or it was reviewed already
+    async fn review(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+        reviewed_by: Uuid,
+        note: Option<String>,
+    ) -> Result<Option<ModerationFlag>, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresModerationRepository {
+    pool: PgPool,
+}
+
+impl PostgresModerationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ModerationRepository for PostgresModerationRepository {
+    async fn create(&self, tenant_id: Uuid, flag: NewModerationFlag) -> Result<(), sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO ai_moderation_flags
+                (organization_id, user_id, api_key_id, operation, stage, action, categories, content, model)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(flag.user_id)
+        .bind(flag.api_key_id)
+        .bind(flag.operation)
+        .bind(flag.stage)
+        .bind(flag.action)
+        .bind(flag.categories)
+        .bind(flag.content)
+        .bind(flag.model)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn list(&self, tenant_id: Uuid, reviewed: Option<bool>, limit: i64) -> Result<Vec<ModerationFlag>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let flags = sqlx::query_as::<_, ModerationFlag>(
+            r#"
+            SELECT id, operation, stage, action, categories, content, model, user_id, api_key_id,
+                   created_at, reviewed_at, reviewed_by, review_note
+            FROM ai_moderation_flags
+            WHERE $1::BOOLEAN IS NULL OR (reviewed_at IS NOT NULL) = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(reviewed)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(flags)
+    }
+
+    async fn find(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<ModerationFlag>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let flag = sqlx::query_as::<_, ModerationFlag>(
+            r#"
+            SELECT id, operation, stage, action, categories, content, model, user_id, api_key_id,
+                   created_at, reviewed_at, reviewed_by, review_note
+            FROM ai_moderation_flags
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(flag)
+    }
+
+    async fn review(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+        reviewed_by: Uuid,
+        note: Option<String>,
+    ) -> Result<Option<ModerationFlag>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let flag = sqlx::query_as::<_, ModerationFlag>(
+            r#"
+            UPDATE ai_moderation_flags
+            SET reviewed_at = NOW(), reviewed_by = $2, review_note = $3
+            WHERE id = $1 AND reviewed_at IS NULL
+            RETURNING id, operation, stage, action, categories, content, model, user_id, api_key_id,
+                      created_at, reviewed_at, reviewed_by, review_note
+            "#,
+        )
+        .bind(id)
+        .bind(reviewed_by)
+        .bind(note)
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(flag)
+    }
+}
@@ -0,0 +1,193 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use super::file_repository::AiFileContent;
+use super::{
+    AiFileRepository, AiRedactionRecord, AiUsageRecord, AiUsageRepository, EmbeddingQuery,
+    EmbeddingRepository, ModerationRepository, NewAiFile, NewEmbedding, NewModerationFlag,
+};
+use crate::entities::ai::{AiFile, EmbeddedDocument, EmbeddingMatch, ModerationFlag};
+use crate::features::ai_integration::model::{AiRedactionSummary, AiUsageSummary};
+
+/// Usage and redaction counts of this process, for running without a database
+/// such as in the `eval` command
+#[derive(Default)]
+pub struct MemoryAiUsageRepository {
+    usage: Mutex<Vec<(Uuid, AiUsageRecord)>>,
+    redactions: Mutex<Vec<(Uuid, AiRedactionRecord, DateTime<Utc>)>>,
+}
+
+impl MemoryAiUsageRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AiUsageRepository for MemoryAiUsageRepository {
+    async fn record(&self, tenant_id: Uuid, record: AiUsageRecord) -> Result<(), sqlx::Error> {
+        self.usage
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((tenant_id, record));
+        Ok(())
+    }
+
+    async fn summary(&self, tenant_id: Uuid) -> Result<Vec<AiUsageSummary>, sqlx::Error> {
+        let usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let mut summary: BTreeMap<(&str, &str), AiUsageSummary> = BTreeMap::new();
+        for (_, record) in usage.iter().filter(|(tenant, _)| *tenant == tenant_id) {
+            let entry = summary
+                .entry((record.operation, record.model.as_str()))
+                .or_insert_with(|| AiUsageSummary {
+                    operation: record.operation.to_string(),
+                    model: record.model.clone(),
+                    requests: 0,
+                    cache_hits: 0,
+                    prompt_tokens: 0,
+                    completion_tokens: 0,
+                    total_tokens: 0,
+                    saved_tokens: 0,
+                });
+            entry.requests += 1;
+            if record.cached {
+                entry.cache_hits += 1;
+                entry.saved_tokens += i64::from(record.usage.total_tokens);
+            } else {
+                entry.prompt_tokens += i64::from(record.usage.prompt_tokens);
+                entry.completion_tokens += i64::from(record.usage.completion_tokens);
+                entry.total_tokens += i64::from(record.usage.total_tokens);
+            }
+        }
+        Ok(summary.into_values().collect())
+    }
+
+    async fn record_redactions(&self, tenant_id: Uuid, record: AiRedactionRecord) -> Result<(), sqlx::Error> {
+        self.redactions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((tenant_id, record, Utc::now()));
+        Ok(())
+    }
+
+    async fn redaction_summary(&self, tenant_id: Uuid) -> Result<Vec<AiRedactionSummary>, sqlx::Error> {
+        let redactions = self.redactions.lock().unwrap_or_else(|e| e.into_inner());
+        let mut summary: BTreeMap<(&str, &str), AiRedactionSummary> = BTreeMap::new();
+        for (_, record, redacted_at) in redactions.iter().filter(|(tenant, _, _)| *tenant == tenant_id) {
+            for (kind, matches) in &record.counts {
+                let entry = summary
+                    .entry((record.operation, kind.as_str()))
+                    .or_insert_with(|| AiRedactionSummary {
+                        operation: record.operation.to_string(),
+                        kind: kind.clone(),
+                        requests: 0,
+                        matches: 0,
+                        last_redacted_at: *redacted_at,
+                    });
+                entry.requests += 1;
+                entry.matches += i64::from(*matches);
+                entry.last_redacted_at = entry.last_redacted_at.max(*redacted_at);
+            }
+        }
+        Ok(summary.into_values().collect())
+    }
+}
+
+/// Stands in for the file, embedding and moderation tables where there is no
+/// database: nothing is kept and nothing is found, so uploaded files and
+/// retrieval aren't available
+#[derive(Default)]
+pub struct OfflineStorage;
+
+impl OfflineStorage {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+fn unavailable() -> sqlx::Error {
+    sqlx::Error::Configuration("Storage isn't available without a database".into())
+}
+
+#[async_trait]
+impl AiFileRepository for OfflineStorage {
+    async fn create(&self, _tenant_id: Uuid, _file: NewAiFile) -> Result<AiFile, sqlx::Error> {
+        Err(unavailable())
+    }
+
+    async fn list(&self, _tenant_id: Uuid) -> Result<Vec<AiFile>, sqlx::Error> {
+        Ok(Vec::new())
+    }
+
+    async fn content(&self, _tenant_id: Uuid, _id: Uuid) -> Result<Option<AiFileContent>, sqlx::Error> {
+        Ok(None)
+    }
+
+    async fn delete(&self, _tenant_id: Uuid, _id: Uuid) -> Result<bool, sqlx::Error> {
+        Ok(false)
+    }
+}
+
+#[async_trait]
+impl EmbeddingRepository for OfflineStorage {
+    async fn upsert(
+        &self,
+        _tenant_id: Uuid,
+        _collection: &str,
+        _documents: Vec<NewEmbedding>,
+    ) -> Result<Vec<EmbeddedDocument>, sqlx::Error> {
+        Err(unavailable())
+    }
+
+    async fn replace_source(
+        &self,
+        _tenant_id: Uuid,
+        _collection: &str,
+        _source_id: &str,
+        _chunks: Vec<NewEmbedding>,
+    ) -> Result<Vec<EmbeddedDocument>, sqlx::Error> {
+        Err(unavailable())
+    }
+
+    async fn search(
+        &self,
+        _tenant_id: Uuid,
+        _collection: &str,
+        _query: EmbeddingQuery,
+    ) -> Result<Vec<EmbeddingMatch>, sqlx::Error> {
+        Ok(Vec::new())
+    }
+
+    async fn delete(&self, _tenant_id: Uuid, _collection: &str, _document_id: &str) -> Result<bool, sqlx::Error> {
+        Ok(false)
+    }
+}
+
+/// Flags are dropped, the policy still applies to the request
+#[async_trait]
+impl ModerationRepository for OfflineStorage {
+    async fn create(&self, _tenant_id: Uuid, _flag: NewModerationFlag) -> Result<(), sqlx::Error> {
+        Ok(())
+    }
+
+    async fn list(&self, _tenant_id: Uuid, _reviewed: Option<bool>, _limit: i64) -> Result<Vec<ModerationFlag>, sqlx::Error> {
+        Ok(Vec::new())
+    }
+
+    async fn find(&self, _tenant_id: Uuid, _id: Uuid) -> Result<Option<ModerationFlag>, sqlx::Error> {
+        Ok(None)
+    }
+
+    async fn review(
+        &self,
+        _tenant_id: Uuid,
+        _id: Uuid,
+        _reviewed_by: Uuid,
+        _note: Option<String>,
+    ) -> Result<Option<ModerationFlag>, sqlx::Error> {
+        Ok(None)
+    }
+}
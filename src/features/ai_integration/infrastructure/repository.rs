@@ -1,27 +1,229 @@
 use async_trait::async_trait;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::entities::ai::ChatMessage;
-use crate::shared::error::AppError;
+use super::MockAIRepository;
+use crate::entities::ai::{Candidate, ChatMessage, ChatRole, CitationSource, EmbeddingTask, MessagePart, SafetySetting};
+use crate::shared::error::{AppError, SafetyRating};
+use crate::shared::config::AiRoutingConfig;
+
+/// Token counts reported by the model
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub total_tokens: i32,
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AiCompletion {
+    pub text: String,
+    pub model: String,
+    pub usage: TokenUsage,
+    /// Gemini `finishReason`, e.g. STOP or MAX_TOKENS
+    pub finish_reason: String,
+    /// Tools the model wants called before it answers
+    pub tool_calls: Vec<ToolCall>,
+    /// Every candidate the model generated. `text`, `finish_reason` and
+    /// `tool_calls` are those of the first one that wasn't blocked.
+    pub candidates: Vec<Candidate>,
+}
+
+/// A function the model may call, sent as Gemini `functionDeclarations`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolDeclaration {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the arguments
+    pub parameters: serde_json::Value,
+}
+
+/// A call the model asked for, Gemini `functionCall`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub name: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+/// The calls of one model turn and their results, sent back as `functionResponse`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolStep {
+    pub calls: Vec<ToolCall>,
+    /// One object per call, e.g. `{"result": ...}` or `{"error": "..."}`
+    pub results: Vec<serde_json::Value>,
+}
+
+/// Sampling settings sent as Gemini `generationConfig`, unset fields use the model defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<i32>,
+    /// Number of alternative answers, 1 when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub candidate_count: Option<i32>,
+    /// "application/json" together with `response_schema`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_mime_type: Option<String>,
+    /// Schema the output must follow
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<serde_json::Value>,
+}
+
+/// Per-call settings. Safety settings override the deployment defaults per category.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompletionOptions {
+    /// Sent as Gemini `systemInstruction`
+    pub system_instruction: Option<String>,
+    pub generation: GenerationConfig,
+    pub safety_settings: Vec<SafetySetting>,
+    /// Functions the model may call
+    pub tools: Vec<ToolDeclaration>,
+}
+
+/// Length of every embedding vector, the `embeddings.embedding` column is `vector(768)`
+pub const EMBEDDING_DIMENSIONS: usize = 768;
+
+#[derive(Debug, Clone)]
+pub struct AiEmbeddings {
+    pub model: String,
+    /// One vector of `EMBEDDING_DIMENSIONS` per text, in order
+    pub vectors: Vec<Vec<f32>>,
+}
+
+/// A model as described by the provider's model list
+#[derive(Debug, Clone)]
+pub struct ProviderModel {
+    pub name: String,
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    pub input_token_limit: Option<i32>,
+    pub output_token_limit: Option<i32>,
+    /// e.g. "generateContent" or "countTokens"
+    pub supported_methods: Vec<String>,
+}
+
+/// Kinds of input a model accepts besides text
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModelCapabilities {
+    /// Images and documents as message parts
+    pub multimodal: bool,
+    /// Calling tools declared in `CompletionOptions::tools`
+    pub function_calling: bool,
+}
 
 #[async_trait]
 pub trait AIRepository: Send + Sync {
-    async fn chat(&self, message: String, history: Vec<ChatMessage>) -> Result<String, AppError>;
-    async fn generate(&self, prompt: String) -> Result<String, AppError>;
+    /// Model that answers the calls
+    fn model(&self) -> &str;
+    fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities::default()
+    }
+    /// Uploaded files in `message` and `history` must already be resolved to
+    /// inline data. `steps` are the tool calls made so far after `message`.
+    async fn chat(
+        &self,
+        message: ChatMessage,
+        history: Vec<ChatMessage>,
+        steps: &[ToolStep],
+        options: &CompletionOptions,
+    ) -> Result<AiCompletion, AppError>;
+    async fn generate(&self, prompt: String, options: &CompletionOptions) -> Result<AiCompletion, AppError>;
+    /// Model that turns text into vectors
+    fn embedding_model(&self) -> &str;
+    async fn embed(&self, texts: Vec<String>, task: EmbeddingTask) -> Result<AiEmbeddings, AppError>;
+    /// All models of the provider, not only this one. Empty when the provider has no list.
+    async fn list_models(&self) -> Result<Vec<ProviderModel>, AppError> {
+        Ok(Vec::new())
+    }
+    /// Prompt tokens of a chat as counted by the provider, `None` when it can't count
+    async fn count_tokens(
+        &self,
+        _message: ChatMessage,
+        _history: Vec<ChatMessage>,
+        _options: &CompletionOptions,
+    ) -> Result<Option<i32>, AppError> {
+        Ok(None)
+    }
+}
+
+/// Builds the model of a `provider:model` pair, e.g. "gemini:gemini-1.5-pro"
+pub fn create_ai_repository(
+    target: &str,
+    config: &AiRoutingConfig,
+    gemini_api_key: &str,
+) -> anyhow::Result<Arc<dyn AIRepository>> {
+    let Some((provider, model)) = target.split_once(':').filter(|(_, model)| !model.is_empty()) else {
+        anyhow::bail!("AI models must be given as provider:model, got {}", target);
+    };
+    let repository: Arc<dyn AIRepository> = match provider {
+        "gemini" if gemini_api_key.is_empty() => anyhow::bail!("GEMINI_API_KEY must be set for {}", target),
+        "gemini" => Arc::new(GeminiRepository::new(
+            gemini_api_key.to_string(),
+            model.to_string(),
+            config.embedding_model.clone(),
+            parse_safety_settings(&config.gemini_safety_settings)?,
+        )),
+        "mock" => Arc::new(MockAIRepository::new(model)),
+        other => anyhow::bail!("Unknown AI provider: {}", other),
+    };
+    Ok(repository)
 }
 
 pub struct GeminiRepository {
     client: Arc<Client>,
     api_key: String,
     model: String,
+    embedding_model: String,
+    /// Deployment defaults from `GEMINI_SAFETY_SETTINGS`
+    safety_settings: Vec<SafetySetting>,
 }
 
+/// Older models that only accept text
+const TEXT_ONLY_MODELS: &[&str] = &["gemini-pro", "gemini-1.0-pro"];
+
+/// Most texts `batchEmbedContents` accepts per call
+const MAX_EMBED_BATCH: usize = 100;
+
+/// Finish reasons that mean the output was withheld
+const BLOCKED_FINISH_REASONS: &[&str] = &["SAFETY", "RECITATION", "BLOCKLIST", "PROHIBITED_CONTENT", "SPII"];
+
 // Gemini API request/response structures
 #[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct GeminiRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiParts>,
     contents: Vec<GeminiContent>,
+    generation_config: GenerationConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    safety_settings: Vec<SafetySetting>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<GeminiTool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiTool {
+    function_declarations: Vec<ToolDeclaration>,
+}
+
+/// Content without a role, for `systemInstruction` and embeddings
+#[derive(Debug, Serialize)]
+struct GeminiParts {
+    parts: Vec<GeminiPart>,
 }
 
 #[derive(Debug, Serialize)]
@@ -31,78 +233,314 @@ struct GeminiContent {
 }
 
 #[derive(Debug, Serialize)]
-struct GeminiPart {
-    text: String,
+#[serde(rename_all = "camelCase")]
+enum GeminiPart {
+    Text(String),
+    InlineData(GeminiBlob),
+    FunctionCall(ToolCall),
+    FunctionResponse(GeminiFunctionResponse),
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiFunctionResponse {
+    name: String,
+    response: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiBlob {
+    mime_type: String,
+    /// Base64 encoded
+    data: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiEmbedRequest {
+    /// `models/<name>`, only sent inside `batchEmbedContents`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    content: GeminiParts,
+    task_type: EmbeddingTask,
+    output_dimensionality: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct GeminiBatchEmbedRequest {
+    requests: Vec<GeminiEmbedRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiEmbedResponse {
+    embedding: GeminiEmbedding,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiBatchEmbedResponse {
+    #[serde(default)]
+    embeddings: Vec<GeminiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiEmbedding {
+    values: Vec<f32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCountTokensRequest {
+    generate_content_request: GeminiGenerateContentRequest,
+}
+
+/// A `GeminiRequest` that names its model, as `countTokens` wants it
+#[derive(Debug, Serialize)]
+struct GeminiGenerateContentRequest {
+    model: String,
+    #[serde(flatten)]
+    request: GeminiRequest,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCountTokensResponse {
+    #[serde(default)]
+    total_tokens: i32,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiModelList {
+    #[serde(default)]
+    models: Vec<GeminiModel>,
+    #[serde(default)]
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiModel {
+    /// `models/<name>`
+    name: String,
+    display_name: Option<String>,
+    description: Option<String>,
+    input_token_limit: Option<i32>,
+    output_token_limit: Option<i32>,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct GeminiResponse {
+    #[serde(default)]
     candidates: Vec<GeminiCandidate>,
+    #[serde(default)]
+    prompt_feedback: Option<GeminiPromptFeedback>,
+    #[serde(default)]
+    usage_metadata: Option<GeminiUsageMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiPromptFeedback {
+    block_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<GeminiSafetyRating>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiSafetyRating {
+    category: String,
+    probability: String,
+    #[serde(default)]
+    blocked: bool,
+}
+
+impl From<GeminiSafetyRating> for SafetyRating {
+    fn from(rating: GeminiSafetyRating) -> Self {
+        Self {
+            category: rating.category,
+            probability: rating.probability,
+            blocked: rating.blocked,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct GeminiUsageMetadata {
+    prompt_token_count: i32,
+    candidates_token_count: i32,
+    total_token_count: i32,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct GeminiCandidate {
-    content: GeminiContentResponse,
+    /// Missing when the output was blocked
+    #[serde(default)]
+    content: Option<GeminiContentResponse>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+    #[serde(default)]
+    safety_ratings: Vec<GeminiSafetyRating>,
+    #[serde(default)]
+    citation_metadata: Option<GeminiCitationMetadata>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCitationMetadata {
+    /// Some API versions call the list `citations`
+    #[serde(default, alias = "citations")]
+    citation_sources: Vec<GeminiCitationSource>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCitationSource {
+    #[serde(default)]
+    start_index: Option<i32>,
+    #[serde(default)]
+    end_index: Option<i32>,
+    #[serde(default)]
+    uri: Option<String>,
+    #[serde(default)]
+    license: Option<String>,
+}
+
+impl From<GeminiCitationSource> for CitationSource {
+    fn from(source: GeminiCitationSource) -> Self {
+        Self {
+            start_index: source.start_index,
+            end_index: source.end_index,
+            uri: source.uri,
+            license: source.license,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct GeminiContentResponse {
+    #[serde(default)]
     parts: Vec<GeminiPartResponse>,
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct GeminiPartResponse {
-    text: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    function_call: Option<ToolCall>,
 }
 
 impl GeminiRepository {
-    pub fn new(api_key: String) -> Self {
+    pub fn new(api_key: String, model: String, embedding_model: String, safety_settings: Vec<SafetySetting>) -> Self {
         let client = Client::new();
-        // Use latest Gemini model - gemini-2.0-flash-exp, gemini-pro, or custom
-        let model = std::env::var("GEMINI_MODEL")
-            .unwrap_or_else(|_| "gemini-2.0-flash-exp".to_string());
-        
+
         Self {
             client: Arc::new(client),
             api_key,
             model,
+            embedding_model,
+            safety_settings,
         }
     }
 
-    fn convert_history(&self, history: Vec<ChatMessage>) -> Vec<GeminiContent> {
-        history
-            .into_iter()
-            .map(|msg| {
-                // Gemini API only accepts "user" and "model" roles
-                // Convert "assistant" to "model" for compatibility
-                let role = if msg.role == "user" {
-                    "user".to_string()
-                } else {
-                    "model".to_string()
-                };
-                
-                GeminiContent {
-                    role,
-                    parts: vec![GeminiPart { text: msg.content }],
+    /// Deployment defaults with the per-call settings applied on top
+    fn safety_settings(&self, overrides: &[SafetySetting]) -> Vec<SafetySetting> {
+        let mut settings: Vec<SafetySetting> = self
+            .safety_settings
+            .iter()
+            .filter(|default| !overrides.iter().any(|o| o.category == default.category))
+            .copied()
+            .collect();
+        settings.extend_from_slice(overrides);
+        settings
+    }
+
+    fn convert_message(&self, message: ChatMessage) -> Result<GeminiContent, AppError> {
+        let mut parts = Vec::with_capacity(message.parts.len() + 1);
+        if !message.content.is_empty() {
+            parts.push(GeminiPart::Text(message.content));
+        }
+        for part in message.parts {
+            parts.push(match part {
+                MessagePart::Text(text) => GeminiPart::Text(text),
+                MessagePart::InlineData(inline) => GeminiPart::InlineData(GeminiBlob {
+                    mime_type: inline.mime_type,
+                    data: inline.data,
+                }),
+                MessagePart::FileId(id) => {
+                    return Err(AppError::Internal(anyhow::anyhow!(
+                        "File {} was not resolved before calling Gemini",
+                        id
+                    )));
                 }
-            })
-            .collect()
+            });
+        }
+
+        Ok(GeminiContent {
+            role: gemini_role(message.role).to_string(),
+            parts,
+        })
     }
 
-    async fn call_gemini_api(&self, contents: Vec<GeminiContent>) -> Result<String, AppError> {
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-            self.model, self.api_key
+    fn embed_request(&self, text: String, task: EmbeddingTask, in_batch: bool) -> GeminiEmbedRequest {
+        GeminiEmbedRequest {
+            model: in_batch.then(|| format!("models/{}", self.embedding_model)),
+            content: GeminiParts {
+                parts: vec![GeminiPart::Text(text)],
+            },
+            task_type: task,
+            output_dimensionality: EMBEDDING_DIMENSIONS,
+        }
+    }
+
+    /// `embedContent` for a single text, `batchEmbedContents` otherwise
+    async fn call_embed_api(&self, mut texts: Vec<String>, task: EmbeddingTask) -> Result<Vec<Vec<f32>>, AppError> {
+        let base = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}",
+            self.embedding_model
         );
 
-        let request_body = GeminiRequest { contents };
+        let embeddings = if texts.len() == 1 {
+            let body = self.embed_request(texts.remove(0), task, false);
+            let response: GeminiEmbedResponse = self
+                .post(&format!("{}:embedContent?key={}", base, self.api_key), &body)
+                .await?;
+            vec![response.embedding]
+        } else {
+            let body = GeminiBatchEmbedRequest {
+                requests: texts
+                    .into_iter()
+                    .map(|text| self.embed_request(text, task, true))
+                    .collect(),
+            };
+            let response: GeminiBatchEmbedResponse = self
+                .post(&format!("{}:batchEmbedContents?key={}", base, self.api_key), &body)
+                .await?;
+            response.embeddings
+        };
 
-        let response = self.client
-            .post(&url)
-            .json(&request_body)
+        Ok(embeddings.into_iter().map(|embedding| embedding.values).collect())
+    }
+
+    async fn post<R: DeserializeOwned>(&self, url: &str, body: &impl Serialize) -> Result<R, AppError> {
+        self.send(self.client.post(url).json(body)).await
+    }
+
+    async fn get<R: DeserializeOwned>(&self, url: &str) -> Result<R, AppError> {
+        self.send(self.client.get(url)).await
+    }
+
+    async fn send<R: DeserializeOwned>(&self, request: reqwest::RequestBuilder) -> Result<R, AppError> {
+        let response = request
             .send()
             .await
-            .map_err(|e| AppError::ExternalService(format!("Failed to call Gemini API: {}", e)))?;
+            .map_err(|e| AppError::ExternalService(format!("Failed to call Gemini API: {}", e.without_url())))?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -113,42 +551,283 @@ impl GeminiRepository {
             )));
         }
 
-        let gemini_response: GeminiResponse = response
+        response
             .json()
             .await
-            .map_err(|e| AppError::ExternalService(format!("Failed to parse Gemini response: {}", e)))?;
+            .map_err(|e| AppError::ExternalService(format!("Failed to parse Gemini response: {}", e)))
+    }
+
+    fn gemini_request(&self, contents: Vec<GeminiContent>, options: &CompletionOptions) -> GeminiRequest {
+        GeminiRequest {
+            system_instruction: options.system_instruction.clone().map(|text| GeminiParts {
+                parts: vec![GeminiPart::Text(text)],
+            }),
+            contents,
+            generation_config: options.generation.clone(),
+            safety_settings: self.safety_settings(&options.safety_settings),
+            tools: if options.tools.is_empty() {
+                Vec::new()
+            } else {
+                vec![GeminiTool {
+                    function_declarations: options.tools.clone(),
+                }]
+            },
+        }
+    }
 
-        let text = gemini_response
-            .candidates
-            .first()
-            .and_then(|c| c.content.parts.first())
-            .map(|p| p.text.clone())
-            .ok_or_else(|| AppError::ExternalService("No response from Gemini".to_string()))?;
+    /// History, the current message and the tool steps after it
+    fn chat_contents(
+        &self,
+        message: ChatMessage,
+        history: Vec<ChatMessage>,
+        steps: &[ToolStep],
+    ) -> Result<Vec<GeminiContent>, AppError> {
+        let mut contents = history
+            .into_iter()
+            .chain(std::iter::once(message))
+            .map(|message| self.convert_message(message))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(text)
+        // Each tool step is the model's calls followed by our results
+        for step in steps {
+            contents.push(GeminiContent {
+                role: "model".to_string(),
+                parts: step.calls.iter().cloned().map(GeminiPart::FunctionCall).collect(),
+            });
+            contents.push(GeminiContent {
+                role: "user".to_string(),
+                parts: step
+                    .calls
+                    .iter()
+                    .zip(&step.results)
+                    .map(|(call, result)| {
+                        GeminiPart::FunctionResponse(GeminiFunctionResponse {
+                            name: call.name.clone(),
+                            response: result.clone(),
+                        })
+                    })
+                    .collect(),
+            });
+        }
+        Ok(contents)
+    }
+
+    async fn call_gemini_api(
+        &self,
+        contents: Vec<GeminiContent>,
+        options: &CompletionOptions,
+    ) -> Result<AiCompletion, AppError> {
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
+            self.model, self.api_key
+        );
+        let request_body = self.gemini_request(contents, options);
+
+        let gemini_response: GeminiResponse = self.post(&url, &request_body).await?;
+
+        let usage = gemini_response.usage_metadata.unwrap_or_default();
+        if gemini_response.candidates.is_empty() {
+            // A blocked prompt gets no candidates, only `promptFeedback`
+            return Err(match gemini_response.prompt_feedback {
+                Some(GeminiPromptFeedback {
+                    block_reason: Some(reason),
+                    safety_ratings,
+                }) => AppError::PromptBlocked {
+                    reason,
+                    safety_ratings: safety_ratings.into_iter().map(Into::into).collect(),
+                },
+                _ => AppError::ExternalService("No response from Gemini".to_string()),
+            });
+        }
+
+        let mut candidates = Vec::with_capacity(gemini_response.candidates.len());
+        let mut answer = None;
+        for (index, candidate) in gemini_response.candidates.into_iter().enumerate() {
+            let finish_reason = candidate.finish_reason.unwrap_or_else(|| "STOP".to_string());
+            let mut text = String::new();
+            let mut tool_calls = Vec::new();
+            for part in candidate.content.map(|content| content.parts).unwrap_or_default() {
+                text.extend(part.text);
+                tool_calls.extend(part.function_call);
+            }
+            let blocked = BLOCKED_FINISH_REASONS.contains(&finish_reason.as_str());
+            if answer.is_none() && !blocked {
+                answer = Some((text.clone(), finish_reason.clone(), tool_calls));
+            }
+            candidates.push(Candidate {
+                index: index as i32,
+                text,
+                finish_reason,
+                safety_ratings: candidate.safety_ratings.into_iter().map(Into::into).collect(),
+                citation_sources: candidate
+                    .citation_metadata
+                    .map(|metadata| metadata.citation_sources.into_iter().map(Into::into).collect())
+                    .unwrap_or_default(),
+            });
+        }
+
+        // The request only fails when every candidate was blocked
+        let Some((text, finish_reason, tool_calls)) = answer else {
+            let first = candidates.swap_remove(0);
+            return Err(AppError::ResponseBlocked {
+                finish_reason: first.finish_reason,
+                safety_ratings: first.safety_ratings,
+            });
+        };
+
+        Ok(AiCompletion {
+            text,
+            model: self.model.clone(),
+            finish_reason,
+            tool_calls,
+            candidates,
+            usage: TokenUsage {
+                prompt_tokens: usage.prompt_token_count,
+                completion_tokens: usage.candidates_token_count,
+                total_tokens: usage.total_token_count,
+            },
+        })
     }
 }
 
 #[async_trait]
 impl AIRepository for GeminiRepository {
-    async fn chat(&self, message: String, history: Vec<ChatMessage>) -> Result<String, AppError> {
-        let mut contents = self.convert_history(history);
-        
-        // Add the current user message
-        contents.push(GeminiContent {
-            role: "user".to_string(),
-            parts: vec![GeminiPart { text: message }],
-        });
+    fn model(&self) -> &str {
+        &self.model
+    }
 
-        self.call_gemini_api(contents).await
+    fn capabilities(&self) -> ModelCapabilities {
+        ModelCapabilities {
+            multimodal: !TEXT_ONLY_MODELS.contains(&self.model.as_str()),
+            function_calling: true,
+        }
     }
 
-    async fn generate(&self, prompt: String) -> Result<String, AppError> {
+    async fn chat(
+        &self,
+        message: ChatMessage,
+        history: Vec<ChatMessage>,
+        steps: &[ToolStep],
+        options: &CompletionOptions,
+    ) -> Result<AiCompletion, AppError> {
+        let contents = self.chat_contents(message, history, steps)?;
+        self.call_gemini_api(contents, options).await
+    }
+
+    async fn generate(&self, prompt: String, options: &CompletionOptions) -> Result<AiCompletion, AppError> {
         let contents = vec![GeminiContent {
             role: "user".to_string(),
-            parts: vec![GeminiPart { text: prompt }],
+            parts: vec![GeminiPart::Text(prompt)],
         }];
 
-        self.call_gemini_api(contents).await
+        self.call_gemini_api(contents, options).await
+    }
+
+    fn embedding_model(&self) -> &str {
+        &self.embedding_model
+    }
+
+    async fn embed(&self, texts: Vec<String>, task: EmbeddingTask) -> Result<AiEmbeddings, AppError> {
+        let expected = texts.len();
+        let mut vectors = Vec::with_capacity(expected);
+        let mut texts = texts.into_iter().peekable();
+        while texts.peek().is_some() {
+            let batch: Vec<String> = texts.by_ref().take(MAX_EMBED_BATCH).collect();
+            vectors.extend(self.call_embed_api(batch, task).await?);
+        }
+
+        if vectors.len() != expected || vectors.iter().any(|v| v.len() != EMBEDDING_DIMENSIONS) {
+            return Err(AppError::ExternalService(format!(
+                "Gemini returned {} embeddings for {} texts, expected {} dimensions each",
+                vectors.len(),
+                expected,
+                EMBEDDING_DIMENSIONS
+            )));
+        }
+
+        Ok(AiEmbeddings {
+            model: self.embedding_model.clone(),
+            vectors,
+        })
     }
+
+    /// Follows `nextPageToken` through all pages of `models.list`
+    async fn list_models(&self) -> Result<Vec<ProviderModel>, AppError> {
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut url = format!(
+                "https://generativelanguage.googleapis.com/v1beta/models?pageSize=1000&key={}",
+                self.api_key
+            );
+            if let Some(token) = &page_token {
+                url.push_str(&format!("&pageToken={}", token));
+            }
+            let page: GeminiModelList = self.get(&url).await?;
+
+            models.extend(page.models.into_iter().map(|model| ProviderModel {
+                name: model.name.trim_start_matches("models/").to_string(),
+                display_name: model.display_name,
+                description: model.description,
+                input_token_limit: model.input_token_limit,
+                output_token_limit: model.output_token_limit,
+                supported_methods: model.supported_generation_methods,
+            }));
+            page_token = page.next_page_token.filter(|token| !token.is_empty());
+            if page_token.is_none() {
+                return Ok(models);
+            }
+        }
+    }
+
+    async fn count_tokens(
+        &self,
+        message: ChatMessage,
+        history: Vec<ChatMessage>,
+        options: &CompletionOptions,
+    ) -> Result<Option<i32>, AppError> {
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:countTokens?key={}",
+            self.model, self.api_key
+        );
+        let contents = self.chat_contents(message, history, &[])?;
+        let body = GeminiCountTokensRequest {
+            generate_content_request: GeminiGenerateContentRequest {
+                model: format!("models/{}", self.model),
+                request: self.gemini_request(contents, options),
+            },
+        };
+
+        let response: GeminiCountTokensResponse = self.post(&url, &body).await?;
+        Ok(Some(response.total_tokens))
+    }
+}
+
+fn gemini_role(role: ChatRole) -> &'static str {
+    match role {
+        ChatRole::User => "user",
+        ChatRole::Model => "model",
+    }
+}
+
+/// Parses `HARM_CATEGORY_HARASSMENT=BLOCK_ONLY_HIGH,HARM_CATEGORY_HATE_SPEECH=BLOCK_NONE`
+fn parse_safety_settings(value: &str) -> anyhow::Result<Vec<SafetySetting>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|setting| !setting.is_empty())
+        .map(|setting| {
+            let (category, threshold) = setting.split_once('=').ok_or_else(|| {
+                anyhow::anyhow!("GEMINI_SAFETY_SETTINGS entry must be CATEGORY=THRESHOLD: {}", setting)
+            })?;
+            let parse = |name: &str| serde_json::Value::String(name.trim().to_string());
+
+            Ok(SafetySetting {
+                category: serde_json::from_value(parse(category))
+                    .map_err(|_| anyhow::anyhow!("Unknown harm category in GEMINI_SAFETY_SETTINGS: {}", category))?,
+                threshold: serde_json::from_value(parse(threshold))
+                    .map_err(|_| anyhow::anyhow!("Unknown threshold in GEMINI_SAFETY_SETTINGS: {}", threshold))?,
+            })
+        })
+        .collect()
 }
@@ -0,0 +1,181 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::{CompletionOptions, TokenUsage, ToolStep};
+use crate::entities::ai::{AiRequestLog, ChatMessage};
+use crate::shared::database::begin_tenant_transaction;
+
+/// A call as it was sent to the model, enough to send it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LoggedRequest {
+    Chat {
+        message: ChatMessage,
+        history: Vec<ChatMessage>,
+        /// Tool calls and results of earlier turns of the same chat
+        steps: Vec<ToolStep>,
+        options: CompletionOptions,
+    },
+    Generate {
+        prompt: String,
+        options: CompletionOptions,
+    },
+}
+
+/// A finished call and who made it
+#[derive(Debug, Clone)]
+pub struct NewRequestLog {
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub operation: &'static str,
+    pub provider: Option<String>,
+    pub requested_model: String,
+    pub model: Option<String>,
+    pub request: serde_json::Value,
+    pub response: Option<String>,
+    pub tool_calls: Option<serde_json::Value>,
+    pub finish_reason: Option<String>,
+    pub error: Option<String>,
+    pub usage: TokenUsage,
+    pub latency_ms: i64,
+}
+
+/// Conditions a log must meet, unset ones match everything
+#[derive(Debug, Clone, Default)]
+pub struct RequestLogFilter {
+    pub operation: Option<String>,
+    /// The requested or the answering model
+    pub model: Option<String>,
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    /// Only failed calls (true) or only answered ones (false)
+    pub failed: Option<bool>,
+    /// Contained in the request or response, ignoring case
+    pub text: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: i64,
+}
+
+/// Logs are written and searched per tenant under row-level security. The
+/// retention task deletes old logs of all tenants.
+#[async_trait]
+pub trait RequestLogRepository: Send + Sync {
+    async fn create(&self, tenant_id: Uuid, log: NewRequestLog) -> Result<(), sqlx::Error>;
+    /// Newest first
+    async fn search(&self, tenant_id: Uuid, filter: &RequestLogFilter) -> Result<Vec<AiRequestLog>, sqlx::Error>;
+    async fn find(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<AiRequestLog>, sqlx::Error>;
+    /// Deletes logs older than `age`, returns how many
+    async fn purge(&self, age: Duration) -> Result<u64, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresRequestLogRepository {
+    pool: PgPool,
+}
+
+impl PostgresRequestLogRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl RequestLogRepository for PostgresRequestLogRepository {
+    async fn create(&self, tenant_id: Uuid, log: NewRequestLog) -> Result<(), sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO ai_request_logs
+                (organization_id, user_id, api_key_id, operation, provider, requested_model, model, request,
+                 response, tool_calls, finish_reason, error, prompt_tokens, completion_tokens, total_tokens, latency_ms)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(log.user_id)
+        .bind(log.api_key_id)
+        .bind(log.operation)
+        .bind(log.provider)
+        .bind(log.requested_model)
+        .bind(log.model)
+        .bind(log.request)
+        .bind(log.response)
+        .bind(log.tool_calls)
+        .bind(log.finish_reason)
+        .bind(log.error)
+        .bind(log.usage.prompt_tokens)
+        .bind(log.usage.completion_tokens)
+        .bind(log.usage.total_tokens)
+        .bind(log.latency_ms)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn search(&self, tenant_id: Uuid, filter: &RequestLogFilter) -> Result<Vec<AiRequestLog>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let logs = sqlx::query_as::<_, AiRequestLog>(
+            r#"
+            SELECT id, operation, provider, requested_model, model, request, response, tool_calls, finish_reason,
+                   error, prompt_tokens, completion_tokens, total_tokens, latency_ms, user_id, api_key_id, created_at
+            FROM ai_request_logs
+            WHERE ($1::VARCHAR IS NULL OR operation = $1)
+              AND ($2::VARCHAR IS NULL OR requested_model = $2 OR model = $2)
+              AND ($3::UUID IS NULL OR user_id = $3)
+              AND ($4::UUID IS NULL OR api_key_id = $4)
+              AND ($5::BOOLEAN IS NULL OR (error IS NOT NULL) = $5)
+              AND ($6::TEXT IS NULL
+                   OR POSITION(LOWER($6) IN LOWER(request::TEXT)) > 0
+                   OR POSITION(LOWER($6) IN LOWER(COALESCE(response, ''))) > 0)
+              AND ($7::TIMESTAMPTZ IS NULL OR created_at >= $7)
+              AND ($8::TIMESTAMPTZ IS NULL OR created_at < $8)
+            ORDER BY created_at DESC
+            LIMIT $9
+            "#,
+        )
+        .bind(&filter.operation)
+        .bind(&filter.model)
+        .bind(filter.user_id)
+        .bind(filter.api_key_id)
+        .bind(filter.failed)
+        .bind(&filter.text)
+        .bind(filter.since)
+        .bind(filter.until)
+        .bind(filter.limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(logs)
+    }
+
+    async fn find(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<AiRequestLog>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let log = sqlx::query_as::<_, AiRequestLog>(
+            r#"
+            SELECT id, operation, provider, requested_model, model, request, response, tool_calls, finish_reason,
+                   error, prompt_tokens, completion_tokens, total_tokens, latency_ms, user_id, api_key_id, created_at
+            FROM ai_request_logs
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(log)
+    }
+
+    async fn purge(&self, age: Duration) -> Result<u64, sqlx::Error> {
+        let deleted = sqlx::query("DELETE FROM ai_request_logs WHERE created_at < NOW() - make_interval(secs => $1)")
+            .bind(age.as_secs_f64())
+            .execute(&self.pool)
+            .await?;
+        Ok(deleted.rows_affected())
+    }
+}
@@ -0,0 +1,144 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{types::Json, FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::entities::ai::{PromptTemplate, TemplateVariable};
+use crate::shared::database::begin_tenant_transaction;
+
+/// Input for the next version of a template
+#[derive(Debug, Clone)]
+pub struct NewPromptTemplate {
+    pub name: String,
+    pub description: Option<String>,
+    pub system_instruction: Option<String>,
+    pub body: String,
+    pub variables: Vec<TemplateVariable>,
+    pub created_by: Option<Uuid>,
+}
+
+#[derive(Debug, FromRow)]
+struct PromptTemplateRow {
+    id: Uuid,
+    name: String,
+    version: i32,
+    description: Option<String>,
+    system_instruction: Option<String>,
+    body: String,
+    variables: Json<Vec<TemplateVariable>>,
+    created_by: Option<Uuid>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<PromptTemplateRow> for PromptTemplate {
+    fn from(row: PromptTemplateRow) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            version: row.version,
+            description: row.description,
+            system_instruction: row.system_instruction,
+            body: row.body,
+            variables: row.variables.0,
+            created_by: row.created_by,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Templates are stored per tenant under row-level security
+#[async_trait]
+pub trait PromptTemplateRepository: Send + Sync {
+    /// Stores the template as version 1, or as the next version of an existing name
+    async fn create(&self, tenant_id: Uuid, template: NewPromptTemplate) -> Result<PromptTemplate, sqlx::Error>;
+    /// Latest version of every template
+    async fn list(&self, tenant_id: Uuid) -> Result<Vec<PromptTemplate>, sqlx::Error>;
+    /// A specific version, or the latest one
+    async fn find(
+        &self,
+        tenant_id: Uuid,
+        name: &str,
+        version: Option<i32>,
+    ) -> Result<Option<PromptTemplate>, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresPromptTemplateRepository {
+    pool: PgPool,
+}
+
+impl PostgresPromptTemplateRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl PromptTemplateRepository for PostgresPromptTemplateRepository {
+    async fn create(&self, tenant_id: Uuid, template: NewPromptTemplate) -> Result<PromptTemplate, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+
+        // Serializes concurrent creates of the same name, so versions don't collide
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+            .bind(format!("prompt_templates:{}:{}", tenant_id, template.name))
+            .execute(&mut *tx)
+            .await?;
+
+        let row = sqlx::query_as::<_, PromptTemplateRow>(
+            r#"
+            INSERT INTO prompt_templates
+                (organization_id, name, version, description, system_instruction, body, variables, created_by)
+            SELECT $1, $2, COALESCE(MAX(version), 0) + 1, $3, $4, $5, $6, $7
+            FROM prompt_templates
+            WHERE name = $2
+            RETURNING *
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(&template.name)
+        .bind(template.description)
+        .bind(template.system_instruction)
+        .bind(template.body)
+        .bind(Json(template.variables))
+        .bind(template.created_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(row.into())
+    }
+
+    async fn list(&self, tenant_id: Uuid) -> Result<Vec<PromptTemplate>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let rows = sqlx::query_as::<_, PromptTemplateRow>(
+            "SELECT DISTINCT ON (name) * FROM prompt_templates ORDER BY name, version DESC",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn find(
+        &self,
+        tenant_id: Uuid,
+        name: &str,
+        version: Option<i32>,
+    ) -> Result<Option<PromptTemplate>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let row = sqlx::query_as::<_, PromptTemplateRow>(
+            r#"
+            SELECT * FROM prompt_templates
+            WHERE name = $1 AND ($2::INTEGER IS NULL OR version = $2)
+            ORDER BY version DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(name)
+        .bind(version)
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(row.map(Into::into))
+    }
+}
@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::TokenUsage;
+use crate::features::ai_integration::model::{AiRedactionSummary, AiUsageSummary};
+use crate::shared::database::begin_tenant_transaction;
+
+/// One AI call and who made it
+#[derive(Debug, Clone)]
+pub struct AiUsageRecord {
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub operation: &'static str,
+    pub model: String,
+    pub usage: TokenUsage,
+    /// Answered from the response cache, `usage` holds the tokens it saved
+    pub cached: bool,
+}
+
+/// Personal data redacted from the prompts of one request
+#[derive(Debug, Clone)]
+pub struct AiRedactionRecord {
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    pub operation: &'static str,
+    /// Matches per detector or pattern name
+    pub counts: Vec<(String, i32)>,
+}
+
+/// Usage is stored per tenant under row-level security
+#[async_trait]
+pub trait AiUsageRepository: Send + Sync {
+    async fn record(&self, tenant_id: Uuid, record: AiUsageRecord) -> Result<(), sqlx::Error>;
+    async fn summary(&self, tenant_id: Uuid) -> Result<Vec<AiUsageSummary>, sqlx::Error>;
+    async fn record_redactions(&self, tenant_id: Uuid, record: AiRedactionRecord) -> Result<(), sqlx::Error>;
+    async fn redaction_summary(&self, tenant_id: Uuid) -> Result<Vec<AiRedactionSummary>, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresAiUsageRepository {
+    pool: PgPool,
+}
+
+impl PostgresAiUsageRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AiUsageRepository for PostgresAiUsageRepository {
+    async fn record(&self, tenant_id: Uuid, record: AiUsageRecord) -> Result<(), sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO ai_usage
+                (organization_id, user_id, api_key_id, operation, model, prompt_tokens, completion_tokens, total_tokens, cached)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(record.user_id)
+        .bind(record.api_key_id)
+        .bind(record.operation)
+        .bind(record.model)
+        .bind(record.usage.prompt_tokens)
+        .bind(record.usage.completion_tokens)
+        .bind(record.usage.total_tokens)
+        .bind(record.cached)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await
+    }
+
+    async fn summary(&self, tenant_id: Uuid) -> Result<Vec<AiUsageSummary>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let summary = sqlx::query_as::<_, AiUsageSummary>(
+            r#"
+            SELECT operation,
+                   model,
+                   COUNT(*) AS requests,
+                   COUNT(*) FILTER (WHERE cached) AS cache_hits,
+                   COALESCE(SUM(prompt_tokens) FILTER (WHERE NOT cached), 0)::BIGINT AS prompt_tokens,
+                   COALESCE(SUM(completion_tokens) FILTER (WHERE NOT cached), 0)::BIGINT AS completion_tokens,
+                   COALESCE(SUM(total_tokens) FILTER (WHERE NOT cached), 0)::BIGINT AS total_tokens,
+                   COALESCE(SUM(total_tokens) FILTER (WHERE cached), 0)::BIGINT AS saved_tokens
+            FROM ai_usage
+            GROUP BY operation, model
+            ORDER BY operation, model
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(summary)
+    }
+
+    async fn record_redactions(&self, tenant_id: Uuid, record: AiRedactionRecord) -> Result<(), sqlx::Error> {
+        let (kinds, matches): (Vec<String>, Vec<i32>) = record.counts.into_iter().unzip();
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        sqlx::query(
+            r#"
+            INSERT INTO ai_redactions (organization_id, user_id, api_key_id, operation, kind, matches)
+            SELECT $1, $2, $3, $4, counts.kind, counts.matches
+            FROM UNNEST($5::VARCHAR[], $6::INTEGER[]) AS counts(kind, matches)
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(record.user_id)
+        .bind(record.api_key_id)
+        .bind(record.operation)
+        .bind(kinds)
+        .bind(matches)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await
+    }
+
+    async fn redaction_summary(&self, tenant_id: Uuid) -> Result<Vec<AiRedactionSummary>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let summary = sqlx::query_as::<_, AiRedactionSummary>(
+            r#"
+            SELECT operation,
+                   kind,
+                   COUNT(*) AS requests,
+                   COALESCE(SUM(matches), 0)::BIGINT AS matches,
+                   MAX(created_at) AS last_redacted_at
+            FROM ai_redactions
+            GROUP BY operation, kind
+            ORDER BY operation, kind
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(summary)
+    }
+}
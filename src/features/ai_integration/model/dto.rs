@@ -1,34 +1,677 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use async_graphql::{InputObject, SimpleObject};
+use async_graphql::{InputObject, Json, SimpleObject};
+use sqlx::FromRow;
 use validator::Validate;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
 
-use crate::entities::ai::ChatMessage;
+use crate::entities::ai::{
+    Candidate, ChatMessage, ContextStrategy, DocumentFormat, EmbeddedDocument, EmbeddingMatch, EmbeddingTask, MessagePart, SafetySetting,
+    TemplateVariable,
+};
+use crate::entities::user::UserActivity;
 
 #[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
 pub struct ChatRequest {
-    #[validate(length(min = 1, message = "Message cannot be empty"))]
+    /// Text of the message, may be empty when `parts` are given
+    #[serde(default)]
+    #[graphql(default)]
     pub message: String,
+    /// Images and documents, inline or uploaded to `/ai/files`, sent after `message`
+    #[serde(default)]
+    #[graphql(default)]
+    pub parts: Vec<MessagePart>,
     #[serde(default)]
     pub history: Vec<ChatMessage>,
+    /// Instructions for the model that apply to the whole conversation
+    #[serde(default)]
+    pub system_instruction: Option<String>,
+    /// 0 makes answers (nearly) deterministic and cacheable, unset uses the model default
+    #[validate(range(min = 0.0, max = 2.0, message = "Temperature must be between 0 and 2"))]
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Alternative answers returned in `candidates`, 1 when unset
+    #[validate(range(min = 1, max = 8, message = "candidate_count must be between 1 and 8"))]
+    #[serde(default)]
+    pub candidate_count: Option<i32>,
+    /// `false` skips the response cache, `true` also caches answers with a temperature above 0
+    #[serde(default)]
+    pub cache: Option<bool>,
+    /// Overrides the deployment safety settings for the listed categories
+    #[serde(default)]
+    #[graphql(default)]
+    pub safety_settings: Vec<SafetySetting>,
+    /// Names of tools from `/ai/tools` the model may call before answering
+    #[serde(default)]
+    #[graphql(default)]
+    pub tools: Vec<String>,
+    /// Grounds the answer in chunks retrieved from a collection
+    #[serde(default)]
+    #[validate]
+    pub rag: Option<RagOptions>,
+    /// How to shorten a history too long for the model, the deployment default when unset
+    #[serde(default)]
+    pub context_strategy: Option<ContextStrategy>,
+    /// A route such as "fast" or an allowed model name, the deployment default when unset
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
+/// Retrieval for a chat, the message is the search query
+#[derive(Debug, Serialize, Deserialize, Validate, InputObject, ToSchema)]
+pub struct RagOptions {
+    pub collection: String,
+    /// Chunks added to the prompt, 4 by default
+    #[validate(range(min = 1, max = 20, message = "top_k must be between 1 and 20"))]
+    #[serde(default = "default_rag_top_k")]
+    #[graphql(default_with = "default_rag_top_k()")]
+    pub top_k: i64,
+    /// Leaves out chunks with a lower score
+    #[validate(range(min = -1.0, max = 1.0, message = "min_score must be between -1 and 1"))]
+    #[serde(default)]
+    pub min_score: Option<f64>,
+    /// Only chunks whose metadata contains all of these keys and values
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub filter: Option<Json<serde_json::Map<String, serde_json::Value>>>,
+}
+
+fn default_rag_top_k() -> i64 {
+    4
+}
+
+/// A retrieved chunk the answer can refer to as `[index]`
+#[derive(Debug, Clone, Serialize, SimpleObject, ToSchema)]
+pub struct Citation {
+    pub index: i32,
+    /// Ingested document, or the stored document itself
+    pub document_id: String,
+    pub chunk_id: String,
+    pub chunk_index: Option<i32>,
+    pub score: f64,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, InputObject, ToSchema)]
 pub struct GenerateRequest {
     #[validate(length(min = 1, message = "Prompt cannot be empty"))]
     pub prompt: String,
     #[serde(default)]
+    pub system_instruction: Option<String>,
+    #[serde(default)]
     pub max_tokens: Option<i32>,
+    /// 0 makes answers (nearly) deterministic and cacheable, unset uses the model default
+    #[validate(range(min = 0.0, max = 2.0, message = "Temperature must be between 0 and 2"))]
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Alternative answers returned in `candidates`, 1 when unset
+    #[validate(range(min = 1, max = 8, message = "candidate_count must be between 1 and 8"))]
+    #[serde(default)]
+    pub candidate_count: Option<i32>,
+    /// `false` skips the response cache, `true` also caches answers with a temperature above 0
+    #[serde(default)]
+    pub cache: Option<bool>,
+    /// Overrides the deployment safety settings for the listed categories
+    #[serde(default)]
+    #[graphql(default)]
+    pub safety_settings: Vec<SafetySetting>,
+    /// JSON schema the answer must follow. The answer is returned parsed in `json`.
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub response_schema: Option<Json<serde_json::Value>>,
+    /// A route such as "fast" or an allowed model name, the deployment default when unset
+    #[serde(default)]
+    pub model: Option<String>,
 }
 
 #[derive(Debug, Serialize, SimpleObject, ToSchema)]
 pub struct ChatResponse {
     pub response: String,
+    /// Model that answered
+    pub model: String,
+    /// Answered by a later model of the route because the first failed or timed out
+    pub fallback: bool,
+    /// Why the model stopped, e.g. STOP or MAX_TOKENS
+    pub finish_reason: String,
+    /// Answered from the response cache
+    pub cached: bool,
+    /// Tools the model called, in order
+    pub tool_calls: Vec<ToolInvocation>,
+    /// Sources added to the prompt for a `rag` chat
+    pub citations: Vec<Citation>,
+    pub context: ContextUsage,
+    /// Every answer the model generated, `response` is the first one that wasn't blocked
+    pub candidates: Vec<Candidate>,
+}
+
+/// How the conversation was fitted into the model's context window
+#[derive(Debug, Clone, Serialize, SimpleObject, ToSchema)]
+pub struct ContextUsage {
+    /// Estimated tokens of the prompt, system instruction and tools included
+    pub estimated_tokens: i32,
+    /// Tokens the prompt may use with this model, room for the answer excluded
+    pub token_limit: i32,
+    /// Set when the history had to be shortened
+    pub strategy: Option<ContextStrategy>,
+    /// Oldest history messages left out
+    pub dropped_messages: i32,
+    /// Oldest history messages replaced by a summary
+    pub summarized_messages: i32,
+}
+
+/// One tool call made for the model, also sent as `tool_call` event by `/ai/chat/stream`
+#[derive(Debug, Clone, Serialize, SimpleObject, ToSchema)]
+pub struct ToolInvocation {
+    /// Model turn that asked for the call, starting at 1
+    pub step: i32,
+    pub name: String,
+    #[schema(value_type = Object)]
+    pub arguments: Json<serde_json::Value>,
+    /// Returned by the tool, unset when it failed
+    #[schema(value_type = Option<Object>)]
+    pub result: Option<Json<serde_json::Value>>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+}
+
+/// Frames a client sends over `/ai/chat/ws`, as JSON text messages
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatClientFrame {
+    /// Starts a chat. A connection runs one chat at a time.
+    Start {
+        /// Chosen by the client and repeated in every frame about this chat
+        #[serde(default)]
+        id: Option<String>,
+        request: Box<ChatRequest>,
+    },
+    /// Stops the running chat and the request to the model. Ignored when no chat is running.
+    Cancel,
+}
+
+/// Frames the server sends over `/ai/chat/ws`, as JSON text messages
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChatServerFrame {
+    /// The next words of the answer, the chunks of a chat add up to its text
+    Chunk { id: Option<String>, text: String },
+    /// A tool call made for the model, as soon as it is done
+    ToolEvent { id: Option<String>, tool_call: ToolInvocation },
+    /// The chat ended, answered or cancelled
+    Done {
+        id: Option<String>,
+        cancelled: bool,
+        /// The answer, or the part of it sent before the chat was cancelled
+        text: String,
+        /// Unset when cancelled
+        response: Option<ChatResponse>,
+    },
+    /// The chat failed or a frame was rejected, `id` is unset for frames that could not be read
+    Error { id: Option<String>, message: String },
+}
+
+/// A tool chat requests can enable
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ToolInfo {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the arguments
+    #[schema(value_type = Object)]
+    pub parameters: serde_json::Value,
+}
+
+/// A model requests can select by `name`
+#[derive(Debug, Clone, Serialize, SimpleObject, ToSchema)]
+pub struct ModelInfo {
+    pub name: String,
+    /// e.g. "gemini" or "mock"
+    pub provider: String,
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    /// Context window in tokens, from the provider or else the configured limit
+    pub input_token_limit: i32,
+    pub output_token_limit: Option<i32>,
+    /// Images and documents in messages
+    pub multimodal: bool,
+    pub function_calling: bool,
+    /// Provider methods such as "generateContent" or "countTokens", empty when unknown
+    pub supported_methods: Vec<String>,
+}
+
+/// A route requests can select by `name`, its models are tried in order
+#[derive(Debug, Clone, Serialize, SimpleObject, ToSchema)]
+pub struct RouteInfo {
+    pub name: String,
+    pub models: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, SimpleObject, ToSchema)]
+pub struct ModelListResponse {
+    pub models: Vec<ModelInfo>,
+    pub routes: Vec<RouteInfo>,
+    /// Route or model answering requests that name none
+    pub default_model: String,
+}
+
+/// A chat message to measure, with the same fields as a chat request
+#[derive(Debug, Deserialize, InputObject, ToSchema)]
+pub struct CountTokensRequest {
+    #[serde(default)]
+    #[graphql(default)]
+    pub message: String,
+    #[serde(default)]
+    #[graphql(default)]
+    pub parts: Vec<MessagePart>,
+    #[serde(default)]
+    #[graphql(default)]
+    pub history: Vec<ChatMessage>,
+    #[serde(default)]
+    pub system_instruction: Option<String>,
+    /// Tool declarations count as prompt tokens too
+    #[serde(default)]
+    #[graphql(default)]
+    pub tools: Vec<String>,
+    /// Route or model, the deployment default when unset
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+pub struct CountTokensResponse {
+    pub total_tokens: i32,
+    /// Model that counted, the first of a route
     pub model: String,
+    /// Counted by the provider, otherwise estimated at about four characters per token
+    pub exact: bool,
+    /// Tokens a prompt may use with this model, room for the answer excluded
+    pub token_limit: i32,
+    /// Whether a chat would be sent as is. Longer ones get their history shortened.
+    pub fits: bool,
 }
 
 #[derive(Debug, Serialize, SimpleObject, ToSchema)]
 pub struct GenerateResponse {
     pub text: String,
+    /// `text` parsed, when the request had a `response_schema`
+    #[schema(value_type = Option<Object>)]
+    pub json: Option<Json<serde_json::Value>>,
+    /// Model that answered
+    pub model: String,
+    /// Answered by a later model of the route because the first failed or timed out
+    pub fallback: bool,
+    /// Why the model stopped, e.g. STOP or MAX_TOKENS
+    pub finish_reason: String,
+    /// Answered from the response cache
+    pub cached: bool,
+    /// Every answer the model generated, `text` is the first one that wasn't blocked
+    pub candidates: Vec<Candidate>,
+}
+
+/// Generate requests to answer in the background
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateBatchRequest {
+    #[validate(length(min = 1, message = "At least one item is required"))]
+    #[validate]
+    pub items: Vec<GenerateRequest>,
+}
+
+/// AI usage of the current organization, per operation and model
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct AiUsageSummary {
+    pub operation: String,
+    pub model: String,
+    pub requests: i64,
+    /// Requests answered from the response cache
+    pub cache_hits: i64,
+    /// Billed tokens, cache hits excluded
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub total_tokens: i64,
+    /// Tokens cache hits would have cost
+    pub saved_tokens: i64,
+}
+
+/// Personal data redacted from prompts of the current organization, per operation and detector
+#[derive(Debug, Serialize, FromRow, ToSchema)]
+pub struct AiRedactionSummary {
+    pub operation: String,
+    /// Built-in detector (email, phone, card, iban) or configured pattern
+    pub kind: String,
+    /// Requests with at least one match
+    pub requests: i64,
+    pub matches: i64,
+    pub last_redacted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ModerationFlagQuery {
+    /// Only reviewed (true) or open (false) flags, all when unset
+    pub reviewed: Option<bool>,
+    /// At most 500, defaults to 100
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct ReviewModerationFlagRequest {
+    /// Why the flag was dismissed or acted on
+    #[validate(length(max = 2000, message = "Note must be at most 2000 characters"))]
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RequestLogQuery {
+    /// e.g. chat, generate, summarize or replay
+    pub operation: Option<String>,
+    /// The requested or the answering model
+    pub model: Option<String>,
+    pub user_id: Option<Uuid>,
+    pub api_key_id: Option<Uuid>,
+    /// Only failed (true) or answered (false) calls
+    pub failed: Option<bool>,
+    /// Text contained in the request or response, ignoring case
+    pub q: Option<String>,
+    /// RFC 3339 time, e.g. 2024-12-01T00:00:00Z
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// At most 200, defaults to 50
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct ReplayRequest {
+    /// Allowed model or route, defaults to the model that answered the logged call
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffOp {
+    Equal,
+    /// Only in the logged response
+    Delete,
+    /// Only in the replayed response
+    Insert,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DiffSegment {
+    pub op: DiffOp,
+    pub text: String,
+}
+
+/// A logged call sent again, and how its answer differs
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReplayResponse {
+    pub log_id: Uuid,
+    /// The model that answered now
+    pub model: String,
+    /// The model that answered the logged call
+    pub original_model: Option<String>,
+    pub response: String,
+    /// Unset when the logged call failed
+    pub original_response: Option<String>,
+    /// Tools the model asked for instead of answering, they aren't called
+    #[schema(value_type = Option<Object>)]
+    pub tool_calls: Option<serde_json::Value>,
+    pub finish_reason: String,
+    pub total_tokens: i32,
+    pub latency_ms: i64,
+    pub original_latency_ms: i64,
+    pub identical: bool,
+    /// Word by word, from the logged response to the replayed one
+    pub diff: Vec<DiffSegment>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreatePromptTemplateRequest {
+    /// Lowercase letters, digits, `-` and `_`. An existing name gets a new version.
+    #[validate(length(min = 1, max = 100, message = "Name must be between 1 and 100 characters"))]
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub system_instruction: Option<String>,
+    #[validate(length(min = 1, message = "Body cannot be empty"))]
+    pub body: String,
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct TemplateVersionQuery {
+    /// Defaults to the latest version
+    pub version: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct RenderTemplateRequest {
+    /// Values for the template variables
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub variables: serde_json::Map<String, serde_json::Value>,
+    /// Defaults to the latest version
+    #[serde(default)]
+    pub version: Option<i32>,
+    #[serde(default)]
+    pub max_tokens: Option<i32>,
+    #[validate(range(min = 0.0, max = 2.0, message = "Temperature must be between 0 and 2"))]
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// See `/ai/generate`
+    #[validate(range(min = 1, max = 8, message = "candidate_count must be between 1 and 8"))]
+    #[serde(default)]
+    pub candidate_count: Option<i32>,
+    #[serde(default)]
+    pub cache: Option<bool>,
+    #[serde(default)]
+    pub safety_settings: Vec<SafetySetting>,
+    /// JSON schema the answer must follow, see `/ai/generate`
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub response_schema: Option<Json<serde_json::Value>>,
+    /// Route or model, see `/ai/generate`
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TemplateGenerateResponse {
+    pub template: String,
+    /// Version that was rendered
+    pub template_version: i32,
+    #[serde(flatten)]
+    pub generation: GenerateResponse,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct EmbedRequest {
+    #[validate(length(min = 1, max = 100, message = "Between 1 and 100 texts are required"))]
+    pub texts: Vec<String>,
+    /// Defaults to SEMANTIC_SIMILARITY
+    #[serde(default)]
+    pub task_type: EmbeddingTask,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EmbedResponse {
+    pub model: String,
+    pub dimensions: usize,
+    /// One vector per text, in order
+    pub embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct StoreDocumentsRequest {
+    /// Documents with an existing id are replaced
+    #[validate(length(min = 1, max = 100, message = "Between 1 and 100 documents are required"))]
+    #[validate]
+    pub documents: Vec<DocumentInput>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
+pub struct DocumentInput {
+    #[validate(length(min = 1, max = 255, message = "Id must be between 1 and 255 characters"))]
+    pub id: String,
+    #[validate(length(min = 1, message = "Content cannot be empty"))]
+    pub content: String,
+    /// Returned with search results and usable in search filters
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StoreDocumentsResponse {
+    pub documents: Vec<EmbeddedDocument>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct SearchRequest {
+    #[validate(length(min = 1, message = "Query cannot be empty"))]
+    pub query: String,
+    /// Number of matches to return, 5 by default
+    #[validate(range(min = 1, max = 50, message = "top_k must be between 1 and 50"))]
+    #[serde(default = "default_top_k")]
+    pub top_k: i64,
+    /// Leaves out matches with a lower score
+    #[validate(range(min = -1.0, max = 1.0, message = "min_score must be between -1 and 1"))]
+    #[serde(default)]
+    pub min_score: Option<f64>,
+    /// Only documents whose metadata contains all of these keys and values
+    #[serde(default)]
+    #[schema(value_type = Option<Object>)]
+    pub filter: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+fn default_top_k() -> i64 {
+    5
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchResponse {
+    pub model: String,
+    /// Best match first
+    pub matches: Vec<EmbeddingMatch>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct IngestDocumentRequest {
+    /// Ingesting an existing id replaces all of its chunks
+    #[validate(length(min = 1, max = 200, message = "Id must be between 1 and 200 characters"))]
+    pub id: String,
+    #[validate(length(min = 1, message = "Content cannot be empty"))]
+    pub content: String,
+    #[serde(default)]
+    pub format: DocumentFormat,
+    /// Copied to every chunk, usable in search filters
+    #[serde(default)]
+    #[schema(value_type = Object)]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+    /// Characters per chunk, `AI_CHUNK_SIZE` when unset
+    #[validate(range(min = 100, max = 8000, message = "chunk_size must be between 100 and 8000"))]
+    #[serde(default)]
+    pub chunk_size: Option<usize>,
+    /// Characters a chunk repeats from the previous one, `AI_CHUNK_OVERLAP` when unset
+    #[serde(default)]
+    pub chunk_overlap: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IngestDocumentResponse {
+    pub document_id: String,
+    pub chunks: Vec<EmbeddedDocument>,
+}
+
+/// What a bio suggestion builds on besides the user's name and organization
+#[derive(Debug, Default, Deserialize, Validate, InputObject, ToSchema)]
+pub struct BioSuggestionRequest {
+    /// Facts to mention, e.g. the job title, team or interests. The model is
+    /// told not to invent anything else.
+    #[validate(length(max = 20, message = "At most 20 facts are allowed"))]
+    #[serde(default)]
+    #[graphql(default)]
+    pub facts: Vec<String>,
+    /// e.g. "friendly" or "formal", professional when unset
+    #[validate(length(min = 1, max = 50, message = "Tone must be between 1 and 50 characters"))]
+    #[serde(default)]
+    pub tone: Option<String>,
+    /// Length limit of each suggestion, 60 by default
+    #[validate(range(min = 10, max = 300, message = "max_words must be between 10 and 300"))]
+    #[serde(default)]
+    pub max_words: Option<i32>,
+    /// Alternative bios to suggest, 1 by default
+    #[validate(range(min = 1, max = 5, message = "suggestions must be between 1 and 5"))]
+    #[serde(default)]
+    pub suggestions: Option<i32>,
+    /// Route or model, see `/ai/generate`
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+pub struct BioSuggestionResponse {
+    pub user_id: Uuid,
+    /// Suggested bios, blocked candidates left out
+    pub suggestions: Vec<String>,
+    /// Model that answered
     pub model: String,
+    /// Answered from the response cache
+    pub cached: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DuplicateUsersQuery {
+    /// Cosine similarity a member needs to be reported, 0.9 by default
+    pub min_score: Option<f64>,
+    /// At most 50, defaults to 10
+    pub limit: Option<i64>,
+}
+
+/// A member that may be the same person as the user
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+pub struct DuplicateUser {
+    pub id: Uuid,
+    pub name: String,
+    pub email: String,
+    /// Cosine similarity of the name and email embeddings
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+pub struct DuplicateUsersResponse {
+    pub user_id: Uuid,
+    /// Embedding model that compared them, unset when there was nobody to compare with
+    pub model: Option<String>,
+    /// Other members the user was compared with
+    pub compared: i32,
+    /// Most similar first
+    pub duplicates: Vec<DuplicateUser>,
+}
+
+#[derive(Debug, Default, Deserialize, Validate, InputObject, ToSchema)]
+pub struct ExplainActivityRequest {
+    /// How far back to look, 30 days by default
+    #[validate(range(min = 1, max = 365, message = "days must be between 1 and 365"))]
+    #[serde(default)]
+    pub days: Option<i64>,
+    /// Route or model, see `/ai/generate`
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+pub struct ActivityExplanationResponse {
+    pub user_id: Uuid,
+    /// Plain-language summary of `events`
+    pub explanation: String,
+    /// The events that were explained, newest first
+    pub events: Vec<UserActivity>,
+    pub since: DateTime<Utc>,
+    /// Model that answered, unset when there was no activity to explain
+    pub model: Option<String>,
+    /// Answered from the response cache
+    pub cached: bool,
 }
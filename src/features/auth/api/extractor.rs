@@ -0,0 +1,100 @@
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::{
+        header::{AUTHORIZATION, UPGRADE},
+        request::Parts,
+    },
+};
+
+use crate::{
+    features::auth::domain::ApiKeyService,
+    features::auth::model::{AuthUser, Principal},
+    shared::error::AppError,
+    app::state::AppState,
+};
+
+fn bearer_token(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .or_else(|| websocket_token(parts))
+}
+
+/// Browsers can't set headers on a WebSocket handshake, so it may pass the
+/// token as `access_token` query parameter instead
+fn websocket_token(parts: &Parts) -> Option<&str> {
+    let upgrade = parts.headers.get(UPGRADE)?.to_str().ok()?;
+    if !upgrade.eq_ignore_ascii_case("websocket") {
+        return None;
+    }
+    parts
+        .uri
+        .query()?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("access_token="))
+        .filter(|token| !token.is_empty())
+}
+
+/// Session tokens and API keys share the bearer header, keys are recognized by their prefix
+async fn authenticate(token: &str, state: &AppState) -> Result<Principal, AppError> {
+    if ApiKeyService::is_api_key(token) {
+        let key = state.api_key_service.authenticate(token).await?;
+        tracing::debug!(api_key_id = %key.key_id, api_key_name = %key.name, "authenticated with API key");
+        Ok(Principal::ApiKey(key))
+    } else {
+        state.auth_service.authenticate(token).await.map(Principal::User)
+    }
+}
+
+/// Requires `Authorization: Bearer <session token or API key>`. The result is
+/// cached in the request extensions, so other extractors can reuse it.
+impl FromRequestParts<AppState> for Principal {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        if let Some(principal) = parts.extensions.get::<Principal>() {
+            return Ok(principal.clone());
+        }
+
+        let token = bearer_token(parts)
+            .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))?;
+        let principal = authenticate(token, state).await?;
+
+        parts.extensions.insert(principal.clone());
+        Ok(principal)
+    }
+}
+
+/// Anonymous requests yield `None`, an invalid token is still rejected
+impl OptionalFromRequestParts<AppState> for Principal {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        if bearer_token(parts).is_none() {
+            return Ok(None);
+        }
+        <Principal as FromRequestParts<AppState>>::from_request_parts(parts, state)
+            .await
+            .map(Some)
+    }
+}
+
+/// Like `Principal`, but only accepts user sessions
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        match <Principal as FromRequestParts<AppState>>::from_request_parts(parts, state).await? {
+            Principal::User(auth) => Ok(auth),
+            Principal::ApiKey(_) => Err(AppError::Forbidden(
+                "This endpoint requires a user session".to_string(),
+            )),
+        }
+    }
+}
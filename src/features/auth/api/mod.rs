@@ -0,0 +1,4 @@
+pub mod extractor;
+pub mod rest;
+
+pub use rest::*;
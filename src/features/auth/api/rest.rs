@@ -0,0 +1,527 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Redirect,
+    Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    features::auth::model::{
+        ApiKeyResponse, AuthUser, ConfirmEmailVerificationRequest, CreateApiKeyRequest,
+        CreatedApiKeyResponse, ConfirmPasswordResetRequest,
+        EmailVerificationRequest, LoginRequest, LoginResponse, OidcCallbackQuery,
+        OidcProviderResponse, PasswordResetRequest,
+        RecoveryCodesResponse, TwoFactorCodeRequest, TwoFactorEnrollmentResponse,
+        TwoFactorLoginRequest, UpdateProfileRequest,
+    },
+    features::organizations::model::TenantContext,
+    features::user_management::model::UserResponse,
+    shared::error::AppError,
+    app::state::AppState,
+};
+
+/// Send an email verification link
+#[utoipa::path(
+    post,
+    path = "/auth/email-verification",
+    tag = "auth",
+    request_body = EmailVerificationRequest,
+    responses(
+        (status = 202, description = "Verification email sent if the account exists"),
+        (status = 400, description = "Validation error")
+    )
+)]
+pub async fn request_email_verification(
+    State(state): State<AppState>,
+    Json(payload): Json<EmailVerificationRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    state.auth_service.request_email_verification(&payload.email).await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "message": "If the account exists, a verification email has been sent" })),
+    ))
+}
+
+/// Confirm an email address with a token from the verification email
+#[utoipa::path(
+    post,
+    path = "/auth/email-verification/confirm",
+    tag = "auth",
+    request_body = ConfirmEmailVerificationRequest,
+    responses(
+        (status = 200, description = "Email verified", body = UserResponse),
+        (status = 400, description = "Invalid or expired token")
+    )
+)]
+pub async fn confirm_email_verification(
+    State(state): State<AppState>,
+    Json(payload): Json<ConfirmEmailVerificationRequest>,
+) -> Result<Json<UserResponse>, AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    let user = state
+        .auth_service
+        .confirm_email_verification(&payload.token)
+        .await?;
+
+    Ok(Json(UserResponse::from(user)))
+}
+
+/// Send a password reset link
+#[utoipa::path(
+    post,
+    path = "/auth/password-reset",
+    tag = "auth",
+    request_body = PasswordResetRequest,
+    responses(
+        (status = 202, description = "Reset email sent if the account exists"),
+        (status = 400, description = "Validation error")
+    )
+)]
+pub async fn request_password_reset(
+    State(state): State<AppState>,
+    Json(payload): Json<PasswordResetRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    state.auth_service.request_password_reset(&payload.email).await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "message": "If the account exists, a password reset email has been sent" })),
+    ))
+}
+
+/// Set a new password with a token from the reset email
+#[utoipa::path(
+    post,
+    path = "/auth/password-reset/confirm",
+    tag = "auth",
+    request_body = ConfirmPasswordResetRequest,
+    responses(
+        (status = 200, description = "Password updated"),
+        (status = 400, description = "Invalid or expired token")
+    )
+)]
+pub async fn confirm_password_reset(
+    State(state): State<AppState>,
+    Json(payload): Json<ConfirmPasswordResetRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    state
+        .auth_service
+        .confirm_password_reset(&payload.token, &payload.new_password)
+        .await?;
+
+    Ok(Json(serde_json::json!({ "message": "Password updated" })))
+}
+
+/// Log in with email and password
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Session token, or a two-factor challenge", body = LoginResponse),
+        (status = 401, description = "Invalid email or password")
+    )
+)]
+pub async fn login(
+    State(state): State<AppState>,
+    Json(payload): Json<LoginRequest>,
+) -> Result<Json<LoginResponse>, AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    let response = state
+        .auth_service
+        .login(&payload.email, &payload.password)
+        .await?;
+    Ok(Json(response))
+}
+
+/// Complete a login challenge with a TOTP or recovery code
+#[utoipa::path(
+    post,
+    path = "/auth/login/2fa",
+    tag = "auth",
+    request_body = TwoFactorLoginRequest,
+    responses(
+        (status = 200, description = "Session token", body = LoginResponse),
+        (status = 401, description = "Invalid challenge or code")
+    )
+)]
+pub async fn login_two_factor(
+    State(state): State<AppState>,
+    Json(payload): Json<TwoFactorLoginRequest>,
+) -> Result<Json<LoginResponse>, AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    let response = state
+        .auth_service
+        .login_two_factor(&payload.challenge_token, &payload.code)
+        .await?;
+    Ok(Json(response))
+}
+
+/// End the current session
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    tag = "auth",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Logged out"),
+        (status = 401, description = "Not authenticated")
+    )
+)]
+pub async fn logout(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<serde_json::Value>, AppError> {
+    state.auth_service.logout(&auth).await?;
+
+    Ok(Json(serde_json::json!({ "message": "Logged out" })))
+}
+
+/// Get the authenticated user
+#[utoipa::path(
+    get,
+    path = "/auth/me",
+    tag = "auth",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Current user", body = UserResponse),
+        (status = 401, description = "Not authenticated")
+    )
+)]
+pub async fn me(auth: AuthUser) -> Json<UserResponse> {
+    Json(UserResponse::from(auth.user))
+}
+
+/// Update the authenticated user's name or email
+#[utoipa::path(
+    put,
+    path = "/auth/me",
+    tag = "auth",
+    request_body = UpdateProfileRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Updated user, a new email is unverified", body = UserResponse),
+        (status = 400, description = "Validation error"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Changing the email of an account with 2FA needs a recent second factor")
+    )
+)]
+pub async fn update_me(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<UpdateProfileRequest>,
+) -> Result<Json<UserResponse>, AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    let user = state.auth_service.update_profile(&auth, payload).await?;
+    Ok(Json(UserResponse::from(user)))
+}
+
+/// Start TOTP enrollment
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/enroll",
+    tag = "auth",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Secret and provisioning URI", body = TwoFactorEnrollmentResponse),
+        (status = 400, description = "Two-factor authentication already enabled"),
+        (status = 401, description = "Not authenticated")
+    )
+)]
+pub async fn enroll_two_factor(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<TwoFactorEnrollmentResponse>, AppError> {
+    let response = state.auth_service.enroll_two_factor(&auth).await?;
+    Ok(Json(response))
+}
+
+/// Confirm TOTP enrollment with a first code
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/confirm",
+    tag = "auth",
+    security(("bearer" = [])),
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor enabled, recovery codes are shown once", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code or no pending enrollment"),
+        (status = 401, description = "Not authenticated")
+    )
+)]
+pub async fn confirm_two_factor(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    let response = state
+        .auth_service
+        .confirm_two_factor(&auth, &payload.code)
+        .await?;
+    Ok(Json(response))
+}
+
+/// Disable two-factor authentication
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/disable",
+    tag = "auth",
+    security(("bearer" = [])),
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor disabled"),
+        (status = 401, description = "Not authenticated or invalid code")
+    )
+)]
+pub async fn disable_two_factor(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    state
+        .auth_service
+        .disable_two_factor(&auth, &payload.code)
+        .await?;
+
+    Ok(Json(serde_json::json!({ "message": "Two-factor authentication disabled" })))
+}
+
+/// Replace all recovery codes
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/recovery-codes",
+    tag = "auth",
+    security(("bearer" = [])),
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "New recovery codes, shown once", body = RecoveryCodesResponse),
+        (status = 401, description = "Not authenticated or invalid code")
+    )
+)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    let response = state
+        .auth_service
+        .regenerate_recovery_codes(&auth, &payload.code)
+        .await?;
+    Ok(Json(response))
+}
+
+/// Re-verify the second factor for the current session
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/step-up",
+    tag = "auth",
+    security(("bearer" = [])),
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Session marked as recently verified"),
+        (status = 401, description = "Not authenticated or invalid code")
+    )
+)]
+pub async fn step_up(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    state.auth_service.step_up(&auth, &payload.code).await?;
+
+    Ok(Json(serde_json::json!({ "message": "Second factor verified" })))
+}
+
+/// List configured identity providers
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/providers",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Configured OpenID Connect providers", body = Vec<OidcProviderResponse>)
+    )
+)]
+pub async fn oidc_providers(State(state): State<AppState>) -> Json<Vec<OidcProviderResponse>> {
+    Json(state.oidc_service.providers())
+}
+
+/// Redirect to an identity provider to sign in
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/{provider}/authorize",
+    tag = "auth",
+    params(
+        ("provider" = String, Path, description = "Provider name")
+    ),
+    responses(
+        (status = 303, description = "Redirect to the provider"),
+        (status = 404, description = "Unknown provider"),
+        (status = 502, description = "Provider discovery failed")
+    )
+)]
+pub async fn oidc_authorize(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+) -> Result<Redirect, AppError> {
+    let url = state.oidc_service.authorization_url(&provider).await?;
+    Ok(Redirect::to(&url))
+}
+
+/// Provider redirect target, signs the user in
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/{provider}/callback",
+    tag = "auth",
+    params(
+        ("provider" = String, Path, description = "Provider name"),
+        OidcCallbackQuery
+    ),
+    responses(
+        (status = 200, description = "Session token, or a two-factor challenge", body = LoginResponse),
+        (status = 401, description = "Invalid state or ID token"),
+        (status = 409, description = "Email belongs to an existing account and is not verified by the provider")
+    )
+)]
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    Query(query): Query<OidcCallbackQuery>,
+) -> Result<Json<LoginResponse>, AppError> {
+    if let Some(error) = query.error {
+        return Err(AppError::Unauthorized(format!(
+            "Identity provider returned an error: {}",
+            query.error_description.unwrap_or(error)
+        )));
+    }
+
+    let (Some(code), Some(login_state)) = (query.code, query.state) else {
+        return Err(AppError::Validation("Missing code or state".to_string()));
+    };
+
+    let response = state
+        .oidc_service
+        .callback(&provider, &code, &login_state)
+        .await?;
+    Ok(Json(response))
+}
+
+/// Create an API key for the current organization. The key is only shown in this response.
+#[utoipa::path(
+    post,
+    path = "/auth/api-keys",
+    tag = "auth",
+    request_body = CreateApiKeyRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "API key created", body = CreatedApiKeyResponse),
+        (status = 400, description = "Validation error"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role and recent second factor required")
+    )
+)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedApiKeyResponse>), AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+    state.auth_service.authorize_tenant_admin(&tenant)?;
+
+    let response = state.api_key_service.create(&tenant, payload).await?;
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// List API keys of the current organization, including expired and revoked ones
+#[utoipa::path(
+    get,
+    path = "/auth/api-keys",
+    tag = "auth",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "All API keys", body = Vec<ApiKeyResponse>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role and recent second factor required")
+    )
+)]
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+) -> Result<Json<Vec<ApiKeyResponse>>, AppError> {
+    state.auth_service.authorize_tenant_admin(&tenant)?;
+
+    let keys = state.api_key_service.list(&tenant).await?;
+    Ok(Json(keys))
+}
+
+/// Revoke an API key
+#[utoipa::path(
+    delete,
+    path = "/auth/api-keys/{id}",
+    tag = "auth",
+    params(
+        ("id" = Uuid, Path, description = "API key id")
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "API key revoked"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Organization owner or admin role and recent second factor required"),
+        (status = 404, description = "API key not found or already revoked")
+    )
+)]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    tenant: TenantContext,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    state.auth_service.authorize_tenant_admin(&tenant)?;
+
+    state.api_key_service.revoke(&tenant, id).await?;
+    Ok(Json(serde_json::json!({ "message": "API key revoked" })))
+}
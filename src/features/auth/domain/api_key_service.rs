@@ -0,0 +1,147 @@
+use chrono::Utc;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    features::auth::infrastructure::{ApiKey, ApiKeyRepository, NewApiKey},
+    features::auth::model::{
+        ApiKeyPrincipal, ApiKeyResponse, ApiScope, CreateApiKeyRequest, CreatedApiKeyResponse,
+    },
+    features::organizations::model::TenantContext,
+    shared::error::AppError,
+    shared::security::{generate_token, hash_token},
+};
+
+/// Prefix of every API key, which tells them apart from session tokens
+const API_KEY_PREFIX: &str = "sk_";
+
+/// Characters of the key stored in clear for display
+const DISPLAY_PREFIX_LEN: usize = 11;
+
+#[derive(Clone)]
+pub struct ApiKeyService {
+    api_keys: Arc<dyn ApiKeyRepository>,
+}
+
+impl ApiKeyService {
+    pub fn new(api_keys: Arc<dyn ApiKeyRepository>) -> Self {
+        Self { api_keys }
+    }
+
+    pub fn is_api_key(token: &str) -> bool {
+        token.starts_with(API_KEY_PREFIX)
+    }
+
+    /// Creates a key bound to the tenant. The plain key is only part of this response.
+    pub async fn create(
+        &self,
+        tenant: &TenantContext,
+        request: CreateApiKeyRequest,
+    ) -> Result<CreatedApiKeyResponse, AppError> {
+        if request.expires_at.is_some_and(|at| at <= Utc::now()) {
+            return Err(AppError::Validation("Expiry must be in the future".to_string()));
+        }
+
+        let key = format!("{}{}", API_KEY_PREFIX, generate_token());
+        let mut scopes: Vec<String> = request.scopes.iter().map(|s| s.as_str().to_string()).collect();
+        scopes.sort();
+        scopes.dedup();
+
+        let created_by = tenant.require_user()?.user.id;
+        let api_key = self
+            .api_keys
+            .create(NewApiKey {
+                organization_id: tenant.organization_id(),
+                name: request.name,
+                key_prefix: key[..DISPLAY_PREFIX_LEN].to_string(),
+                key_hash: hash_token(&key),
+                scopes,
+                created_by,
+                expires_at: request.expires_at,
+            })
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(CreatedApiKeyResponse {
+            key,
+            api_key: to_response(api_key),
+        })
+    }
+
+    pub async fn list(&self, tenant: &TenantContext) -> Result<Vec<ApiKeyResponse>, AppError> {
+        let keys = self
+            .api_keys
+            .list(tenant.organization_id())
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(keys.into_iter().map(to_response).collect())
+    }
+
+    pub async fn revoke(&self, tenant: &TenantContext, id: Uuid) -> Result<(), AppError> {
+        let revoked = self
+            .api_keys
+            .revoke(tenant.organization_id(), id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if !revoked {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Resolves a key from the `Authorization` header and records its use
+    pub async fn authenticate(&self, key: &str) -> Result<ApiKeyPrincipal, AppError> {
+        let api_key = self
+            .api_keys
+            .touch(&hash_token(key))
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or_else(|| AppError::Unauthorized("Invalid, expired or revoked API key".to_string()))?;
+
+        Ok(to_principal(api_key))
+    }
+
+    /// The key behind work done on its behalf outside a request, e.g. batch
+    /// jobs. Fails once it is revoked or expired.
+    pub async fn principal(&self, id: Uuid) -> Result<ApiKeyPrincipal, AppError> {
+        let api_key = self
+            .api_keys
+            .find_active(id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or_else(|| AppError::Unauthorized("The API key was revoked or has expired".to_string()))?;
+
+        Ok(to_principal(api_key))
+    }
+}
+
+/// Unknown scope names are ignored, so removing a scope from the code revokes it
+fn parse_scopes(scopes: &[String]) -> Vec<ApiScope> {
+    scopes.iter().filter_map(|s| ApiScope::parse(s)).collect()
+}
+
+fn to_response(api_key: ApiKey) -> ApiKeyResponse {
+    ApiKeyResponse {
+        scopes: parse_scopes(&api_key.scopes),
+        id: api_key.id,
+        organization_id: api_key.organization_id,
+        name: api_key.name,
+        key_prefix: api_key.key_prefix,
+        created_by: api_key.created_by,
+        expires_at: api_key.expires_at,
+        last_used_at: api_key.last_used_at,
+        revoked_at: api_key.revoked_at,
+        created_at: api_key.created_at,
+    }
+}
+
+fn to_principal(api_key: ApiKey) -> ApiKeyPrincipal {
+    ApiKeyPrincipal {
+        scopes: parse_scopes(&api_key.scopes),
+        key_id: api_key.id,
+        name: api_key.name,
+        organization_id: api_key.organization_id,
+    }
+}
@@ -0,0 +1,8 @@
+mod api_key_service;
+mod oidc_service;
+mod service;
+mod two_factor;
+
+pub use api_key_service::ApiKeyService;
+pub use oidc_service::OidcService;
+pub use service::{AuthService, AuthSettings};
@@ -0,0 +1,201 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use super::AuthService;
+use crate::{
+    features::auth::infrastructure::{IdTokenClaims, IdentityRepository, OidcClient, OidcLoginState},
+    features::auth::model::{LoginResponse, OidcProviderResponse},
+    features::user_management::infrastructure::UserRepository,
+    shared::config::OidcConfig,
+    shared::error::AppError,
+    shared::security::{generate_token, hash_token},
+    entities::user::User,
+};
+
+/// How long a user has to finish signing in at the provider
+const LOGIN_STATE_TTL_MINUTES: i64 = 10;
+
+#[derive(Clone)]
+pub struct OidcService {
+    clients: Arc<Vec<OidcClient>>,
+    identities: Arc<dyn IdentityRepository>,
+    users: Arc<dyn UserRepository>,
+    auth_service: AuthService,
+    redirect_base_url: String,
+}
+
+impl OidcService {
+    pub fn new(
+        config: &OidcConfig,
+        identities: Arc<dyn IdentityRepository>,
+        users: Arc<dyn UserRepository>,
+        auth_service: AuthService,
+    ) -> Self {
+        let http = reqwest::Client::new();
+        let clients = config
+            .providers
+            .iter()
+            .map(|provider| OidcClient::new(provider.clone(), http.clone()))
+            .collect();
+
+        Self {
+            clients: Arc::new(clients),
+            identities,
+            users,
+            auth_service,
+            redirect_base_url: config.redirect_base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn providers(&self) -> Vec<OidcProviderResponse> {
+        self.clients
+            .iter()
+            .map(|client| OidcProviderResponse {
+                name: client.config().name.clone(),
+                display_name: client.config().display_name.clone(),
+            })
+            .collect()
+    }
+
+    /// Starts an authorization-code + PKCE flow and returns the provider URL
+    /// the browser should be sent to
+    pub async fn authorization_url(&self, provider: &str) -> Result<String, AppError> {
+        let client = self.client(provider)?;
+
+        let state = generate_token();
+        let nonce = generate_token();
+        let code_verifier = generate_token();
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+        self.identities
+            .save_login_state(
+                hash_token(&state),
+                OidcLoginState {
+                    provider: client.config().name.clone(),
+                    nonce: nonce.clone(),
+                    code_verifier,
+                },
+                Utc::now() + Duration::minutes(LOGIN_STATE_TTL_MINUTES),
+            )
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        client
+            .authorization_url(&self.redirect_uri(provider), &state, &nonce, &code_challenge)
+            .await
+    }
+
+    /// Handles the provider redirect: validates `state`, exchanges the code,
+    /// resolves the local user and signs them in
+    pub async fn callback(
+        &self,
+        provider: &str,
+        code: &str,
+        state: &str,
+    ) -> Result<LoginResponse, AppError> {
+        let client = self.client(provider)?;
+
+        let login_state = self
+            .identities
+            .take_login_state(&hash_token(state))
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .filter(|login_state| login_state.provider == client.config().name)
+            .ok_or_else(|| AppError::Unauthorized("Invalid or expired login state".to_string()))?;
+
+        let claims = client
+            .exchange_code(
+                code,
+                &login_state.code_verifier,
+                &self.redirect_uri(provider),
+                &login_state.nonce,
+            )
+            .await?;
+
+        let user = self.resolve_user(&client.config().name, &claims).await?;
+        self.identities
+            .upsert(user.id, &client.config().name, &claims.sub, claims.email.clone())
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        self.auth_service.start_session(&user).await
+    }
+
+    /// Finds the user for an identity. Unknown identities are linked to an
+    /// existing account only when both the provider and the account have
+    /// verified the email address, otherwise a new user is created. An
+    /// unverified account may have been registered by someone else.
+    async fn resolve_user(&self, provider: &str, claims: &IdTokenClaims) -> Result<User, AppError> {
+        let linked = self
+            .identities
+            .find_user_id(provider, &claims.sub)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        if let Some(user_id) = linked {
+            return self
+                .users
+                .find_by_id(user_id)
+                .await
+                .map_err(|e| AppError::Database(e.to_string()))?
+                .ok_or(AppError::NotFound);
+        }
+
+        let email = claims.email.as_deref().ok_or_else(|| {
+            AppError::Validation("Identity provider did not return an email address".to_string())
+        })?;
+
+        let existing = self
+            .users
+            .find_by_email(email)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        match existing {
+            Some(user) if claims.email_verified && user.email_verified => Ok(user),
+            Some(_) if claims.email_verified => Err(AppError::Conflict(format!(
+                "An account with this email already exists, verify its email address before signing in with {}",
+                provider
+            ))),
+            Some(_) => Err(AppError::Conflict(
+                "An account with this email already exists and the provider has not verified it"
+                    .to_string(),
+            )),
+            None => {
+                let name = claims
+                    .name
+                    .clone()
+                    .or_else(|| claims.preferred_username.clone())
+                    .unwrap_or_else(|| email.split('@').next().unwrap_or(email).to_string());
+
+                let user = self
+                    .users
+                    .create(name, email.to_string(), None)
+                    .await
+                    .map_err(|e| AppError::Database(e.to_string()))?;
+
+                if claims.email_verified {
+                    return self
+                        .users
+                        .mark_email_verified(user.id)
+                        .await
+                        .map_err(|e| AppError::Database(e.to_string()));
+                }
+                Ok(user)
+            }
+        }
+    }
+
+    fn client(&self, provider: &str) -> Result<&OidcClient, AppError> {
+        self.clients
+            .iter()
+            .find(|client| client.config().name == provider)
+            .ok_or(AppError::NotFound)
+    }
+
+    fn redirect_uri(&self, provider: &str) -> String {
+        format!("{}/auth/oidc/{}/callback", self.redirect_base_url, provider)
+    }
+}
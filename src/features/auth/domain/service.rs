@@ -0,0 +1,522 @@
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+use super::two_factor::{
+    build_totp, check_totp, generate_recovery_codes, generate_secret, normalize_recovery_code,
+};
+use crate::{
+    features::auth::infrastructure::{
+        SessionRepository, TokenPurpose, TokenRepository, TwoFactorRepository,
+    },
+    features::auth::model::{
+        ApiScope, AuthUser, LoginResponse, Principal, RecoveryCodesResponse,
+        TwoFactorEnrollmentResponse, UpdateProfileRequest,
+    },
+    features::organizations::model::TenantContext,
+    features::user_management::infrastructure::UserRepository,
+    shared::config::Config,
+    shared::error::AppError,
+    shared::mailer::{EmailTemplates, Mailer},
+    shared::security::{generate_token, hash_password, hash_token, verify_password_if_set},
+    entities::user::User,
+};
+
+#[derive(Debug, Clone)]
+pub struct AuthSettings {
+    /// Base URL of the frontend that handles the links sent by email
+    pub app_base_url: String,
+    pub verification_ttl: Duration,
+    pub password_reset_ttl: Duration,
+    pub session_ttl: Duration,
+    pub two_factor_challenge_ttl: Duration,
+    pub step_up_max_age: Duration,
+    pub admin_requires_step_up: bool,
+    pub totp_issuer: String,
+}
+
+impl AuthSettings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            app_base_url: config.app_base_url.trim_end_matches('/').to_string(),
+            verification_ttl: Duration::minutes(config.mail.verification_ttl_minutes),
+            password_reset_ttl: Duration::minutes(config.mail.password_reset_ttl_minutes),
+            session_ttl: Duration::minutes(config.auth.session_ttl_minutes),
+            two_factor_challenge_ttl: Duration::minutes(config.auth.two_factor_challenge_ttl_minutes),
+            step_up_max_age: Duration::minutes(config.auth.step_up_max_age_minutes),
+            admin_requires_step_up: config.auth.admin_requires_step_up,
+            totp_issuer: config.auth.totp_issuer.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthService {
+    users: Arc<dyn UserRepository>,
+    tokens: Arc<dyn TokenRepository>,
+    sessions: Arc<dyn SessionRepository>,
+    two_factor: Arc<dyn TwoFactorRepository>,
+    mailer: Arc<dyn Mailer>,
+    templates: Arc<EmailTemplates>,
+    settings: AuthSettings,
+}
+
+impl AuthService {
+    pub fn new(
+        users: Arc<dyn UserRepository>,
+        tokens: Arc<dyn TokenRepository>,
+        sessions: Arc<dyn SessionRepository>,
+        two_factor: Arc<dyn TwoFactorRepository>,
+        mailer: Arc<dyn Mailer>,
+        templates: EmailTemplates,
+        settings: AuthSettings,
+    ) -> Self {
+        Self {
+            users,
+            tokens,
+            sessions,
+            two_factor,
+            mailer,
+            templates: Arc::new(templates),
+            settings,
+        }
+    }
+
+    /// Sends a verification link. Unknown or already verified addresses are
+    /// ignored silently so the endpoint can't be used to probe for accounts.
+    pub async fn request_email_verification(&self, email: &str) -> Result<(), AppError> {
+        let Some(user) = self.find_user_by_email(email).await? else {
+            return Ok(());
+        };
+        if user.email_verified {
+            return Ok(());
+        }
+
+        let ttl = self.settings.verification_ttl;
+        let token = self
+            .issue_token(&user, TokenPurpose::EmailVerification, ttl)
+            .await?;
+        let link = format!("{}/verify-email?token={}", self.settings.app_base_url, token);
+        let ttl_minutes = ttl.num_minutes().to_string();
+
+        let email = self.templates.verify_email.render(
+            &user.email,
+            &[
+                ("name", &user.name),
+                ("link", &link),
+                ("token", &token),
+                ("ttl_minutes", &ttl_minutes),
+            ],
+        );
+        self.mailer.send(email).await
+    }
+
+    pub async fn confirm_email_verification(&self, token: &str) -> Result<User, AppError> {
+        let user_id = self
+            .consume_token(TokenPurpose::EmailVerification, token)
+            .await?;
+
+        self.users
+            .mark_email_verified(user_id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Sends a password reset link, with the same silent behaviour for unknown
+    /// addresses as `request_email_verification`
+    pub async fn request_password_reset(&self, email: &str) -> Result<(), AppError> {
+        let Some(user) = self.find_user_by_email(email).await? else {
+            return Ok(());
+        };
+
+        let ttl = self.settings.password_reset_ttl;
+        let token = self
+            .issue_token(&user, TokenPurpose::PasswordReset, ttl)
+            .await?;
+        let link = format!("{}/reset-password?token={}", self.settings.app_base_url, token);
+        let ttl_minutes = ttl.num_minutes().to_string();
+
+        let email = self.templates.password_reset.render(
+            &user.email,
+            &[
+                ("name", &user.name),
+                ("link", &link),
+                ("token", &token),
+                ("ttl_minutes", &ttl_minutes),
+            ],
+        );
+        self.mailer.send(email).await
+    }
+
+    pub async fn confirm_password_reset(
+        &self,
+        token: &str,
+        new_password: &str,
+    ) -> Result<(), AppError> {
+        let user_id = self.consume_token(TokenPurpose::PasswordReset, token).await?;
+        let password_hash = hash_password(new_password)?;
+
+        self.users
+            .set_password_hash(user_id, password_hash)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        // Sign out everywhere, the old password may have been compromised
+        self.sessions
+            .delete_for_user(user_id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    async fn find_user_by_email(&self, email: &str) -> Result<Option<User>, AppError> {
+        self.users
+            .find_by_email(email)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Checks email and password. Accounts with TOTP enabled get a short-lived
+    /// challenge instead of a session, to be completed with `login_two_factor`.
+    pub async fn login(&self, email: &str, password: &str) -> Result<LoginResponse, AppError> {
+        let user = self.find_user_by_email(email).await?;
+        let password_hash = user.as_ref().and_then(|user| user.password_hash.as_deref());
+        let valid = verify_password_if_set(password, password_hash);
+        let user = user
+            .filter(|_| valid)
+            .ok_or_else(|| AppError::Unauthorized("Invalid email or password".to_string()))?;
+
+        self.start_session(&user).await
+    }
+
+    /// Finishes a login whose first factor was already checked, e.g. by an
+    /// identity provider. Issues a 2FA challenge when the account requires one.
+    pub async fn start_session(&self, user: &User) -> Result<LoginResponse, AppError> {
+        if user.two_factor_enabled {
+            let challenge_token = self
+                .issue_token(
+                    user,
+                    TokenPurpose::TwoFactorChallenge,
+                    self.settings.two_factor_challenge_ttl,
+                )
+                .await?;
+
+            return Ok(LoginResponse {
+                token: None,
+                expires_at: None,
+                two_factor_required: true,
+                challenge_token: Some(challenge_token),
+            });
+        }
+
+        self.create_session(user, false).await
+    }
+
+    pub async fn login_two_factor(
+        &self,
+        challenge_token: &str,
+        code: &str,
+    ) -> Result<LoginResponse, AppError> {
+        let user_id = self
+            .tokens
+            .consume(TokenPurpose::TwoFactorChallenge, &hash_token(challenge_token))
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or_else(|| AppError::Unauthorized("Invalid or expired challenge".to_string()))?;
+        let user = self.load_user(user_id).await?;
+
+        if !self.verify_second_factor(&user, code).await? {
+            return Err(AppError::Unauthorized("Invalid two-factor code".to_string()));
+        }
+
+        self.create_session(&user, true).await
+    }
+
+    /// Resolves a session token to the current user
+    pub async fn authenticate(&self, token: &str) -> Result<AuthUser, AppError> {
+        let session = self
+            .sessions
+            .find_active(&hash_token(token))
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or_else(|| AppError::Unauthorized("Invalid or expired session".to_string()))?;
+        let user = self.load_user(session.user_id).await?;
+
+        Ok(AuthUser {
+            user,
+            session_id: session.id,
+            second_factor_at: session.second_factor_at,
+        })
+    }
+
+    /// The user behind work done on their behalf outside a request, e.g.
+    /// batch jobs. There is no session, `session_id` is nil.
+    pub async fn principal(&self, user_id: uuid::Uuid) -> Result<AuthUser, AppError> {
+        Ok(AuthUser {
+            user: self.load_user(user_id).await?,
+            session_id: uuid::Uuid::nil(),
+            second_factor_at: None,
+        })
+    }
+
+    pub async fn logout(&self, auth: &AuthUser) -> Result<(), AppError> {
+        self.sessions
+            .delete(auth.session_id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Updates the user's own name and email. Accounts with two-factor
+    /// authentication need a recent second factor to change the address,
+    /// which password resets are sent to.
+    pub async fn update_profile(&self, auth: &AuthUser, input: UpdateProfileRequest) -> Result<User, AppError> {
+        let email = input.email.unwrap_or_else(|| auth.user.email.clone());
+        if !email.eq_ignore_ascii_case(&auth.user.email) && auth.user.two_factor_enabled && !self.recent_second_factor(auth) {
+            return Err(AppError::Forbidden(
+                "Recent two-factor authentication required".to_string(),
+            ));
+        }
+
+        let name = input.name.unwrap_or_else(|| auth.user.name.clone());
+        self.users
+            .update_account(auth.user.id, name, email)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Starts TOTP enrollment. The secret only becomes active after
+    /// `confirm_two_factor` succeeds with a first code.
+    pub async fn enroll_two_factor(
+        &self,
+        auth: &AuthUser,
+    ) -> Result<TwoFactorEnrollmentResponse, AppError> {
+        if auth.user.two_factor_enabled {
+            return Err(AppError::Validation(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+
+        let secret = generate_secret();
+        let totp = build_totp(&secret, &self.settings.totp_issuer, &auth.user.email)?;
+
+        self.two_factor
+            .set_pending_secret(auth.user.id, secret.clone())
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(TwoFactorEnrollmentResponse {
+            secret,
+            provisioning_uri: totp.get_url(),
+        })
+    }
+
+    pub async fn confirm_two_factor(
+        &self,
+        auth: &AuthUser,
+        code: &str,
+    ) -> Result<RecoveryCodesResponse, AppError> {
+        if auth.user.two_factor_enabled {
+            return Err(AppError::Validation(
+                "Two-factor authentication is already enabled".to_string(),
+            ));
+        }
+        let secret = auth.user.totp_secret.as_deref().ok_or_else(|| {
+            AppError::Validation("Two-factor enrollment has not been started".to_string())
+        })?;
+
+        let totp = build_totp(secret, &self.settings.totp_issuer, &auth.user.email)?;
+        let accepted = match check_totp(&totp, code) {
+            Some(step) => self.use_totp_step(auth.user.id, step).await?,
+            None => false,
+        };
+        if !accepted {
+            return Err(AppError::Validation("Invalid two-factor code".to_string()));
+        }
+
+        let recovery_codes = generate_recovery_codes();
+        self.two_factor
+            .enable(auth.user.id, hash_recovery_codes(&recovery_codes))
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        self.mark_second_factor(auth).await?;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    pub async fn disable_two_factor(&self, auth: &AuthUser, code: &str) -> Result<(), AppError> {
+        self.require_second_factor_code(auth, code).await?;
+
+        self.two_factor
+            .disable(auth.user.id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Replaces all recovery codes, invalidating the previous set
+    pub async fn regenerate_recovery_codes(
+        &self,
+        auth: &AuthUser,
+        code: &str,
+    ) -> Result<RecoveryCodesResponse, AppError> {
+        self.require_second_factor_code(auth, code).await?;
+
+        let recovery_codes = generate_recovery_codes();
+        self.two_factor
+            .replace_recovery_codes(auth.user.id, hash_recovery_codes(&recovery_codes))
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    /// Re-checks the second factor on an existing session so it counts as recent
+    pub async fn step_up(&self, auth: &AuthUser, code: &str) -> Result<(), AppError> {
+        self.require_second_factor_code(auth, code).await?;
+        self.mark_second_factor(auth).await
+    }
+
+    /// Guards admin operations within an organization: a user session with the
+    /// owner or admin role and, when step-up is enabled, a second factor passed
+    /// within `step_up_max_age`.
+    pub fn authorize_tenant_admin(&self, tenant: &TenantContext) -> Result<(), AppError> {
+        let auth = tenant.require_user()?;
+        tenant.require_manager()?;
+
+        if self.settings.admin_requires_step_up && !self.recent_second_factor(auth) {
+            return Err(AppError::Forbidden(
+                "Recent two-factor authentication required".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the session passed a second factor within `step_up_max_age`
+    fn recent_second_factor(&self, auth: &AuthUser) -> bool {
+        auth.second_factor_at
+            .is_some_and(|at| Utc::now() - at <= self.settings.step_up_max_age)
+    }
+
+    /// Admin operations that services may also perform: users go through
+    /// `authorize_tenant_admin`, API keys need `scope`
+    pub fn authorize_tenant_admin_or_scope(
+        &self,
+        tenant: &TenantContext,
+        scope: ApiScope,
+    ) -> Result<(), AppError> {
+        match &tenant.principal {
+            Principal::User(_) => self.authorize_tenant_admin(tenant),
+            Principal::ApiKey(_) => tenant.require_scope(scope),
+        }
+    }
+
+    async fn require_second_factor_code(&self, auth: &AuthUser, code: &str) -> Result<(), AppError> {
+        if !auth.user.two_factor_enabled {
+            return Err(AppError::Validation(
+                "Two-factor authentication is not enabled".to_string(),
+            ));
+        }
+        if !self.verify_second_factor(&auth.user, code).await? {
+            return Err(AppError::Unauthorized("Invalid two-factor code".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Accepts a current TOTP code that wasn't used before or consumes an
+    /// unused recovery code
+    async fn verify_second_factor(&self, user: &User, code: &str) -> Result<bool, AppError> {
+        let Some(secret) = user.totp_secret.as_deref().filter(|_| user.two_factor_enabled) else {
+            return Ok(false);
+        };
+
+        let totp = build_totp(secret, &self.settings.totp_issuer, &user.email)?;
+        if let Some(step) = check_totp(&totp, code) {
+            return self.use_totp_step(user.id, step).await;
+        }
+
+        self.two_factor
+            .consume_recovery_code(user.id, &hash_token(&normalize_recovery_code(code)))
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    async fn use_totp_step(&self, user_id: uuid::Uuid, step: u64) -> Result<bool, AppError> {
+        self.two_factor
+            .use_totp_step(user_id, step as i64)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    async fn mark_second_factor(&self, auth: &AuthUser) -> Result<(), AppError> {
+        self.sessions
+            .mark_second_factor(auth.session_id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn create_session(&self, user: &User, second_factor: bool) -> Result<LoginResponse, AppError> {
+        let token = generate_token();
+        let now = Utc::now();
+        let session = self
+            .sessions
+            .create(
+                user.id,
+                hash_token(&token),
+                second_factor.then_some(now),
+                now + self.settings.session_ttl,
+            )
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(LoginResponse {
+            token: Some(token),
+            expires_at: Some(session.expires_at),
+            two_factor_required: false,
+            challenge_token: None,
+        })
+    }
+
+    async fn load_user(&self, id: uuid::Uuid) -> Result<User, AppError> {
+        self.users
+            .find_by_id(id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or_else(|| AppError::Unauthorized("Account no longer exists".to_string()))
+    }
+
+    /// Creates a new token and revokes any previous one with the same purpose.
+    /// Returns the plain token, which is only ever sent to the user.
+    async fn issue_token(
+        &self,
+        user: &User,
+        purpose: TokenPurpose,
+        ttl: Duration,
+    ) -> Result<String, AppError> {
+        let token = generate_token();
+
+        self.tokens
+            .revoke_all(user.id, purpose)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        self.tokens
+            .create(user.id, purpose, hash_token(&token), Utc::now() + ttl)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(token)
+    }
+
+    async fn consume_token(&self, purpose: TokenPurpose, token: &str) -> Result<uuid::Uuid, AppError> {
+        self.tokens
+            .consume(purpose, &hash_token(token))
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or_else(|| AppError::Validation("Invalid or expired token".to_string()))
+    }
+}
+
+fn hash_recovery_codes(codes: &[String]) -> Vec<String> {
+    codes
+        .iter()
+        .map(|code| hash_token(&normalize_recovery_code(code)))
+        .collect()
+}
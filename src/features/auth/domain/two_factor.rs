@@ -0,0 +1,72 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::{seq::SliceRandom, RngCore};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::shared::error::AppError;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Alphabet without look-alike characters (0/o, 1/l/i)
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// Random 160-bit secret, base32 encoded as expected by authenticator apps
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    match Secret::Raw(bytes.to_vec()).to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => unreachable!("to_encoded always returns an encoded secret"),
+    }
+}
+
+/// Standard 6 digit / 30 second SHA-1 TOTP, accepting one step of clock skew
+pub fn build_totp(secret: &str, issuer: &str, account_name: &str) -> Result<TOTP, AppError> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid TOTP secret: {:?}", e)))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        bytes,
+        Some(issuer.to_string()),
+        account_name.to_string(),
+    )
+    .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid TOTP parameters: {}", e)))
+}
+
+/// Time step the code belongs to, within the accepted skew. Callers store the
+/// step so the same code can't be used twice.
+pub fn check_totp(totp: &TOTP, code: &str) -> Option<u64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let current = now / totp.step;
+    let exact = TOTP { skew: 0, ..totp.clone() };
+
+    (current.saturating_sub(totp.skew as u64)..=current + totp.skew as u64)
+        .find(|step| exact.check(code.trim(), step * totp.step))
+}
+
+/// Codes look like `k7m2p-xq9ht`
+pub fn generate_recovery_codes() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let chars: String = (0..10)
+                .map(|_| *RECOVERY_CODE_ALPHABET.choose(&mut rng).unwrap() as char)
+                .collect();
+            format!("{}-{}", &chars[..5], &chars[5..])
+        })
+        .collect()
+}
+
+/// Lowercases and strips separators so `K7M2P XQ9HT` matches `k7m2p-xq9ht`
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
@@ -0,0 +1,133 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub organization_id: Option<Uuid>,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_by: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewApiKey {
+    pub organization_id: Uuid,
+    pub name: String,
+    pub key_prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub created_by: Uuid,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Columns of `ApiKey`, everything except the hash
+const API_KEY_COLUMNS: &str =
+    "id, organization_id, name, key_prefix, scopes, created_by, expires_at, last_used_at, revoked_at, created_at";
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn create(&self, key: NewApiKey) -> Result<ApiKey, sqlx::Error>;
+    async fn list(&self, organization_id: Uuid) -> Result<Vec<ApiKey>, sqlx::Error>;
+    /// Returns false when the key doesn't exist or was already revoked
+    async fn revoke(&self, organization_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error>;
+    /// Finds a usable key by hash and records the use
+    async fn touch(&self, key_hash: &str) -> Result<Option<ApiKey>, sqlx::Error>;
+    /// A key that is neither revoked nor expired
+    async fn find_active(&self, id: Uuid) -> Result<Option<ApiKey>, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresApiKeyRepository {
+    pool: PgPool,
+}
+
+impl PostgresApiKeyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for PostgresApiKeyRepository {
+    async fn create(&self, key: NewApiKey) -> Result<ApiKey, sqlx::Error> {
+        sqlx::query_as::<_, ApiKey>(&format!(
+            r#"
+            INSERT INTO api_keys (organization_id, name, key_prefix, key_hash, scopes, created_by, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING {}
+            "#,
+            API_KEY_COLUMNS
+        ))
+        .bind(key.organization_id)
+        .bind(key.name)
+        .bind(key.key_prefix)
+        .bind(key.key_hash)
+        .bind(key.scopes)
+        .bind(key.created_by)
+        .bind(key.expires_at)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn list(&self, organization_id: Uuid) -> Result<Vec<ApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, ApiKey>(&format!(
+            "SELECT {} FROM api_keys WHERE organization_id = $1 ORDER BY created_at DESC",
+            API_KEY_COLUMNS
+        ))
+        .bind(organization_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn revoke(&self, organization_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND organization_id = $2 AND revoked_at IS NULL",
+        )
+        .bind(id)
+        .bind(organization_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn touch(&self, key_hash: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, ApiKey>(&format!(
+            r#"
+            UPDATE api_keys
+            SET last_used_at = NOW()
+            WHERE key_hash = $1
+              AND revoked_at IS NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+            RETURNING {}
+            "#,
+            API_KEY_COLUMNS
+        ))
+        .bind(key_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn find_active(&self, id: Uuid) -> Result<Option<ApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, ApiKey>(&format!(
+            r#"
+            SELECT {} FROM api_keys
+            WHERE id = $1
+              AND revoked_at IS NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+            "#,
+            API_KEY_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+}
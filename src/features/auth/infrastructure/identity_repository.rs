@@ -0,0 +1,121 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// An authorization request waiting for the provider callback
+#[derive(Debug, Clone, FromRow)]
+pub struct OidcLoginState {
+    pub provider: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+#[async_trait]
+pub trait IdentityRepository: Send + Sync {
+    async fn find_user_id(&self, provider: &str, subject: &str) -> Result<Option<Uuid>, sqlx::Error>;
+    /// Links an identity to a user, or refreshes `email`/`last_login_at` if already linked
+    async fn upsert(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+        email: Option<String>,
+    ) -> Result<(), sqlx::Error>;
+    async fn save_login_state(
+        &self,
+        state_hash: String,
+        state: OidcLoginState,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+    /// Removes and returns an unexpired login state, so each one is usable once
+    async fn take_login_state(&self, state_hash: &str) -> Result<Option<OidcLoginState>, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresIdentityRepository {
+    pool: PgPool,
+}
+
+impl PostgresIdentityRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdentityRepository for PostgresIdentityRepository {
+    async fn find_user_id(&self, provider: &str, subject: &str) -> Result<Option<Uuid>, sqlx::Error> {
+        sqlx::query_scalar::<_, Uuid>(
+            "SELECT user_id FROM user_identities WHERE provider = $1 AND subject = $2",
+        )
+        .bind(provider)
+        .bind(subject)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn upsert(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+        email: Option<String>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_identities (user_id, provider, subject, email)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (provider, subject)
+            DO UPDATE SET email = EXCLUDED.email, last_login_at = NOW()
+            "#,
+        )
+        .bind(user_id)
+        .bind(provider)
+        .bind(subject)
+        .bind(email)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn save_login_state(
+        &self,
+        state_hash: String,
+        state: OidcLoginState,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        // Opportunistically clean up abandoned logins
+        sqlx::query("DELETE FROM oidc_login_states WHERE expires_at < NOW()")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO oidc_login_states (state_hash, provider, nonce, code_verifier, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(state_hash)
+        .bind(state.provider)
+        .bind(state.nonce)
+        .bind(state.code_verifier)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn take_login_state(&self, state_hash: &str) -> Result<Option<OidcLoginState>, sqlx::Error> {
+        sqlx::query_as::<_, OidcLoginState>(
+            r#"
+            DELETE FROM oidc_login_states
+            WHERE state_hash = $1 AND expires_at > NOW()
+            RETURNING provider, nonce, code_verifier
+            "#,
+        )
+        .bind(state_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+}
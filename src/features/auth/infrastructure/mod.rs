@@ -0,0 +1,13 @@
+mod api_key_repository;
+mod identity_repository;
+mod oidc_client;
+mod repository;
+mod session_repository;
+mod two_factor_repository;
+
+pub use api_key_repository::{ApiKey, ApiKeyRepository, NewApiKey, PostgresApiKeyRepository};
+pub use identity_repository::{IdentityRepository, OidcLoginState, PostgresIdentityRepository};
+pub use oidc_client::{IdTokenClaims, OidcClient};
+pub use repository::{TokenRepository, PostgresTokenRepository, TokenPurpose};
+pub use session_repository::{SessionRepository, PostgresSessionRepository};
+pub use two_factor_repository::{TwoFactorRepository, PostgresTwoFactorRepository};
@@ -0,0 +1,233 @@
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use reqwest::Client;
+use serde::{Deserialize, Deserializer};
+use tokio::sync::RwLock;
+use url::Url;
+
+use crate::shared::config::OidcProviderConfig;
+use crate::shared::error::AppError;
+
+/// Subset of the `.well-known/openid-configuration` document we rely on
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default, deserialize_with = "lenient_bool")]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+    pub nonce: Option<String>,
+}
+
+/// Some providers send `email_verified` as the string "true"
+fn lenient_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Bool(value) => value,
+        serde_json::Value::String(value) => value.eq_ignore_ascii_case("true"),
+        _ => false,
+    })
+}
+
+/// Authorization-code client for one identity provider. Discovery metadata and
+/// the JWKS are fetched lazily and cached; keys are refetched on an unknown `kid`
+/// to follow key rotation.
+pub struct OidcClient {
+    config: OidcProviderConfig,
+    http: Client,
+    metadata: RwLock<Option<ProviderMetadata>>,
+    jwks: RwLock<Option<JwkSet>>,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcProviderConfig, http: Client) -> Self {
+        Self {
+            config,
+            http,
+            metadata: RwLock::new(None),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    pub fn config(&self) -> &OidcProviderConfig {
+        &self.config
+    }
+
+    pub async fn authorization_url(
+        &self,
+        redirect_uri: &str,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String, AppError> {
+        let metadata = self.metadata().await?;
+
+        let mut url = Url::parse(&metadata.authorization_endpoint).map_err(|e| {
+            AppError::ExternalService(format!("Invalid authorization endpoint: {}", e))
+        })?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", &self.config.scopes)
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(url.into())
+    }
+
+    /// Exchanges the authorization code and returns the validated ID token claims
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        redirect_uri: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, AppError> {
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", code_verifier),
+        ];
+        let mut request = self.http.post(&metadata.token_endpoint);
+        match &self.config.client_secret {
+            Some(secret) => request = request.basic_auth(&self.config.client_id, Some(secret)),
+            None => form.push(("client_id", &self.config.client_id)),
+        }
+
+        let response = request
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to call token endpoint: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(AppError::ExternalService(format!(
+                "Token endpoint error ({}): {}",
+                status, error_text
+            )));
+        }
+
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to parse token response: {}", e)))?;
+
+        let claims = self.validate_id_token(&tokens.id_token, &metadata).await?;
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(AppError::Unauthorized("ID token nonce mismatch".to_string()));
+        }
+
+        Ok(claims)
+    }
+
+    async fn validate_id_token(
+        &self,
+        id_token: &str,
+        metadata: &ProviderMetadata,
+    ) -> Result<IdTokenClaims, AppError> {
+        let header = decode_header(id_token)
+            .map_err(|e| AppError::Unauthorized(format!("Malformed ID token: {}", e)))?;
+
+        // Only asymmetric algorithms, the token must be signed with a key from the JWKS
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(AppError::Unauthorized(format!(
+                "Unsupported ID token algorithm: {:?}",
+                header.alg
+            )));
+        }
+
+        let key = self.decoding_key(header.kid.as_deref(), &metadata.jwks_uri).await?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+
+        decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| AppError::Unauthorized(format!("Invalid ID token: {}", e)))
+    }
+
+    async fn decoding_key(&self, kid: Option<&str>, jwks_uri: &str) -> Result<DecodingKey, AppError> {
+        let find = |jwks: &JwkSet| match kid {
+            Some(kid) => jwks.find(kid).cloned(),
+            None => jwks.keys.first().cloned(),
+        };
+
+        if let Some(jwk) = self.jwks.read().await.as_ref().and_then(find) {
+            return DecodingKey::from_jwk(&jwk)
+                .map_err(|e| AppError::ExternalService(format!("Invalid JWK: {}", e)));
+        }
+
+        let jwks: JwkSet = self.fetch_json(jwks_uri).await?;
+        let jwk = find(&jwks)
+            .ok_or_else(|| AppError::Unauthorized("ID token signed with an unknown key".to_string()))?;
+        *self.jwks.write().await = Some(jwks);
+
+        DecodingKey::from_jwk(&jwk)
+            .map_err(|e| AppError::ExternalService(format!("Invalid JWK: {}", e)))
+    }
+
+    async fn metadata(&self) -> Result<ProviderMetadata, AppError> {
+        if let Some(metadata) = self.metadata.read().await.as_ref() {
+            return Ok(metadata.clone());
+        }
+
+        let discovery_url = format!(
+            "{}/.well-known/openid-configuration",
+            self.config.issuer_url.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = self.fetch_json(&discovery_url).await?;
+        // The document must describe the configured provider, or its keys can't be trusted
+        if metadata.issuer.trim_end_matches('/') != self.config.issuer_url.trim_end_matches('/') {
+            return Err(AppError::ExternalService(format!(
+                "Discovery document of {} names issuer {}",
+                self.config.issuer_url, metadata.issuer
+            )));
+        }
+        *self.metadata.write().await = Some(metadata.clone());
+
+        Ok(metadata)
+    }
+
+    async fn fetch_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, AppError> {
+        let response = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to fetch {}: {}", url, e)))?;
+
+        if !response.status().is_success() {
+            return Err(AppError::ExternalService(format!(
+                "Failed to fetch {} ({})",
+                url,
+                response.status()
+            )));
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to parse {}: {}", url, e)))
+    }
+}
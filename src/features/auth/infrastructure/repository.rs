@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    EmailVerification,
+    PasswordReset,
+    /// Issued after a correct password when the account has TOTP enabled
+    TwoFactorChallenge,
+}
+
+impl TokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::EmailVerification => "email_verification",
+            TokenPurpose::PasswordReset => "password_reset",
+            TokenPurpose::TwoFactorChallenge => "two_factor_challenge",
+        }
+    }
+}
+
+/// Storage for single-use, expiring tokens. Only token hashes are persisted.
+#[async_trait]
+pub trait TokenRepository: Send + Sync {
+    async fn create(
+        &self,
+        user_id: Uuid,
+        purpose: TokenPurpose,
+        token_hash: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+    /// Marks a valid token as used and returns its owner
+    async fn consume(
+        &self,
+        purpose: TokenPurpose,
+        token_hash: &str,
+    ) -> Result<Option<Uuid>, sqlx::Error>;
+    async fn revoke_all(&self, user_id: Uuid, purpose: TokenPurpose) -> Result<(), sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresTokenRepository {
+    pool: PgPool,
+}
+
+impl PostgresTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl TokenRepository for PostgresTokenRepository {
+    async fn create(
+        &self,
+        user_id: Uuid,
+        purpose: TokenPurpose,
+        token_hash: String,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .bind(token_hash)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn consume(
+        &self,
+        purpose: TokenPurpose,
+        token_hash: &str,
+    ) -> Result<Option<Uuid>, sqlx::Error> {
+        // A single UPDATE keeps consumption atomic, so a token can't be used twice
+        sqlx::query_scalar::<_, Uuid>(
+            r#"
+            UPDATE user_tokens
+            SET consumed_at = NOW()
+            WHERE token_hash = $1
+              AND purpose = $2
+              AND consumed_at IS NULL
+              AND expires_at > NOW()
+            RETURNING user_id
+            "#,
+        )
+        .bind(token_hash)
+        .bind(purpose.as_str())
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn revoke_all(&self, user_id: Uuid, purpose: TokenPurpose) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE user_tokens SET consumed_at = NOW() WHERE user_id = $1 AND purpose = $2 AND consumed_at IS NULL",
+        )
+        .bind(user_id)
+        .bind(purpose.as_str())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub second_factor_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    async fn create(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        second_factor_at: Option<DateTime<Utc>>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Session, sqlx::Error>;
+    async fn find_active(&self, token_hash: &str) -> Result<Option<Session>, sqlx::Error>;
+    async fn mark_second_factor(&self, id: Uuid) -> Result<Session, sqlx::Error>;
+    async fn delete(&self, id: Uuid) -> Result<(), sqlx::Error>;
+    async fn delete_for_user(&self, user_id: Uuid) -> Result<(), sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresSessionRepository {
+    pool: PgPool,
+}
+
+impl PostgresSessionRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SessionRepository for PostgresSessionRepository {
+    async fn create(
+        &self,
+        user_id: Uuid,
+        token_hash: String,
+        second_factor_at: Option<DateTime<Utc>>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Session, sqlx::Error> {
+        sqlx::query_as::<_, Session>(
+            r#"
+            INSERT INTO sessions (user_id, token_hash, second_factor_at, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, user_id, second_factor_at, expires_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(token_hash)
+        .bind(second_factor_at)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn find_active(&self, token_hash: &str) -> Result<Option<Session>, sqlx::Error> {
+        sqlx::query_as::<_, Session>(
+            "SELECT id, user_id, second_factor_at, expires_at FROM sessions WHERE token_hash = $1 AND expires_at > NOW()",
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn mark_second_factor(&self, id: Uuid) -> Result<Session, sqlx::Error> {
+        sqlx::query_as::<_, Session>(
+            "UPDATE sessions SET second_factor_at = NOW() WHERE id = $1 RETURNING id, user_id, second_factor_at, expires_at",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn delete(&self, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM sessions WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_for_user(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// TOTP secrets live on the `users` row, recovery codes in `user_recovery_codes`
+#[async_trait]
+pub trait TwoFactorRepository: Send + Sync {
+    /// Stores a new secret and clears any previous enrollment
+    async fn set_pending_secret(&self, user_id: Uuid, secret: String) -> Result<(), sqlx::Error>;
+    /// Enables TOTP and replaces all recovery codes in one transaction
+    async fn enable(&self, user_id: Uuid, recovery_code_hashes: Vec<String>) -> Result<(), sqlx::Error>;
+    async fn disable(&self, user_id: Uuid) -> Result<(), sqlx::Error>;
+    async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code_hashes: Vec<String>,
+    ) -> Result<(), sqlx::Error>;
+    /// Records the time step of an accepted TOTP code, returns false if that
+    /// step or a later one was already used
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, sqlx::Error>;
+    /// Marks an unused recovery code as used, returns false if none matched
+    async fn consume_recovery_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresTwoFactorRepository {
+    pool: PgPool,
+}
+
+impl PostgresTwoFactorRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+async fn insert_recovery_codes(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: Uuid,
+    code_hashes: Vec<String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO user_recovery_codes (user_id, code_hash) SELECT $1, UNNEST($2::VARCHAR[])",
+    )
+    .bind(user_id)
+    .bind(code_hashes)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl TwoFactorRepository for PostgresTwoFactorRepository {
+    async fn set_pending_secret(&self, user_id: Uuid, secret: String) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE users SET totp_secret = $1, totp_enabled_at = NULL, totp_last_step = NULL, updated_at = NOW() WHERE id = $2",
+        )
+        .bind(secret)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn enable(&self, user_id: Uuid, recovery_code_hashes: Vec<String>) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE users SET totp_enabled_at = NOW(), updated_at = NOW() WHERE id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        insert_recovery_codes(&mut tx, user_id, recovery_code_hashes).await?;
+
+        tx.commit().await
+    }
+
+    async fn disable(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE users SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL, updated_at = NOW() WHERE id = $1",
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await
+    }
+
+    async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code_hashes: Vec<String>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        insert_recovery_codes(&mut tx, user_id, code_hashes).await?;
+        tx.commit().await
+    }
+
+    async fn use_totp_step(&self, user_id: Uuid, step: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE users SET totp_last_step = $2 WHERE id = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)",
+        )
+        .bind(user_id)
+        .bind(step)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn consume_recovery_code(&self, user_id: Uuid, code_hash: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE user_recovery_codes SET used_at = NOW() WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL",
+        )
+        .bind(user_id)
+        .bind(code_hash)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,4 @@
+pub mod api;
+pub mod model;
+pub mod domain;
+pub mod infrastructure;
@@ -0,0 +1,13 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::entities::user::User;
+
+/// The user behind an authenticated request
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user: User,
+    pub session_id: Uuid,
+    /// When this session last passed a TOTP or recovery code check
+    pub second_factor_at: Option<DateTime<Utc>>,
+}
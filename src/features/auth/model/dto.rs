@@ -0,0 +1,140 @@
+use async_graphql::{InputObject, SimpleObject};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::ApiScope;
+
+#[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
+pub struct EmailVerificationRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
+pub struct ConfirmEmailVerificationRequest {
+    #[validate(length(min = 1, message = "Token cannot be empty"))]
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
+pub struct PasswordResetRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
+pub struct ConfirmPasswordResetRequest {
+    #[validate(length(min = 1, message = "Token cannot be empty"))]
+    pub token: String,
+    #[validate(length(min = 8, max = 128, message = "Password must be between 8 and 128 characters"))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
+pub struct LoginRequest {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+    #[validate(length(min = 1, message = "Password cannot be empty"))]
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
+pub struct TwoFactorLoginRequest {
+    #[validate(length(min = 1, message = "Challenge token cannot be empty"))]
+    pub challenge_token: String,
+    /// TOTP code or unused recovery code
+    #[validate(length(min = 1, message = "Code cannot be empty"))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
+pub struct TwoFactorCodeRequest {
+    /// TOTP code or unused recovery code
+    #[validate(length(min = 1, message = "Code cannot be empty"))]
+    pub code: String,
+}
+
+/// Changes to the current user's own account
+#[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
+pub struct UpdateProfileRequest {
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
+    pub name: Option<String>,
+    /// Resets the verification of the address
+    #[validate(email(message = "Invalid email format"))]
+    pub email: Option<String>,
+}
+
+/// Either a session token, or a challenge to complete with `/auth/login/2fa`
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+pub struct LoginResponse {
+    pub token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub two_factor_required: bool,
+    pub challenge_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+pub struct TwoFactorEnrollmentResponse {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// `otpauth://` URI, usually rendered as a QR code
+    pub provisioning_uri: String,
+}
+
+/// Plain recovery codes, only returned once
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, SimpleObject, ToSchema)]
+pub struct OidcProviderResponse {
+    pub name: String,
+    pub display_name: String,
+}
+
+/// Query parameters of the provider redirect
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 255, message = "Name must be between 1 and 255 characters"))]
+    pub name: String,
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    pub scopes: Vec<ApiScope>,
+    /// The key stops working after this moment. Keys without expiry are valid until revoked.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// API key metadata. The key itself is never returned after creation.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKeyResponse {
+    pub id: Uuid,
+    pub organization_id: Option<Uuid>,
+    pub name: String,
+    /// Leading characters of the key, to recognize it
+    pub key_prefix: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_by: Option<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreatedApiKeyResponse {
+    /// The plain key, only shown once. Send it as `Authorization: Bearer <key>`.
+    pub key: String,
+    pub api_key: ApiKeyResponse,
+}
@@ -0,0 +1,7 @@
+mod auth_user;
+mod dto;
+mod principal;
+
+pub use auth_user::AuthUser;
+pub use dto::*;
+pub use principal::{ApiKeyPrincipal, ApiScope, Principal};
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::AuthUser;
+use crate::shared::error::AppError;
+
+/// Permissions an API key can be granted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum ApiScope {
+    #[serde(rename = "users:read")]
+    UsersRead,
+    #[serde(rename = "users:write")]
+    UsersWrite,
+    #[serde(rename = "ai:invoke")]
+    AiInvoke,
+}
+
+impl ApiScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiScope::UsersRead => "users:read",
+            ApiScope::UsersWrite => "users:write",
+            ApiScope::AiInvoke => "ai:invoke",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "users:read" => Some(ApiScope::UsersRead),
+            "users:write" => Some(ApiScope::UsersWrite),
+            "ai:invoke" => Some(ApiScope::AiInvoke),
+            _ => None,
+        }
+    }
+}
+
+/// A service authenticated with an API key
+#[derive(Debug, Clone)]
+pub struct ApiKeyPrincipal {
+    pub key_id: Uuid,
+    pub name: String,
+    /// The organization the key acts in
+    pub organization_id: Option<Uuid>,
+    pub scopes: Vec<ApiScope>,
+}
+
+/// Whoever is behind an authenticated request: a signed-in user or an API key
+#[derive(Debug, Clone)]
+pub enum Principal {
+    User(AuthUser),
+    ApiKey(ApiKeyPrincipal),
+}
+
+impl Principal {
+    /// API keys are limited to their scopes. Users can read and invoke AI,
+    /// `users:write` comes with an owner or admin role in an organization,
+    /// see `TenantContext::require_scope`.
+    pub fn require_scope(&self, scope: ApiScope) -> Result<(), AppError> {
+        match self {
+            Principal::User(_) if scope != ApiScope::UsersWrite => Ok(()),
+            Principal::User(_) => Err(AppError::Forbidden(
+                "Organization owner or admin role required".to_string(),
+            )),
+            Principal::ApiKey(key) if key.scopes.contains(&scope) => Ok(()),
+            Principal::ApiKey(_) => Err(AppError::Forbidden(format!(
+                "API key is missing the {} scope",
+                scope.as_str()
+            ))),
+        }
+    }
+}
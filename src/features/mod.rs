@@ -1,2 +1,4 @@
 pub mod user_management;
 pub mod ai_integration;
+pub mod auth;
+pub mod organizations;
@@ -0,0 +1,42 @@
+use axum::{
+    extract::{FromRequestParts, OptionalFromRequestParts},
+    http::request::Parts,
+};
+
+use crate::{
+    features::auth::model::Principal,
+    features::organizations::model::{TenantContext, TenantSelector},
+    shared::error::AppError,
+    app::state::AppState,
+};
+
+/// Requires an authenticated caller and resolves the organization it acts on
+impl FromRequestParts<AppState> for TenantContext {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let principal = <Principal as FromRequestParts<AppState>>::from_request_parts(parts, state).await?;
+        let selector = TenantSelector::from_headers(&parts.headers);
+
+        state.organization_service.resolve(principal, &selector).await
+    }
+}
+
+/// Anonymous requests yield `None`. Authenticated requests must resolve a tenant.
+impl OptionalFromRequestParts<AppState> for TenantContext {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        let Some(principal) =
+            <Principal as OptionalFromRequestParts<AppState>>::from_request_parts(parts, state).await?
+        else {
+            return Ok(None);
+        };
+        let selector = TenantSelector::from_headers(&parts.headers);
+
+        state.organization_service.resolve(principal, &selector).await.map(Some)
+    }
+}
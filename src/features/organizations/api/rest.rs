@@ -0,0 +1,236 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    entities::organization::Organization,
+    features::auth::model::{ApiScope, AuthUser, Principal},
+    features::organizations::model::{
+        AddMemberRequest, CreateOrganizationRequest, InvitationResponse, MemberResponse, TenantContext,
+        TenantSelector, UpdateMemberRequest, UserOrganizationResponse,
+    },
+    shared::error::AppError,
+    app::state::AppState,
+};
+
+/// Create an organization, the caller becomes its owner
+#[utoipa::path(
+    post,
+    path = "/organizations",
+    tag = "organizations",
+    request_body = CreateOrganizationRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Organization created", body = Organization),
+        (status = 400, description = "Validation error"),
+        (status = 401, description = "Not authenticated"),
+        (status = 409, description = "Slug is already taken")
+    )
+)]
+pub async fn create_organization(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Json(payload): Json<CreateOrganizationRequest>,
+) -> Result<(StatusCode, Json<Organization>), AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+
+    let organization = state.organization_service.create(&auth, payload).await?;
+    Ok((StatusCode::CREATED, Json(organization)))
+}
+
+/// Organizations the current user belongs to
+#[utoipa::path(
+    get,
+    path = "/organizations",
+    tag = "organizations",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Organizations with the user's role", body = Vec<UserOrganizationResponse>),
+        (status = 401, description = "Not authenticated")
+    )
+)]
+pub async fn list_organizations(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Vec<UserOrganizationResponse>>, AppError> {
+    let organizations = state.organization_service.list_for_user(&auth).await?;
+    Ok(Json(organizations))
+}
+
+/// List members of an organization
+#[utoipa::path(
+    get,
+    path = "/organizations/{id}/members",
+    tag = "organizations",
+    params(
+        ("id" = Uuid, Path, description = "Organization id")
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Members with their roles", body = Vec<MemberResponse>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Not a member of this organization")
+    )
+)]
+pub async fn list_members(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<MemberResponse>>, AppError> {
+    let tenant = tenant(&state, principal, id).await?;
+    tenant.require_scope(ApiScope::UsersRead)?;
+
+    let members = state.organization_service.members(&tenant).await?;
+    Ok(Json(members))
+}
+
+/// Invite an existing account to an organization. It becomes a member once
+/// it accepts the invitation.
+#[utoipa::path(
+    post,
+    path = "/organizations/{id}/members",
+    tag = "organizations",
+    params(
+        ("id" = Uuid, Path, description = "Organization id")
+    ),
+    request_body = AddMemberRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 201, description = "Invitation sent"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Owner or admin role and recent second factor required"),
+        (status = 404, description = "No account with this email"),
+        (status = 409, description = "Already a member or invited")
+    )
+)]
+pub async fn add_member(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<AddMemberRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), AppError> {
+    if let Err(e) = payload.validate() {
+        return Err(AppError::Validation(e.to_string()));
+    }
+    let tenant = tenant(&state, principal, id).await?;
+    state.auth_service.authorize_tenant_admin(&tenant)?;
+
+    state.organization_service.invite_member(&tenant, payload).await?;
+    Ok((StatusCode::CREATED, Json(serde_json::json!({ "message": "Invitation sent" }))))
+}
+
+/// Invitations of the current user that are waiting for an answer
+#[utoipa::path(
+    get,
+    path = "/organizations/invitations",
+    tag = "organizations",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Pending invitations with the offered role", body = Vec<InvitationResponse>),
+        (status = 401, description = "Not authenticated")
+    )
+)]
+pub async fn list_invitations(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Vec<InvitationResponse>>, AppError> {
+    let invitations = state.organization_service.invitations(&auth).await?;
+    Ok(Json(invitations))
+}
+
+/// Accept an invitation and join the organization
+#[utoipa::path(
+    post,
+    path = "/organizations/invitations/{id}/accept",
+    tag = "organizations",
+    params(
+        ("id" = Uuid, Path, description = "Organization id")
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Joined the organization"),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "No invitation to this organization")
+    )
+)]
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let role = state.organization_service.accept_invitation(&auth, id).await?;
+    Ok(Json(serde_json::json!({ "message": "Joined the organization", "role": role })))
+}
+
+/// Decline an invitation
+#[utoipa::path(
+    post,
+    path = "/organizations/invitations/{id}/decline",
+    tag = "organizations",
+    params(
+        ("id" = Uuid, Path, description = "Organization id")
+    ),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Invitation declined"),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "No invitation to this organization")
+    )
+)]
+pub async fn decline_invitation(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    state.organization_service.decline_invitation(&auth, id).await?;
+    Ok(Json(serde_json::json!({ "message": "Invitation declined" })))
+}
+
+/// Change the role of a member
+#[utoipa::path(
+    put,
+    path = "/organizations/{id}/members/{user_id}",
+    tag = "organizations",
+    params(
+        ("id" = Uuid, Path, description = "Organization id"),
+        ("user_id" = Uuid, Path, description = "Member user id")
+    ),
+    request_body = UpdateMemberRequest,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Role updated"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Owner or admin role and recent second factor required, owners to grant, or take away the owner role"),
+        (status = 404, description = "Not a member"),
+        (status = 409, description = "Would leave the organization without an owner")
+    )
+)]
+pub async fn update_member(
+    State(state): State<AppState>,
+    principal: Principal,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateMemberRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let tenant = tenant(&state, principal, id).await?;
+    state.auth_service.authorize_tenant_admin(&tenant)?;
+
+    state
+        .organization_service
+        .set_member_role(&tenant, user_id, payload.role)
+        .await?;
+    Ok(Json(serde_json::json!({ "message": "Role updated" })))
+}
+
+/// These endpoints take the organization from the path instead of the header
+async fn tenant(state: &AppState, principal: Principal, id: Uuid) -> Result<TenantContext, AppError> {
+    state
+        .organization_service
+        .resolve(principal, &TenantSelector::for_organization(id.to_string()))
+        .await
+}
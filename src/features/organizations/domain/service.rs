@@ -0,0 +1,397 @@
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    entities::organization::{OrgRole, Organization},
+    features::auth::model::{AuthUser, Principal},
+    features::organizations::infrastructure::{Member, OrganizationRepository},
+    features::organizations::model::{
+        AddMemberRequest, CreateOrganizationRequest, InvitationResponse, MemberResponse, TenantContext,
+        TenantSelector, UserOrganizationResponse,
+    },
+    features::user_management::infrastructure::UserRepository,
+    shared::error::AppError,
+    shared::mailer::{EmailTemplate, Mailer},
+};
+
+#[derive(Clone)]
+pub struct OrganizationService {
+    organizations: Arc<dyn OrganizationRepository>,
+    users: Arc<dyn UserRepository>,
+    mailer: Arc<dyn Mailer>,
+    invitation_template: Arc<EmailTemplate>,
+    /// Base URL of the frontend, invitation emails link to its `/invitations` page
+    app_base_url: String,
+    tenant_base_domain: Option<String>,
+}
+
+impl OrganizationService {
+    pub fn new(
+        organizations: Arc<dyn OrganizationRepository>,
+        users: Arc<dyn UserRepository>,
+        mailer: Arc<dyn Mailer>,
+        invitation_template: EmailTemplate,
+        app_base_url: &str,
+        tenant_base_domain: Option<String>,
+    ) -> Self {
+        Self {
+            organizations,
+            users,
+            mailer,
+            invitation_template: Arc::new(invitation_template),
+            app_base_url: app_base_url.trim_end_matches('/').to_string(),
+            tenant_base_domain: tenant_base_domain
+                .map(|domain| domain.trim_start_matches('.').to_lowercase()),
+        }
+    }
+
+    /// Creates an organization owned by the current user
+    pub async fn create(
+        &self,
+        auth: &AuthUser,
+        input: CreateOrganizationRequest,
+    ) -> Result<Organization, AppError> {
+        let slug = input.slug.to_lowercase();
+        let valid = slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            && !slug.starts_with('-')
+            && !slug.ends_with('-');
+        if !valid {
+            return Err(AppError::Validation(
+                "Slug may only contain lowercase letters, digits and inner dashes".to_string(),
+            ));
+        }
+
+        let existing = self
+            .organizations
+            .find_by_slug(&slug)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if existing.is_some() {
+            return Err(AppError::Conflict("Slug is already taken".to_string()));
+        }
+
+        self.organizations
+            .create(input.name, slug, auth.user.id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    pub async fn list_for_user(&self, auth: &AuthUser) -> Result<Vec<UserOrganizationResponse>, AppError> {
+        let organizations = self
+            .organizations
+            .list_for_user(auth.user.id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(organizations
+            .into_iter()
+            .map(|membership| UserOrganizationResponse {
+                organization: membership.organization,
+                role: membership.role,
+            })
+            .collect())
+    }
+
+    /// Resolves the tenant of a request. API keys are bound to one
+    /// organization; users pick one with the `X-Organization` header or a
+    /// subdomain, or get their only organization by default.
+    pub async fn resolve(
+        &self,
+        principal: Principal,
+        selector: &TenantSelector,
+    ) -> Result<TenantContext, AppError> {
+        let requested = self.requested_organization(selector).await?;
+
+        match &principal {
+            Principal::ApiKey(key) => {
+                let organization_id = key.organization_id.ok_or_else(|| {
+                    AppError::Forbidden("API key is not bound to an organization".to_string())
+                })?;
+                let organization = match requested {
+                    Some(Some(organization)) if organization.id == organization_id => organization,
+                    Some(_) => {
+                        return Err(AppError::Forbidden(
+                            "API key belongs to another organization".to_string(),
+                        ))
+                    }
+                    None => self
+                        .organizations
+                        .find_by_id(organization_id)
+                        .await
+                        .map_err(|e| AppError::Database(e.to_string()))?
+                        .ok_or(AppError::NotFound)?,
+                };
+
+                Ok(TenantContext {
+                    organization,
+                    principal,
+                    role: None,
+                })
+            }
+            Principal::User(auth) => {
+                let organization = match requested {
+                    Some(Some(organization)) => organization,
+                    Some(None) => return Err(not_a_member()),
+                    None => self.default_organization(auth).await?,
+                };
+
+                let role = self
+                    .organizations
+                    .find_role(organization.id, auth.user.id)
+                    .await
+                    .map_err(|e| AppError::Database(e.to_string()))?;
+                // Global admins can act in every organization
+                let role = match role {
+                    Some(role) => role,
+                    None if auth.user.is_admin() => OrgRole::Owner,
+                    None => return Err(not_a_member()),
+                };
+
+                Ok(TenantContext {
+                    organization,
+                    principal,
+                    role: Some(role),
+                })
+            }
+        }
+    }
+
+    pub async fn members(&self, tenant: &TenantContext) -> Result<Vec<MemberResponse>, AppError> {
+        let members = self
+            .organizations
+            .list_members(tenant.organization_id())
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(members.into_iter().map(to_response).collect())
+    }
+
+    /// Invites an existing account to the organization and lets it know by
+    /// email. The account only becomes a member once it accepts.
+    pub async fn invite_member(
+        &self,
+        tenant: &TenantContext,
+        input: AddMemberRequest,
+    ) -> Result<(), AppError> {
+        let inviter = tenant.require_user()?;
+        self.require_role_grant(tenant, input.role)?;
+
+        let user = self
+            .users
+            .find_by_email(&input.email)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or(AppError::NotFound)?;
+
+        let role = self
+            .organizations
+            .find_role(tenant.organization_id(), user.id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if role.is_some() {
+            return Err(AppError::Conflict("User is already a member".to_string()));
+        }
+
+        let invited = self
+            .organizations
+            .create_invitation(tenant.organization_id(), user.id, input.role, inviter.user.id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if !invited {
+            return Err(AppError::Conflict("User is already invited".to_string()));
+        }
+
+        let link = format!("{}/invitations", self.app_base_url);
+        let role = role_name(input.role);
+        let email = self.invitation_template.render(
+            &user.email,
+            &[
+                ("name", &user.name),
+                ("inviter", &inviter.user.name),
+                ("organization", &tenant.organization.name),
+                ("role", role),
+                ("link", &link),
+            ],
+        );
+        self.mailer.send(email).await
+    }
+
+    /// Pending invitations of the current user
+    pub async fn invitations(&self, auth: &AuthUser) -> Result<Vec<InvitationResponse>, AppError> {
+        let invitations = self
+            .organizations
+            .list_invitations(auth.user.id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(invitations
+            .into_iter()
+            .map(|invitation| InvitationResponse {
+                organization: invitation.organization,
+                role: invitation.role,
+                invited_at: invitation.invited_at,
+            })
+            .collect())
+    }
+
+    /// Joins the organization with the role of the invitation
+    pub async fn accept_invitation(&self, auth: &AuthUser, organization_id: Uuid) -> Result<OrgRole, AppError> {
+        self.organizations
+            .accept_invitation(organization_id, auth.user.id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or(AppError::NotFound)
+    }
+
+    pub async fn decline_invitation(&self, auth: &AuthUser, organization_id: Uuid) -> Result<(), AppError> {
+        let declined = self
+            .organizations
+            .decline_invitation(organization_id, auth.user.id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if !declined {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    pub async fn set_member_role(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        role: OrgRole,
+    ) -> Result<(), AppError> {
+        self.require_role_grant(tenant, role)?;
+        if role != OrgRole::Owner {
+            self.ensure_owner_may_leave(tenant, user_id).await?;
+        }
+
+        let updated = self
+            .organizations
+            .set_role(tenant.organization_id(), user_id, role)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if !updated {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Guards removing or demoting a member: only owners may do that to an
+    /// owner, and an organization must keep at least one
+    pub async fn ensure_owner_may_leave(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+    ) -> Result<(), AppError> {
+        let role = self
+            .organizations
+            .find_role(tenant.organization_id(), user_id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if role != Some(OrgRole::Owner) {
+            return Ok(());
+        }
+        if tenant.role != Some(OrgRole::Owner) {
+            return Err(AppError::Forbidden(
+                "Only owners can remove or demote owners".to_string(),
+            ));
+        }
+
+        let owners = self
+            .organizations
+            .count_owners(tenant.organization_id())
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+        if owners <= 1 {
+            return Err(AppError::Conflict(
+                "The last owner of an organization cannot be removed or demoted".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Only owners can hand out the owner role
+    fn require_role_grant(&self, tenant: &TenantContext, role: OrgRole) -> Result<(), AppError> {
+        if role == OrgRole::Owner && tenant.role != Some(OrgRole::Owner) {
+            return Err(AppError::Forbidden(
+                "Only owners can grant the owner role".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// `None` when nothing was requested, `Some(None)` for an unknown organization
+    async fn requested_organization(
+        &self,
+        selector: &TenantSelector,
+    ) -> Result<Option<Option<Organization>>, AppError> {
+        let requested = selector
+            .organization
+            .clone()
+            .or_else(|| selector.host.as_deref().and_then(|host| self.subdomain(host)));
+        let Some(requested) = requested else {
+            return Ok(None);
+        };
+
+        let organization = match Uuid::parse_str(&requested) {
+            Ok(id) => self.organizations.find_by_id(id).await,
+            Err(_) => self.organizations.find_by_slug(&requested).await,
+        }
+        .map_err(|e| AppError::Database(e.to_string()))?;
+
+        Ok(Some(organization))
+    }
+
+    /// `acme.example.com` -> `acme` when `TENANT_BASE_DOMAIN=example.com`
+    fn subdomain(&self, host: &str) -> Option<String> {
+        let domain = self.tenant_base_domain.as_deref()?;
+        let host = host.split(':').next()?.to_lowercase();
+        let slug = host.strip_suffix(domain)?.strip_suffix('.')?;
+
+        (!slug.is_empty() && !slug.contains('.')).then(|| slug.to_string())
+    }
+
+    async fn default_organization(&self, auth: &AuthUser) -> Result<Organization, AppError> {
+        let mut organizations = self
+            .organizations
+            .list_for_user(auth.user.id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?;
+
+        match organizations.len() {
+            1 => Ok(organizations.remove(0).organization),
+            0 => Err(AppError::Forbidden(
+                "You are not a member of any organization".to_string(),
+            )),
+            _ => Err(AppError::Validation(
+                "Select an organization with the X-Organization header".to_string(),
+            )),
+        }
+    }
+}
+
+fn role_name(role: OrgRole) -> &'static str {
+    match role {
+        OrgRole::Owner => "owner",
+        OrgRole::Admin => "admin",
+        OrgRole::Member => "member",
+    }
+}
+
+/// Also used for unknown organizations, so their existence isn't revealed
+fn not_a_member() -> AppError {
+    AppError::Forbidden("You are not a member of this organization".to_string())
+}
+
+fn to_response(member: Member) -> MemberResponse {
+    MemberResponse {
+        user_id: member.user_id,
+        name: member.name,
+        email: member.email,
+        role: member.role,
+        joined_at: member.joined_at,
+    }
+}
@@ -0,0 +1,253 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::entities::organization::{OrgRole, Organization};
+use crate::shared::database::begin_tenant_transaction;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct UserOrganization {
+    #[sqlx(flatten)]
+    pub organization: Organization,
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Member {
+    pub user_id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub role: OrgRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+/// A pending invitation of the user to an organization
+#[derive(Debug, Clone, FromRow)]
+pub struct Invitation {
+    #[sqlx(flatten)]
+    pub organization: Organization,
+    pub role: OrgRole,
+    pub invited_at: DateTime<Utc>,
+}
+
+/// Lookups by id, slug or user run before a tenant is known and are global.
+/// Methods taking a `tenant_id` run under row-level security.
+#[async_trait]
+pub trait OrganizationRepository: Send + Sync {
+    /// Creates the organization with `owner_id` as its first owner
+    async fn create(&self, name: String, slug: String, owner_id: Uuid) -> Result<Organization, sqlx::Error>;
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Organization>, sqlx::Error>;
+    async fn find_by_slug(&self, slug: &str) -> Result<Option<Organization>, sqlx::Error>;
+    async fn find_role(&self, organization_id: Uuid, user_id: Uuid) -> Result<Option<OrgRole>, sqlx::Error>;
+    async fn list_for_user(&self, user_id: Uuid) -> Result<Vec<UserOrganization>, sqlx::Error>;
+    async fn list_members(&self, tenant_id: Uuid) -> Result<Vec<Member>, sqlx::Error>;
+    /// Returns false when the user already is invited
+    async fn create_invitation(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        role: OrgRole,
+        invited_by: Uuid,
+    ) -> Result<bool, sqlx::Error>;
+    async fn list_invitations(&self, user_id: Uuid) -> Result<Vec<Invitation>, sqlx::Error>;
+    /// Turns the invitation into a membership with the invited role, `None`
+    /// when there was no invitation
+    async fn accept_invitation(&self, organization_id: Uuid, user_id: Uuid) -> Result<Option<OrgRole>, sqlx::Error>;
+    async fn decline_invitation(&self, organization_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error>;
+    async fn set_role(&self, tenant_id: Uuid, user_id: Uuid, role: OrgRole) -> Result<bool, sqlx::Error>;
+    async fn count_owners(&self, tenant_id: Uuid) -> Result<i64, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresOrganizationRepository {
+    pool: PgPool,
+}
+
+impl PostgresOrganizationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OrganizationRepository for PostgresOrganizationRepository {
+    async fn create(&self, name: String, slug: String, owner_id: Uuid) -> Result<Organization, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let organization = sqlx::query_as::<_, Organization>(
+            "INSERT INTO organizations (name, slug) VALUES ($1, $2) RETURNING *",
+        )
+        .bind(name)
+        .bind(slug)
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO organization_memberships (organization_id, user_id, role) VALUES ($1, $2, $3)",
+        )
+        .bind(organization.id)
+        .bind(owner_id)
+        .bind(OrgRole::Owner)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(organization)
+    }
+
+    async fn find_by_id(&self, id: Uuid) -> Result<Option<Organization>, sqlx::Error> {
+        sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn find_by_slug(&self, slug: &str) -> Result<Option<Organization>, sqlx::Error> {
+        sqlx::query_as::<_, Organization>("SELECT * FROM organizations WHERE slug = LOWER($1)")
+            .bind(slug)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn find_role(&self, organization_id: Uuid, user_id: Uuid) -> Result<Option<OrgRole>, sqlx::Error> {
+        sqlx::query_scalar::<_, OrgRole>(
+            "SELECT role FROM organization_memberships WHERE organization_id = $1 AND user_id = $2",
+        )
+        .bind(organization_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn list_for_user(&self, user_id: Uuid) -> Result<Vec<UserOrganization>, sqlx::Error> {
+        sqlx::query_as::<_, UserOrganization>(
+            r#"
+            SELECT o.*, m.role
+            FROM organizations o
+            JOIN organization_memberships m ON m.organization_id = o.id
+            WHERE m.user_id = $1
+            ORDER BY o.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn list_members(&self, tenant_id: Uuid) -> Result<Vec<Member>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let members = sqlx::query_as::<_, Member>(
+            r#"
+            SELECT u.id AS user_id, u.name, u.email, m.role, m.created_at AS joined_at
+            FROM organization_memberships m
+            JOIN users u ON u.id = m.user_id
+            ORDER BY m.created_at
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(members)
+    }
+
+    async fn create_invitation(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        role: OrgRole,
+        invited_by: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let result = sqlx::query(
+            r#"
+            INSERT INTO organization_invitations (organization_id, user_id, role, invited_by)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .bind(role)
+        .bind(invited_by)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list_invitations(&self, user_id: Uuid) -> Result<Vec<Invitation>, sqlx::Error> {
+        sqlx::query_as::<_, Invitation>(
+            r#"
+            SELECT o.*, i.role, i.created_at AS invited_at
+            FROM organizations o
+            JOIN organization_invitations i ON i.organization_id = o.id
+            WHERE i.user_id = $1
+            ORDER BY i.created_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn accept_invitation(&self, organization_id: Uuid, user_id: Uuid) -> Result<Option<OrgRole>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let role = sqlx::query_scalar::<_, OrgRole>(
+            "DELETE FROM organization_invitations WHERE organization_id = $1 AND user_id = $2 RETURNING role",
+        )
+        .bind(organization_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(role) = role else {
+            return Ok(None);
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO organization_memberships (organization_id, user_id, role)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(organization_id)
+        .bind(user_id)
+        .bind(role)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(Some(role))
+    }
+
+    async fn decline_invitation(&self, organization_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM organization_invitations WHERE organization_id = $1 AND user_id = $2")
+            .bind(organization_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_role(&self, tenant_id: Uuid, user_id: Uuid, role: OrgRole) -> Result<bool, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let result = sqlx::query("UPDATE organization_memberships SET role = $1 WHERE user_id = $2")
+            .bind(role)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_owners(&self, tenant_id: Uuid) -> Result<i64, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM organization_memberships WHERE role = 'owner'",
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(count)
+    }
+}
@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::entities::organization::{OrgRole, Organization};
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateOrganizationRequest {
+    #[validate(length(min = 1, max = 255, message = "Name must be between 1 and 255 characters"))]
+    pub name: String,
+    /// Lowercase letters, digits and dashes, used in subdomains and the `X-Organization` header
+    #[validate(length(min = 2, max = 63, message = "Slug must be between 2 and 63 characters"))]
+    pub slug: String,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct AddMemberRequest {
+    /// Email of an existing account
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+    #[serde(default = "default_member_role")]
+    pub role: OrgRole,
+}
+
+fn default_member_role() -> OrgRole {
+    OrgRole::Member
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateMemberRequest {
+    pub role: OrgRole,
+}
+
+/// An organization the current user is invited to
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InvitationResponse {
+    #[serde(flatten)]
+    pub organization: Organization,
+    /// Role the user gets on accepting
+    pub role: OrgRole,
+    pub invited_at: DateTime<Utc>,
+}
+
+/// An organization the current user belongs to
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserOrganizationResponse {
+    #[serde(flatten)]
+    pub organization: Organization,
+    pub role: OrgRole,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MemberResponse {
+    pub user_id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub role: OrgRole,
+    pub joined_at: DateTime<Utc>,
+}
@@ -0,0 +1,5 @@
+mod dto;
+mod tenant;
+
+pub use dto::*;
+pub use tenant::{TenantContext, TenantSelector};
@@ -0,0 +1,92 @@
+use axum::http::{header::HOST, HeaderMap};
+use uuid::Uuid;
+
+use crate::{
+    entities::organization::{OrgRole, Organization},
+    features::auth::model::{ApiScope, AuthUser, Principal},
+    shared::error::AppError,
+};
+
+/// Header selecting the organization by id or slug
+pub const ORGANIZATION_HEADER: &str = "x-organization";
+
+/// What the client asked for. API keys are bound to their organization and
+/// take precedence over both.
+#[derive(Debug, Clone, Default)]
+pub struct TenantSelector {
+    /// Value of the `X-Organization` header
+    pub organization: Option<String>,
+    /// Host, used for `<slug>.<TENANT_BASE_DOMAIN>`
+    pub host: Option<String>,
+}
+
+impl TenantSelector {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+
+        Self {
+            organization: header(ORGANIZATION_HEADER),
+            host: header(HOST.as_str()),
+        }
+    }
+
+    pub fn for_organization(organization: impl Into<String>) -> Self {
+        Self {
+            organization: Some(organization.into()),
+            host: None,
+        }
+    }
+}
+
+/// The organization a request acts on, with the caller's standing in it
+#[derive(Debug, Clone)]
+pub struct TenantContext {
+    pub organization: Organization,
+    pub principal: Principal,
+    /// Role of the signed-in user. `None` for API keys, which are limited by scopes.
+    pub role: Option<OrgRole>,
+}
+
+impl TenantContext {
+    pub fn organization_id(&self) -> Uuid {
+        self.organization.id
+    }
+
+    /// Users hold `users:write` through an owner or admin role
+    pub fn require_scope(&self, scope: ApiScope) -> Result<(), AppError> {
+        match (&self.principal, scope) {
+            (Principal::User(_), ApiScope::UsersWrite) => self.require_manager(),
+            _ => self.principal.require_scope(scope),
+        }
+    }
+
+    pub fn user(&self) -> Option<&AuthUser> {
+        match &self.principal {
+            Principal::User(auth) => Some(auth),
+            Principal::ApiKey(_) => None,
+        }
+    }
+
+    pub fn require_user(&self) -> Result<&AuthUser, AppError> {
+        self.user()
+            .ok_or_else(|| AppError::Forbidden("This endpoint requires a user session".to_string()))
+    }
+
+    /// Users must be an owner or admin of the organization. API keys pass,
+    /// their scopes are checked separately.
+    pub fn require_manager(&self) -> Result<(), AppError> {
+        match (&self.principal, self.role) {
+            (Principal::ApiKey(_), _) => Ok(()),
+            (Principal::User(_), Some(role)) if role.can_manage() => Ok(()),
+            (Principal::User(_), _) => Err(AppError::Forbidden(
+                "Organization owner or admin role required".to_string(),
+            )),
+        }
+    }
+}
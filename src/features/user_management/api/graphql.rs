@@ -1,36 +1,162 @@
-use async_graphql::{Context, EmptySubscription, Object, Schema};
+use async_graphql::{Context, EmptySubscription, Object, Schema, Upload};
+use std::io::Read;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
     features::user_management::model::{CreateUserRequest, UpdateUserRequest},
     features::user_management::domain::UserService,
-    features::ai_integration::model::{ChatRequest, ChatResponse, GenerateRequest, GenerateResponse},
-    features::ai_integration::domain::AIService,
+    features::ai_integration::model::{
+        ActivityExplanationResponse, BioSuggestionRequest, BioSuggestionResponse, ChatRequest, ChatResponse,
+        CountTokensRequest, CountTokensResponse, DuplicateUsersResponse, ExplainActivityRequest, GenerateRequest,
+        GenerateResponse, ModelListResponse,
+    },
+    features::ai_integration::domain::{AIService, UserInsightsService},
+    features::auth::model::{
+        ApiScope, AuthUser, ConfirmEmailVerificationRequest, ConfirmPasswordResetRequest,
+        EmailVerificationRequest, LoginRequest, LoginResponse, OidcProviderResponse,
+        PasswordResetRequest, RecoveryCodesResponse, TwoFactorCodeRequest, TwoFactorEnrollmentResponse,
+        TwoFactorLoginRequest, Principal, UpdateProfileRequest,
+    },
+    features::auth::domain::{AuthService, OidcService},
+    features::organizations::domain::OrganizationService,
+    features::organizations::model::{TenantContext, TenantSelector},
+    entities::ai::AiFile,
     entities::user::User,
 };
 
+/// The caller, set by the GraphQL handler from the `Authorization` header
+fn principal<'a>(ctx: &Context<'a>) -> async_graphql::Result<&'a Principal> {
+    ctx.data_opt::<Principal>()
+        .ok_or_else(|| async_graphql::Error::new("Authentication required"))
+}
+
+/// The signed-in user. API keys are rejected.
+fn auth_user<'a>(ctx: &Context<'a>) -> async_graphql::Result<&'a AuthUser> {
+    match principal(ctx)? {
+        Principal::User(auth) => Ok(auth),
+        Principal::ApiKey(_) => Err(async_graphql::Error::new(
+            "This operation requires a user session",
+        )),
+    }
+}
+
+/// The organization the caller acts on, resolved like the REST `TenantContext`
+async fn tenant(ctx: &Context<'_>) -> async_graphql::Result<TenantContext> {
+    let principal = principal(ctx)?.clone();
+    let selector = ctx.data::<TenantSelector>()?;
+    ctx.data::<OrganizationService>()?
+        .resolve(principal, selector)
+        .await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))
+}
+
+/// Resolves the tenant for an AI feature about user `id`. Members may ask
+/// about themselves, everyone else needs owner or admin.
+async fn user_insights_tenant(ctx: &Context<'_>, id: Uuid, allow_self: bool) -> async_graphql::Result<TenantContext> {
+    let tenant = tenant_with_scope(ctx, ApiScope::AiInvoke).await?;
+    tenant
+        .require_scope(ApiScope::UsersRead)
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+    if !allow_self || tenant.user().is_none_or(|auth| auth.user.id != id) {
+        tenant
+            .require_manager()
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+    }
+    Ok(tenant)
+}
+
+/// Resolves the tenant and checks `scope`
+async fn tenant_with_scope(ctx: &Context<'_>, scope: ApiScope) -> async_graphql::Result<TenantContext> {
+    let tenant = tenant(ctx).await?;
+    tenant
+        .require_scope(scope)
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+    Ok(tenant)
+}
+
 pub struct QueryRoot;
 
 #[Object]
 impl QueryRoot {
+    /// Members of the current organization
     async fn users(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<User>> {
+        let tenant = tenant_with_scope(ctx, ApiScope::UsersRead).await?;
+
         let service = ctx.data::<UserService>()?;
         let users = service
-            .get_users()
+            .get_users(tenant.organization_id())
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         Ok(users)
     }
 
     async fn user(&self, ctx: &Context<'_>, id: Uuid) -> async_graphql::Result<Option<User>> {
+        let tenant = tenant_with_scope(ctx, ApiScope::UsersRead).await?;
+
         let service = ctx.data::<UserService>()?;
-        match service.get_user(id).await {
+        match service.get_user(tenant.organization_id(), id).await {
             Ok(user) => Ok(Some(user)),
             Err(crate::shared::error::AppError::NotFound) => Ok(None),
             Err(e) => Err(async_graphql::Error::new(e.to_string())),
         }
     }
+
+    /// The authenticated user
+    async fn me(&self, ctx: &Context<'_>) -> async_graphql::Result<User> {
+        Ok(auth_user(ctx)?.user.clone())
+    }
+
+    /// Configured OpenID Connect providers
+    async fn oidc_providers(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<OidcProviderResponse>> {
+        let service = ctx.data::<OidcService>()?;
+        Ok(service.providers())
+    }
+
+    /// Models and routes AI requests can choose from
+    async fn ai_models(&self, ctx: &Context<'_>) -> async_graphql::Result<ModelListResponse> {
+        tenant_with_scope(ctx, ApiScope::AiInvoke).await?;
+
+        let service = ctx.data::<AIService>()?;
+        Ok(service.list_models().await)
+    }
+
+    /// Tokens a chat message would use, exact when the provider counted it
+    async fn count_tokens(
+        &self,
+        ctx: &Context<'_>,
+        input: CountTokensRequest,
+    ) -> async_graphql::Result<CountTokensResponse> {
+        let tenant = tenant_with_scope(ctx, ApiScope::AiInvoke).await?;
+
+        let service = ctx.data::<AIService>()?;
+        let response = service
+            .count_tokens(&tenant, input)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(response)
+    }
+
+    /// Members likely to be the same person as the user, by embeddings of
+    /// name and email. Needs owner or admin.
+    async fn user_duplicates(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        min_score: Option<f64>,
+        limit: Option<i64>,
+    ) -> async_graphql::Result<DuplicateUsersResponse> {
+        let tenant = user_insights_tenant(ctx, id, false).await?;
+
+        let service = ctx.data::<UserInsightsService>()?;
+        let response = service
+            .find_duplicates(&tenant, id, min_score, limit)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(response)
+    }
 }
 
 pub struct MutationRoot;
@@ -45,12 +171,15 @@ impl MutationRoot {
         if let Err(e) = input.validate() {
             return Err(async_graphql::Error::new(e.to_string()));
         }
-
+        // Anonymous requests are sign-ups, authenticated ones add a member to the organization
         let service = ctx.data::<UserService>()?;
-        let user = service
-            .create_user(input)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        let user = if ctx.data_opt::<Principal>().is_some() {
+            let tenant = tenant_with_scope(ctx, ApiScope::UsersWrite).await?;
+            service.create_member(tenant.organization_id(), input).await
+        } else {
+            service.create_user(input).await
+        }
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
 
         Ok(user)
     }
@@ -64,25 +193,297 @@ impl MutationRoot {
         if let Err(e) = input.validate() {
             return Err(async_graphql::Error::new(e.to_string()));
         }
+        let tenant = tenant(ctx).await?;
+        // Members may edit their own profile, everyone else needs owner or admin
+        let own_account = tenant.user().is_some_and(|auth| auth.user.id == id);
+        if !own_account {
+            tenant
+                .require_scope(ApiScope::UsersWrite)
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        }
 
         let service = ctx.data::<UserService>()?;
         let user = service
-            .update_user(id, input)
+            .update_user(tenant.organization_id(), id, input, own_account)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
 
         Ok(user)
     }
 
+    /// Removes a user from the current organization. Requires an owner or admin
+    /// with a recent second factor, or an API key with `users:write`.
     async fn delete_user(&self, ctx: &Context<'_>, id: Uuid) -> async_graphql::Result<bool> {
+        let tenant = tenant(ctx).await?;
+        ctx.data::<AuthService>()?
+            .authorize_tenant_admin_or_scope(&tenant, ApiScope::UsersWrite)
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        ctx.data::<OrganizationService>()?
+            .ensure_owner_may_leave(&tenant, id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
         let service = ctx.data::<UserService>()?;
-        match service.delete_user(id).await {
+        match service.delete_user(tenant.organization_id(), id).await {
             Ok(_) => Ok(true),
             Err(crate::shared::error::AppError::NotFound) => Ok(false),
             Err(e) => Err(async_graphql::Error::new(e.to_string())),
         }
     }
 
+    /// Send an email verification link. Returns true even for unknown addresses.
+    async fn request_email_verification(
+        &self,
+        ctx: &Context<'_>,
+        input: EmailVerificationRequest,
+    ) -> async_graphql::Result<bool> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+
+        let service = ctx.data::<AuthService>()?;
+        service
+            .request_email_verification(&input.email)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    /// Confirm an email address with a token from the verification email
+    async fn confirm_email_verification(
+        &self,
+        ctx: &Context<'_>,
+        input: ConfirmEmailVerificationRequest,
+    ) -> async_graphql::Result<User> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+
+        let service = ctx.data::<AuthService>()?;
+        let user = service
+            .confirm_email_verification(&input.token)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(user)
+    }
+
+    /// Send a password reset link. Returns true even for unknown addresses.
+    async fn request_password_reset(
+        &self,
+        ctx: &Context<'_>,
+        input: PasswordResetRequest,
+    ) -> async_graphql::Result<bool> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+
+        let service = ctx.data::<AuthService>()?;
+        service
+            .request_password_reset(&input.email)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    /// Set a new password with a token from the reset email
+    async fn confirm_password_reset(
+        &self,
+        ctx: &Context<'_>,
+        input: ConfirmPasswordResetRequest,
+    ) -> async_graphql::Result<bool> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+
+        let service = ctx.data::<AuthService>()?;
+        service
+            .confirm_password_reset(&input.token, &input.new_password)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    /// Log in with email and password
+    async fn login(
+        &self,
+        ctx: &Context<'_>,
+        input: LoginRequest,
+    ) -> async_graphql::Result<LoginResponse> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+
+        let service = ctx.data::<AuthService>()?;
+        let response = service
+            .login(&input.email, &input.password)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(response)
+    }
+
+    /// Complete a login challenge with a TOTP or recovery code
+    async fn login_two_factor(
+        &self,
+        ctx: &Context<'_>,
+        input: TwoFactorLoginRequest,
+    ) -> async_graphql::Result<LoginResponse> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+
+        let service = ctx.data::<AuthService>()?;
+        let response = service
+            .login_two_factor(&input.challenge_token, &input.code)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(response)
+    }
+
+    /// Start an OpenID Connect login. Send the browser to the returned URL,
+    /// the provider redirects back to `/auth/oidc/{provider}/callback`.
+    async fn oidc_authorization_url(
+        &self,
+        ctx: &Context<'_>,
+        provider: String,
+    ) -> async_graphql::Result<String> {
+        let service = ctx.data::<OidcService>()?;
+        let url = service
+            .authorization_url(&provider)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(url)
+    }
+
+    /// Update the current user's own name or email
+    async fn update_me(&self, ctx: &Context<'_>, input: UpdateProfileRequest) -> async_graphql::Result<User> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+        let auth = auth_user(ctx)?;
+
+        let service = ctx.data::<AuthService>()?;
+        let user = service
+            .update_profile(auth, input)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(user)
+    }
+
+    /// End the current session
+    async fn logout(&self, ctx: &Context<'_>) -> async_graphql::Result<bool> {
+        let auth = auth_user(ctx)?;
+        let service = ctx.data::<AuthService>()?;
+        service
+            .logout(auth)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    /// Start TOTP enrollment
+    async fn enroll_two_factor(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<TwoFactorEnrollmentResponse> {
+        let auth = auth_user(ctx)?;
+        let service = ctx.data::<AuthService>()?;
+        let response = service
+            .enroll_two_factor(auth)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(response)
+    }
+
+    /// Confirm TOTP enrollment with a first code. Recovery codes are only returned here.
+    async fn confirm_two_factor(
+        &self,
+        ctx: &Context<'_>,
+        input: TwoFactorCodeRequest,
+    ) -> async_graphql::Result<RecoveryCodesResponse> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+
+        let auth = auth_user(ctx)?;
+        let service = ctx.data::<AuthService>()?;
+        let response = service
+            .confirm_two_factor(auth, &input.code)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(response)
+    }
+
+    /// Disable two-factor authentication
+    async fn disable_two_factor(
+        &self,
+        ctx: &Context<'_>,
+        input: TwoFactorCodeRequest,
+    ) -> async_graphql::Result<bool> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+
+        let auth = auth_user(ctx)?;
+        let service = ctx.data::<AuthService>()?;
+        service
+            .disable_two_factor(auth, &input.code)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(true)
+    }
+
+    /// Replace all recovery codes
+    async fn regenerate_recovery_codes(
+        &self,
+        ctx: &Context<'_>,
+        input: TwoFactorCodeRequest,
+    ) -> async_graphql::Result<RecoveryCodesResponse> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+
+        let auth = auth_user(ctx)?;
+        let service = ctx.data::<AuthService>()?;
+        let response = service
+            .regenerate_recovery_codes(auth, &input.code)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(response)
+    }
+
+    /// Re-verify the second factor for the current session
+    async fn step_up(
+        &self,
+        ctx: &Context<'_>,
+        input: TwoFactorCodeRequest,
+    ) -> async_graphql::Result<bool> {
+        if let Err(e) = input.validate() {
+            return Err(async_graphql::Error::new(e.to_string()));
+        }
+
+        let auth = auth_user(ctx)?;
+        let service = ctx.data::<AuthService>()?;
+        service
+            .step_up(auth, &input.code)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(true)
+    }
+
     /// Chat with AI
     async fn chat(
         &self,
@@ -92,10 +493,11 @@ impl MutationRoot {
         if let Err(e) = input.validate() {
             return Err(async_graphql::Error::new(e.to_string()));
         }
+        let tenant = tenant_with_scope(ctx, ApiScope::AiInvoke).await?;
 
         let service = ctx.data::<AIService>()?;
         let response = service
-            .chat(input)
+            .chat(&tenant, input)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
 
@@ -111,15 +513,78 @@ impl MutationRoot {
         if let Err(e) = input.validate() {
             return Err(async_graphql::Error::new(e.to_string()));
         }
+        let tenant = tenant_with_scope(ctx, ApiScope::AiInvoke).await?;
 
         let service = ctx.data::<AIService>()?;
         let response = service
-            .generate(input)
+            .generate(&tenant, input)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
 
         Ok(response)
     }
+
+    /// Suggest profile bios for a member
+    async fn suggest_user_bio(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        #[graphql(default)] input: BioSuggestionRequest,
+    ) -> async_graphql::Result<BioSuggestionResponse> {
+        let tenant = user_insights_tenant(ctx, id, true).await?;
+
+        let service = ctx.data::<UserInsightsService>()?;
+        let response = service
+            .suggest_bio(&tenant, id, input)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(response)
+    }
+
+    /// Explain a member's recent account activity in plain language
+    async fn explain_user_activity(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        #[graphql(default)] input: ExplainActivityRequest,
+    ) -> async_graphql::Result<ActivityExplanationResponse> {
+        let tenant = user_insights_tenant(ctx, id, true).await?;
+
+        let service = ctx.data::<UserInsightsService>()?;
+        let response = service
+            .explain_activity(&tenant, id, input)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(response)
+    }
+
+    /// Upload an image or document, reference it in chat messages as `{fileId: ...}`
+    async fn upload_file(&self, ctx: &Context<'_>, file: Upload) -> async_graphql::Result<AiFile> {
+        let tenant = tenant_with_scope(ctx, ApiScope::AiInvoke).await?;
+
+        let upload = file.value(ctx)?;
+        let filename = upload.filename.clone();
+        let mime_type = upload
+            .content_type
+            .clone()
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        // Uploads are buffered in temporary files
+        let data = tokio::task::spawn_blocking(move || {
+            let mut data = Vec::new();
+            upload.into_read().read_to_end(&mut data).map(|_| data)
+        })
+        .await??;
+
+        let service = ctx.data::<AIService>()?;
+        let file = service
+            .upload_file(&tenant, filename, mime_type, data)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(file)
+    }
 }
 
 pub type AppSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
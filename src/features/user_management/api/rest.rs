@@ -6,6 +6,8 @@ use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    features::auth::model::ApiScope,
+    features::organizations::model::TenantContext,
     features::user_management::model::{CreateUserRequest, UpdateUserRequest, UserResponse},
     shared::error::AppError,
     app::state::AppState,
@@ -15,24 +17,24 @@ use crate::{
     get,
     path = "/users",
     tag = "users",
+    security(("bearer" = [])),
     responses(
-        (status = 200, description = "List all users", body = Vec<UserResponse>)
+        (status = 200, description = "Members of the current organization", body = Vec<UserResponse>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the users:read scope")
     )
 )]
 pub async fn get_users(
     State(state): State<AppState>,
+    tenant: TenantContext,
 ) -> Result<Json<Vec<UserResponse>>, AppError> {
-    let users = state.user_service.get_users().await?;
+    tenant.require_scope(ApiScope::UsersRead)?;
+
+    let users = state.user_service.get_users(tenant.organization_id()).await?;
 
     let response = users
         .into_iter()
-        .map(|u| UserResponse {
-            id: u.id.to_string(),
-            name: u.name,
-            email: u.email,
-            created_at: u.created_at.to_rfc3339(),
-            updated_at: u.updated_at.to_rfc3339(),
-        })
+        .map(UserResponse::from)
         .collect();
 
     Ok(Json(response))
@@ -45,24 +47,24 @@ pub async fn get_users(
     params(
         ("id" = Uuid, Path, description = "User database id")
     ),
+    security(("bearer" = [])),
     responses(
         (status = 200, description = "Get user by id", body = UserResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API key is missing the users:read scope"),
         (status = 404, description = "User not found")
     )
 )]
 pub async fn get_user(
     State(state): State<AppState>,
+    tenant: TenantContext,
     Path(id): Path<Uuid>,
 ) -> Result<Json<UserResponse>, AppError> {
-    let user = state.user_service.get_user(id).await?;
-
-    Ok(Json(UserResponse {
-        id: user.id.to_string(),
-        name: user.name,
-        email: user.email,
-        created_at: user.created_at.to_rfc3339(),
-        updated_at: user.updated_at.to_rfc3339(),
-    }))
+    tenant.require_scope(ApiScope::UsersRead)?;
+
+    let user = state.user_service.get_user(tenant.organization_id(), id).await?;
+
+    Ok(Json(UserResponse::from(user)))
 }
 
 #[utoipa::path(
@@ -70,28 +72,35 @@ pub async fn get_user(
     path = "/users",
     tag = "users",
     request_body = CreateUserRequest,
+    security((), ("bearer" = [])),
     responses(
-        (status = 200, description = "Create a new user", body = UserResponse),
-        (status = 400, description = "Validation error")
+        (status = 200, description = "Sign up, or create a member of the current organization when authenticated", body = UserResponse),
+        (status = 400, description = "Validation error"),
+        (status = 403, description = "Owner or admin role, or an API key with users:write, required")
     )
 )]
 pub async fn create_user(
     State(state): State<AppState>,
+    tenant: Option<TenantContext>,
     Json(payload): Json<CreateUserRequest>,
 ) -> Result<Json<UserResponse>, AppError> {
     if let Err(e) = payload.validate() {
         return Err(AppError::Validation(e.to_string()));
     }
 
-    let user = state.user_service.create_user(payload).await?;
+    // Anonymous requests are sign-ups, authenticated ones add a member to the organization
+    let user = match tenant {
+        Some(tenant) => {
+            tenant.require_scope(ApiScope::UsersWrite)?;
+            state
+                .user_service
+                .create_member(tenant.organization_id(), payload)
+                .await?
+        }
+        None => state.user_service.create_user(payload).await?,
+    };
 
-    Ok(Json(UserResponse {
-        id: user.id.to_string(),
-        name: user.name,
-        email: user.email,
-        created_at: user.created_at.to_rfc3339(),
-        updated_at: user.updated_at.to_rfc3339(),
-    }))
+    Ok(Json(UserResponse::from(user)))
 }
 
 #[utoipa::path(
@@ -102,13 +111,17 @@ pub async fn create_user(
         ("id" = Uuid, Path, description = "User database id")
     ),
     request_body = UpdateUserRequest,
+    security(("bearer" = [])),
     responses(
         (status = 200, description = "Update user", body = UserResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Only owners and admins may edit other members, API keys need users:write; accounts in other organizations too can only be edited by themselves"),
         (status = 404, description = "User not found")
     )
 )]
 pub async fn update_user(
     State(state): State<AppState>,
+    tenant: TenantContext,
     Path(id): Path<Uuid>,
     Json(payload): Json<UpdateUserRequest>,
 ) -> Result<Json<UserResponse>, AppError> {
@@ -116,15 +129,18 @@ pub async fn update_user(
         return Err(AppError::Validation(e.to_string()));
     }
 
-    let updated_user = state.user_service.update_user(id, payload).await?;
+    // Members may edit their own profile, everyone else needs owner or admin
+    let own_account = tenant.user().is_some_and(|auth| auth.user.id == id);
+    if !own_account {
+        tenant.require_scope(ApiScope::UsersWrite)?;
+    }
 
-    Ok(Json(UserResponse {
-        id: updated_user.id.to_string(),
-        name: updated_user.name,
-        email: updated_user.email,
-        created_at: updated_user.created_at.to_rfc3339(),
-        updated_at: updated_user.updated_at.to_rfc3339(),
-    }))
+    let updated_user = state
+        .user_service
+        .update_user(tenant.organization_id(), id, payload, own_account)
+        .await?;
+
+    Ok(Json(UserResponse::from(updated_user)))
 }
 
 #[utoipa::path(
@@ -134,16 +150,26 @@ pub async fn update_user(
     params(
         ("id" = Uuid, Path, description = "User database id")
     ),
+    security(("bearer" = [])),
     responses(
-        (status = 200, description = "Delete user"),
-        (status = 404, description = "User not found")
+        (status = 200, description = "User removed from the organization, the account itself stays"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Owner or admin role and recent second factor, or an API key with users:write, required; only owners may remove owners"),
+        (status = 404, description = "User not found"),
+        (status = 409, description = "The last owner cannot be removed")
     )
 )]
 pub async fn delete_user(
     State(state): State<AppState>,
+    tenant: TenantContext,
     Path(id): Path<Uuid>,
 ) -> Result<Json<serde_json::Value>, AppError> {
-    state.user_service.delete_user(id).await?;
+    state
+        .auth_service
+        .authorize_tenant_admin_or_scope(&tenant, ApiScope::UsersWrite)?;
+    state.organization_service.ensure_owner_may_leave(&tenant, id).await?;
+
+    state.user_service.delete_user(tenant.organization_id(), id).await?;
 
-    Ok(Json(serde_json::json!({ "message": "User deleted" })))
+    Ok(Json(serde_json::json!({ "message": "User removed" })))
 }
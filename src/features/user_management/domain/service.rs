@@ -1,65 +1,134 @@
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
     features::user_management::model::{CreateUserRequest, UpdateUserRequest},
-    features::user_management::infrastructure::UserRepository,
+    features::user_management::infrastructure::{UserActivityRepository, UserRepository},
     shared::error::AppError,
-    entities::user::User,
+    shared::security::hash_password,
+    entities::organization::OrgRole,
+    entities::user::{User, UserActivity},
 };
 
 #[derive(Clone)]
 pub struct UserService {
     repository: Arc<dyn UserRepository>,
+    activity: Arc<dyn UserActivityRepository>,
 }
 
 impl UserService {
-    pub fn new(repository: Arc<dyn UserRepository>) -> Self {
-        Self { repository }
+    pub fn new(repository: Arc<dyn UserRepository>, activity: Arc<dyn UserActivityRepository>) -> Self {
+        Self { repository, activity }
     }
 
-    pub async fn get_users(&self) -> Result<Vec<User>, AppError> {
+    pub async fn get_users(&self, tenant_id: Uuid) -> Result<Vec<User>, AppError> {
         self.repository
-            .find_all()
+            .find_all(tenant_id)
             .await
             .map_err(|e| AppError::Database(e.to_string()))
     }
 
-    pub async fn get_user(&self, id: Uuid) -> Result<User, AppError> {
+    pub async fn get_user(&self, tenant_id: Uuid, id: Uuid) -> Result<User, AppError> {
         self.repository
-            .find_by_id(id)
+            .find_in_tenant(tenant_id, id)
             .await
             .map_err(|e| AppError::Database(e.to_string()))?
             .ok_or(AppError::NotFound)
     }
 
+    pub async fn find_user_by_email(&self, tenant_id: Uuid, email: &str) -> Result<User, AppError> {
+        self.repository
+            .find_in_tenant_by_email(tenant_id, email)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or(AppError::NotFound)
+    }
+
+    /// What a member did in the organization since `since`, newest first.
+    /// `include_account` adds the global account history, which only the
+    /// user themselves may see.
+    pub async fn get_activity(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+        since: DateTime<Utc>,
+        limit: i64,
+        include_account: bool,
+    ) -> Result<Vec<UserActivity>, AppError> {
+        self.repository
+            .find_in_tenant(tenant_id, id)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))?
+            .ok_or(AppError::NotFound)?;
+
+        self.activity
+            .recent(tenant_id, id, since, limit, include_account)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Self-service sign-up. The account belongs to no organization yet.
     pub async fn create_user(&self, input: CreateUserRequest) -> Result<User, AppError> {
+        let password_hash = input.password.as_deref().map(hash_password).transpose()?;
+
         self.repository
-            .create(input.name, input.email)
+            .create(input.name, input.email, password_hash)
             .await
             .map_err(|e| AppError::Database(e.to_string()))
     }
 
+    /// Creates an account as a member of the organization
+    pub async fn create_member(
+        &self,
+        tenant_id: Uuid,
+        input: CreateUserRequest,
+    ) -> Result<User, AppError> {
+        let password_hash = input.password.as_deref().map(hash_password).transpose()?;
+
+        self.repository
+            .create_in_tenant(tenant_id, input.name, input.email, password_hash, OrgRole::Member)
+            .await
+            .map_err(|e| AppError::Database(e.to_string()))
+    }
+
+    /// Accounts that are also members of other organizations can only be
+    /// changed by themselves, an organization doesn't own them
     pub async fn update_user(
         &self,
+        tenant_id: Uuid,
         id: Uuid,
         input: UpdateUserRequest,
+        own_account: bool,
     ) -> Result<User, AppError> {
-        let user = self.get_user(id).await?;
+        let user = self.get_user(tenant_id, id).await?;
+
+        if !own_account {
+            let memberships = self
+                .repository
+                .count_memberships(id)
+                .await
+                .map_err(|e| AppError::Database(e.to_string()))?;
+            if memberships > 1 {
+                return Err(AppError::Forbidden(
+                    "The account also belongs to other organizations, only its owner can change it".to_string(),
+                ));
+            }
+        }
 
         let name = input.name.unwrap_or(user.name);
-        let email = input.email.unwrap_or(user.email);
 
         self.repository
-            .update(id, name, email)
+            .update(tenant_id, id, name)
             .await
             .map_err(|e| AppError::Database(e.to_string()))
     }
 
-    pub async fn delete_user(&self, id: Uuid) -> Result<(), AppError> {
+    /// Removes the user from the organization. The account itself stays.
+    pub async fn delete_user(&self, tenant_id: Uuid, id: Uuid) -> Result<(), AppError> {
         let deleted = self
             .repository
-            .delete(id)
+            .remove_from_tenant(tenant_id, id)
             .await
             .map_err(|e| AppError::Database(e.to_string()))?;
 
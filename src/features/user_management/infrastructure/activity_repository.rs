@@ -0,0 +1,124 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::entities::user::UserActivity;
+
+/// There is no separate audit table, the history is read from the records
+/// that carry timestamps
+#[async_trait]
+pub trait UserActivityRepository: Send + Sync {
+    /// Newest first, at most `limit` since `since`. Empty when the user isn't
+    /// a member of the tenant. Only the tenant's records are read, unless
+    /// `include_account` also asks for the global account history: sign-ins,
+    /// SSO, recovery codes and account changes, wherever they happened.
+    async fn recent(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        since: DateTime<Utc>,
+        limit: i64,
+        include_account: bool,
+    ) -> Result<Vec<UserActivity>, sqlx::Error>;
+}
+
+#[derive(Clone)]
+pub struct PostgresUserActivityRepository {
+    pool: PgPool,
+}
+
+impl PostgresUserActivityRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserActivityRepository for PostgresUserActivityRepository {
+    async fn recent(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        since: DateTime<Utc>,
+        limit: i64,
+        include_account: bool,
+    ) -> Result<Vec<UserActivity>, sqlx::Error> {
+        // Sessions, identities and recovery codes are global, so this runs
+        // outside row-level security. Membership is checked in the query,
+        // rows of other organizations are filtered by `tenant_id` and the
+        // global ones are left out unless `include_account` is set.
+        sqlx::query_as::<_, UserActivity>(
+            r#"
+            WITH member AS (
+                SELECT role, created_at
+                FROM organization_memberships
+                WHERE organization_id = $1 AND user_id = $2
+            )
+            SELECT occurred_at, kind, detail FROM (
+                SELECT created_at AS occurred_at, 'account_created' AS kind, 'Account created' AS detail
+                FROM users WHERE id = $2 AND $5
+                UNION ALL
+                SELECT updated_at, 'account_updated', 'Profile, password or email verification changed'
+                FROM users WHERE id = $2 AND $5 AND updated_at > created_at + INTERVAL '1 second'
+                UNION ALL
+                SELECT email_verified_at, 'email_verified', 'Email address verified'
+                FROM users WHERE id = $2 AND $5 AND email_verified_at IS NOT NULL
+                UNION ALL
+                SELECT totp_enabled_at, 'two_factor_enabled', 'Two-factor authentication enabled'
+                FROM users WHERE id = $2 AND $5 AND totp_enabled_at IS NOT NULL
+                UNION ALL
+                SELECT created_at, 'joined_organization', 'Joined the organization as ' || role
+                FROM member
+                UNION ALL
+                SELECT created_at, 'login',
+                       CASE WHEN second_factor_at IS NOT NULL THEN 'Signed in with a second factor' ELSE 'Signed in' END
+                FROM sessions WHERE user_id = $2 AND $5
+                UNION ALL
+                SELECT used_at, 'recovery_code_used', 'Used a two-factor recovery code'
+                FROM user_recovery_codes WHERE user_id = $2 AND $5 AND used_at IS NOT NULL
+                UNION ALL
+                SELECT created_at, 'sso_linked', 'Linked a ' || provider || ' account'
+                FROM user_identities WHERE user_id = $2 AND $5
+                UNION ALL
+                SELECT last_login_at, 'sso_login', 'Signed in with ' || provider
+                FROM user_identities WHERE user_id = $2 AND $5 AND last_login_at > created_at
+                UNION ALL
+                SELECT created_at, 'api_key_created',
+                       'Created API key "' || name || '" with scopes ' || array_to_string(scopes, ', ')
+                FROM api_keys WHERE organization_id = $1 AND created_by = $2
+                UNION ALL
+                SELECT last_used_at, 'api_key_used', 'API key "' || name || '" last used'
+                FROM api_keys WHERE organization_id = $1 AND created_by = $2 AND last_used_at IS NOT NULL
+                UNION ALL
+                SELECT revoked_at, 'api_key_revoked', 'Revoked API key "' || name || '"'
+                FROM api_keys WHERE organization_id = $1 AND created_by = $2 AND revoked_at IS NOT NULL
+                UNION ALL
+                -- One event per day and operation, AI calls can be many
+                SELECT MAX(created_at), 'ai_usage',
+                       COUNT(*) || ' AI ' || operation || ' calls using ' || SUM(total_tokens) || ' tokens'
+                FROM ai_usage
+                WHERE organization_id = $1 AND user_id = $2 AND created_at >= $3
+                GROUP BY date_trunc('day', created_at), operation
+                UNION ALL
+                SELECT created_at, 'moderation_flag',
+                       'Moderation ' || CASE action WHEN 'block' THEN 'blocked' ELSE 'flagged' END
+                       || ' a ' || operation || CASE stage WHEN 'input' THEN ' prompt' ELSE ' answer' END
+                       || ' for ' || array_to_string(categories, ', ')
+                       || CASE WHEN reviewed_at IS NOT NULL THEN ', since reviewed' ELSE '' END
+                FROM ai_moderation_flags WHERE organization_id = $1 AND user_id = $2
+            ) events
+            WHERE occurred_at >= $3 AND EXISTS (SELECT 1 FROM member)
+            ORDER BY occurred_at DESC
+            LIMIT $4
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .bind(since)
+        .bind(limit)
+        .bind(include_account)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
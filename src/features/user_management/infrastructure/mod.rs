@@ -1,3 +1,5 @@
+mod activity_repository;
 mod repository;
 
+pub use activity_repository::{PostgresUserActivityRepository, UserActivityRepository};
 pub use repository::{UserRepository, PostgresUserRepository};
@@ -2,20 +2,46 @@ use async_trait::async_trait;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::entities::organization::OrgRole;
 use crate::entities::user::User;
+use crate::shared::database::begin_tenant_transaction;
 
+/// Methods taking a `tenant_id` only see members of that organization, which is
+/// enforced by row-level security. The others work on global accounts and are
+/// used by authentication.
 #[async_trait]
 pub trait UserRepository: Send + Sync {
-    async fn find_all(&self) -> Result<Vec<User>, sqlx::Error>;
+    async fn find_all(&self, tenant_id: Uuid) -> Result<Vec<User>, sqlx::Error>;
+    async fn find_in_tenant(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<User>, sqlx::Error>;
+    async fn find_in_tenant_by_email(&self, tenant_id: Uuid, email: &str) -> Result<Option<User>, sqlx::Error>;
+    /// Creates an account that is a member of the tenant
+    async fn create_in_tenant(
+        &self,
+        tenant_id: Uuid,
+        name: String,
+        email: String,
+        password_hash: Option<String>,
+        role: OrgRole,
+    ) -> Result<User, sqlx::Error>;
+    /// Only the profile, credentials can't be changed from an organization
+    async fn update(&self, tenant_id: Uuid, id: Uuid, name: String) -> Result<User, sqlx::Error>;
+    /// Removes the membership. The account stays, it may belong to other
+    /// organizations or join one later.
+    async fn remove_from_tenant(&self, tenant_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error>;
     async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, sqlx::Error>;
-    async fn create(&self, name: String, email: String) -> Result<User, sqlx::Error>;
-    async fn update(
+    /// Organizations the account is a member of
+    async fn count_memberships(&self, id: Uuid) -> Result<i64, sqlx::Error>;
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error>;
+    async fn create(
         &self,
-        id: Uuid,
         name: String,
         email: String,
+        password_hash: Option<String>,
     ) -> Result<User, sqlx::Error>;
-    async fn delete(&self, id: Uuid) -> Result<bool, sqlx::Error>;
+    /// Changes the account's own name and email
+    async fn update_account(&self, id: Uuid, name: String, email: String) -> Result<User, sqlx::Error>;
+    async fn mark_email_verified(&self, id: Uuid) -> Result<User, sqlx::Error>;
+    async fn set_password_hash(&self, id: Uuid, password_hash: String) -> Result<(), sqlx::Error>;
 }
 
 #[derive(Clone)]
@@ -31,10 +57,70 @@ impl PostgresUserRepository {
 
 #[async_trait]
 impl UserRepository for PostgresUserRepository {
-    async fn find_all(&self) -> Result<Vec<User>, sqlx::Error> {
-        sqlx::query_as::<_, User>("SELECT * FROM users")
-            .fetch_all(&self.pool)
-            .await
+    async fn find_all(&self, tenant_id: Uuid) -> Result<Vec<User>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let users = sqlx::query_as::<_, User>("SELECT * FROM users")
+            .fetch_all(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(users)
+    }
+
+    async fn find_in_tenant(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<User>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn find_in_tenant_by_email(&self, tenant_id: Uuid, email: &str) -> Result<Option<User>, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = LOWER($1)")
+            .bind(email)
+            .fetch_optional(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn create_in_tenant(
+        &self,
+        tenant_id: Uuid,
+        name: String,
+        email: String,
+        password_hash: Option<String>,
+        role: OrgRole,
+    ) -> Result<User, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+
+        // The row is only visible to the tenant once the membership exists,
+        // so it is read back instead of using RETURNING
+        let id = Uuid::new_v4();
+        sqlx::query("INSERT INTO users (id, name, email, password_hash) VALUES ($1, $2, $3, $4)")
+            .bind(id)
+            .bind(name)
+            .bind(email)
+            .bind(password_hash)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO organization_memberships (organization_id, user_id, role) VALUES ($1, $2, $3)",
+        )
+        .bind(tenant_id)
+        .bind(id)
+        .bind(role)
+        .execute(&mut *tx)
+        .await?;
+        let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(user)
     }
 
     async fn find_by_id(&self, id: Uuid) -> Result<Option<User>, sqlx::Error> {
@@ -44,24 +130,71 @@ impl UserRepository for PostgresUserRepository {
             .await
     }
 
-    async fn create(&self, name: String, email: String) -> Result<User, sqlx::Error> {
+    async fn find_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as::<_, User>("SELECT * FROM users WHERE LOWER(email) = LOWER($1)")
+            .bind(email)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn create(
+        &self,
+        name: String,
+        email: String,
+        password_hash: Option<String>,
+    ) -> Result<User, sqlx::Error> {
         sqlx::query_as::<_, User>(
-            "INSERT INTO users (name, email) VALUES ($1, $2) RETURNING *",
+            "INSERT INTO users (name, email, password_hash) VALUES ($1, $2, $3) RETURNING *",
         )
         .bind(name)
         .bind(email)
+        .bind(password_hash)
         .fetch_one(&self.pool)
         .await
     }
 
-    async fn update(
-        &self,
-        id: Uuid,
-        name: String,
-        email: String,
-    ) -> Result<User, sqlx::Error> {
+    async fn update(&self, tenant_id: Uuid, id: Uuid, name: String) -> Result<User, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users SET name = $1, updated_at = NOW() WHERE id = $2 RETURNING *",
+        )
+        .bind(name)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn remove_from_tenant(&self, tenant_id: Uuid, id: Uuid) -> Result<bool, sqlx::Error> {
+        let mut tx = begin_tenant_transaction(&self.pool, tenant_id).await?;
+        let result = sqlx::query("DELETE FROM organization_memberships WHERE user_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn count_memberships(&self, id: Uuid) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM organization_memberships WHERE user_id = $1")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn update_account(&self, id: Uuid, name: String, email: String) -> Result<User, sqlx::Error> {
+        // Changing the address invalidates a previous verification
         sqlx::query_as::<_, User>(
-            "UPDATE users SET name = $1, email = $2, updated_at = NOW() WHERE id = $3 RETURNING *",
+            r#"
+            UPDATE users
+            SET name = $1,
+                email = $2,
+                email_verified_at = CASE WHEN email = $2 THEN email_verified_at ELSE NULL END,
+                updated_at = NOW()
+            WHERE id = $3
+            RETURNING *
+            "#,
         )
         .bind(name)
         .bind(email)
@@ -70,11 +203,21 @@ impl UserRepository for PostgresUserRepository {
         .await
     }
 
-    async fn delete(&self, id: Uuid) -> Result<bool, sqlx::Error> {
-        let result = sqlx::query("DELETE FROM users WHERE id = $1")
+    async fn mark_email_verified(&self, id: Uuid) -> Result<User, sqlx::Error> {
+        sqlx::query_as::<_, User>(
+            "UPDATE users SET email_verified_at = COALESCE(email_verified_at, NOW()), updated_at = NOW() WHERE id = $1 RETURNING *",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn set_password_hash(&self, id: Uuid, password_hash: String) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE users SET password_hash = $1, updated_at = NOW() WHERE id = $2")
+            .bind(password_hash)
             .bind(id)
             .execute(&self.pool)
             .await?;
-        Ok(result.rows_affected() > 0)
+        Ok(())
     }
 }
@@ -4,20 +4,25 @@ use validator::Validate;
 
 use utoipa::ToSchema;
 
+use crate::entities::user::{Role, User};
+
 #[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
 pub struct CreateUserRequest {
     #[validate(length(min = 1, message = "Name cannot be empty"))]
     pub name: String,
     #[validate(email(message = "Invalid email format"))]
     pub email: String,
+    /// Optional, users without a password can set one via password reset
+    #[validate(length(min = 8, max = 128, message = "Password must be between 8 and 128 characters"))]
+    pub password: Option<String>,
 }
 
+/// Accounts are shared between organizations, so only the profile can be
+/// edited here. Users change their own email with `PUT /auth/me`.
 #[derive(Debug, Deserialize, Validate, InputObject, ToSchema)]
 pub struct UpdateUserRequest {
     #[validate(length(min = 1, message = "Name cannot be empty"))]
     pub name: Option<String>,
-    #[validate(email(message = "Invalid email format"))]
-    pub email: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -25,6 +30,24 @@ pub struct UserResponse {
     pub id: String,
     pub name: String,
     pub email: String,
+    pub role: Role,
+    pub email_verified: bool,
+    pub two_factor_enabled: bool,
     pub created_at: String,
     pub updated_at: String,
 }
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id.to_string(),
+            name: user.name,
+            email: user.email,
+            role: user.role,
+            email_verified: user.email_verified,
+            two_factor_enabled: user.two_factor_enabled,
+            created_at: user.created_at.to_rfc3339(),
+            updated_at: user.updated_at.to_rfc3339(),
+        }
+    }
+}
@@ -4,45 +4,180 @@ mod entities;
 mod features;
 mod app;
 
+use std::net::SocketAddr;
+use std::process::ExitCode;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::shared::config::Config;
 use crate::shared::database::create_pool;
-use crate::features::user_management::infrastructure::PostgresUserRepository;
+use crate::features::user_management::infrastructure::{PostgresUserActivityRepository, PostgresUserRepository};
 use crate::features::user_management::domain::UserService;
-use crate::features::ai_integration::infrastructure::GeminiRepository;
-use crate::features::ai_integration::domain::AIService;
+use crate::features::ai_integration::infrastructure::{
+    create_model_router, create_response_cache, start_eviction, PostgresAiFileRepository, PostgresAiUsageRepository,
+    PostgresBatchRepository, PostgresEmbeddingRepository, PostgresModerationRepository, PostgresPromptTemplateRepository,
+    PostgresRequestLogRepository, RequestLogRepository,
+};
+use crate::features::ai_integration::domain::{
+    AIService, BatchService, ContextWindow, EmbeddingService, FindUserByEmailTool, GetUserTool, ListUsersTool,
+    ModerationPipeline, PromptTemplateService, Redactor, RequestLogService, ToolRegistry, UserInsightsService,
+};
+use crate::features::auth::infrastructure::{
+    PostgresApiKeyRepository, PostgresIdentityRepository, PostgresSessionRepository, PostgresTokenRepository,
+    PostgresTwoFactorRepository,
+};
+use crate::features::auth::domain::{ApiKeyService, AuthService, AuthSettings, OidcService};
+use crate::features::organizations::infrastructure::PostgresOrganizationRepository;
+use crate::features::organizations::domain::OrganizationService;
+use crate::shared::mailer::{create_mailer, EmailTemplates};
+use crate::shared::rate_limit::{create_rate_limit_store, RateLimiter};
 use crate::app::{AppState, create_router};
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> anyhow::Result<ExitCode> {
     let config = Config::init();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("eval") {
+        // The report may go to stdout, so logs go to stderr
+        tracing_subscriber::registry()
+            .with(tracing_subscriber::EnvFilter::new(&config.rust_log))
+            .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+            .init();
+        return app::eval::run(&config, &args[1..]).await;
+    }
+
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(&config.rust_log))
         .with(tracing_subscriber::fmt::layer())
         .init();
 
     // Create database pool and run migrations
-    let pool = create_pool(&config.database_url).await?;
+    let database_url = config.database_url.as_deref().expect("DATABASE_URL must be set");
+    let pool = create_pool(database_url).await?;
 
     // Initialize repositories
-    let user_repository = std::sync::Arc::new(PostgresUserRepository::new(pool));
-    let ai_repository = std::sync::Arc::new(GeminiRepository::new(config.gemini_api_key.clone()));
+    let user_repository = std::sync::Arc::new(PostgresUserRepository::new(pool.clone()));
+    let user_activity_repository = std::sync::Arc::new(PostgresUserActivityRepository::new(pool.clone()));
+    let token_repository = std::sync::Arc::new(PostgresTokenRepository::new(pool.clone()));
+    let session_repository = std::sync::Arc::new(PostgresSessionRepository::new(pool.clone()));
+    let two_factor_repository = std::sync::Arc::new(PostgresTwoFactorRepository::new(pool.clone()));
+    let identity_repository = std::sync::Arc::new(PostgresIdentityRepository::new(pool.clone()));
+    let api_key_repository = std::sync::Arc::new(PostgresApiKeyRepository::new(pool.clone()));
+    let organization_repository = std::sync::Arc::new(PostgresOrganizationRepository::new(pool.clone()));
+    let ai_usage_repository = std::sync::Arc::new(PostgresAiUsageRepository::new(pool.clone()));
+    let ai_file_repository = std::sync::Arc::new(PostgresAiFileRepository::new(pool.clone()));
+    let template_repository = std::sync::Arc::new(PostgresPromptTemplateRepository::new(pool.clone()));
+    let embedding_repository = std::sync::Arc::new(PostgresEmbeddingRepository::new(pool.clone()));
+    let batch_repository = std::sync::Arc::new(PostgresBatchRepository::new(pool.clone()));
+    let moderation_repository = std::sync::Arc::new(PostgresModerationRepository::new(pool.clone()));
+    let request_log_repository = std::sync::Arc::new(PostgresRequestLogRepository::new(pool.clone()));
+    let ai_response_cache = create_response_cache(&config.ai_cache, pool)?;
+    if let Some(cache) = &ai_response_cache {
+        start_eviction(cache.clone());
+    }
+    let ai_models = create_model_router(&config.ai_routing, &config.gemini_api_key)?;
+    let ai_moderation = ModerationPipeline::new(&config.ai_moderation, &ai_models)?;
+    // Logs are only written while logging is on, but searched and purged regardless
+    let ai_request_log: Option<std::sync::Arc<dyn RequestLogRepository>> = match config.ai_request_log.enabled {
+        true => Some(request_log_repository.clone()),
+        false => None,
+    };
+
+    // Initialize mailer
+    let mailer = create_mailer(&config.mail)?;
+    let email_templates = EmailTemplates::load(config.mail.templates_dir.as_deref())?;
+
+    // Initialize rate limiter
+    let rate_limit_store = create_rate_limit_store(&config.rate_limit).await?;
+    let rate_limiter = RateLimiter::new(&config.rate_limit, rate_limit_store);
 
     // Initialize services
-    let user_service = UserService::new(user_repository);
-    let ai_service = AIService::new(ai_repository);
+    let user_service = UserService::new(user_repository.clone(), user_activity_repository);
+    let mut ai_tools = ToolRegistry::new();
+    ai_tools.register(FindUserByEmailTool::new(user_service.clone()));
+    ai_tools.register(GetUserTool::new(user_service.clone()));
+    ai_tools.register(ListUsersTool::new(user_service.clone()));
+    let ai_service = AIService::new(
+        ai_models,
+        ai_usage_repository,
+        ai_response_cache,
+        ai_file_repository,
+        embedding_repository.clone(),
+        config.ai_attachments.clone(),
+        ContextWindow::new(&config.ai_context)?,
+        Redactor::new(&config.ai_redaction)?,
+        ai_moderation,
+        moderation_repository,
+        ai_request_log,
+        ai_tools,
+        config.ai_tool_max_steps,
+    );
+    let template_service = PromptTemplateService::new(template_repository, ai_service.clone());
+    let embedding_service = EmbeddingService::new(embedding_repository, ai_service.clone(), config.ai_chunking);
+    let auth_service = AuthService::new(
+        user_repository.clone(),
+        token_repository,
+        session_repository,
+        two_factor_repository,
+        mailer.clone(),
+        email_templates.clone(),
+        AuthSettings::from_config(&config),
+    );
+    let oidc_service = OidcService::new(
+        &config.oidc,
+        identity_repository,
+        user_repository.clone(),
+        auth_service.clone(),
+    );
+
+    let api_key_service = ApiKeyService::new(api_key_repository);
+    let organization_service = OrganizationService::new(
+        organization_repository,
+        user_repository,
+        mailer,
+        email_templates.organization_invitation,
+        &config.app_base_url,
+        config.tenant_base_domain.clone(),
+    );
+    let batch_service = BatchService::new(
+        batch_repository,
+        ai_service.clone(),
+        auth_service.clone(),
+        api_key_service.clone(),
+        organization_service.clone(),
+        config.ai_batch,
+    );
+    if config.ai_batch.workers_enabled {
+        batch_service.start_workers();
+    }
+    let request_log_service =
+        RequestLogService::new(request_log_repository, ai_service.clone(), config.ai_request_log.clone());
+    request_log_service.start_retention();
+    let user_insights_service = UserInsightsService::new(user_service.clone(), ai_service.clone());
 
     // Create app state and router
-    let state = AppState::new(user_service, ai_service);
+    let state = AppState::new(
+        user_service,
+        ai_service,
+        template_service,
+        embedding_service,
+        batch_service,
+        request_log_service,
+        user_insights_service,
+        auth_service,
+        oidc_service,
+        api_key_service,
+        organization_service,
+        rate_limiter,
+    );
     let app = create_router(state);
 
     // Start server
     let addr_str = format!("{}:{}", config.server_host, config.server_port);
     let listener = tokio::net::TcpListener::bind(&addr_str).await?;
     tracing::info!("listening on {}", addr_str);
-    axum::serve(listener, app).await?;
+    // Connection info provides the client IP for rate limiting
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
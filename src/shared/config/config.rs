@@ -4,25 +4,649 @@ use serde::Deserialize;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
-    pub database_url: String,
+    /// Only the server needs it, `eval` runs without a database
+    pub database_url: Option<String>,
     pub server_host: String,
     pub server_port: u16,
     pub rust_log: String,
     pub gemini_api_key: String,
+    pub app_base_url: String,
+    /// Domain under which `<slug>.<domain>` selects an organization, e.g. "example.com"
+    pub tenant_base_domain: Option<String>,
+    pub mail: MailConfig,
+    pub auth: AuthConfig,
+    pub oidc: OidcConfig,
+    pub rate_limit: RateLimitConfig,
+    pub ai_cache: AiCacheConfig,
+    pub ai_attachments: AiAttachmentConfig,
+    /// Model turns a chat may spend calling tools before it must answer
+    pub ai_tool_max_steps: usize,
+    pub ai_chunking: AiChunkingConfig,
+    pub ai_context: AiContextConfig,
+    pub ai_routing: AiRoutingConfig,
+    pub ai_batch: AiBatchConfig,
+    pub ai_redaction: AiRedactionConfig,
+    pub ai_moderation: AiModerationConfig,
+    pub ai_request_log: AiRequestLogConfig,
+}
+
+/// Outgoing email settings
+#[derive(Deserialize, Debug, Clone)]
+pub struct MailConfig {
+    /// One of "smtp", "file" or "stdout"
+    pub backend: String,
+    pub from: String,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    /// One of "none", "starttls" or "tls"
+    pub smtp_tls: String,
+    pub file_dir: String,
+    /// Directory with template overrides, built-in templates are used when unset
+    pub templates_dir: Option<String>,
+    pub verification_ttl_minutes: i64,
+    pub password_reset_ttl_minutes: i64,
+}
+
+/// Login sessions and two-factor authentication settings
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuthConfig {
+    pub session_ttl_minutes: i64,
+    /// Lifetime of the challenge issued between password and TOTP checks
+    pub two_factor_challenge_ttl_minutes: i64,
+    /// How long a passed second factor counts as "recent" for admin operations
+    pub step_up_max_age_minutes: i64,
+    pub admin_requires_step_up: bool,
+    pub totp_issuer: String,
+}
+
+/// OpenID Connect identity providers
+#[derive(Deserialize, Debug, Clone)]
+pub struct OidcConfig {
+    /// Public URL of this API, used to build `<url>/auth/oidc/<provider>/callback`
+    pub redirect_base_url: String,
+    pub providers: Vec<OidcProviderConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OidcProviderConfig {
+    /// Short name used in URLs, e.g. "company"
+    pub name: String,
+    pub display_name: String,
+    pub issuer_url: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub scopes: String,
+}
+
+/// Limits for images and documents sent to the model
+#[derive(Deserialize, Debug, Clone)]
+pub struct AiAttachmentConfig {
+    /// Largest single file, decoded
+    pub max_file_bytes: usize,
+    /// All files of one request together, decoded
+    pub max_request_bytes: usize,
+    pub allowed_mime_types: Vec<String>,
+}
+
+/// Default chunking of documents ingested for retrieval, in characters
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct AiChunkingConfig {
+    pub chunk_size: usize,
+    /// Characters a chunk repeats from the end of the previous one
+    pub chunk_overlap: usize,
+}
+
+/// Fitting chat histories into the model's context window, in estimated tokens
+#[derive(Deserialize, Debug, Clone)]
+pub struct AiContextConfig {
+    /// One of "drop_oldest" or "summarize", used when a request doesn't choose
+    pub strategy: String,
+    /// Context window by model name prefix, e.g. ("gemini-1.5-pro", 2097152), overriding the built-in ones
+    pub model_limits: Vec<(String, usize)>,
+    /// Context window of models without a known one
+    pub default_limit: usize,
+    /// Tokens kept free for the answer
+    pub reserved_output_tokens: usize,
+}
+
+/// Models requests may choose, as `provider:model` pairs, e.g. "gemini:gemini-1.5-pro"
+#[derive(Deserialize, Debug, Clone)]
+pub struct AiRoutingConfig {
+    /// From `AI_PROVIDER` and `GEMINI_MODEL`, always allowed. Also answers embeddings.
+    pub default_model: String,
+    /// `GEMINI_EMBEDDING_MODEL`, used by Gemini models for embeddings
+    pub embedding_model: String,
+    /// Further models requests may select by name
+    pub models: Vec<String>,
+    pub routes: Vec<AiRouteConfig>,
+    /// Route answering requests that name no model, `default_model` when unset
+    pub default_route: Option<String>,
+    /// Longest a model may take to answer before the next one of the route is tried
+    pub timeout_seconds: u64,
+    /// `GEMINI_SAFETY_SETTINGS` as `CATEGORY=THRESHOLD` pairs, checked when the models are built
+    pub gemini_safety_settings: String,
+}
+
+/// Batch jobs, run in the background by workers inside this binary
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct AiBatchConfig {
+    /// Instances with workers off only accept batches, others run them
+    pub workers_enabled: bool,
+    /// Jobs an instance runs at the same time
+    pub concurrency: usize,
+    /// Attempts per job before it fails, provider errors and timeouts are retried
+    pub max_attempts: i32,
+    /// Delay before the first retry, doubling with every further attempt
+    pub retry_base_seconds: u64,
+    pub poll_interval_ms: u64,
+    /// A running job not finished within this time is claimed again, e.g. after a crash
+    pub lease_seconds: u64,
+    pub max_items: usize,
+    /// Jobs an organization may have waiting or running across its batches
+    pub max_pending_jobs: usize,
+}
+
+/// Every model call of chat and generate requests, kept to reproduce answers
+#[derive(Deserialize, Debug, Clone)]
+pub struct AiRequestLogConfig {
+    pub enabled: bool,
+    /// Logs older than this are deleted, 0 keeps them
+    pub retention_days: u32,
+}
+
+/// Checks of prompts and answers on top of the provider's own safety filters
+#[derive(Deserialize, Debug, Clone)]
+pub struct AiModerationConfig {
+    /// Named rules, each a regular expression, a keyword list or both
+    pub rules: Vec<AiModerationRuleConfig>,
+    /// Allowed model or route that classifies texts, no classifier when unset
+    pub classifier_model: Option<String>,
+    /// Categories the classifier may report
+    pub classifier_categories: Vec<String>,
+    /// log, flag or block, for routes without a policy of their own
+    pub policy: String,
+    /// Policies per route, e.g. ("chat", "flag")
+    pub route_policies: Vec<(String, String)>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AiModerationRuleConfig {
+    pub name: String,
+    pub pattern: Option<String>,
+    /// Matched as whole words, ignoring case
+    pub keywords: Vec<String>,
+}
+
+impl AiModerationConfig {
+    /// Reads `AI_MODERATION_RULES=abuse,threats` with `AI_MODERATION_RULE_<NAME>`
+    /// and/or `AI_MODERATION_KEYWORDS_<NAME>` per rule, and `AI_MODERATION_POLICY_<ROUTE>`
+    /// for the chat and generate routes
+    fn from_env() -> Self {
+        let rules = env::var("AI_MODERATION_RULES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let suffix = name.to_uppercase();
+                let pattern = env::var(format!("AI_MODERATION_RULE_{}", suffix)).ok();
+                let keywords: Vec<String> = env::var(format!("AI_MODERATION_KEYWORDS_{}", suffix))
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|keyword| keyword.trim().to_string())
+                    .filter(|keyword| !keyword.is_empty())
+                    .collect();
+                if pattern.is_none() && keywords.is_empty() {
+                    panic!("AI_MODERATION_RULE_{0} or AI_MODERATION_KEYWORDS_{0} must be set", suffix);
+                }
+                AiModerationRuleConfig {
+                    name: name.to_lowercase(),
+                    pattern,
+                    keywords,
+                }
+            })
+            .collect();
+
+        let route_policies = ["chat", "generate"]
+            .into_iter()
+            .filter_map(|route| {
+                let policy = env::var(format!("AI_MODERATION_POLICY_{}", route.to_uppercase())).ok()?;
+                Some((route.to_string(), policy.trim().to_lowercase()))
+            })
+            .collect();
+
+        Self {
+            rules,
+            classifier_model: env::var("AI_MODERATION_CLASSIFIER_MODEL")
+                .ok()
+                .filter(|model| !model.trim().is_empty()),
+            classifier_categories: env::var("AI_MODERATION_CLASSIFIER_CATEGORIES")
+                .unwrap_or_else(|_| "harassment,hate,sexual,violence,self_harm,illegal".to_string())
+                .split(',')
+                .map(|category| category.trim().to_lowercase())
+                .filter(|category| !category.is_empty())
+                .collect(),
+            policy: env::var("AI_MODERATION_POLICY")
+                .unwrap_or_else(|_| "block".to_string())
+                .trim()
+                .to_lowercase(),
+            route_policies,
+        }
+    }
+}
+
+/// Personal data replaced with placeholders before prompts leave for a provider
+#[derive(Deserialize, Debug, Clone)]
+pub struct AiRedactionConfig {
+    pub enabled: bool,
+    /// Built-in detectors: email, phone, card and iban
+    pub detectors: Vec<String>,
+    /// Named regular expressions, matched before the built-in detectors
+    pub patterns: Vec<(String, String)>,
+    /// Puts the original values back into answers
+    pub rehydrate: bool,
+}
+
+impl AiRedactionConfig {
+    /// Reads `AI_REDACTION_PATTERNS=employee_id,ticket` and `AI_REDACTION_PATTERN_<NAME>` per pattern
+    fn from_env() -> Self {
+        let patterns = env::var("AI_REDACTION_PATTERNS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let key = format!("AI_REDACTION_PATTERN_{}", name.to_uppercase());
+                let pattern = env::var(&key).unwrap_or_else(|_| panic!("{} must be set", key));
+                (name.to_lowercase(), pattern)
+            })
+            .collect();
+
+        Self {
+            enabled: env::var("AI_REDACTION_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse::<bool>()
+                .expect("AI_REDACTION_ENABLED must be true or false"),
+            detectors: env::var("AI_REDACTION_DETECTORS")
+                .unwrap_or_else(|_| "email,phone,card,iban".to_string())
+                .split(',')
+                .map(|detector| detector.trim().to_lowercase())
+                .filter(|detector| !detector.is_empty())
+                .collect(),
+            patterns,
+            rehydrate: env::var("AI_REDACTION_REHYDRATE")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse::<bool>()
+                .expect("AI_REDACTION_REHYDRATE must be true or false"),
+        }
+    }
+}
+
+/// A named chain of models, e.g. "fast", tried in order until one answers
+#[derive(Deserialize, Debug, Clone)]
+pub struct AiRouteConfig {
+    pub name: String,
+    /// `provider:model` pairs, the first is the primary
+    pub models: Vec<String>,
+}
+
+impl AiRoutingConfig {
+    /// Reads `AI_MODELS`, `AI_ROUTES=fast,smart` and `AI_ROUTE_<NAME>` per route
+    fn from_env(ai_provider: &str) -> Self {
+        let list = |value: String| -> Vec<String> {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+
+        let default_model = match ai_provider {
+            "mock" => "mock:mock".to_string(),
+            provider => format!(
+                "{}:{}",
+                provider,
+                env::var("GEMINI_MODEL").unwrap_or_else(|_| "gemini-2.0-flash-exp".to_string())
+            ),
+        };
+
+        let routes = list(env::var("AI_ROUTES").unwrap_or_default())
+            .into_iter()
+            .map(|name| {
+                let key = format!("AI_ROUTE_{}", name.to_uppercase());
+                let models = list(env::var(&key).unwrap_or_else(|_| panic!("{} must be set", key)));
+                AiRouteConfig {
+                    name: name.to_lowercase(),
+                    models,
+                }
+            })
+            .collect();
+
+        Self {
+            default_model,
+            embedding_model: env::var("GEMINI_EMBEDDING_MODEL").unwrap_or_else(|_| "text-embedding-004".to_string()),
+            models: list(env::var("AI_MODELS").unwrap_or_default()),
+            routes,
+            default_route: env::var("AI_DEFAULT_ROUTE").ok().filter(|route| !route.is_empty()),
+            timeout_seconds: env::var("AI_MODEL_TIMEOUT_SECONDS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse::<u64>()
+                .expect("AI_MODEL_TIMEOUT_SECONDS must be a number"),
+            gemini_safety_settings: env::var("GEMINI_SAFETY_SETTINGS").unwrap_or_default(),
+        }
+    }
+}
+
+/// Cache for deterministic AI responses
+#[derive(Deserialize, Debug, Clone)]
+pub struct AiCacheConfig {
+    /// One of "none", "memory" or "postgres"
+    pub backend: String,
+    pub ttl_seconds: u64,
+    pub max_entries: usize,
+}
+
+/// Token-bucket rate limits per route group
+#[derive(Deserialize, Debug, Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// One of "memory" or "redis"
+    pub backend: String,
+    pub redis_url: String,
+    /// Use the first `X-Forwarded-For` address as client IP, only behind a trusted proxy
+    pub trust_forwarded_for: bool,
+    /// Let requests through when the store fails, otherwise they are refused
+    pub fail_open: bool,
+    pub policies: Vec<RateLimitPolicy>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RateLimitPolicy {
+    /// Route group, one of "auth", "users", "ai" or "graphql"
+    pub group: String,
+    /// Bucket size, the number of requests that can be made at once
+    pub burst: u32,
+    /// Refill rate of the bucket
+    pub per_minute: u32,
+}
+
+impl RateLimitConfig {
+    /// Reads `RATE_LIMIT_<GROUP>_BURST` and `RATE_LIMIT_<GROUP>_PER_MINUTE`,
+    /// a rate of 0 disables limiting for that group
+    fn from_env() -> Self {
+        let defaults = [("auth", 10, 20), ("users", 60, 120), ("ai", 10, 20), ("graphql", 60, 120)];
+
+        let policies = defaults
+            .into_iter()
+            .map(|(group, burst, per_minute)| {
+                let var = |key: &str, default: u32| {
+                    let name = format!("RATE_LIMIT_{}_{}", group.to_uppercase(), key);
+                    env::var(&name)
+                        .map(|value| {
+                            value
+                                .parse::<u32>()
+                                .unwrap_or_else(|_| panic!("{} must be a number", name))
+                        })
+                        .unwrap_or(default)
+                };
+
+                RateLimitPolicy {
+                    group: group.to_string(),
+                    burst: var("BURST", burst),
+                    per_minute: var("PER_MINUTE", per_minute),
+                }
+            })
+            .filter(|policy| policy.per_minute > 0 && policy.burst > 0)
+            .collect();
+
+        Self {
+            enabled: env::var("RATE_LIMIT_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse::<bool>()
+                .expect("RATE_LIMIT_ENABLED must be true or false"),
+            backend: env::var("RATE_LIMIT_BACKEND").unwrap_or_else(|_| "memory".to_string()),
+            redis_url: env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string()),
+            trust_forwarded_for: env::var("RATE_LIMIT_TRUST_FORWARDED_FOR")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse::<bool>()
+                .expect("RATE_LIMIT_TRUST_FORWARDED_FOR must be true or false"),
+            fail_open: env::var("RATE_LIMIT_FAIL_OPEN")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse::<bool>()
+                .expect("RATE_LIMIT_FAIL_OPEN must be true or false"),
+            policies,
+        }
+    }
+}
+
+impl OidcConfig {
+    /// Reads `OIDC_PROVIDERS=company,google` and `OIDC_<NAME>_*` variables per provider
+    fn from_env(default_base_url: &str) -> Self {
+        let providers = env::var("OIDC_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let var = |key: &str| env::var(format!("OIDC_{}_{}", name.to_uppercase(), key));
+
+                OidcProviderConfig {
+                    name: name.to_lowercase(),
+                    display_name: var("DISPLAY_NAME").unwrap_or_else(|_| name.to_string()),
+                    issuer_url: var("ISSUER_URL").unwrap_or_else(|_| {
+                        panic!("OIDC_{}_ISSUER_URL must be set", name.to_uppercase())
+                    }),
+                    client_id: var("CLIENT_ID").unwrap_or_else(|_| {
+                        panic!("OIDC_{}_CLIENT_ID must be set", name.to_uppercase())
+                    }),
+                    client_secret: var("CLIENT_SECRET").ok(),
+                    scopes: var("SCOPES").unwrap_or_else(|_| "openid email profile".to_string()),
+                }
+            })
+            .collect();
+
+        Self {
+            redirect_base_url: env::var("OIDC_REDIRECT_BASE_URL")
+                .unwrap_or_else(|_| default_base_url.to_string()),
+            providers,
+        }
+    }
 }
 
 impl Config {
     pub fn init() -> Config {
         dotenv().ok();
-        
-        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let database_url = env::var("DATABASE_URL").ok();
         let server_host = env::var("SERVER_HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
         let server_port = env::var("SERVER_PORT")
             .unwrap_or_else(|_| "3000".to_string())
             .parse::<u16>()
             .expect("SERVER_PORT must be a valid u16");
         let rust_log = env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
-        let gemini_api_key = env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY must be set");
+        let ai_provider = env::var("AI_PROVIDER").unwrap_or_else(|_| "gemini".to_string());
+        // The mock provider works without a key
+        let gemini_api_key = match ai_provider.as_str() {
+            "gemini" => env::var("GEMINI_API_KEY").expect("GEMINI_API_KEY must be set"),
+            _ => env::var("GEMINI_API_KEY").unwrap_or_default(),
+        };
+        let app_base_url = env::var("APP_BASE_URL")
+            .unwrap_or_else(|_| format!("http://{}:{}", server_host, server_port));
+        let tenant_base_domain = env::var("TENANT_BASE_DOMAIN").ok();
+
+        let mail = MailConfig {
+            backend: env::var("MAIL_BACKEND").unwrap_or_else(|_| "stdout".to_string()),
+            from: env::var("MAIL_FROM").unwrap_or_else(|_| "no-reply@localhost".to_string()),
+            smtp_host: env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string()),
+            smtp_port: env::var("SMTP_PORT")
+                .unwrap_or_else(|_| "1025".to_string())
+                .parse::<u16>()
+                .expect("SMTP_PORT must be a valid u16"),
+            smtp_username: env::var("SMTP_USERNAME").ok(),
+            smtp_password: env::var("SMTP_PASSWORD").ok(),
+            smtp_tls: env::var("SMTP_TLS").unwrap_or_else(|_| "none".to_string()),
+            file_dir: env::var("MAIL_FILE_DIR").unwrap_or_else(|_| "./mail".to_string()),
+            templates_dir: env::var("MAIL_TEMPLATES_DIR").ok(),
+            verification_ttl_minutes: env::var("EMAIL_VERIFICATION_TTL_MINUTES")
+                .unwrap_or_else(|_| "1440".to_string())
+                .parse::<i64>()
+                .expect("EMAIL_VERIFICATION_TTL_MINUTES must be a number"),
+            password_reset_ttl_minutes: env::var("PASSWORD_RESET_TTL_MINUTES")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse::<i64>()
+                .expect("PASSWORD_RESET_TTL_MINUTES must be a number"),
+        };
+
+        let auth = AuthConfig {
+            session_ttl_minutes: env::var("SESSION_TTL_MINUTES")
+                .unwrap_or_else(|_| "10080".to_string())
+                .parse::<i64>()
+                .expect("SESSION_TTL_MINUTES must be a number"),
+            two_factor_challenge_ttl_minutes: env::var("TWO_FACTOR_CHALLENGE_TTL_MINUTES")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse::<i64>()
+                .expect("TWO_FACTOR_CHALLENGE_TTL_MINUTES must be a number"),
+            step_up_max_age_minutes: env::var("STEP_UP_MAX_AGE_MINUTES")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse::<i64>()
+                .expect("STEP_UP_MAX_AGE_MINUTES must be a number"),
+            admin_requires_step_up: env::var("ADMIN_REQUIRES_STEP_UP")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse::<bool>()
+                .expect("ADMIN_REQUIRES_STEP_UP must be true or false"),
+            totp_issuer: env::var("TOTP_ISSUER").unwrap_or_else(|_| "Rust Backend Demo".to_string()),
+        };
+
+        let oidc = OidcConfig::from_env(&format!("http://{}:{}", server_host, server_port));
+        let rate_limit = RateLimitConfig::from_env();
+
+        let ai_cache = AiCacheConfig {
+            backend: env::var("AI_CACHE_BACKEND").unwrap_or_else(|_| "none".to_string()),
+            ttl_seconds: env::var("AI_CACHE_TTL_SECONDS")
+                .unwrap_or_else(|_| "3600".to_string())
+                .parse::<u64>()
+                .expect("AI_CACHE_TTL_SECONDS must be a number"),
+            max_entries: env::var("AI_CACHE_MAX_ENTRIES")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse::<usize>()
+                .expect("AI_CACHE_MAX_ENTRIES must be a number"),
+        };
+
+        let ai_attachments = AiAttachmentConfig {
+            max_file_bytes: env::var("AI_MAX_FILE_BYTES")
+                .unwrap_or_else(|_| "5242880".to_string())
+                .parse::<usize>()
+                .expect("AI_MAX_FILE_BYTES must be a number"),
+            max_request_bytes: env::var("AI_MAX_REQUEST_BYTES")
+                .unwrap_or_else(|_| "15728640".to_string())
+                .parse::<usize>()
+                .expect("AI_MAX_REQUEST_BYTES must be a number"),
+            allowed_mime_types: env::var("AI_ALLOWED_MIME_TYPES")
+                .unwrap_or_else(|_| {
+                    "image/png,image/jpeg,image/webp,image/heic,image/heif,application/pdf,text/plain".to_string()
+                })
+                .split(',')
+                .map(|mime_type| mime_type.trim().to_lowercase())
+                .filter(|mime_type| !mime_type.is_empty())
+                .collect(),
+        };
+
+        let ai_tool_max_steps = env::var("AI_TOOL_MAX_STEPS")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<usize>()
+            .expect("AI_TOOL_MAX_STEPS must be a number");
+
+        let ai_chunking = AiChunkingConfig {
+            chunk_size: env::var("AI_CHUNK_SIZE")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse::<usize>()
+                .expect("AI_CHUNK_SIZE must be a number"),
+            chunk_overlap: env::var("AI_CHUNK_OVERLAP")
+                .unwrap_or_else(|_| "200".to_string())
+                .parse::<usize>()
+                .expect("AI_CHUNK_OVERLAP must be a number"),
+        };
+
+        let ai_context = AiContextConfig {
+            strategy: env::var("AI_CONTEXT_STRATEGY").unwrap_or_else(|_| "drop_oldest".to_string()),
+            model_limits: env::var("AI_CONTEXT_LIMITS")
+                .unwrap_or_default()
+                .split(',')
+                .filter(|entry| !entry.trim().is_empty())
+                .map(|entry| {
+                    let (model, limit) = entry
+                        .split_once('=')
+                        .expect("AI_CONTEXT_LIMITS must be a list of model=tokens");
+                    let limit = limit
+                        .trim()
+                        .parse::<usize>()
+                        .expect("AI_CONTEXT_LIMITS must be a list of model=tokens");
+                    (model.trim().to_string(), limit)
+                })
+                .collect(),
+            default_limit: env::var("AI_CONTEXT_DEFAULT_LIMIT")
+                .unwrap_or_else(|_| "32768".to_string())
+                .parse::<usize>()
+                .expect("AI_CONTEXT_DEFAULT_LIMIT must be a number"),
+            reserved_output_tokens: env::var("AI_CONTEXT_RESERVED_TOKENS")
+                .unwrap_or_else(|_| "8192".to_string())
+                .parse::<usize>()
+                .expect("AI_CONTEXT_RESERVED_TOKENS must be a number"),
+        };
+
+        let ai_routing = AiRoutingConfig::from_env(&ai_provider);
+
+        let ai_batch = AiBatchConfig {
+            workers_enabled: env::var("AI_BATCH_WORKERS_ENABLED")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse::<bool>()
+                .expect("AI_BATCH_WORKERS_ENABLED must be true or false"),
+            concurrency: env::var("AI_BATCH_CONCURRENCY")
+                .unwrap_or_else(|_| "4".to_string())
+                .parse::<usize>()
+                .expect("AI_BATCH_CONCURRENCY must be a number"),
+            max_attempts: env::var("AI_BATCH_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "3".to_string())
+                .parse::<i32>()
+                .expect("AI_BATCH_MAX_ATTEMPTS must be a number"),
+            retry_base_seconds: env::var("AI_BATCH_RETRY_BASE_SECONDS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse::<u64>()
+                .expect("AI_BATCH_RETRY_BASE_SECONDS must be a number"),
+            poll_interval_ms: env::var("AI_BATCH_POLL_INTERVAL_MS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse::<u64>()
+                .expect("AI_BATCH_POLL_INTERVAL_MS must be a number"),
+            lease_seconds: env::var("AI_BATCH_LEASE_SECONDS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse::<u64>()
+                .expect("AI_BATCH_LEASE_SECONDS must be a number"),
+            max_items: env::var("AI_BATCH_MAX_ITEMS")
+                .unwrap_or_else(|_| "10000".to_string())
+                .parse::<usize>()
+                .expect("AI_BATCH_MAX_ITEMS must be a number"),
+            max_pending_jobs: env::var("AI_BATCH_MAX_PENDING_JOBS")
+                .unwrap_or_else(|_| "10000".to_string())
+                .parse::<usize>()
+                .expect("AI_BATCH_MAX_PENDING_JOBS must be a number"),
+        };
+
+        let ai_redaction = AiRedactionConfig::from_env();
+        let ai_moderation = AiModerationConfig::from_env();
+
+        let ai_request_log = AiRequestLogConfig {
+            enabled: env::var("AI_REQUEST_LOG_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse::<bool>()
+                .expect("AI_REQUEST_LOG_ENABLED must be true or false"),
+            retention_days: env::var("AI_REQUEST_LOG_RETENTION_DAYS")
+                .unwrap_or_else(|_| "30".to_string())
+                .parse::<u32>()
+                .expect("AI_REQUEST_LOG_RETENTION_DAYS must be a number"),
+        };
 
         Config {
             database_url,
@@ -30,6 +654,22 @@ impl Config {
             server_port,
             rust_log,
             gemini_api_key,
+            app_base_url,
+            tenant_base_domain,
+            mail,
+            auth,
+            oidc,
+            rate_limit,
+            ai_cache,
+            ai_attachments,
+            ai_tool_max_steps,
+            ai_chunking,
+            ai_context,
+            ai_routing,
+            ai_batch,
+            ai_redaction,
+            ai_moderation,
+            ai_request_log,
         }
     }
 }
@@ -1,3 +1,3 @@
 mod config;
 
-pub use config::Config;
+pub use config::{AiAttachmentConfig, AiBatchConfig, AiCacheConfig, AiChunkingConfig, AiContextConfig, AiModerationConfig, AiRedactionConfig, AiRequestLogConfig, AiRoutingConfig, Config, MailConfig, OidcConfig, OidcProviderConfig, RateLimitConfig, RateLimitPolicy};
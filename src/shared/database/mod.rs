@@ -1,3 +1,5 @@
 mod connection;
+mod tenant;
 
 pub use connection::create_pool;
+pub use tenant::begin_tenant_transaction;
@@ -0,0 +1,24 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Starts a transaction limited to one tenant by row-level security.
+///
+/// The transaction switches to the `app_tenant` role, to which the policies
+/// apply, and sets `app.tenant_id`, which they compare against. Both settings
+/// are local to the transaction, so the pooled connection is clean afterwards.
+pub async fn begin_tenant_transaction(
+    pool: &PgPool,
+    tenant_id: Uuid,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("SET LOCAL ROLE app_tenant")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("SELECT set_config('app.tenant_id', $1, true)")
+        .bind(tenant_id.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+    Ok(tx)
+}
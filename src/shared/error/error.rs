@@ -1,10 +1,21 @@
 use axum::{
-    http::StatusCode,
+    http::{header::RETRY_AFTER, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use async_graphql::SimpleObject;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use thiserror::Error;
+use utoipa::ToSchema;
+
+/// How likely content is to be harmful in one category, as reported by the model
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject, ToSchema)]
+pub struct SafetyRating {
+    pub category: String,
+    pub probability: String,
+    pub blocked: bool,
+}
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -14,28 +25,121 @@ pub enum AppError {
     Validation(String),
     #[error("User not found")]
     NotFound,
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Too many requests, retry after {retry_after} seconds")]
+    TooManyRequests { retry_after: u64 },
+    /// The model refused the input, `reason` is Gemini's `blockReason`
+    #[error("Prompt blocked: {reason}")]
+    PromptBlocked { reason: String, safety_ratings: Vec<SafetyRating> },
+    /// The model stopped generating, e.g. with `finishReason` SAFETY or RECITATION
+    #[error("Response blocked: {finish_reason}")]
+    ResponseBlocked { finish_reason: String, safety_ratings: Vec<SafetyRating> },
+    /// Our own moderation stopped the prompt or the answer, `stage` is "prompt" or "response"
+    #[error("The {stage} was blocked by moderation: {}", .categories.join(", "))]
+    ModerationBlocked { stage: &'static str, categories: Vec<String> },
+    /// The model can't handle this kind of input, e.g. images for a text-only model
+    #[error("Model {model} does not support {capability}")]
+    UnsupportedCapability { model: String, capability: &'static str },
     #[error("External service error: {0}")]
     ExternalService(String),
     #[error("Internal server error")]
     Internal(#[from] anyhow::Error),
 }
 
+impl AppError {
+    /// What clients are told, as the `error` of the response body. Database
+    /// and internal errors are only described in the log.
+    pub fn public_message(&self) -> String {
+        match self {
+            AppError::Database(_) => "Database error".to_string(),
+            AppError::Validation(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
+            | AppError::Conflict(msg)
+            | AppError::ExternalService(msg) => msg.clone(),
+            AppError::NotFound => "Resource not found".to_string(),
+            AppError::TooManyRequests { retry_after } => {
+                format!("Too many requests, retry in {} seconds", retry_after)
+            }
+            AppError::PromptBlocked { .. } => "The prompt was blocked by the model's safety filters".to_string(),
+            AppError::ResponseBlocked { .. } => "The response was blocked by the model".to_string(),
+            AppError::ModerationBlocked { stage, .. } => format!("The {} was blocked by moderation", stage),
+            AppError::UnsupportedCapability { model, capability } => {
+                format!("Model {} does not support {}", model, capability)
+            }
+            AppError::Internal(_) => "Internal server error".to_string(),
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
+        let error_message = self.public_message();
+        let status = match self {
             AppError::Database(msg) => {
                 tracing::error!("Database error: {}", msg);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            AppError::Validation(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::TooManyRequests { retry_after } => {
+                let body = Json(json!({ "error": error_message }));
+                return (
+                    StatusCode::TOO_MANY_REQUESTS,
+                    [(RETRY_AFTER, retry_after.to_string())],
+                    body,
+                )
+                    .into_response();
+            }
+            AppError::PromptBlocked { reason, safety_ratings } => {
+                let body = Json(json!({
+                    "error": error_message,
+                    "block_reason": reason,
+                    "safety_ratings": safety_ratings
+                }));
+                return (StatusCode::BAD_REQUEST, body).into_response();
+            }
+            AppError::ResponseBlocked { finish_reason, safety_ratings } => {
+                let body = Json(json!({
+                    "error": error_message,
+                    "finish_reason": finish_reason,
+                    "safety_ratings": safety_ratings
+                }));
+                return (StatusCode::UNPROCESSABLE_ENTITY, body).into_response();
+            }
+            AppError::ModerationBlocked { stage, categories } => {
+                let status = match stage {
+                    "prompt" => StatusCode::BAD_REQUEST,
+                    _ => StatusCode::UNPROCESSABLE_ENTITY,
+                };
+                let body = Json(json!({
+                    "error": error_message,
+                    "categories": categories
+                }));
+                return (status, body).into_response();
+            }
+            AppError::UnsupportedCapability { capability, .. } => {
+                let body = Json(json!({
+                    "error": error_message,
+                    "capability": capability
+                }));
+                return (StatusCode::BAD_REQUEST, body).into_response();
             }
-            AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
-            AppError::NotFound => (StatusCode::NOT_FOUND, "Resource not found".to_string()),
             AppError::ExternalService(msg) => {
                 tracing::error!("External service error: {}", msg);
-                (StatusCode::BAD_GATEWAY, msg)
+                StatusCode::BAD_GATEWAY
             }
             AppError::Internal(e) => {
                 tracing::error!("Internal error: {:?}", e);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string())
+                StatusCode::INTERNAL_SERVER_ERROR
             }
         };
 
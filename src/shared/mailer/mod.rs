@@ -0,0 +1,5 @@
+mod templates;
+mod transport;
+
+pub use templates::{EmailTemplate, EmailTemplates};
+pub use transport::{create_mailer, Email, Mailer};
@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use super::Email;
+
+const VERIFY_EMAIL_SUBJECT: &str = "Confirm your email address";
+const VERIFY_EMAIL_BODY: &str = "Hi {{name}},
+
+Please confirm your email address by opening the link below:
+
+{{link}}
+
+The link expires in {{ttl_minutes}} minutes. If you did not request this, you can ignore this email.
+";
+
+const PASSWORD_RESET_SUBJECT: &str = "Reset your password";
+const PASSWORD_RESET_BODY: &str = "Hi {{name}},
+
+Someone asked to reset the password for your account. Open the link below to choose a new one:
+
+{{link}}
+
+The link expires in {{ttl_minutes}} minutes. If you did not request this, you can ignore this email.
+";
+
+const ORGANIZATION_INVITATION_SUBJECT: &str = "You're invited to join {{organization}}";
+const ORGANIZATION_INVITATION_BODY: &str = "Hi {{name}},
+
+{{inviter}} invited you to join {{organization}} as {{role}}. Open the link below to accept or decline:
+
+{{link}}
+
+You won't be added to the organization unless you accept.
+";
+
+/// Subject and body with `{{variable}}` placeholders
+#[derive(Debug, Clone)]
+pub struct EmailTemplate {
+    pub subject: String,
+    pub body: String,
+}
+
+impl EmailTemplate {
+    fn new(subject: &str, body: &str) -> Self {
+        Self {
+            subject: subject.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    /// Loads `<name>.subject.txt` and `<name>.body.txt` from `dir`,
+    /// keeping the built-in text for any file that is missing
+    fn load(dir: Option<&Path>, name: &str, default: EmailTemplate) -> std::io::Result<Self> {
+        let Some(dir) = dir else {
+            return Ok(default);
+        };
+
+        let read = |file: String, fallback: String| -> std::io::Result<String> {
+            let path = dir.join(file);
+            if path.exists() {
+                std::fs::read_to_string(path)
+            } else {
+                Ok(fallback)
+            }
+        };
+
+        Ok(Self {
+            subject: read(format!("{}.subject.txt", name), default.subject)?
+                .trim()
+                .to_string(),
+            body: read(format!("{}.body.txt", name), default.body)?,
+        })
+    }
+
+    pub fn render(&self, to: &str, vars: &[(&str, &str)]) -> Email {
+        let fill = |text: &str| {
+            vars.iter().fold(text.to_string(), |acc, (key, value)| {
+                acc.replace(&format!("{{{{{}}}}}", key), value)
+            })
+        };
+
+        Email {
+            to: to.to_string(),
+            subject: fill(&self.subject),
+            body: fill(&self.body),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EmailTemplates {
+    pub verify_email: EmailTemplate,
+    pub password_reset: EmailTemplate,
+    pub organization_invitation: EmailTemplate,
+}
+
+impl EmailTemplates {
+    pub fn load(dir: Option<&str>) -> std::io::Result<Self> {
+        let dir = dir.map(Path::new);
+
+        Ok(Self {
+            verify_email: EmailTemplate::load(
+                dir,
+                "verify_email",
+                EmailTemplate::new(VERIFY_EMAIL_SUBJECT, VERIFY_EMAIL_BODY),
+            )?,
+            password_reset: EmailTemplate::load(
+                dir,
+                "password_reset",
+                EmailTemplate::new(PASSWORD_RESET_SUBJECT, PASSWORD_RESET_BODY),
+            )?,
+            organization_invitation: EmailTemplate::load(
+                dir,
+                "organization_invitation",
+                EmailTemplate::new(ORGANIZATION_INVITATION_SUBJECT, ORGANIZATION_INVITATION_BODY),
+            )?,
+        })
+    }
+}
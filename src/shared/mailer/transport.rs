@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncFileTransport, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::sync::Arc;
+
+use crate::shared::config::MailConfig;
+use crate::shared::error::AppError;
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), AppError>;
+}
+
+/// Builds the mailer selected by `MAIL_BACKEND`
+pub fn create_mailer(config: &MailConfig) -> anyhow::Result<Arc<dyn Mailer>> {
+    let from: Mailbox = config.from.parse()?;
+
+    let mailer: Arc<dyn Mailer> = match config.backend.as_str() {
+        "smtp" => Arc::new(SmtpMailer::new(config, from)?),
+        "file" => Arc::new(FileMailer::new(&config.file_dir, from)?),
+        "stdout" => Arc::new(StdoutMailer),
+        other => anyhow::bail!("Unknown MAIL_BACKEND: {}", other),
+    };
+
+    Ok(mailer)
+}
+
+fn build_message(from: &Mailbox, email: Email) -> Result<Message, AppError> {
+    let to: Mailbox = email
+        .to
+        .parse()
+        .map_err(|e| AppError::Validation(format!("Invalid recipient address: {}", e)))?;
+
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(email.subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(email.body)
+        .map_err(|e| AppError::Internal(e.into()))
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &MailConfig, from: Mailbox) -> anyhow::Result<Self> {
+        let mut builder = match config.smtp_tls.as_str() {
+            // Plain connection, e.g. a local MailHog sink
+            "none" => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.smtp_host),
+            "starttls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?,
+            "tls" => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.smtp_host)?,
+            other => anyhow::bail!("Unknown SMTP_TLS mode: {}", other),
+        }
+        .port(config.smtp_port);
+
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let message = build_message(&self.from, email)?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Failed to send email: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+/// Writes every message as an `.eml` file into a directory
+pub struct FileMailer {
+    transport: AsyncFileTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(dir: &str, from: Mailbox) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        Ok(Self {
+            transport: AsyncFileTransport::new(dir),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        let message = build_message(&self.from, email)?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| AppError::Internal(e.into()))?;
+
+        Ok(())
+    }
+}
+
+/// Prints messages to the log instead of delivering them
+pub struct StdoutMailer;
+
+#[async_trait]
+impl Mailer for StdoutMailer {
+    async fn send(&self, email: Email) -> Result<(), AppError> {
+        tracing::info!(
+            "Email to {}\nSubject: {}\n\n{}",
+            email.to,
+            email.subject,
+            email.body
+        );
+        Ok(())
+    }
+}
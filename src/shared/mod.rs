@@ -1,3 +1,6 @@
 pub mod config;
 pub mod error;
 pub mod database;
+pub mod mailer;
+pub mod security;
+pub mod rate_limit;
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::RateLimitStore;
+use crate::shared::config::{RateLimitConfig, RateLimitPolicy};
+
+/// Outcome of taking a token, with the values for the `RateLimit-*` headers
+#[derive(Debug, Clone)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    /// Bucket size
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again
+    pub reset_after: u64,
+    /// Seconds until the next token, 0 when the request was allowed
+    pub retry_after: u64,
+}
+
+impl RateLimitDecision {
+    pub fn new(policy: &RateLimitPolicy, allowed: bool, tokens: f64) -> Self {
+        let rate = f64::from(policy.per_minute) / 60.0;
+        let seconds_until = |target: f64| ((target - tokens).max(0.0) / rate).ceil() as u64;
+
+        Self {
+            allowed,
+            limit: policy.burst,
+            remaining: tokens.floor() as u32,
+            reset_after: seconds_until(f64::from(policy.burst)),
+            retry_after: if allowed { 0 } else { seconds_until(1.0).max(1) },
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    policies: Arc<HashMap<String, RateLimitPolicy>>,
+    trust_forwarded_for: bool,
+    fail_open: bool,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig, store: Arc<dyn RateLimitStore>) -> Self {
+        let policies = if config.enabled {
+            config
+                .policies
+                .iter()
+                .map(|policy| (policy.group.clone(), policy.clone()))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        Self {
+            store,
+            policies: Arc::new(policies),
+            trust_forwarded_for: config.trust_forwarded_for,
+            fail_open: config.fail_open,
+        }
+    }
+
+    pub fn trust_forwarded_for(&self) -> bool {
+        self.trust_forwarded_for
+    }
+
+    /// Takes a token from the client's bucket for the route group. `None` when
+    /// the group isn't limited. Store failures are logged and, depending on
+    /// `fail_open`, let the request through so an unavailable Redis doesn't take
+    /// the API down, or refuse it like an empty bucket.
+    pub async fn check(&self, group: &str, client: &str) -> Option<RateLimitDecision> {
+        let policy = self.policies.get(group)?;
+
+        match self.store.take(&format!("{}:{}", group, client), policy).await {
+            Ok(decision) => Some(decision),
+            Err(e) if self.fail_open => {
+                tracing::warn!(group, "Rate limit store unavailable, letting the request through: {}", e);
+                None
+            }
+            Err(e) => {
+                tracing::warn!(group, "Rate limit store unavailable, refusing the request: {}", e);
+                Some(RateLimitDecision::new(policy, false, 0.0))
+            }
+        }
+    }
+}
@@ -0,0 +1,5 @@
+mod limiter;
+mod store;
+
+pub use limiter::{RateLimitDecision, RateLimiter};
+pub use store::{create_rate_limit_store, RateLimitStore};
@@ -0,0 +1,151 @@
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, Script};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::RateLimitDecision;
+use crate::shared::config::{RateLimitConfig, RateLimitPolicy};
+use crate::shared::error::AppError;
+
+/// Keeps one token bucket per key
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Refills the bucket for the elapsed time and takes one token if available
+    async fn take(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, AppError>;
+}
+
+/// Builds the store selected by `RATE_LIMIT_BACKEND`
+pub async fn create_rate_limit_store(config: &RateLimitConfig) -> anyhow::Result<Arc<dyn RateLimitStore>> {
+    let store: Arc<dyn RateLimitStore> = match config.backend.as_str() {
+        "memory" => Arc::new(MemoryStore::default()),
+        "redis" => Arc::new(RedisStore::connect(&config.redis_url).await?),
+        other => anyhow::bail!("Unknown RATE_LIMIT_BACKEND: {}", other),
+    };
+
+    Ok(store)
+}
+
+fn refill_per_second(policy: &RateLimitPolicy) -> f64 {
+    f64::from(policy.per_minute) / 60.0
+}
+
+/// Full and idle buckets are dropped once the map grows past this size
+const MEMORY_STORE_PRUNE_THRESHOLD: usize = 10_000;
+
+/// Buckets untouched for this long are dropped even if another policy left them short
+const MEMORY_STORE_IDLE_TTL: Duration = Duration::from_secs(15 * 60);
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Per-process buckets. Each instance limits on its own, use Redis to share
+/// limits between instances.
+#[derive(Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn take(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, AppError> {
+        let now = Instant::now();
+        let burst = f64::from(policy.burst);
+        let rate = refill_per_second(policy);
+        let refilled = |bucket: &Bucket| {
+            (bucket.tokens + now.duration_since(bucket.updated_at).as_secs_f64() * rate).min(burst)
+        };
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() > MEMORY_STORE_PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| {
+                refilled(bucket) < burst && now.duration_since(bucket.updated_at) < MEMORY_STORE_IDLE_TTL
+            });
+        }
+        if buckets.len() > MEMORY_STORE_PRUNE_THRESHOLD {
+            // Still too many recent keys, keep the most recently used half
+            let mut updated: Vec<Instant> = buckets.values().map(|bucket| bucket.updated_at).collect();
+            let keep = MEMORY_STORE_PRUNE_THRESHOLD / 2;
+            let (_, cutoff, _) = updated.select_nth_unstable_by(keep, |a, b| b.cmp(a));
+            let cutoff = *cutoff;
+            buckets.retain(|_, bucket| bucket.updated_at > cutoff);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated_at: now,
+        });
+        let mut tokens = refilled(bucket);
+        let allowed = tokens >= 1.0;
+        if allowed {
+            tokens -= 1.0;
+        }
+        bucket.tokens = tokens;
+        bucket.updated_at = now;
+
+        Ok(RateLimitDecision::new(policy, allowed, tokens))
+    }
+}
+
+/// Refill and take in one round trip. Tokens are returned as a string because
+/// Redis truncates Lua numbers to integers.
+const TAKE_SCRIPT: &str = r#"
+local burst = tonumber(ARGV[1])
+local rate_per_ms = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or burst
+local updated_at = tonumber(bucket[2]) or now
+tokens = math.min(burst, tokens + math.max(0, now - updated_at) * rate_per_ms)
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil((burst - tokens) / rate_per_ms) + 1000)
+return {allowed, tostring(tokens)}
+"#;
+
+/// Buckets shared by all instances. Keys expire once their bucket is full again.
+pub struct RedisStore {
+    connection: ConnectionManager,
+    script: Script,
+}
+
+impl RedisStore {
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+
+        Ok(Self {
+            connection,
+            script: Script::new(TAKE_SCRIPT),
+        })
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisStore {
+    async fn take(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, AppError> {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or_default();
+
+        let (allowed, tokens): (i64, String) = self
+            .script
+            .key(format!("ratelimit:{}", key))
+            .arg(policy.burst)
+            .arg(refill_per_second(policy) / 1000.0)
+            .arg(now_ms)
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(|e| AppError::ExternalService(format!("Redis error: {}", e)))?;
+
+        let tokens = tokens.parse::<f64>().unwrap_or_default();
+        Ok(RateLimitDecision::new(policy, allowed == 1, tokens))
+    }
+}
@@ -0,0 +1,5 @@
+mod password;
+mod token;
+
+pub use password::{hash_password, verify_password_if_set};
+pub use token::{generate_token, hash_token};
@@ -0,0 +1,45 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use std::sync::LazyLock;
+
+use crate::shared::error::AppError;
+
+pub fn hash_password(password: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to hash password: {}", e)))
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// Like `verify_password`, but checks a throwaway hash when there is none so
+/// the answer takes as long for unknown accounts as for known ones
+pub fn verify_password_if_set(password: &str, password_hash: Option<&str>) -> bool {
+    static DUMMY_HASH: LazyLock<String> =
+        LazyLock::new(|| hash_password(&generate_dummy_password()).unwrap_or_default());
+
+    match password_hash {
+        Some(hash) => verify_password(password, hash),
+        None => {
+            verify_password(password, &DUMMY_HASH);
+            false
+        }
+    }
+}
+
+fn generate_dummy_password() -> String {
+    SaltString::generate(&mut OsRng).to_string()
+}
@@ -6,9 +6,14 @@ echo "=== Testing Gemini AI Integration ==="
 echo ""
 
 BASE_URL="http://127.0.0.1:3005"
+# Session token or API key with the ai:invoke scope
+TOKEN=${TOKEN:?Set TOKEN to a session token or API key}
+ORG=${ORG:-default}
 
 echo "1. Testing /ai/chat endpoint..."
 curl -X POST "$BASE_URL/ai/chat" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
   -H "Content-Type: application/json" \
   -d '{
     "message": "Hello! What is Rust programming language?",
@@ -18,14 +23,304 @@ echo -e "\n"
 
 echo "2. Testing /ai/generate endpoint..."
 curl -X POST "$BASE_URL/ai/generate" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
   -H "Content-Type: application/json" \
   -d '{
     "prompt": "Write a haiku about coding in Rust"
   }'
 echo -e "\n"
 
-echo "3. Testing /ai/chat/stream endpoint..."
+# With AI_CACHE_BACKEND set, the second call should report "cached": true
+echo "3. Testing the response cache with a deterministic prompt..."
+for i in 1 2; do
+  curl -s -X POST "$BASE_URL/ai/generate" \
+    -H "Authorization: Bearer $TOKEN" \
+    -H "X-Organization: $ORG" \
+    -H "Content-Type: application/json" \
+    -d '{"prompt": "Name three Rust web frameworks", "temperature": 0}' | jq '{cached, model}'
+done
+echo ""
+
+echo "4. Testing a system instruction with assistant history..."
+curl -s -X POST "$BASE_URL/ai/chat" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "message": "And in one word?",
+    "history": [
+      {"role": "user", "content": "Describe Rust"},
+      {"role": "assistant", "content": "A fast, memory-safe systems language."}
+    ],
+    "system_instruction": "Answer as briefly as possible"
+  }' | jq .
+echo ""
+
+# Needs an owner/admin token; each run adds a new version
+echo "5. Testing prompt templates..."
+curl -s -X POST "$BASE_URL/ai/templates" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "name": "summarize",
+    "body": "Summarize in {{words}} words: {{text}}",
+    "variables": [
+      {"name": "text", "type": "string"},
+      {"name": "words", "type": "integer", "required": false, "default": 20}
+    ]
+  }' | jq '{name, version}'
+curl -s -X POST "$BASE_URL/ai/templates/summarize/render-and-generate" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{"variables": {"text": "Rust guarantees memory safety without a garbage collector."}}' | jq .
+echo ""
+
+echo "6. Testing an image upload and a chat about it..."
+printf 'Rust mascot: Ferris the crab' > /tmp/ferris.txt
+FILE_ID=$(curl -s -X POST "$BASE_URL/ai/files" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -F "file=@/tmp/ferris.txt;type=text/plain" | jq -r .id)
+echo "File: $FILE_ID"
+curl -s -X POST "$BASE_URL/ai/chat" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d "{\"message\": \"What does the attached file say?\", \"parts\": [{\"file_id\": \"$FILE_ID\"}]}" | jq .
+curl -s -X DELETE "$BASE_URL/ai/files/$FILE_ID" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" | jq .
+echo ""
+
+echo "7. Testing tool calls (the token needs users:read)..."
+curl -s "$BASE_URL/ai/tools" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" | jq '[.[].name]'
+curl -s -N -X POST "$BASE_URL/ai/chat/stream" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "message": "How many users does our organization have?",
+    "tools": ["list_users", "find_user_by_email"]
+  }'
+echo ""
+
+echo "8. Testing structured output..."
+curl -s -X POST "$BASE_URL/ai/generate" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "prompt": "List two Rust web frameworks",
+    "response_schema": {
+      "type": "array",
+      "items": {"type": "object", "properties": {"name": {"type": "string"}}, "required": ["name"]}
+    }
+  }' | jq '.json'
+echo ""
+
+echo "9. Testing embeddings and semantic search..."
+curl -s -X POST "$BASE_URL/ai/embeddings" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{"texts": ["Rust is fast", "Rust is safe"]}' | jq '{model, dimensions, count: (.embeddings | length)}'
+curl -s -X PUT "$BASE_URL/ai/collections/test-faq/documents" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "documents": [
+      {"id": "password", "content": "To reset your password, use the link on the login page.", "metadata": {"topic": "account"}},
+      {"id": "invoices", "content": "Invoices are sent on the first day of each month.", "metadata": {"topic": "billing"}}
+    ]
+  }' | jq '[.documents[].id]'
+curl -s -X POST "$BASE_URL/ai/collections/test-faq/search" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{"query": "I forgot my password", "top_k": 2}' | jq '.matches | map({id, score})'
+curl -s -X DELETE "$BASE_URL/ai/collections/test-faq/documents/invoices" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" -w "%{http_code}\n"
+echo ""
+
+echo "10. Testing retrieval-augmented chat (deterministic with AI_PROVIDER=mock)..."
+curl -s -X POST "$BASE_URL/ai/collections/test-handbook/ingest" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "id": "handbook",
+    "format": "html",
+    "content": "<h1>Vacation</h1><p>Employees get 30 days of paid vacation per year.</p><h1>Equipment</h1><p>Every employee gets a laptop and a monitor.</p>",
+    "chunk_size": 100,
+    "chunk_overlap": 0
+  }' | jq '[.chunks[] | {id, content}]'
+curl -s -X POST "$BASE_URL/ai/chat" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{"message": "How many days of paid vacation do employees get?", "rag": {"collection": "test-handbook", "top_k": 1}}' \
+  | jq '{response, citations: [.citations[] | {document_id, chunk_id, score}]}'
+curl -s -X DELETE "$BASE_URL/ai/collections/test-handbook/documents/handbook" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" -w "%{http_code}\n"
+echo ""
+
+# Shortened only when the server runs with a small window, e.g. AI_CONTEXT_LIMITS=mock=300 AI_CONTEXT_RESERVED_TOKENS=50
+echo "11. Testing context window management of a long history..."
+HISTORY=$(jq -n '[range(12) | {role: "user", content: "Question \(.): tell me more about robots and their history"}, {role: "model", content: "Answer \(.): robots were first built to automate repetitive factory work"}]')
+for STRATEGY in drop_oldest summarize; do
+  curl -s -X POST "$BASE_URL/ai/chat" \
+    -H "Authorization: Bearer $TOKEN" \
+    -H "X-Organization: $ORG" \
+    -H "Content-Type: application/json" \
+    -d "$(jq -n --argjson history "$HISTORY" --arg strategy "$STRATEGY" \
+      '{message: "Summarize what we discussed", history: $history, context_strategy: $strategy}')" \
+    | jq '{response, context}'
+done
+echo ""
+
+# Needs a route, e.g. AI_MODELS=mock:mock-backup AI_ROUTES=fast AI_ROUTE_FAST=gemini:gemini-2.0-flash-exp,mock:mock-backup
+echo "12. Testing model selection and routes..."
+for MODEL in fast unknown-model; do
+  curl -s -X POST "$BASE_URL/ai/generate" \
+    -H "Authorization: Bearer $TOKEN" \
+    -H "X-Organization: $ORG" \
+    -H "Content-Type: application/json" \
+    -d "{\"prompt\": \"Name one Rust web framework\", \"model\": \"$MODEL\"}" \
+    | jq '{model, fallback, error}'
+done
+echo ""
+
+echo "13. Testing the model list and token counting..."
+curl -s "$BASE_URL/ai/models" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  | jq '{default_model, models: [.models[] | {name, provider, input_token_limit}], routes}'
+curl -s -X POST "$BASE_URL/ai/count-tokens" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "message": "And what about borrowing?",
+    "history": [
+      {"role": "user", "content": "What is ownership in Rust?"},
+      {"role": "model", "content": "Every value has a single owner."}
+    ],
+    "system_instruction": "Answer in one sentence"
+  }' | jq
+echo ""
+
+echo "14. Testing a batch of generate requests..."
+BATCH_ID=$(curl -s -X POST "$BASE_URL/ai/batches" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "items": [
+      {"prompt": "Name a Rust web framework", "temperature": 0},
+      {"prompt": "Name a Rust async runtime", "temperature": 0},
+      {"prompt": "Name a Rust ORM", "temperature": 0}
+    ]
+  }' | jq -r '.id')
+echo "Batch: $BATCH_ID"
+for _ in $(seq 1 30); do
+  STATUS=$(curl -s "$BASE_URL/ai/batches/$BATCH_ID" \
+    -H "Authorization: Bearer $TOKEN" \
+    -H "X-Organization: $ORG" | jq -r '.status')
+  [ "$STATUS" = "completed" ] && break
+  sleep 1
+done
+echo "Status: $STATUS"
+curl -s "$BASE_URL/ai/batches/$BATCH_ID/results" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" | jq -c '{index, status, text: .response.text, error}'
+echo ""
+
+echo "15. Testing PII redaction (run the server with AI_REDACTION_ENABLED=true, the summary needs an owner or admin)..."
+curl -s -X POST "$BASE_URL/ai/chat" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "message": "Repeat exactly: mail jane@example.com, call +49 30 1234567, pay to DE89 3704 0044 0532 0130 00",
+    "temperature": 0
+  }' | jq '{response}'
+curl -s "$BASE_URL/ai/redactions" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" | jq
+echo ""
+
+echo "16. Testing moderation (run the server with AI_MODERATION_RULES=abuse AI_MODERATION_KEYWORDS_ABUSE=idiot, the flags need an owner or admin)..."
+curl -s -w "\nHTTP %{http_code}\n" -X POST "$BASE_URL/ai/chat" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "message": "You are an idiot"
+  }'
+FLAG_ID=$(curl -s "$BASE_URL/ai/moderation/flags?reviewed=false&limit=1" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" | jq -r '.[0].id')
+echo "Flag ID: $FLAG_ID"
+curl -s -X POST "$BASE_URL/ai/moderation/flags/$FLAG_ID/review" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{"note": "Test message"}' | jq '{id, stage, action, categories, reviewed_at}'
+echo ""
+
+echo "17. Testing the request log (run the server with AI_REQUEST_LOG_ENABLED=true, the logs need an owner or admin)..."
+LOG_ID=$(curl -s "$BASE_URL/ai/logs?operation=chat&limit=1" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" | jq -r '.[0].id')
+echo "Log ID: $LOG_ID"
+curl -s -X POST "$BASE_URL/ai/logs/$LOG_ID/replay" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{}' | jq '{model, original_model, identical, latency_ms, original_latency_ms, diff}'
+echo ""
+
+echo "18. Testing multiple candidates with finish reasons and citations..."
+curl -s -X POST "$BASE_URL/ai/generate" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{
+    "prompt": "Suggest a name for a pet crab",
+    "candidate_count": 3,
+    "temperature": 1
+  }' | jq '{text, candidates: [.candidates[] | {index, text, finish_reason, citation_sources}]}'
+echo ""
+
+echo "19. Testing user insights (owner/admin, the token needs users:read)..."
+ME=$(curl -s "$BASE_URL/auth/me" -H "Authorization: Bearer $TOKEN" | jq -r '.id')
+curl -s -X POST "$BASE_URL/ai/users/$ME/bio" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{"facts": ["Leads the platform team", "Writes Rust"], "tone": "friendly", "suggestions": 2}' | jq '.suggestions'
+curl -s "$BASE_URL/ai/users/$ME/duplicates?min_score=0.5" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" | jq '{compared, duplicates}'
+curl -s -X POST "$BASE_URL/ai/users/$ME/activity/explain" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Content-Type: application/json" \
+  -d '{"days": 7}' | jq '{explanation, events: (.events | length)}'
+echo ""
+
+echo "20. Testing /ai/chat/stream endpoint..."
 curl -X POST "$BASE_URL/ai/chat/stream" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
   -H "Content-Type: application/json" \
   -d '{
     "message": "Tell me a short story about a robot"
@@ -33,4 +328,14 @@ curl -X POST "$BASE_URL/ai/chat/stream" \
   -N
 echo -e "\n"
 
+echo "21. Testing /ai/chat/ws handshake (a full session needs a WebSocket client such as websocat)..."
+curl -s -o /dev/null --max-time 2 -w "HTTP %{http_code}\n" "$BASE_URL/ai/chat/ws" \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
+  -H "Connection: Upgrade" \
+  -H "Upgrade: websocket" \
+  -H "Sec-WebSocket-Version: 13" \
+  -H "Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ=="
+echo ""
+
 echo "=== All tests completed ==="
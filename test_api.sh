@@ -1,9 +1,15 @@
 #!/bin/bash
 
 BASE_URL="http://localhost:3005"
+# Session token or API key with users:read and users:write
+TOKEN=${TOKEN:?Set TOKEN to a session token or API key}
+# Organization id or slug, API keys can only select their own organization
+ORG=${ORG:-default}
 
-echo "1. Creating a user..."
+echo "1. Adding a user to the organization..."
 CREATE_RES=$(curl -s -X POST $BASE_URL/users \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
   -H "Content-Type: application/json" \
   -d '{"name": "Alice", "email": "alice@example.com"}')
 echo $CREATE_RES
@@ -11,18 +17,22 @@ USER_ID=$(echo $CREATE_RES | jq -r '.id')
 echo "Created User ID: $USER_ID"
 
 echo -e "\n2. Getting all users..."
-curl -s $BASE_URL/users | jq .
+curl -s $BASE_URL/users -H "Authorization: Bearer $TOKEN" -H "X-Organization: $ORG" | jq .
 
 echo -e "\n3. Getting user by ID..."
-curl -s $BASE_URL/users/$USER_ID | jq .
+curl -s $BASE_URL/users/$USER_ID -H "Authorization: Bearer $TOKEN" -H "X-Organization: $ORG" | jq .
 
 echo -e "\n4. Updating user..."
 curl -s -X PUT $BASE_URL/users/$USER_ID \
+  -H "Authorization: Bearer $TOKEN" \
+  -H "X-Organization: $ORG" \
   -H "Content-Type: application/json" \
   -d '{"name": "Alice Updated"}' | jq .
 
-echo -e "\n5. Deleting user..."
-curl -s -X DELETE $BASE_URL/users/$USER_ID | jq .
+# Removing requires an organization owner/admin session that recently passed 2FA
+echo -e "\n5. Removing the user from the organization (the account stays)..."
+curl -s -X DELETE $BASE_URL/users/$USER_ID \
+  -H "Authorization: Bearer $TOKEN" -H "X-Organization: $ORG" | jq .
 
-echo -e "\n6. Verifying deletion (should fail)..."
-curl -s -w "%{http_code}" $BASE_URL/users/$USER_ID
+echo -e "\n6. Verifying removal (should be 404)..."
+curl -s -w "%{http_code}" $BASE_URL/users/$USER_ID -H "Authorization: Bearer $TOKEN" -H "X-Organization: $ORG"
@@ -0,0 +1,68 @@
+#!/bin/bash
+
+# Test email verification and password reset.
+# Run with MAIL_BACKEND=smtp and the MailHog container from docker-compose,
+# tokens are read back from the MailHog API.
+
+BASE_URL="http://localhost:3005"
+MAILHOG_URL="http://localhost:8025"
+
+latest_token() {
+  curl -s "$MAILHOG_URL/api/v2/messages?limit=1" \
+    | jq -r '.items[0].Content.Body' \
+    | perl -MMIME::QuotedPrint -0777 -ne 'print decode_qp($_)' \
+    | grep -o 'token=[0-9a-f]*' | head -1 | cut -d= -f2
+}
+
+echo "1. Creating a user..."
+curl -s -X POST $BASE_URL/users \
+  -H "Content-Type: application/json" \
+  -d '{"name": "Bob", "email": "bob@example.com"}' | jq .
+
+echo -e "\n2. Requesting email verification..."
+curl -s -X POST $BASE_URL/auth/email-verification \
+  -H "Content-Type: application/json" \
+  -d '{"email": "bob@example.com"}' | jq .
+sleep 1
+TOKEN=$(latest_token)
+echo "Token: $TOKEN"
+
+echo -e "\n3. Confirming email (email_verified should be true)..."
+curl -s -X POST $BASE_URL/auth/email-verification/confirm \
+  -H "Content-Type: application/json" \
+  -d "{\"token\": \"$TOKEN\"}" | jq .
+
+echo -e "\n4. Reusing the token (should fail)..."
+curl -s -w "%{http_code}" -X POST $BASE_URL/auth/email-verification/confirm \
+  -H "Content-Type: application/json" \
+  -d "{\"token\": \"$TOKEN\"}"
+
+echo -e "\n\n5. Requesting password reset..."
+curl -s -X POST $BASE_URL/auth/password-reset \
+  -H "Content-Type: application/json" \
+  -d '{"email": "bob@example.com"}' | jq .
+sleep 1
+TOKEN=$(latest_token)
+
+echo -e "\n6. Confirming password reset..."
+curl -s -X POST $BASE_URL/auth/password-reset/confirm \
+  -H "Content-Type: application/json" \
+  -d "{\"token\": \"$TOKEN\", \"new_password\": \"correct horse battery\"}" | jq .
+
+echo -e "\n7. Logging in..."
+SESSION=$(curl -s -X POST $BASE_URL/auth/login \
+  -H "Content-Type: application/json" \
+  -d '{"email": "bob@example.com", "password": "correct horse battery"}' | jq -r '.token')
+echo "Session: $SESSION"
+
+echo -e "\n8. Starting TOTP enrollment..."
+curl -s -X POST $BASE_URL/auth/2fa/enroll \
+  -H "Authorization: Bearer $SESSION" | jq .
+echo "Add the secret to an authenticator app, then confirm with:"
+echo "  curl -X POST $BASE_URL/auth/2fa/confirm -H 'Authorization: Bearer $SESSION' -H 'Content-Type: application/json' -d '{\"code\": \"123456\"}'"
+
+echo -e "\n9. Listing invitations (owners invite with POST /organizations/{id}/members)..."
+curl -s $BASE_URL/organizations/invitations \
+  -H "Authorization: Bearer $SESSION" | jq .
+echo "Accept one with:"
+echo "  curl -X POST $BASE_URL/organizations/invitations/<organization id>/accept -H 'Authorization: Bearer $SESSION'"
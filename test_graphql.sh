@@ -1,6 +1,9 @@
 #!/bin/bash
 
 BASE_URL="http://127.0.0.1:3005/graphql"
+# Session token or API key with users:read and users:write
+TOKEN=${TOKEN:?Set TOKEN to a session token or API key}
+ORG=${ORG:-default}
 
 echo "Testing GraphQL API at $BASE_URL"
 
@@ -8,12 +11,12 @@ echo "Testing GraphQL API at $BASE_URL"
 echo "1. Creating User..."
 CREATE_QUERY='mutation { createUser(input: {name: "GraphQL User", email: "graphql@example.com"}) { id name email } }'
 PAYLOAD=$(jq -n --arg q "$CREATE_QUERY" '{query: $q}')
-curl -s -X POST -H "Content-Type: application/json" -d "$PAYLOAD" $BASE_URL | jq
+curl -s -X POST -H "Authorization: Bearer $TOKEN" -H "X-Organization: $ORG" -H "Content-Type: application/json" -d "$PAYLOAD" $BASE_URL | jq
 echo ""
 
 # 2. Get Users
 echo "2. Getting Users..."
 GET_QUERY='query { users { id name email } }'
 PAYLOAD=$(jq -n --arg q "$GET_QUERY" '{query: $q}')
-curl -s -X POST -H "Content-Type: application/json" -d "$PAYLOAD" $BASE_URL | jq
+curl -s -X POST -H "Authorization: Bearer $TOKEN" -H "X-Organization: $ORG" -H "Content-Type: application/json" -d "$PAYLOAD" $BASE_URL | jq
 echo ""